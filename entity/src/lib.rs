@@ -2,6 +2,16 @@
 
 pub mod prelude;
 
+pub mod linked_identity;
+pub mod login_attempt;
+pub mod oidc_login;
+pub mod recovery_code;
+pub mod refresh_token;
+pub mod role_permission;
 pub mod sea_orm_active_enums;
+pub mod session;
+pub mod token_revocation;
+pub mod totp_secret;
 pub mod traits;
 pub mod user;
+pub mod user_token;
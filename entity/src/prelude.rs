@@ -1,3 +1,13 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.10.6
 
+pub use super::linked_identity::Entity as LinkedIdentity;
+pub use super::login_attempt::Entity as LoginAttempt;
+pub use super::oidc_login::Entity as OidcLogin;
+pub use super::recovery_code::Entity as RecoveryCode;
+pub use super::refresh_token::Entity as RefreshToken;
+pub use super::role_permission::Entity as RolePermission;
+pub use super::session::Entity as Session;
+pub use super::token_revocation::Entity as TokenRevocation;
+pub use super::totp_secret::Entity as TotpSecret;
 pub use super::user::Entity as User;
+pub use super::user_token::Entity as UserToken;
@@ -25,3 +25,23 @@ pub enum Status {
     #[sea_orm(string_value = "Online")]
     Online,
 }
+
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(32))")]
+pub enum TokenPurpose {
+    #[sea_orm(string_value = "PasswordReset")]
+    PasswordReset,
+    #[sea_orm(string_value = "EmailVerification")]
+    EmailVerification,
+    #[sea_orm(string_value = "EmailChange")]
+    EmailChange,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+pub enum AttemptScope {
+    #[sea_orm(string_value = "Account")]
+    Account,
+    #[sea_orm(string_value = "Ip")]
+    Ip,
+}
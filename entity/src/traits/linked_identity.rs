@@ -0,0 +1,65 @@
+use sea_orm::{
+    prelude::Uuid, ActiveValue, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, InsertResult,
+    QueryFilter,
+};
+
+use crate::{
+    linked_identity::{self, ActiveModel},
+    prelude::LinkedIdentity,
+};
+
+impl LinkedIdentity {
+    pub fn create_active_model(
+        user_id: &Uuid,
+        issuer: &str,
+        subject: &str,
+        created_at: i64,
+    ) -> ActiveModel {
+        ActiveModel {
+            id: ActiveValue::Set(Uuid::new_v4()),
+            user_id: ActiveValue::Set(user_id.to_owned()),
+            issuer: ActiveValue::Set(issuer.to_string()),
+            subject: ActiveValue::Set(subject.to_string()),
+            created_at: ActiveValue::Set(created_at),
+        }
+    }
+
+    // All following traits are tested in integration database tests
+    pub async fn find_one(
+        issuer: &str,
+        subject: &str,
+        conn: &DatabaseConnection,
+    ) -> Result<Option<linked_identity::Model>, DbErr> {
+        LinkedIdentity::find()
+            .filter(linked_identity::Column::Issuer.eq(issuer.to_string()))
+            .filter(linked_identity::Column::Subject.eq(subject.to_string()))
+            .one(conn)
+            .await
+    }
+
+    pub async fn insert_one(
+        model: linked_identity::ActiveModel,
+        conn: &DatabaseConnection,
+    ) -> Result<InsertResult<linked_identity::ActiveModel>, DbErr> {
+        LinkedIdentity::insert(model).exec(conn).await
+    }
+}
+
+#[cfg(test)]
+mod test_linked_identity {
+    use sea_orm::prelude::Uuid;
+
+    use crate::prelude::LinkedIdentity;
+
+    #[test]
+    fn create_model_from_data() {
+        let user_id = Uuid::new_v4();
+        let got = LinkedIdentity::create_active_model(&user_id, "https://idp.test", "subject", 100);
+
+        assert_eq!(got.user_id.unwrap(), user_id);
+        assert_eq!(got.issuer.unwrap(), "https://idp.test");
+        assert_eq!(got.subject.unwrap(), "subject");
+        assert_eq!(got.created_at.unwrap(), 100);
+        assert!(!got.id.unwrap().is_nil());
+    }
+}
@@ -0,0 +1,175 @@
+use sea_orm::{
+    prelude::Uuid,
+    sea_query::{Expr, OnConflict},
+    ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, DeleteResult,
+    EntityTrait, InsertResult, QueryFilter, UpdateResult,
+};
+
+use crate::{
+    login_attempt::{self, ActiveModel},
+    prelude::LoginAttempt,
+    sea_orm_active_enums::AttemptScope,
+};
+
+impl LoginAttempt {
+    pub fn create_active_model(
+        scope: AttemptScope,
+        subject: &str,
+        failed_at: i64,
+        locked_until: Option<i64>,
+    ) -> ActiveModel {
+        ActiveModel {
+            id: ActiveValue::Set(Uuid::new_v4()),
+            scope: ActiveValue::Set(scope),
+            subject: ActiveValue::Set(subject.to_string()),
+            failures: ActiveValue::Set(1),
+            last_failed_at: ActiveValue::Set(failed_at),
+            locked_until: ActiveValue::Set(locked_until),
+        }
+    }
+
+    // All following traits are tested in integration database tests
+    pub async fn find_one(
+        scope: AttemptScope,
+        subject: &str,
+        conn: &DatabaseConnection,
+    ) -> Result<Option<login_attempt::Model>, DbErr> {
+        LoginAttempt::find()
+            .filter(login_attempt::Column::Scope.eq(scope))
+            .filter(login_attempt::Column::Subject.eq(subject.to_string()))
+            .one(conn)
+            .await
+    }
+
+    pub async fn insert_one(
+        model: login_attempt::ActiveModel,
+        conn: &DatabaseConnection,
+    ) -> Result<InsertResult<login_attempt::ActiveModel>, DbErr> {
+        LoginAttempt::insert(model).exec(conn).await
+    }
+
+    /// Counts a failure in a single statement, so that concurrent failures are all counted.
+    /// The count starts over when the last failure was more than `window` seconds ago.
+    pub async fn record_failure(
+        scope: AttemptScope,
+        subject: &str,
+        failed_at: i64,
+        window: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<login_attempt::Model, DbErr> {
+        let failures = Expr::cust(&format!(
+            r#"CASE WHEN "login_attempt"."last_failed_at" < "excluded"."last_failed_at" - {}
+            THEN 1 ELSE "login_attempt"."failures" + 1 END"#,
+            window
+        ));
+        let insert = LoginAttempt::insert(Self::create_active_model(
+            scope.clone(),
+            subject,
+            failed_at,
+            None,
+        ))
+        .on_conflict(
+            OnConflict::columns([login_attempt::Column::Scope, login_attempt::Column::Subject])
+                .values([
+                    (login_attempt::Column::Failures, failures),
+                    (
+                        login_attempt::Column::LastFailedAt,
+                        Expr::cust(r#""excluded"."last_failed_at""#),
+                    ),
+                ])
+                .to_owned(),
+        );
+
+        // SQLite is only read back after the insert, as sea-orm does not use RETURNING there
+        if conn.support_returning() {
+            return insert.exec_with_returning(conn).await;
+        }
+        insert.exec_without_returning(conn).await?;
+        Self::find_one(scope, subject, conn)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(subject.to_string()))
+    }
+
+    /// Never shortens a lockout set by a concurrent failure
+    pub async fn lock_until(
+        id: &Uuid,
+        locked_until: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<UpdateResult, DbErr> {
+        LoginAttempt::update_many()
+            .col_expr(
+                login_attempt::Column::LockedUntil,
+                Expr::value(locked_until),
+            )
+            .filter(login_attempt::Column::Id.eq(id.to_owned()))
+            .filter(
+                login_attempt::Column::LockedUntil
+                    .is_null()
+                    .or(login_attempt::Column::LockedUntil.lt(locked_until)),
+            )
+            .exec(conn)
+            .await
+    }
+
+    pub async fn delete_one(
+        scope: AttemptScope,
+        subject: &str,
+        conn: &DatabaseConnection,
+    ) -> Result<DeleteResult, DbErr> {
+        LoginAttempt::delete_many()
+            .filter(login_attempt::Column::Scope.eq(scope))
+            .filter(login_attempt::Column::Subject.eq(subject.to_string()))
+            .exec(conn)
+            .await
+    }
+}
+
+impl login_attempt::Model {
+    /// The number of seconds until another attempt is allowed, if any
+    pub fn retry_after(&self, now: i64) -> Option<u64> {
+        match self.locked_until {
+            Some(locked_until) if locked_until > now => Some((locked_until - now) as u64),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_login_attempt {
+    use sea_orm::prelude::Uuid;
+
+    use crate::{login_attempt, prelude::LoginAttempt, sea_orm_active_enums::AttemptScope};
+
+    #[test]
+    fn create_model_from_data() {
+        let got =
+            LoginAttempt::create_active_model(AttemptScope::Account, "test@test.com", 100, None);
+
+        assert_eq!(got.scope.unwrap(), AttemptScope::Account);
+        assert_eq!(got.subject.unwrap(), "test@test.com");
+        assert_eq!(got.failures.unwrap(), 1);
+        assert_eq!(got.last_failed_at.unwrap(), 100);
+        assert!(got.locked_until.unwrap().is_none());
+        assert!(!got.id.unwrap().is_nil());
+    }
+
+    #[test]
+    fn retry_after_only_while_locked() {
+        let model = login_attempt::Model {
+            id: Uuid::new_v4(),
+            scope: AttemptScope::Ip,
+            subject: "127.0.0.1".to_string(),
+            failures: 5,
+            last_failed_at: 100,
+            locked_until: None,
+        };
+        assert_eq!(model.retry_after(150), None);
+
+        let model = login_attempt::Model {
+            locked_until: Some(200),
+            ..model
+        };
+        assert_eq!(model.retry_after(150), Some(50));
+        assert_eq!(model.retry_after(200), None);
+    }
+}
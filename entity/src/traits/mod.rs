@@ -1,3 +1,13 @@
+pub mod linked_identity;
+pub mod login_attempt;
+pub mod oidc_login;
+pub mod recovery_code;
+pub mod refresh_token;
 pub mod role;
+pub mod role_permission;
+pub mod session;
 pub mod status;
+pub mod token_revocation;
+pub mod totp_secret;
 pub mod user;
+pub mod user_token;
@@ -0,0 +1,99 @@
+use sea_orm::{
+    prelude::Uuid, ActiveValue, ColumnTrait, DatabaseConnection, DbErr, DeleteResult, EntityTrait,
+    InsertResult, QueryFilter,
+};
+
+use crate::{
+    oidc_login::{self, ActiveModel},
+    prelude::OidcLogin,
+};
+
+impl OidcLogin {
+    pub fn create_active_model(
+        state_hash: &str,
+        nonce: &str,
+        code_verifier: &str,
+        created_at: i64,
+        expires_at: i64,
+    ) -> ActiveModel {
+        ActiveModel {
+            id: ActiveValue::Set(Uuid::new_v4()),
+            state_hash: ActiveValue::Set(state_hash.to_string()),
+            nonce: ActiveValue::Set(nonce.to_string()),
+            code_verifier: ActiveValue::Set(code_verifier.to_string()),
+            created_at: ActiveValue::Set(created_at),
+            expires_at: ActiveValue::Set(expires_at),
+        }
+    }
+
+    // All following traits are tested in integration database tests
+    pub async fn find_one_by_state(
+        state_hash: &str,
+        conn: &DatabaseConnection,
+    ) -> Result<Option<oidc_login::Model>, DbErr> {
+        OidcLogin::find()
+            .filter(oidc_login::Column::StateHash.eq(state_hash.to_string()))
+            .one(conn)
+            .await
+    }
+
+    pub async fn insert_one(
+        model: oidc_login::ActiveModel,
+        conn: &DatabaseConnection,
+    ) -> Result<InsertResult<oidc_login::ActiveModel>, DbErr> {
+        OidcLogin::insert(model).exec(conn).await
+    }
+
+    pub async fn delete_one(id: &Uuid, conn: &DatabaseConnection) -> Result<DeleteResult, DbErr> {
+        OidcLogin::delete_by_id(id.to_owned()).exec(conn).await
+    }
+
+    pub async fn delete_expired(
+        now: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<DeleteResult, DbErr> {
+        OidcLogin::delete_many()
+            .filter(oidc_login::Column::ExpiresAt.lte(now))
+            .exec(conn)
+            .await
+    }
+}
+
+impl oidc_login::Model {
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at <= now
+    }
+}
+
+#[cfg(test)]
+mod test_oidc_login {
+    use sea_orm::prelude::Uuid;
+
+    use crate::{oidc_login, prelude::OidcLogin};
+
+    #[test]
+    fn create_model_from_data() {
+        let got = OidcLogin::create_active_model("state", "nonce", "verifier", 100, 200);
+
+        assert_eq!(got.state_hash.unwrap(), "state");
+        assert_eq!(got.nonce.unwrap(), "nonce");
+        assert_eq!(got.code_verifier.unwrap(), "verifier");
+        assert_eq!(got.created_at.unwrap(), 100);
+        assert_eq!(got.expires_at.unwrap(), 200);
+        assert!(!got.id.unwrap().is_nil());
+    }
+
+    #[test]
+    fn expired_at_expiry() {
+        let model = oidc_login::Model {
+            id: Uuid::new_v4(),
+            state_hash: "state".to_string(),
+            nonce: "nonce".to_string(),
+            code_verifier: "verifier".to_string(),
+            created_at: 100,
+            expires_at: 200,
+        };
+        assert!(!model.is_expired(199));
+        assert!(model.is_expired(200));
+    }
+}
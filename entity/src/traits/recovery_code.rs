@@ -0,0 +1,85 @@
+use sea_orm::{
+    prelude::Uuid, sea_query::Expr, ActiveValue, ColumnTrait, DatabaseConnection, DbErr,
+    DeleteResult, EntityTrait, InsertResult, QueryFilter, UpdateResult,
+};
+
+use crate::{
+    prelude::RecoveryCode,
+    recovery_code::{self, ActiveModel},
+};
+
+impl RecoveryCode {
+    pub fn create_active_model(user_id: &Uuid, code_hash: &str, created_at: i64) -> ActiveModel {
+        ActiveModel {
+            id: ActiveValue::Set(Uuid::new_v4()),
+            user_id: ActiveValue::Set(user_id.to_owned()),
+            code_hash: ActiveValue::Set(code_hash.to_string()),
+            created_at: ActiveValue::Set(created_at),
+            used_at: ActiveValue::Set(None),
+        }
+    }
+
+    // All following traits are tested in integration database tests
+    pub async fn find_unused(
+        user_id: &Uuid,
+        code_hash: &str,
+        conn: &DatabaseConnection,
+    ) -> Result<Option<recovery_code::Model>, DbErr> {
+        RecoveryCode::find()
+            .filter(recovery_code::Column::UserId.eq(user_id.to_owned()))
+            .filter(recovery_code::Column::CodeHash.eq(code_hash.to_string()))
+            .filter(recovery_code::Column::UsedAt.is_null())
+            .one(conn)
+            .await
+    }
+
+    pub async fn insert_all(
+        models: Vec<recovery_code::ActiveModel>,
+        conn: &DatabaseConnection,
+    ) -> Result<InsertResult<recovery_code::ActiveModel>, DbErr> {
+        RecoveryCode::insert_many(models).exec(conn).await
+    }
+
+    // Only an unused code is updated so the same code cannot be redeemed twice
+    pub async fn mark_used(
+        id: &Uuid,
+        now: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<UpdateResult, DbErr> {
+        RecoveryCode::update_many()
+            .col_expr(recovery_code::Column::UsedAt, Expr::value(now))
+            .filter(recovery_code::Column::Id.eq(id.to_owned()))
+            .filter(recovery_code::Column::UsedAt.is_null())
+            .exec(conn)
+            .await
+    }
+
+    pub async fn delete_for_user(
+        user_id: &Uuid,
+        conn: &DatabaseConnection,
+    ) -> Result<DeleteResult, DbErr> {
+        RecoveryCode::delete_many()
+            .filter(recovery_code::Column::UserId.eq(user_id.to_owned()))
+            .exec(conn)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test_recovery_code {
+    use sea_orm::prelude::Uuid;
+
+    use crate::prelude::RecoveryCode;
+
+    #[test]
+    fn create_model_from_data() {
+        let user_id = Uuid::new_v4();
+        let got = RecoveryCode::create_active_model(&user_id, "hash", 100);
+
+        assert_eq!(got.user_id.unwrap(), user_id);
+        assert_eq!(got.code_hash.unwrap(), "hash");
+        assert_eq!(got.created_at.unwrap(), 100);
+        assert!(got.used_at.unwrap().is_none());
+        assert!(!got.id.unwrap().is_nil());
+    }
+}
@@ -0,0 +1,155 @@
+use sea_orm::{
+    prelude::Uuid, sea_query::Expr, ActiveValue, ColumnTrait, DatabaseConnection, DbErr,
+    EntityTrait, InsertResult, QueryFilter, UpdateResult,
+};
+
+use crate::{
+    prelude::RefreshToken,
+    refresh_token::{self, ActiveModel},
+};
+
+impl RefreshToken {
+    pub fn create_active_model(
+        user_id: &Uuid,
+        family_id: &Uuid,
+        token_hash: &str,
+        created_at: i64,
+        expires_at: i64,
+    ) -> ActiveModel {
+        ActiveModel {
+            id: ActiveValue::Set(Uuid::new_v4()),
+            user_id: ActiveValue::Set(user_id.to_owned()),
+            family_id: ActiveValue::Set(family_id.to_owned()),
+            token_hash: ActiveValue::Set(token_hash.to_string()),
+            created_at: ActiveValue::Set(created_at),
+            expires_at: ActiveValue::Set(expires_at),
+            rotated_at: ActiveValue::Set(None),
+            revoked_at: ActiveValue::Set(None),
+        }
+    }
+
+    // All following traits are tested in integration database tests
+    pub async fn find_one_by_hash(
+        token_hash: &str,
+        conn: &DatabaseConnection,
+    ) -> Result<Option<refresh_token::Model>, DbErr> {
+        RefreshToken::find()
+            .filter(refresh_token::Column::TokenHash.eq(token_hash.to_string()))
+            .one(conn)
+            .await
+    }
+
+    pub async fn insert_one(
+        model: refresh_token::ActiveModel,
+        conn: &DatabaseConnection,
+    ) -> Result<InsertResult<refresh_token::ActiveModel>, DbErr> {
+        RefreshToken::insert(model).exec(conn).await
+    }
+
+    // Only a token that has not yet been rotated or revoked is updated so that
+    // two concurrent refreshes with the same token cannot both succeed
+    pub async fn mark_rotated(
+        id: &Uuid,
+        now: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<UpdateResult, DbErr> {
+        RefreshToken::update_many()
+            .col_expr(refresh_token::Column::RotatedAt, Expr::value(now))
+            .filter(refresh_token::Column::Id.eq(id.to_owned()))
+            .filter(refresh_token::Column::RotatedAt.is_null())
+            .filter(refresh_token::Column::RevokedAt.is_null())
+            .exec(conn)
+            .await
+    }
+
+    pub async fn revoke_family(
+        family_id: &Uuid,
+        now: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<UpdateResult, DbErr> {
+        RefreshToken::update_many()
+            .col_expr(refresh_token::Column::RevokedAt, Expr::value(now))
+            .filter(refresh_token::Column::FamilyId.eq(family_id.to_owned()))
+            .filter(refresh_token::Column::RevokedAt.is_null())
+            .exec(conn)
+            .await
+    }
+
+    pub async fn revoke_all_for_user(
+        user_id: &Uuid,
+        now: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<UpdateResult, DbErr> {
+        RefreshToken::update_many()
+            .col_expr(refresh_token::Column::RevokedAt, Expr::value(now))
+            .filter(refresh_token::Column::UserId.eq(user_id.to_owned()))
+            .filter(refresh_token::Column::RevokedAt.is_null())
+            .exec(conn)
+            .await
+    }
+}
+
+impl refresh_token::Model {
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at <= now
+    }
+
+    pub fn is_rotated(&self) -> bool {
+        self.rotated_at.is_some()
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+}
+
+#[cfg(test)]
+mod test_refresh_token {
+    use sea_orm::prelude::Uuid;
+
+    use crate::{prelude::RefreshToken, refresh_token};
+
+    #[test]
+    fn create_model_from_data() {
+        let user_id = Uuid::new_v4();
+        let family_id = Uuid::new_v4();
+        let got = RefreshToken::create_active_model(&user_id, &family_id, "hash", 100, 200);
+
+        assert_eq!(got.user_id.unwrap(), user_id);
+        assert_eq!(got.family_id.unwrap(), family_id);
+        assert_eq!(got.token_hash.unwrap(), "hash");
+        assert_eq!(got.created_at.unwrap(), 100);
+        assert_eq!(got.expires_at.unwrap(), 200);
+        assert!(got.rotated_at.unwrap().is_none());
+        assert!(got.revoked_at.unwrap().is_none());
+
+        let id = got.id.unwrap();
+        assert!(!id.is_nil());
+    }
+
+    #[test]
+    fn model_state_checks() {
+        let model = refresh_token::Model {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            family_id: Uuid::new_v4(),
+            token_hash: "hash".to_string(),
+            created_at: 100,
+            expires_at: 200,
+            rotated_at: None,
+            revoked_at: None,
+        };
+        assert!(!model.is_expired(199));
+        assert!(model.is_expired(200));
+        assert!(!model.is_rotated());
+        assert!(!model.is_revoked());
+
+        let model = refresh_token::Model {
+            rotated_at: Some(150),
+            revoked_at: Some(160),
+            ..model
+        };
+        assert!(model.is_rotated());
+        assert!(model.is_revoked());
+    }
+}
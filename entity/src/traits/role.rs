@@ -1,14 +1,11 @@
 use std::str::FromStr;
 
+use sea_orm::{DbErr, TryFromU64};
 use thiserror::Error;
 
 use crate::sea_orm_active_enums::Role;
 
 impl Role {
-    pub fn meets_requirements(&self, role: &Role) -> bool {
-        self.to_int() >= role.to_int()
-    }
-
     pub fn to_int(&self) -> u8 {
         match self {
             Role::Admin => 3,
@@ -27,6 +24,13 @@ impl Role {
         }
     }
 }
+// Required for roles to be part of a composite primary key, as in `role_permission`
+impl TryFromU64 for Role {
+    fn try_from_u64(_: u64) -> Result<Self, DbErr> {
+        Err(DbErr::ConvertFromU64("Role"))
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum ParsingError {
     #[error("No role corresponding to {0}")]
@@ -0,0 +1,66 @@
+use sea_orm::{
+    ActiveValue, ColumnTrait, DatabaseConnection, DbErr, DeleteResult, EntityTrait, InsertResult,
+    QueryFilter,
+};
+
+use crate::{
+    prelude::RolePermission,
+    role_permission::{self, ActiveModel},
+    sea_orm_active_enums::Role,
+};
+
+impl RolePermission {
+    pub fn create_active_model(role: &Role, permission: &str) -> ActiveModel {
+        ActiveModel {
+            role: ActiveValue::Set(role.to_owned()),
+            permission: ActiveValue::Set(permission.to_string()),
+        }
+    }
+
+    // All following traits are tested in integration database tests
+    pub async fn find_all(conn: &DatabaseConnection) -> Result<Vec<role_permission::Model>, DbErr> {
+        RolePermission::find().all(conn).await
+    }
+
+    pub async fn find_one(
+        role: &Role,
+        permission: &str,
+        conn: &DatabaseConnection,
+    ) -> Result<Option<role_permission::Model>, DbErr> {
+        RolePermission::find_by_id((role.to_owned(), permission.to_string()))
+            .one(conn)
+            .await
+    }
+
+    pub async fn insert_one(
+        model: role_permission::ActiveModel,
+        conn: &DatabaseConnection,
+    ) -> Result<InsertResult<role_permission::ActiveModel>, DbErr> {
+        RolePermission::insert(model).exec(conn).await
+    }
+
+    pub async fn delete_one(
+        role: &Role,
+        permission: &str,
+        conn: &DatabaseConnection,
+    ) -> Result<DeleteResult, DbErr> {
+        RolePermission::delete_many()
+            .filter(role_permission::Column::Role.eq(role.to_owned()))
+            .filter(role_permission::Column::Permission.eq(permission.to_string()))
+            .exec(conn)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test_role_permission {
+    use crate::{prelude::RolePermission, sea_orm_active_enums::Role};
+
+    #[test]
+    fn create_model_from_data() {
+        let got = RolePermission::create_active_model(&Role::Teacher, "grades:publish");
+
+        assert_eq!(got.role.unwrap(), Role::Teacher);
+        assert_eq!(got.permission.unwrap(), "grades:publish");
+    }
+}
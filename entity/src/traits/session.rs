@@ -0,0 +1,165 @@
+use sea_orm::{
+    prelude::Uuid, sea_query::Expr, ActiveValue, ColumnTrait, DatabaseConnection, DbErr,
+    EntityTrait, InsertResult, PaginatorTrait, QueryFilter, QueryOrder, Select, UpdateResult,
+};
+
+use crate::{
+    prelude::Session,
+    session::{self, ActiveModel},
+};
+
+impl Session {
+    pub fn create_active_model(
+        user_id: &Uuid,
+        device_label: Option<String>,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+        now: i64,
+    ) -> ActiveModel {
+        ActiveModel {
+            id: ActiveValue::Set(Uuid::new_v4()),
+            user_id: ActiveValue::Set(user_id.to_owned()),
+            device_label: ActiveValue::Set(device_label),
+            user_agent: ActiveValue::Set(user_agent),
+            ip_address: ActiveValue::Set(ip_address),
+            created_at: ActiveValue::Set(now),
+            last_seen_at: ActiveValue::Set(now),
+            revoked_at: ActiveValue::Set(None),
+            mfa_authenticated: ActiveValue::Set(false),
+        }
+    }
+
+    // Sessions that have not been revoked and were seen after `seen_since`
+    fn find_active(user_id: &Uuid, seen_since: i64) -> Select<Session> {
+        Session::find()
+            .filter(session::Column::UserId.eq(user_id.to_owned()))
+            .filter(session::Column::RevokedAt.is_null())
+            .filter(session::Column::LastSeenAt.gt(seen_since))
+    }
+
+    // All following traits are tested in integration database tests
+    pub async fn find_one_by_id(
+        id: &Uuid,
+        conn: &DatabaseConnection,
+    ) -> Result<Option<session::Model>, DbErr> {
+        Session::find_by_id(*id).one(conn).await
+    }
+
+    pub async fn find_active_for_user(
+        user_id: &Uuid,
+        seen_since: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<Vec<session::Model>, DbErr> {
+        Session::find_active(user_id, seen_since)
+            .order_by_desc(session::Column::LastSeenAt)
+            .all(conn)
+            .await
+    }
+
+    pub async fn count_active_for_user(
+        user_id: &Uuid,
+        seen_since: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<u64, DbErr> {
+        Session::find_active(user_id, seen_since).count(conn).await
+    }
+
+    pub async fn insert_one(
+        model: session::ActiveModel,
+        conn: &DatabaseConnection,
+    ) -> Result<InsertResult<session::ActiveModel>, DbErr> {
+        Session::insert(model).exec(conn).await
+    }
+
+    pub async fn touch(
+        id: &Uuid,
+        now: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<UpdateResult, DbErr> {
+        Session::update_many()
+            .col_expr(session::Column::LastSeenAt, Expr::value(now))
+            .filter(session::Column::Id.eq(id.to_owned()))
+            .exec(conn)
+            .await
+    }
+
+    pub async fn revoke_one(
+        id: &Uuid,
+        now: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<UpdateResult, DbErr> {
+        Session::update_many()
+            .col_expr(session::Column::RevokedAt, Expr::value(now))
+            .filter(session::Column::Id.eq(id.to_owned()))
+            .filter(session::Column::RevokedAt.is_null())
+            .exec(conn)
+            .await
+    }
+
+    pub async fn revoke_all_for_user(
+        user_id: &Uuid,
+        now: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<UpdateResult, DbErr> {
+        Session::update_many()
+            .col_expr(session::Column::RevokedAt, Expr::value(now))
+            .filter(session::Column::UserId.eq(user_id.to_owned()))
+            .filter(session::Column::RevokedAt.is_null())
+            .exec(conn)
+            .await
+    }
+}
+
+impl session::Model {
+    pub fn is_active(&self, seen_since: i64) -> bool {
+        self.revoked_at.is_none() && self.last_seen_at > seen_since
+    }
+}
+
+#[cfg(test)]
+mod test_session {
+    use sea_orm::prelude::Uuid;
+
+    use crate::{prelude::Session, session};
+
+    #[test]
+    fn create_model_from_data() {
+        let user_id = Uuid::new_v4();
+        let got = Session::create_active_model(
+            &user_id,
+            Some("laptop".to_string()),
+            Some("Mozilla/5.0".to_string()),
+            Some("10.0.0.1".to_string()),
+            100,
+        );
+
+        assert_eq!(got.user_id.unwrap(), user_id);
+        assert_eq!(got.device_label.unwrap(), Some("laptop".to_string()));
+        assert_eq!(got.user_agent.unwrap(), Some("Mozilla/5.0".to_string()));
+        assert_eq!(got.ip_address.unwrap(), Some("10.0.0.1".to_string()));
+        assert_eq!(got.created_at.unwrap(), 100);
+        assert_eq!(got.last_seen_at.unwrap(), 100);
+        assert!(got.revoked_at.unwrap().is_none());
+        assert!(!got.id.unwrap().is_nil());
+    }
+
+    #[test]
+    fn active_until_revoked_or_idle() {
+        let mut model = session::Model {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            device_label: None,
+            user_agent: None,
+            ip_address: None,
+            created_at: 100,
+            last_seen_at: 200,
+            revoked_at: None,
+            mfa_authenticated: false,
+        };
+        assert!(model.is_active(199));
+        assert!(!model.is_active(200));
+
+        model.revoked_at = Some(300);
+        assert!(!model.is_active(199));
+    }
+}
@@ -0,0 +1,122 @@
+use sea_orm::{
+    prelude::Uuid, ActiveValue, ColumnTrait, DatabaseConnection, DbErr, DeleteResult, EntityTrait,
+    InsertResult, QueryFilter,
+};
+
+use crate::{
+    prelude::TokenRevocation,
+    token_revocation::{self, ActiveModel},
+};
+
+impl TokenRevocation {
+    /// A revocation for a single token, identified by its `jti` claim
+    pub fn create_token_active_model(
+        user_id: &Uuid,
+        jti: &Uuid,
+        revoked_at: i64,
+        expires_at: i64,
+    ) -> ActiveModel {
+        ActiveModel {
+            id: ActiveValue::Set(Uuid::new_v4()),
+            user_id: ActiveValue::Set(user_id.to_owned()),
+            jti: ActiveValue::Set(Some(jti.to_owned())),
+            session_id: ActiveValue::Set(None),
+            revoked_at: ActiveValue::Set(revoked_at),
+            expires_at: ActiveValue::Set(expires_at),
+        }
+    }
+
+    /// A revocation for every token belonging to the user that was issued before
+    /// `revoked_at`, in milliseconds since the epoch
+    pub fn create_user_active_model(
+        user_id: &Uuid,
+        revoked_at: i64,
+        expires_at: i64,
+    ) -> ActiveModel {
+        ActiveModel {
+            id: ActiveValue::Set(Uuid::new_v4()),
+            user_id: ActiveValue::Set(user_id.to_owned()),
+            jti: ActiveValue::Set(None),
+            session_id: ActiveValue::Set(None),
+            revoked_at: ActiveValue::Set(revoked_at),
+            expires_at: ActiveValue::Set(expires_at),
+        }
+    }
+
+    /// A revocation for every token issued within the session
+    pub fn create_session_active_model(
+        user_id: &Uuid,
+        session_id: &Uuid,
+        revoked_at: i64,
+        expires_at: i64,
+    ) -> ActiveModel {
+        ActiveModel {
+            id: ActiveValue::Set(Uuid::new_v4()),
+            user_id: ActiveValue::Set(user_id.to_owned()),
+            jti: ActiveValue::Set(None),
+            session_id: ActiveValue::Set(Some(session_id.to_owned())),
+            revoked_at: ActiveValue::Set(revoked_at),
+            expires_at: ActiveValue::Set(expires_at),
+        }
+    }
+
+    // All following traits are tested in integration database tests
+    pub async fn find_unexpired(
+        now: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<Vec<token_revocation::Model>, DbErr> {
+        TokenRevocation::find()
+            .filter(token_revocation::Column::ExpiresAt.gt(now))
+            .all(conn)
+            .await
+    }
+
+    pub async fn insert_one(
+        model: token_revocation::ActiveModel,
+        conn: &DatabaseConnection,
+    ) -> Result<InsertResult<token_revocation::ActiveModel>, DbErr> {
+        TokenRevocation::insert(model).exec(conn).await
+    }
+
+    pub async fn delete_expired(
+        now: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<DeleteResult, DbErr> {
+        TokenRevocation::delete_many()
+            .filter(token_revocation::Column::ExpiresAt.lte(now))
+            .exec(conn)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test_token_revocation {
+    use sea_orm::prelude::Uuid;
+
+    use crate::prelude::TokenRevocation;
+
+    #[test]
+    fn create_token_model_from_data() {
+        let user_id = Uuid::new_v4();
+        let jti = Uuid::new_v4();
+        let got = TokenRevocation::create_token_active_model(&user_id, &jti, 100, 200);
+
+        assert_eq!(got.user_id.unwrap(), user_id);
+        assert_eq!(got.jti.unwrap(), Some(jti));
+        assert_eq!(got.revoked_at.unwrap(), 100);
+        assert_eq!(got.expires_at.unwrap(), 200);
+        assert!(!got.id.unwrap().is_nil());
+    }
+
+    #[test]
+    fn create_user_model_from_data() {
+        let user_id = Uuid::new_v4();
+        let got = TokenRevocation::create_user_active_model(&user_id, 100, 200);
+
+        assert_eq!(got.user_id.unwrap(), user_id);
+        assert!(got.jti.unwrap().is_none());
+        assert_eq!(got.revoked_at.unwrap(), 100);
+        assert_eq!(got.expires_at.unwrap(), 200);
+        assert!(!got.id.unwrap().is_nil());
+    }
+}
@@ -0,0 +1,126 @@
+use sea_orm::{
+    prelude::Uuid, sea_query::Expr, ActiveValue, ColumnTrait, Condition, DatabaseConnection, DbErr,
+    DeleteResult, EntityTrait, InsertResult, QueryFilter, UpdateResult,
+};
+
+use crate::{
+    prelude::TotpSecret,
+    totp_secret::{self, ActiveModel},
+};
+
+impl TotpSecret {
+    pub fn create_active_model(user_id: &Uuid, secret: &str, created_at: i64) -> ActiveModel {
+        ActiveModel {
+            id: ActiveValue::Set(Uuid::new_v4()),
+            user_id: ActiveValue::Set(user_id.to_owned()),
+            secret: ActiveValue::Set(secret.to_string()),
+            created_at: ActiveValue::Set(created_at),
+            confirmed_at: ActiveValue::Set(None),
+            last_used_step: ActiveValue::Set(None),
+        }
+    }
+
+    // All following traits are tested in integration database tests
+    pub async fn find_one_by_user(
+        user_id: &Uuid,
+        conn: &DatabaseConnection,
+    ) -> Result<Option<totp_secret::Model>, DbErr> {
+        TotpSecret::find()
+            .filter(totp_secret::Column::UserId.eq(user_id.to_owned()))
+            .one(conn)
+            .await
+    }
+
+    pub async fn insert_one(
+        model: totp_secret::ActiveModel,
+        conn: &DatabaseConnection,
+    ) -> Result<InsertResult<totp_secret::ActiveModel>, DbErr> {
+        TotpSecret::insert(model).exec(conn).await
+    }
+
+    pub async fn delete_for_user(
+        user_id: &Uuid,
+        conn: &DatabaseConnection,
+    ) -> Result<DeleteResult, DbErr> {
+        TotpSecret::delete_many()
+            .filter(totp_secret::Column::UserId.eq(user_id.to_owned()))
+            .exec(conn)
+            .await
+    }
+
+    pub async fn confirm(
+        id: &Uuid,
+        now: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<UpdateResult, DbErr> {
+        TotpSecret::update_many()
+            .col_expr(totp_secret::Column::ConfirmedAt, Expr::value(now))
+            .filter(totp_secret::Column::Id.eq(id.to_owned()))
+            .filter(totp_secret::Column::ConfirmedAt.is_null())
+            .exec(conn)
+            .await
+    }
+
+    // Only a later time step is recorded so the same code cannot be used twice
+    pub async fn mark_step_used(
+        id: &Uuid,
+        step: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<UpdateResult, DbErr> {
+        TotpSecret::update_many()
+            .col_expr(totp_secret::Column::LastUsedStep, Expr::value(step))
+            .filter(totp_secret::Column::Id.eq(id.to_owned()))
+            .filter(
+                Condition::any()
+                    .add(totp_secret::Column::LastUsedStep.is_null())
+                    .add(totp_secret::Column::LastUsedStep.lt(step)),
+            )
+            .exec(conn)
+            .await
+    }
+}
+
+impl totp_secret::Model {
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+}
+
+#[cfg(test)]
+mod test_totp_secret {
+    use sea_orm::prelude::Uuid;
+
+    use crate::{prelude::TotpSecret, totp_secret};
+
+    #[test]
+    fn create_model_from_data() {
+        let user_id = Uuid::new_v4();
+        let got = TotpSecret::create_active_model(&user_id, "SECRET", 100);
+
+        assert_eq!(got.user_id.unwrap(), user_id);
+        assert_eq!(got.secret.unwrap(), "SECRET");
+        assert_eq!(got.created_at.unwrap(), 100);
+        assert!(got.confirmed_at.unwrap().is_none());
+        assert!(got.last_used_step.unwrap().is_none());
+        assert!(!got.id.unwrap().is_nil());
+    }
+
+    #[test]
+    fn confirmed_once_confirmed_at_is_set() {
+        let model = totp_secret::Model {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            secret: "SECRET".to_string(),
+            created_at: 100,
+            confirmed_at: None,
+            last_used_step: None,
+        };
+        assert!(!model.is_confirmed());
+
+        let model = totp_secret::Model {
+            confirmed_at: Some(150),
+            ..model
+        };
+        assert!(model.is_confirmed());
+    }
+}
@@ -1,14 +1,105 @@
+use juniper::GraphQLEnum;
 use sea_orm::{
-    prelude::Uuid, ActiveValue, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, InsertResult,
-    QueryFilter,
+    prelude::Uuid,
+    sea_query::{Expr, Func, LikeExpr},
+    ActiveValue, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DatabaseTransaction,
+    DbErr, DeleteResult, EntityTrait, InsertResult, Order, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect, QueryTrait, Select,
 };
+use tracing::instrument;
 
 use crate::{
-    prelude::User,
+    prelude::{Session, User},
     sea_orm_active_enums::{Role, Status},
+    session,
     user::{self, ActiveModel},
 };
 
+#[derive(Debug, Default, Clone)]
+pub struct UserFilter {
+    pub role: Option<Role>,
+    // Any of the statuses, where an empty list matches nothing
+    pub statuses: Option<Vec<Status>>,
+    // Case-insensitive substring of the name or email
+    pub search: Option<String>,
+    // Only match the search against names, for callers that may not see emails
+    pub search_names_only: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum UserOrderField {
+    Name,
+    Email,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserOrder {
+    pub field: UserOrderField,
+    pub direction: SortDirection,
+}
+
+impl Default for UserOrder {
+    fn default() -> Self {
+        UserOrder {
+            field: UserOrderField::Name,
+            direction: SortDirection::Asc,
+        }
+    }
+}
+
+impl UserOrder {
+    fn column(&self) -> user::Column {
+        match self.field {
+            UserOrderField::Name => user::Column::Name,
+            UserOrderField::Email => user::Column::Email,
+        }
+    }
+
+    fn order(&self) -> Order {
+        match self.direction {
+            SortDirection::Asc => Order::Asc,
+            SortDirection::Desc => Order::Desc,
+        }
+    }
+}
+
+/// The position of a row within an ordering, i.e. the value of the ordered
+/// column and the id that breaks ties between equal values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserCursor {
+    pub value: String,
+    pub id: Uuid,
+}
+
+impl UserCursor {
+    pub fn from_model(model: &user::Model, order: &UserOrder) -> Self {
+        let value = match order.field {
+            UserOrderField::Name => model.name.to_owned(),
+            UserOrderField::Email => model.email.to_owned(),
+        };
+        UserCursor {
+            value,
+            id: model.id,
+        }
+    }
+}
+
+impl user::Model {
+    pub fn is_email_verified(&self) -> bool {
+        self.email_verified_at.is_some()
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended_at.is_some()
+    }
+}
+
 impl User {
     pub fn create_active_model(email: &str, name: &str, password: &str) -> ActiveModel {
         ActiveModel {
@@ -18,10 +109,14 @@ impl User {
             password: ActiveValue::Set(password.to_string()),
             role: ActiveValue::Set(Role::Guest),
             status: ActiveValue::Set(Status::Online),
+            email_verified_at: ActiveValue::Set(None),
+            suspended_at: ActiveValue::Set(None),
         }
     }
 
-    // All following traits are tested in integration dataase tests
+    // All following traits are tested in integration dataase tests.
+    // Their spans leave out emails and models, which hold personal data and password hashes.
+    #[instrument(level = "debug", skip_all, err)]
     pub async fn find_one_by_email(
         email: &str,
         conn: &DatabaseConnection,
@@ -32,6 +127,7 @@ impl User {
             .await
     }
 
+    #[instrument(level = "debug", skip(conn), err)]
     pub async fn find_one_by_id(
         id: &Uuid,
         conn: &DatabaseConnection,
@@ -39,12 +135,117 @@ impl User {
         User::find_by_id(*id).one(conn).await
     }
 
+    #[instrument(level = "debug", skip_all, err)]
     pub async fn find_all(conn: &DatabaseConnection) -> Result<Vec<user::Model>, DbErr> {
         User::find().all(conn).await
     }
 
+    pub fn find_filtered(filter: &UserFilter) -> Select<User> {
+        let mut query = User::find();
+        if let Some(role) = &filter.role {
+            query = query.filter(user::Column::Role.eq(role.to_owned()));
+        }
+        // Enum values are only cast for equality, so each status is compared separately.
+        // An empty condition would be dropped, so no statuses uses an empty IN instead.
+        if let Some(statuses) = &filter.statuses {
+            query = match statuses.is_empty() {
+                true => query.filter(user::Column::Status.is_in(Vec::<Status>::new())),
+                false => query.filter(statuses.iter().fold(Condition::any(), |any, status| {
+                    any.add(user::Column::Status.eq(status.to_owned()))
+                })),
+            };
+        }
+        if let Some(search) = &filter.search {
+            let pattern = format!("%{}%", escape_like(&search.to_lowercase()));
+            let pattern = LikeExpr::new(pattern).escape('\\');
+            let mut condition =
+                Condition::any().add(lower(user::Column::Name).like(pattern.clone()));
+            if !filter.search_names_only {
+                condition = condition.add(lower(user::Column::Email).like(pattern));
+            }
+            query = query.filter(condition);
+        }
+        query
+    }
+
+    /// Rows are ordered by the requested column and then by id, so that rows with
+    /// equal values still have a stable position to continue after
+    pub fn find_page(
+        filter: &UserFilter,
+        order: &UserOrder,
+        after: Option<&UserCursor>,
+        limit: u64,
+    ) -> Select<User> {
+        let column = order.column();
+        let mut query = User::find_filtered(filter)
+            .order_by(column, order.order())
+            .order_by(user::Column::Id, order.order());
+        if let Some(after) = after {
+            let (beyond_value, beyond_id) = match order.direction {
+                SortDirection::Asc => (
+                    column.gt(after.value.as_str()),
+                    user::Column::Id.gt(after.id),
+                ),
+                SortDirection::Desc => (
+                    column.lt(after.value.as_str()),
+                    user::Column::Id.lt(after.id),
+                ),
+            };
+            query = query.filter(
+                Condition::any().add(beyond_value).add(
+                    Condition::all()
+                        .add(column.eq(after.value.as_str()))
+                        .add(beyond_id),
+                ),
+            );
+        }
+        query.limit(limit)
+    }
+
+    #[instrument(level = "debug", skip_all, err)]
+    pub async fn count_filtered(
+        filter: &UserFilter,
+        conn: &DatabaseConnection,
+    ) -> Result<u64, DbErr> {
+        User::find_filtered(filter).count(conn).await
+    }
+
+    /// Online users without a session that was seen after `seen_since`
+    #[instrument(level = "debug", skip(conn), err)]
+    pub async fn find_idle_online(
+        seen_since: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<Vec<user::Model>, DbErr> {
+        let recently_seen = Session::find()
+            .select_only()
+            .column(session::Column::UserId)
+            .filter(session::Column::RevokedAt.is_null())
+            .filter(session::Column::LastSeenAt.gt(seen_since))
+            .into_query();
+        User::find()
+            .filter(user::Column::Status.eq(Status::Online))
+            .filter(user::Column::Id.not_in_subquery(recently_seen))
+            .all(conn)
+            .await
+    }
+
+    /// Whether the user is the only Admin that has not been suspended. The Admins are
+    /// locked until the transaction ends, so a concurrent change to any of them waits
+    /// and then sees this transaction's changes.
+    #[instrument(level = "debug", skip(txn), err)]
+    pub async fn is_last_active_admin(id: &Uuid, txn: &DatabaseTransaction) -> Result<bool, DbErr> {
+        let admins = User::find()
+            .filter(user::Column::Role.eq(Role::Admin))
+            .filter(user::Column::SuspendedAt.is_null())
+            .lock_exclusive()
+            .all(txn)
+            .await?;
+        Ok(admins.len() <= 1 && admins.iter().any(|admin| &admin.id == id))
+    }
+
     // These methods are fairly simple but are their own methods
     // so that they can be more concisely tested
+    #[instrument(level = "debug", skip_all, err)]
     pub async fn insert_one(
         model: user::ActiveModel,
         conn: &DatabaseConnection,
@@ -52,16 +253,39 @@ impl User {
         user::Entity::insert(model).exec(conn).await
     }
 
+    #[instrument(level = "debug", skip_all, err)]
     pub async fn update_one(
         model: user::ActiveModel,
-        conn: &DatabaseConnection,
+        conn: &impl ConnectionTrait,
     ) -> Result<user::Model, DbErr> {
         User::update(model).exec(conn).await
     }
+
+    #[instrument(level = "debug", skip(conn), err)]
+    pub async fn delete_one(id: &Uuid, conn: &impl ConnectionTrait) -> Result<DeleteResult, DbErr> {
+        User::delete_by_id(*id).exec(conn).await
+    }
+}
+
+fn lower(column: user::Column) -> Expr {
+    Expr::expr(Func::lower(Expr::tbl(User, column)))
+}
+
+// The search is matched literally, so LIKE wildcards in it are escaped.
+// Not every backend treats backslash as an escape by default, so the
+// pattern has to be used with an explicit `ESCAPE '\'`.
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
 
 #[cfg(test)]
 mod test_user {
+    use sea_orm::{prelude::Uuid, DbBackend, QueryTrait};
+
+    use super::{escape_like, SortDirection, UserCursor, UserFilter, UserOrder, UserOrderField};
     use crate::{
         prelude::User,
         sea_orm_active_enums::{Role, Status},
@@ -76,8 +300,92 @@ mod test_user {
         assert_eq!(got.password.unwrap(), "testpassword");
         assert_eq!(got.role.unwrap(), Role::Guest);
         assert_eq!(got.status.unwrap(), Status::Online);
+        assert!(got.email_verified_at.unwrap().is_none());
+        assert!(got.suspended_at.unwrap().is_none());
 
         let id = got.id.unwrap();
         assert!(!id.is_nil());
     }
+
+    #[test]
+    fn filter_by_role_status_and_search() {
+        let filter = UserFilter {
+            role: Some(Role::Student),
+            statuses: Some(vec![Status::Online]),
+            search: Some("Ada_".to_string()),
+            search_names_only: false,
+        };
+        let got = User::find_filtered(&filter)
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(got.contains(r#""user"."role" = CAST('Student' AS role)"#));
+        assert!(got.contains(r#""user"."status" = CAST('Online' AS status)"#));
+        assert!(got.contains(
+            r#"(LOWER("user"."name") LIKE E'%ada\\_%' ESCAPE E'\\' OR LOWER("user"."email") LIKE E'%ada\\_%' ESCAPE E'\\')"#
+        ));
+    }
+
+    // SQLite has no default LIKE escape character, so it relies on the ESCAPE clause
+    #[test]
+    fn escape_search_on_sqlite() {
+        let filter = UserFilter {
+            search: Some("50%_off".to_string()),
+            search_names_only: true,
+            ..Default::default()
+        };
+        let got = User::find_filtered(&filter)
+            .build(DbBackend::Sqlite)
+            .to_string();
+
+        assert!(got.contains(r#"LOWER("user"."name") LIKE '%50\%\_off%' ESCAPE '\'"#));
+    }
+
+    #[test]
+    fn search_names_only() {
+        let filter = UserFilter {
+            statuses: Some(vec![]),
+            search: Some("ada".to_string()),
+            search_names_only: true,
+            ..Default::default()
+        };
+        let got = User::find_filtered(&filter)
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(got.contains("1 = 2"));
+        assert!(got.contains(r#"LOWER("user"."name") LIKE '%ada%'"#));
+        assert!(!got.contains("email\") LIKE"));
+    }
+
+    #[test]
+    fn page_continues_after_cursor() {
+        let id = Uuid::nil();
+        let cursor = UserCursor {
+            value: "Ben".to_string(),
+            id,
+        };
+        let order = UserOrder {
+            field: UserOrderField::Name,
+            direction: SortDirection::Desc,
+        };
+        let filter = UserFilter {
+            role: Some(Role::Student),
+            ..Default::default()
+        };
+        let got = User::find_page(&filter, &order, Some(&cursor), 3)
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(got.contains(&format!(
+            r#"AS role) AND ("user"."name" < 'Ben' OR ("user"."name" = 'Ben' AND "user"."id" < '{}'))"#,
+            id
+        )));
+        assert!(got.ends_with(r#"ORDER BY "user"."name" DESC, "user"."id" DESC LIMIT 3"#));
+    }
+
+    #[test]
+    fn escape_like_wildcards() {
+        assert_eq!(escape_like("50%_off\\"), "50\\%\\_off\\\\");
+    }
 }
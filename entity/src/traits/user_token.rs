@@ -0,0 +1,140 @@
+use sea_orm::{
+    prelude::Uuid, sea_query::Expr, ActiveValue, ColumnTrait, DatabaseConnection, DbErr,
+    EntityTrait, InsertResult, QueryFilter, UpdateResult,
+};
+
+use crate::{
+    prelude::UserToken,
+    sea_orm_active_enums::TokenPurpose,
+    user_token::{self, ActiveModel},
+};
+
+impl UserToken {
+    pub fn create_active_model(
+        user_id: &Uuid,
+        purpose: TokenPurpose,
+        token_hash: &str,
+        payload: Option<String>,
+        created_at: i64,
+        expires_at: i64,
+    ) -> ActiveModel {
+        ActiveModel {
+            id: ActiveValue::Set(Uuid::new_v4()),
+            user_id: ActiveValue::Set(user_id.to_owned()),
+            purpose: ActiveValue::Set(purpose),
+            token_hash: ActiveValue::Set(token_hash.to_string()),
+            payload: ActiveValue::Set(payload),
+            created_at: ActiveValue::Set(created_at),
+            expires_at: ActiveValue::Set(expires_at),
+            used_at: ActiveValue::Set(None),
+        }
+    }
+
+    // All following traits are tested in integration database tests
+    pub async fn find_one_by_hash(
+        token_hash: &str,
+        purpose: TokenPurpose,
+        conn: &DatabaseConnection,
+    ) -> Result<Option<user_token::Model>, DbErr> {
+        UserToken::find()
+            .filter(user_token::Column::TokenHash.eq(token_hash.to_string()))
+            .filter(user_token::Column::Purpose.eq(purpose))
+            .one(conn)
+            .await
+    }
+
+    pub async fn insert_one(
+        model: user_token::ActiveModel,
+        conn: &DatabaseConnection,
+    ) -> Result<InsertResult<user_token::ActiveModel>, DbErr> {
+        UserToken::insert(model).exec(conn).await
+    }
+
+    // Only an unused token is updated so the same token cannot be redeemed twice
+    pub async fn mark_used(
+        id: &Uuid,
+        now: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<UpdateResult, DbErr> {
+        UserToken::update_many()
+            .col_expr(user_token::Column::UsedAt, Expr::value(now))
+            .filter(user_token::Column::Id.eq(id.to_owned()))
+            .filter(user_token::Column::UsedAt.is_null())
+            .exec(conn)
+            .await
+    }
+
+    /// Marks every outstanding token with the given purpose as used so that only a
+    /// subsequently issued token can be redeemed
+    pub async fn invalidate_for_user(
+        user_id: &Uuid,
+        purpose: TokenPurpose,
+        now: i64,
+        conn: &DatabaseConnection,
+    ) -> Result<UpdateResult, DbErr> {
+        UserToken::update_many()
+            .col_expr(user_token::Column::UsedAt, Expr::value(now))
+            .filter(user_token::Column::UserId.eq(user_id.to_owned()))
+            .filter(user_token::Column::Purpose.eq(purpose))
+            .filter(user_token::Column::UsedAt.is_null())
+            .exec(conn)
+            .await
+    }
+}
+
+impl user_token::Model {
+    pub fn is_redeemable(&self, now: i64) -> bool {
+        self.used_at.is_none() && self.expires_at > now
+    }
+}
+
+#[cfg(test)]
+mod test_user_token {
+    use sea_orm::prelude::Uuid;
+
+    use crate::{prelude::UserToken, sea_orm_active_enums::TokenPurpose, user_token};
+
+    #[test]
+    fn create_model_from_data() {
+        let user_id = Uuid::new_v4();
+        let got = UserToken::create_active_model(
+            &user_id,
+            TokenPurpose::PasswordReset,
+            "hash",
+            Some("payload".to_string()),
+            100,
+            200,
+        );
+
+        assert_eq!(got.user_id.unwrap(), user_id);
+        assert_eq!(got.purpose.unwrap(), TokenPurpose::PasswordReset);
+        assert_eq!(got.token_hash.unwrap(), "hash");
+        assert_eq!(got.payload.unwrap(), Some("payload".to_string()));
+        assert_eq!(got.created_at.unwrap(), 100);
+        assert_eq!(got.expires_at.unwrap(), 200);
+        assert!(got.used_at.unwrap().is_none());
+        assert!(!got.id.unwrap().is_nil());
+    }
+
+    #[test]
+    fn only_unused_unexpired_tokens_are_redeemable() {
+        let model = user_token::Model {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            purpose: TokenPurpose::PasswordReset,
+            token_hash: "hash".to_string(),
+            payload: None,
+            created_at: 100,
+            expires_at: 200,
+            used_at: None,
+        };
+        assert!(model.is_redeemable(199));
+        assert!(!model.is_redeemable(200));
+
+        let model = user_token::Model {
+            used_at: Some(150),
+            ..model
+        };
+        assert!(!model.is_redeemable(160));
+    }
+}
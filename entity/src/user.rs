@@ -14,9 +14,60 @@ pub struct Model {
     pub password: String,
     pub status: Status,
     pub role: Role,
+    pub email_verified_at: Option<i64>,
+    pub suspended_at: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::linked_identity::Entity")]
+    LinkedIdentity,
+    #[sea_orm(has_many = "super::recovery_code::Entity")]
+    RecoveryCode,
+    #[sea_orm(has_many = "super::refresh_token::Entity")]
+    RefreshToken,
+    #[sea_orm(has_many = "super::session::Entity")]
+    Session,
+    #[sea_orm(has_one = "super::totp_secret::Entity")]
+    TotpSecret,
+    #[sea_orm(has_many = "super::user_token::Entity")]
+    UserToken,
+}
+
+impl Related<super::linked_identity::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LinkedIdentity.def()
+    }
+}
+
+impl Related<super::recovery_code::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecoveryCode.def()
+    }
+}
+
+impl Related<super::refresh_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RefreshToken.def()
+    }
+}
+
+impl Related<super::session::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Session.def()
+    }
+}
+
+impl Related<super::totp_secret::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TotpSecret.def()
+    }
+}
+
+impl Related<super::user_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserToken.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
@@ -1,12 +1,40 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20230115_000001_create_refresh_token_table;
+mod m20230122_000001_create_token_revocation_table;
+mod m20230129_000001_create_user_token_table;
+mod m20230205_000001_add_user_email_verified_at;
+mod m20230212_000001_create_login_attempt_table;
+mod m20230219_000001_create_totp_secret_table;
+mod m20230226_000001_create_recovery_code_table;
+mod m20230305_000001_create_linked_identity_table;
+mod m20230312_000001_create_oidc_login_table;
+mod m20230319_000001_create_role_permission_table;
+mod m20230326_000001_add_user_suspended_at;
+mod m20230402_000001_create_session_table;
+mod m20230409_000001_add_token_revocation_session_id;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20230115_000001_create_refresh_token_table::Migration),
+            Box::new(m20230122_000001_create_token_revocation_table::Migration),
+            Box::new(m20230129_000001_create_user_token_table::Migration),
+            Box::new(m20230205_000001_add_user_email_verified_at::Migration),
+            Box::new(m20230212_000001_create_login_attempt_table::Migration),
+            Box::new(m20230219_000001_create_totp_secret_table::Migration),
+            Box::new(m20230226_000001_create_recovery_code_table::Migration),
+            Box::new(m20230305_000001_create_linked_identity_table::Migration),
+            Box::new(m20230312_000001_create_oidc_login_table::Migration),
+            Box::new(m20230319_000001_create_role_permission_table::Migration),
+            Box::new(m20230326_000001_add_user_suspended_at::Migration),
+            Box::new(m20230402_000001_create_session_table::Migration),
+            Box::new(m20230409_000001_add_token_revocation_session_id::Migration),
+        ]
     }
 }
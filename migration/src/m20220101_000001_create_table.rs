@@ -1,4 +1,7 @@
-use sea_orm_migration::prelude::{sea_query::extension::postgres::Type, *};
+use sea_orm_migration::{
+    prelude::{sea_query::extension::postgres::Type, *},
+    sea_orm::DbBackend,
+};
 
 #[derive(DeriveMigrationName)]
 pub struct Migration;
@@ -6,23 +9,26 @@ pub struct Migration;
 #[async_trait::async_trait]
 impl MigrationTrait for Migration {
     async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        manager
-            .create_type(
-                Type::create()
-                    .as_enum(Role::Table)
-                    .values([Role::Guest, Role::Student, Role::Teacher, Role::Admin])
-                    .to_owned(),
-            )
-            .await?;
+        let native_enums = has_native_enums(manager);
+        if native_enums {
+            manager
+                .create_type(
+                    Type::create()
+                        .as_enum(Role::Table)
+                        .values([Role::Guest, Role::Student, Role::Teacher, Role::Admin])
+                        .to_owned(),
+                )
+                .await?;
 
-        manager
-            .create_type(
-                Type::create()
-                    .as_enum(Status::Table)
-                    .values([Status::Online, Status::Offline, Status::Hidden])
-                    .to_owned(),
-            )
-            .await?;
+            manager
+                .create_type(
+                    Type::create()
+                        .as_enum(Status::Table)
+                        .values([Status::Online, Status::Offline, Status::Hidden])
+                        .to_owned(),
+                )
+                .await?;
+        }
         manager
             .create_table(
                 Table::create()
@@ -38,30 +44,43 @@ impl MigrationTrait for Migration {
                     .col(ColumnDef::new(User::Name).string().not_null())
                     .col(ColumnDef::new(User::Email).string().unique_key().not_null())
                     .col(ColumnDef::new(User::Password).string().not_null())
-                    .col(
-                        ColumnDef::new(User::Status)
-                            .custom(Status::Table)
-                            .not_null(),
-                    )
-                    .col(ColumnDef::new(User::Role).custom(Role::Table).not_null())
+                    .col(enum_column(User::Status, Status::Table, native_enums).not_null())
+                    .col(enum_column(User::Role, Role::Table, native_enums).not_null())
                     .to_owned(),
             )
             .await
     }
 
     async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        manager
-            .drop_type(Type::drop().name(Status::Table).to_owned())
-            .await?;
-        manager
-            .drop_type(Type::drop().name(Role::Table).to_owned())
-            .await?;
         manager
             .drop_table(Table::drop().table(User::Table).to_owned())
-            .await
+            .await?;
+        if has_native_enums(manager) {
+            manager
+                .drop_type(Type::drop().name(Status::Table).to_owned())
+                .await?;
+            manager
+                .drop_type(Type::drop().name(Role::Table).to_owned())
+                .await?;
+        }
+        Ok(())
     }
 }
 
+// Only Postgres has enum types, elsewhere the variants are stored as text
+fn has_native_enums(manager: &SchemaManager) -> bool {
+    manager.get_database_backend() == DbBackend::Postgres
+}
+
+fn enum_column(column: User, enum_type: impl Iden + 'static, native: bool) -> ColumnDef {
+    let mut def = ColumnDef::new(column);
+    match native {
+        true => def.custom(enum_type),
+        false => def.string(),
+    };
+    def
+}
+
 #[derive(Iden)]
 pub enum User {
     Table,
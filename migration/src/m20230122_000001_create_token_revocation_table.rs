@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Revocations are not tied to the user by a foreign key, as they have to
+// outlive a deleted user so that other instances keep rejecting its tokens
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TokenRevocation::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TokenRevocation::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(TokenRevocation::UserId).uuid().not_null())
+                    .col(ColumnDef::new(TokenRevocation::Jti).uuid())
+                    // In milliseconds, so that tokens issued within the same
+                    // second as a revocation can be told apart
+                    .col(
+                        ColumnDef::new(TokenRevocation::RevokedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TokenRevocation::ExpiresAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-token_revocation-expires_at")
+                    .table(TokenRevocation::Table)
+                    .col(TokenRevocation::ExpiresAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TokenRevocation::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum TokenRevocation {
+    Table,
+    Id,
+    UserId,
+    Jti,
+    RevokedAt,
+    ExpiresAt,
+}
@@ -0,0 +1,76 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserToken::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UserToken::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(UserToken::UserId).uuid().not_null())
+                    .col(ColumnDef::new(UserToken::Purpose).string_len(32).not_null())
+                    .col(
+                        ColumnDef::new(UserToken::TokenHash)
+                            .string()
+                            .unique_key()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(UserToken::Payload).string())
+                    .col(
+                        ColumnDef::new(UserToken::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UserToken::ExpiresAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(UserToken::UsedAt).big_integer())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-user_token-user_id")
+                            .from(UserToken::Table, UserToken::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UserToken::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum UserToken {
+    Table,
+    Id,
+    UserId,
+    Purpose,
+    TokenHash,
+    Payload,
+    CreatedAt,
+    ExpiresAt,
+    UsedAt,
+}
+
+#[derive(Iden)]
+pub enum User {
+    Table,
+    Id,
+}
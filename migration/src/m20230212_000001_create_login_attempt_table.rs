@@ -0,0 +1,61 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(LoginAttempt::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(LoginAttempt::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(LoginAttempt::Scope)
+                            .string_len(16)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(LoginAttempt::Subject).string().not_null())
+                    .col(ColumnDef::new(LoginAttempt::Failures).integer().not_null())
+                    .col(
+                        ColumnDef::new(LoginAttempt::LastFailedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(LoginAttempt::LockedUntil).big_integer())
+                    .index(
+                        Index::create()
+                            .name("idx-login_attempt-scope-subject")
+                            .col(LoginAttempt::Scope)
+                            .col(LoginAttempt::Subject)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LoginAttempt::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum LoginAttempt {
+    Table,
+    Id,
+    Scope,
+    Subject,
+    Failures,
+    LastFailedAt,
+    LockedUntil,
+}
@@ -0,0 +1,68 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TotpSecret::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TotpSecret::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(TotpSecret::UserId)
+                            .uuid()
+                            .unique_key()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(TotpSecret::Secret).string().not_null())
+                    .col(
+                        ColumnDef::new(TotpSecret::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(TotpSecret::ConfirmedAt).big_integer())
+                    .col(ColumnDef::new(TotpSecret::LastUsedStep).big_integer())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-totp_secret-user_id")
+                            .from(TotpSecret::Table, TotpSecret::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TotpSecret::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum TotpSecret {
+    Table,
+    Id,
+    UserId,
+    Secret,
+    CreatedAt,
+    ConfirmedAt,
+    LastUsedStep,
+}
+
+#[derive(Iden)]
+pub enum User {
+    Table,
+    Id,
+}
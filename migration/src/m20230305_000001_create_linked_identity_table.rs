@@ -0,0 +1,68 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(LinkedIdentity::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(LinkedIdentity::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(LinkedIdentity::UserId).uuid().not_null())
+                    .col(ColumnDef::new(LinkedIdentity::Issuer).string().not_null())
+                    .col(ColumnDef::new(LinkedIdentity::Subject).string().not_null())
+                    .col(
+                        ColumnDef::new(LinkedIdentity::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx-linked_identity-issuer-subject")
+                            .col(LinkedIdentity::Issuer)
+                            .col(LinkedIdentity::Subject)
+                            .unique(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-linked_identity-user_id")
+                            .from(LinkedIdentity::Table, LinkedIdentity::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LinkedIdentity::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum LinkedIdentity {
+    Table,
+    Id,
+    UserId,
+    Issuer,
+    Subject,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+pub enum User {
+    Table,
+    Id,
+}
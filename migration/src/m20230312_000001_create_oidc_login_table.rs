@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(OidcLogin::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OidcLogin::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(OidcLogin::StateHash)
+                            .string()
+                            .unique_key()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OidcLogin::Nonce).string().not_null())
+                    .col(ColumnDef::new(OidcLogin::CodeVerifier).string().not_null())
+                    .col(
+                        ColumnDef::new(OidcLogin::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OidcLogin::ExpiresAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OidcLogin::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum OidcLogin {
+    Table,
+    Id,
+    StateHash,
+    Nonce,
+    CodeVerifier,
+    CreatedAt,
+    ExpiresAt,
+}
@@ -0,0 +1,93 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Roles are seeded with the permissions the previous linear role ordering implied
+const DEFAULT_PERMISSIONS: &[(&str, &[&str])] = &[
+    ("Guest", &["courses:read"]),
+    ("Student", &["courses:read", "grades:read"]),
+    (
+        "Teacher",
+        &[
+            "courses:read",
+            "courses:write",
+            "grades:read",
+            "grades:publish",
+            "users:read",
+        ],
+    ),
+    (
+        "Admin",
+        &[
+            "courses:read",
+            "courses:write",
+            "grades:read",
+            "grades:publish",
+            "users:read",
+            "users:manage",
+            "permissions:manage",
+        ],
+    ),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RolePermission::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RolePermission::Role)
+                            .custom(Role::Table)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RolePermission::Permission)
+                            .string_len(64)
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(RolePermission::Role)
+                            .col(RolePermission::Permission),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        let mut insert = Query::insert();
+        insert
+            .into_table(RolePermission::Table)
+            .columns([RolePermission::Role, RolePermission::Permission]);
+        for (role, permissions) in DEFAULT_PERMISSIONS {
+            for permission in permissions.iter() {
+                insert.values_panic([
+                    Expr::val(*role).as_enum(Role::Table),
+                    Expr::val(*permission).into(),
+                ]);
+            }
+        }
+        manager.exec_stmt(insert).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RolePermission::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum RolePermission {
+    Table,
+    Role,
+    Permission,
+}
+
+#[derive(Iden)]
+pub enum Role {
+    Table,
+}
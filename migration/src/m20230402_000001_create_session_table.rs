@@ -0,0 +1,75 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Session::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Session::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(Session::UserId).uuid().not_null())
+                    .col(ColumnDef::new(Session::DeviceLabel).string())
+                    .col(ColumnDef::new(Session::UserAgent).string())
+                    .col(ColumnDef::new(Session::IpAddress).string())
+                    .col(ColumnDef::new(Session::CreatedAt).big_integer().not_null())
+                    .col(ColumnDef::new(Session::LastSeenAt).big_integer().not_null())
+                    .col(ColumnDef::new(Session::RevokedAt).big_integer())
+                    .col(
+                        ColumnDef::new(Session::MfaAuthenticated)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-session-user_id")
+                            .from(Session::Table, Session::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-session-user_id")
+                    .table(Session::Table)
+                    .col(Session::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Session::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Session {
+    Table,
+    Id,
+    UserId,
+    DeviceLabel,
+    UserAgent,
+    IpAddress,
+    CreatedAt,
+    LastSeenAt,
+    RevokedAt,
+    MfaAuthenticated,
+}
+
+#[derive(Iden)]
+pub enum User {
+    Table,
+    Id,
+}
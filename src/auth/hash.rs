@@ -1,37 +1,188 @@
+use argon2::{
+    Argon2, Params as Argon2Params, Version, ARGON2D_IDENT, ARGON2ID_IDENT, ARGON2I_IDENT,
+};
 use pbkdf2::{
     password_hash::{
-        rand_core::OsRng, Error, PasswordHash, PasswordHasher, PasswordVerifier, SaltString,
+        errors::InvalidValue, rand_core::OsRng, Error, PasswordHash, PasswordHasher,
+        PasswordVerifier, SaltString,
     },
-    Pbkdf2,
+    Algorithm as Pbkdf2Algorithm, Params as Pbkdf2Params, Pbkdf2,
 };
 
+use crate::config::{config, PasswordConfig, PasswordHashAlgorithm};
+
+/// The algorithm and parameters new password hashes are created with.
+/// Hashes created with any supported algorithm can still be verified.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Argon2id {
+        memory: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+    Pbkdf2 {
+        rounds: u32,
+    },
+}
+
+impl Default for HashAlgorithm {
+    fn default() -> Self {
+        HashAlgorithm::Argon2id {
+            memory: Argon2Params::DEFAULT_M_COST,
+            iterations: Argon2Params::DEFAULT_T_COST,
+            parallelism: Argon2Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl HashAlgorithm {
+    pub fn from_config(config: &PasswordConfig) -> Self {
+        match config.algorithm {
+            PasswordHashAlgorithm::Argon2id => HashAlgorithm::Argon2id {
+                memory: config.argon2_memory_kib,
+                iterations: config.argon2_iterations,
+                parallelism: config.argon2_parallelism,
+            },
+            PasswordHashAlgorithm::Pbkdf2 => HashAlgorithm::Pbkdf2 {
+                rounds: config.pbkdf2_rounds,
+            },
+        }
+    }
+
+    /// Whether hashes can be created with the parameters
+    pub fn check(&self) -> Result<(), Error> {
+        match self {
+            HashAlgorithm::Argon2id {
+                memory,
+                iterations,
+                parallelism,
+            } => HashAlgorithm::argon2(*memory, *iterations, *parallelism).map(|_| ()),
+            HashAlgorithm::Pbkdf2 { rounds: 0 } => {
+                Err(Error::ParamValueInvalid(InvalidValue::TooShort))
+            }
+            HashAlgorithm::Pbkdf2 { .. } => Ok(()),
+        }
+    }
+
+    fn argon2(memory: u32, iterations: u32, parallelism: u32) -> Result<Argon2<'static>, Error> {
+        let params = Argon2Params::new(memory, iterations, parallelism, None)?;
+        Ok(Argon2::new(
+            argon2::Algorithm::Argon2id,
+            Version::V0x13,
+            params,
+        ))
+    }
+}
+
 pub fn hash(password: &str) -> Result<String, Error> {
+    hash_with(&HashAlgorithm::from_config(&config().password), password)
+}
+
+pub fn hash_with(algorithm: &HashAlgorithm, password: &str) -> Result<String, Error> {
     let password = password.as_bytes();
     let salt = SaltString::generate(&mut OsRng);
-    let hash = Pbkdf2.hash_password(password, &salt)?;
+    let hash = match algorithm {
+        HashAlgorithm::Argon2id {
+            memory,
+            iterations,
+            parallelism,
+        } => HashAlgorithm::argon2(*memory, *iterations, *parallelism)?
+            .hash_password(password, &salt)?,
+        HashAlgorithm::Pbkdf2 { rounds } => Pbkdf2.hash_password_customized(
+            password,
+            None,
+            None,
+            Pbkdf2Params {
+                rounds: *rounds,
+                ..Default::default()
+            },
+            &salt,
+        )?,
+    };
     Ok(hash.to_string())
 }
 
+// The PHC string prefix names the algorithm the hash was created with,
+// and the parameters stored alongside it are used to verify the password
 pub fn verify(password: &str, hash: &str) -> Result<(), Error> {
     let password = password.as_bytes();
     let parsed_hash = PasswordHash::new(hash)?;
-    Pbkdf2.verify_password(password, &parsed_hash)
+    let algorithm = parsed_hash.algorithm;
+    if algorithm == ARGON2ID_IDENT || algorithm == ARGON2I_IDENT || algorithm == ARGON2D_IDENT {
+        Argon2::default().verify_password(password, &parsed_hash)
+    } else if Pbkdf2Algorithm::try_from(algorithm).is_ok() {
+        Pbkdf2.verify_password(password, &parsed_hash)
+    } else {
+        Err(Error::Algorithm)
+    }
+}
+
+/// Whether the hash was created with a different algorithm or different parameters
+/// than the ones new hashes are created with
+pub fn needs_rehash(hash: &str, algorithm: &HashAlgorithm) -> bool {
+    let parsed_hash = match PasswordHash::new(hash) {
+        Ok(parsed) => parsed,
+        Err(_) => return true,
+    };
+
+    match algorithm {
+        HashAlgorithm::Argon2id {
+            memory,
+            iterations,
+            parallelism,
+        } => {
+            if parsed_hash.algorithm != ARGON2ID_IDENT {
+                return true;
+            }
+            match Argon2Params::try_from(&parsed_hash) {
+                Ok(params) => {
+                    params.m_cost() != *memory
+                        || params.t_cost() != *iterations
+                        || params.p_cost() != *parallelism
+                }
+                Err(_) => true,
+            }
+        }
+        HashAlgorithm::Pbkdf2 { rounds } => {
+            if parsed_hash.algorithm != Pbkdf2Algorithm::Pbkdf2Sha256.ident() {
+                return true;
+            }
+            match parsed_hash.params.get_decimal("i") {
+                Some(stored) => stored != *rounds,
+                None => true,
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use argon2::Argon2;
     use pbkdf2::{
         password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
         Pbkdf2,
     };
     use rand_core::OsRng;
 
-    use super::{hash, verify};
+    use super::{hash_with, needs_rehash, verify, HashAlgorithm};
 
     #[test]
     fn create_password_hash() {
-        let result = hash("testpassword").unwrap();
+        let result = hash_with(&HashAlgorithm::default(), "testpassword").unwrap();
         let parsed_hash = PasswordHash::new(&result).unwrap();
+        assert_eq!(parsed_hash.algorithm.as_str(), "argon2id");
+
+        let got = Argon2::default().verify_password("testpassword".as_bytes(), &parsed_hash);
+        assert!(got.is_ok());
+    }
+
+    #[test]
+    fn create_pbkdf2_password_hash() {
+        let result = hash_with(&HashAlgorithm::Pbkdf2 { rounds: 1000 }, "testpassword").unwrap();
+        let parsed_hash = PasswordHash::new(&result).unwrap();
+        assert_eq!(parsed_hash.algorithm.as_str(), "pbkdf2-sha256");
+        assert_eq!(parsed_hash.params.get_decimal("i"), Some(1000));
+
         let got = Pbkdf2.verify_password("testpassword".as_bytes(), &parsed_hash);
         assert!(got.is_ok());
     }
@@ -46,6 +197,14 @@ mod test {
         assert!(got.is_ok());
     }
 
+    #[test]
+    fn verify_argon2_match_returns_ok() {
+        let password_hash = hash_with(&HashAlgorithm::default(), "testpassword").unwrap();
+        let got = verify("testpassword", &password_hash);
+
+        assert!(got.is_ok());
+    }
+
     #[test]
     fn verify_mismatch_returns_error() {
         let password = "testpassword".as_bytes();
@@ -57,4 +216,64 @@ mod test {
         let err = got.err().unwrap().to_string();
         assert_eq!(err, "invalid password")
     }
+
+    #[test]
+    fn verify_argon2_mismatch_returns_error() {
+        let password_hash = hash_with(&HashAlgorithm::default(), "testpassword").unwrap();
+        let got = verify("differentpassword", &password_hash);
+
+        assert!(got.is_err());
+        let err = got.err().unwrap().to_string();
+        assert_eq!(err, "invalid password")
+    }
+
+    #[test]
+    fn verify_unknown_algorithm_returns_error() {
+        let got = verify("testpassword", "$scrypt$ln=16,r=8,p=1$c2FsdA$aGFzaA");
+        assert!(got.is_err());
+    }
+
+    #[test]
+    fn outdated_algorithm_needs_rehash() {
+        let password_hash = hash_with(&HashAlgorithm::Pbkdf2 { rounds: 1000 }, "test").unwrap();
+        assert!(needs_rehash(&password_hash, &HashAlgorithm::default()));
+
+        let password_hash = hash_with(&HashAlgorithm::default(), "test").unwrap();
+        assert!(needs_rehash(
+            &password_hash,
+            &HashAlgorithm::Pbkdf2 { rounds: 1000 }
+        ));
+    }
+
+    #[test]
+    fn outdated_params_need_rehash() {
+        let current = HashAlgorithm::Argon2id {
+            memory: 8192,
+            iterations: 3,
+            parallelism: 1,
+        };
+        let password_hash = hash_with(&HashAlgorithm::default(), "test").unwrap();
+        assert!(needs_rehash(&password_hash, &current));
+
+        let password_hash = hash_with(&HashAlgorithm::Pbkdf2 { rounds: 1000 }, "test").unwrap();
+        assert!(needs_rehash(
+            &password_hash,
+            &HashAlgorithm::Pbkdf2 { rounds: 2000 }
+        ));
+    }
+
+    #[test]
+    fn current_hash_does_not_need_rehash() {
+        let password_hash = hash_with(&HashAlgorithm::default(), "test").unwrap();
+        assert!(!needs_rehash(&password_hash, &HashAlgorithm::default()));
+
+        let algorithm = HashAlgorithm::Pbkdf2 { rounds: 1000 };
+        let password_hash = hash_with(&algorithm, "test").unwrap();
+        assert!(!needs_rehash(&password_hash, &algorithm));
+    }
+
+    #[test]
+    fn unparseable_hash_needs_rehash() {
+        assert!(needs_rehash("notahash", &HashAlgorithm::default()));
+    }
 }
@@ -1,50 +1,144 @@
 use std::str::FromStr;
 use std::time::Duration;
 
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use sea_orm::prelude::Uuid;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    auth::{keys::keys, permission::role_has_permission, revocation::is_revoked},
+    config::config,
     errors::{AuthorizationError, TimeError},
-    get_env,
-    time::{Time, HOUR_IN_SECONDS},
+    time::Time,
 };
-use entity::sea_orm_active_enums::Role;
-
-pub fn create_jwt(uid: &Uuid, role: &Role) -> Result<String, AuthorizationError> {
-    let binding = get_env("JWT_SECRET");
-    let secret = binding.as_bytes();
+use entity::{sea_orm_active_enums::Role, user};
+
+/// `mfa_authenticated` should only be set once the user has proven possession
+/// of their second factor
+pub fn create_jwt(
+    user: &user::Model,
+    session_id: &Uuid,
+    mfa_authenticated: bool,
+) -> Result<String, AuthorizationError> {
+    let mut claims = Claims::new(
+        &user.id,
+        &user.role,
+        user.is_email_verified(),
+        config().tokens.access_token_lifetime(),
+    )
+    .map_err(|e| AuthorizationError::EncodingError(e.to_string()))?;
+    claims.sid = Some(session_id.to_owned());
+    claims.mfa_authenticated = mfa_authenticated;
+    keys().encode(&claims)
+}
 
-    let claims = Claims::new(uid, role, Duration::from_secs(HOUR_IN_SECONDS.into()))
-        .map_err(|e| AuthorizationError::EncodingError(e.to_string()))?;
-    let header = Header::new(Algorithm::HS512);
-    encode(&header, &claims, &EncodingKey::from_secret(secret))
-        .map_err(|e| AuthorizationError::EncodingError(e.to_string()))
+/// A short-lived token that can only be exchanged for a full token
+/// by completing two-factor authentication
+pub fn create_mfa_pending_jwt(user: &user::Model) -> Result<String, AuthorizationError> {
+    let mut claims = Claims::new(
+        &user.id,
+        &user.role,
+        user.is_email_verified(),
+        config().tokens.mfa_pending_lifetime(),
+    )
+    .map_err(|e| AuthorizationError::EncodingError(e.to_string()))?;
+    claims.mfa_pending = true;
+    keys().encode(&claims)
 }
 
-#[allow(dead_code)]
-pub fn authorize(role: &Role, token: &str) -> Result<Uuid, AuthorizationError> {
+/// Grants access based on the permissions mapped to the role in the token
+pub fn authorize_permission(permission: &str, token: &str) -> Result<Uuid, AuthorizationError> {
     let claims = get_claims_from_token(token)?;
+    check_permission(
+        permission,
+        &claims,
+        config().jwt.require_email_verification,
+        &config().mfa.required_roles(),
+        role_has_permission,
+    )
+}
 
+/// Whether the claims may use the permission, after the same account checks
+/// as `authorize_permission`
+pub fn has_permission(permission: &str, claims: &Claims) -> bool {
+    check_permission(
+        permission,
+        claims,
+        config().jwt.require_email_verification,
+        &config().mfa.required_roles(),
+        role_has_permission,
+    )
+    .is_ok()
+}
+
+// Accounts that have not verified their email, when verification is required, or
+// that have not completed two-factor authentication, when their role requires it,
+// can only use the permissions that guests have
+pub(crate) fn check_permission(
+    permission: &str,
+    claims: &Claims,
+    require_email_verification: bool,
+    mfa_required_roles: &[Role],
+    has_permission: impl Fn(&str, &str) -> bool,
+) -> Result<Uuid, AuthorizationError> {
     let decoded_role = Role::from_str(&claims.role).unwrap_or(Role::Guest);
-    match decoded_role.meets_requirements(role) {
-        true => Ok(claims.sub),
-        false => Err(AuthorizationError::InsufficientPermission {
-            required: role.to_str(),
-            permission: decoded_role.to_str(),
-        }),
+    if !has_permission(&decoded_role.to_str(), permission) {
+        return Err(AuthorizationError::MissingPermission {
+            required: permission.to_string(),
+            role: decoded_role.to_str(),
+        });
+    }
+
+    check_account(
+        !has_permission(&Role::Guest.to_str(), permission),
+        &decoded_role,
+        claims,
+        require_email_verification,
+        mfa_required_roles,
+    )?;
+    Ok(claims.sub)
+}
+
+fn check_account(
+    elevated: bool,
+    decoded_role: &Role,
+    claims: &Claims,
+    require_email_verification: bool,
+    mfa_required_roles: &[Role],
+) -> Result<(), AuthorizationError> {
+    if require_email_verification && elevated && !claims.email_verified {
+        return Err(AuthorizationError::EmailNotVerified);
     }
+
+    if elevated && mfa_required_roles.contains(decoded_role) && !claims.mfa_authenticated {
+        return Err(AuthorizationError::MfaRequired);
+    }
+
+    Ok(())
 }
 
 pub fn get_claims_from_token(token: &str) -> Result<Claims, AuthorizationError> {
-    let claims = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(get_env("JWT_SECRET").as_bytes()),
-        &Validation::new(Algorithm::HS512),
-    )
-    .map_err(|e| AuthorizationError::DecodingError(e.to_string()))
-    .map(|decoded| decoded.claims)?;
+    let claims = decode_claims(token)?;
+    if claims.mfa_pending {
+        return Err(AuthorizationError::MfaPending);
+    }
+    Ok(claims)
+}
+
+/// Only accepts tokens created by `create_mfa_pending_jwt`
+pub fn get_mfa_pending_claims(token: &str) -> Result<Claims, AuthorizationError> {
+    let claims = decode_claims(token)?;
+    if !claims.mfa_pending {
+        return Err(AuthorizationError::MfaNotPending);
+    }
+    Ok(claims)
+}
+
+fn decode_claims(token: &str) -> Result<Claims, AuthorizationError> {
+    let claims = keys().decode(token)?;
+
+    if is_revoked(&claims) {
+        return Err(AuthorizationError::TokenRevoked);
+    }
 
     Ok(claims)
 }
@@ -52,40 +146,75 @@ pub fn get_claims_from_token(token: &str) -> Result<Claims, AuthorizationError>
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Claims {
     pub sub: Uuid,
+    // Tokens issued before sessions were tracked have no session
+    #[serde(default)]
+    pub sid: Option<Uuid>,
     pub role: String,
     pub exp: u64,
+    pub iat: u64,
+    // `iat` in milliseconds, so that a token issued within the same second as a
+    // revocation can be told apart. Missing from tokens issued before it was added.
+    #[serde(default)]
+    pub iat_ms: u64,
+    pub jti: Uuid,
+    #[serde(default)]
+    pub email_verified: bool,
+    #[serde(default)]
+    pub mfa_pending: bool,
+    #[serde(default)]
+    pub mfa_authenticated: bool,
 }
 
 impl Claims {
-    pub fn new(sub: &Uuid, role: &Role, exp: Duration) -> Result<Claims, TimeError> {
+    pub fn new(
+        sub: &Uuid,
+        role: &Role,
+        email_verified: bool,
+        exp: Duration,
+    ) -> Result<Claims, TimeError> {
+        let issued = Time::now()?;
         let expiration = Time::now_plus_duration(exp)?;
         let claim = Claims {
             sub: sub.to_owned(),
+            sid: None,
             role: role.to_str(),
             exp: expiration.as_secs(),
+            iat: issued.as_secs(),
+            iat_ms: issued.as_millis() as u64,
+            jti: Uuid::new_v4(),
+            email_verified,
+            mfa_pending: false,
+            mfa_authenticated: false,
         };
         Ok(claim)
     }
+
+    /// When the token was issued, in milliseconds since the epoch
+    pub fn issued_at_millis(&self) -> u64 {
+        match self.iat_ms {
+            0 => self.iat * 1000,
+            iat_ms => iat_ms,
+        }
+    }
 }
 
 #[cfg(test)]
 mod test_create_jwt {
-    use std::env;
     use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-    use dotenvy::dotenv;
     use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
     use sea_orm::prelude::Uuid;
 
     use crate::time::HOUR_IN_SECONDS;
 
     use super::{create_jwt, Claims};
-    use entity::sea_orm_active_enums::Role;
+    use entity::{
+        sea_orm_active_enums::{Role, Status},
+        user,
+    };
 
     #[test]
     fn create_jwt_success() {
-        dotenv().ok();
-        env::set_var("JWT_SECRET", "jwtsecret");
         let id = Uuid::new_v4();
         let exp = SystemTime::now()
             .checked_add(Duration::from_secs(HOUR_IN_SECONDS.into()))
@@ -93,7 +222,18 @@ mod test_create_jwt {
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs();
-        let res = create_jwt(&id, &Role::Student).unwrap();
+        let user = user::Model {
+            id,
+            name: "test user".to_string(),
+            email: "test@test.com".to_string(),
+            password: "passwordhash".to_string(),
+            status: Status::Online,
+            role: Role::Student,
+            email_verified_at: Some(100),
+            suspended_at: None,
+        };
+        let session_id = Uuid::new_v4();
+        let res = create_jwt(&user, &session_id, false).unwrap();
 
         let claim = decode::<Claims>(
             &res,
@@ -104,89 +244,63 @@ mod test_create_jwt {
         .claims;
 
         assert_eq!(claim.sub, id);
+        assert_eq!(claim.sid, Some(session_id));
         assert_eq!(claim.role, "Student");
         assert_eq!(claim.exp, exp);
+        assert!(claim.email_verified);
     }
 }
 
 #[cfg(test)]
-mod test_authorize {
-    use std::env;
-    use std::time::Duration;
-
-    use dotenvy::dotenv;
+mod test_authorize_permission {
     use sea_orm::prelude::Uuid;
 
-    use super::authorize;
+    use super::authorize_permission;
     use crate::{
+        auth::permission::{COURSES_READ, GRADES_READ, USERS_MANAGE},
         testutils::create_test_jwt,
-        time::{Time, HOUR_IN_SECONDS},
+        time::Time,
     };
     use entity::sea_orm_active_enums::Role;
 
     #[test]
     fn authorization_success() {
-        dotenv().ok();
-        env::set_var("JWT_SECRET", "jwtsecret");
         let id = Uuid::new_v4();
-        let token = create_test_jwt(
-            &id,
-            &Role::Admin,
-            Time::now_plus_duration(Duration::from_secs(HOUR_IN_SECONDS.into()))
-                .unwrap()
-                .as_secs(),
-        );
-        let got = authorize(&Role::Guest, &token).unwrap();
+        let token = create_test_jwt(&id, &Role::Admin, Time::hour_hence().unwrap().as_secs());
+        let got = authorize_permission(COURSES_READ, &token).unwrap();
 
         assert_eq!(got, id);
     }
 
     #[test]
-    fn authorization_success_on_role_tie() {
-        dotenv().ok();
-        env::set_var("JWT_SECRET", "jwtsecret");
+    fn authorization_success_on_granted_permission() {
         let id = Uuid::new_v4();
-        let token = create_test_jwt(
-            &id,
-            &Role::Student,
-            Time::now_plus_duration(Duration::from_secs(HOUR_IN_SECONDS.into()))
-                .unwrap()
-                .as_secs(),
-        );
-        let got = authorize(&Role::Student, &token).unwrap();
+        let token = create_test_jwt(&id, &Role::Student, Time::hour_hence().unwrap().as_secs());
+        let got = authorize_permission(GRADES_READ, &token).unwrap();
 
         assert_eq!(got, id);
     }
 
     #[test]
-    fn authorization_failure_on_role_too_low() {
-        dotenv().ok();
-        env::set_var("JWT_SECRET", "jwtsecret");
+    fn authorization_failure_on_missing_permission() {
         let id = Uuid::new_v4();
-        let token = create_test_jwt(
-            &id,
-            &Role::Guest,
-            Time::now_plus_duration(Duration::from_secs(HOUR_IN_SECONDS.into()))
-                .unwrap()
-                .as_secs(),
-        );
-        let got = authorize(&Role::Admin, &token);
+        let token = create_test_jwt(&id, &Role::Guest, Time::hour_hence().unwrap().as_secs());
+        let got = authorize_permission(USERS_MANAGE, &token);
 
         assert!(got.is_err());
 
         let err = got.err().unwrap().to_string();
         assert_eq!(
             err,
-            "Required permission is Admin but user has permission Guest"
+            "Permission `users:manage` is not granted to role Guest"
         );
     }
 
     #[test]
-    fn authorization_faiure_on_token_expired() {
-        env::set_var("JWT_SECRET", "jwtsecret");
+    fn authorization_failure_on_token_expired() {
         let id = Uuid::new_v4();
-        let token = create_test_jwt(&id, &Role::Guest, 100);
-        let got = authorize(&Role::Admin, &token);
+        let token = create_test_jwt(&id, &Role::Admin, 100);
+        let got = authorize_permission(COURSES_READ, &token);
 
         assert!(got.is_err());
 
@@ -196,16 +310,123 @@ mod test_authorize {
 }
 
 #[cfg(test)]
-mod test_claims_from_token {
-    use std::env;
+mod test_check_permission {
+    use sea_orm::prelude::Uuid;
 
-    use dotenvy::dotenv;
+    use super::{check_permission, Claims};
+    use crate::auth::permission::{PermissionCache, COURSES_READ, GRADES_PUBLISH, USERS_MANAGE};
     use entity::sea_orm_active_enums::Role;
+
+    fn claims(role: &Role, email_verified: bool) -> Claims {
+        Claims {
+            sub: Uuid::new_v4(),
+            sid: None,
+            role: role.to_str(),
+            exp: 3700,
+            iat: 100,
+            iat_ms: 100_000,
+            jti: Uuid::new_v4(),
+            email_verified,
+            mfa_pending: false,
+            mfa_authenticated: false,
+        }
+    }
+
+    fn defaults(role: &str, permission: &str) -> bool {
+        PermissionCache::default().grants(role, permission)
+    }
+
+    #[test]
+    fn granted_permission_passes() {
+        let claims = claims(&Role::Teacher, true);
+        let got = check_permission(GRADES_PUBLISH, &claims, false, &[], defaults);
+        assert_eq!(got.unwrap(), claims.sub);
+    }
+
+    #[test]
+    fn higher_role_does_not_imply_permission() {
+        let got = check_permission(
+            USERS_MANAGE,
+            &claims(&Role::Teacher, true),
+            false,
+            &[],
+            defaults,
+        );
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Permission `users:manage` is not granted to role Teacher"
+        );
+    }
+
+    #[test]
+    fn guest_permission_passes_unverified() {
+        let got = check_permission(
+            COURSES_READ,
+            &claims(&Role::Student, false),
+            true,
+            &[],
+            defaults,
+        );
+        assert!(got.is_ok());
+    }
+
+    #[test]
+    fn unverified_fails_elevated_permission_when_required() {
+        let got = check_permission(
+            USERS_MANAGE,
+            &claims(&Role::Admin, false),
+            true,
+            &[],
+            defaults,
+        );
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Email address must be verified"
+        );
+    }
+
+    #[test]
+    fn mfa_required_for_elevated_permission() {
+        let got = check_permission(
+            USERS_MANAGE,
+            &claims(&Role::Admin, true),
+            false,
+            &[Role::Admin],
+            defaults,
+        );
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Two-factor authentication is required for this account"
+        );
+
+        let got = check_permission(
+            COURSES_READ,
+            &claims(&Role::Admin, true),
+            false,
+            &[Role::Admin],
+            defaults,
+        );
+        assert!(got.is_ok());
+    }
+}
+
+#[cfg(test)]
+mod test_claims_from_token {
+    use entity::{
+        sea_orm_active_enums::{Role, Status},
+        token_revocation, user,
+    };
     use sea_orm::prelude::Uuid;
 
-    use crate::{testutils::create_test_jwt, time::Time};
+    use crate::{
+        auth::revocation::revoke_token,
+        testutils::{create_mock_conn, create_test_jwt},
+        time::Time,
+    };
 
-    use super::get_claims_from_token;
+    use super::{
+        create_jwt, create_mfa_pending_jwt, get_claims_from_token, get_mfa_pending_claims,
+    };
 
     #[test]
     fn fail_on_false_token() {
@@ -218,8 +439,6 @@ mod test_claims_from_token {
 
     #[test]
     fn succeed_on_valid_token() {
-        dotenv().ok();
-        env::set_var("JWT_SECRET", "jwtsecret");
         let id = Uuid::new_v4();
         let now_plus_hour = Time::hour_hence().unwrap().as_secs();
         let token = create_test_jwt(&id, &Role::Admin, now_plus_hour);
@@ -232,6 +451,59 @@ mod test_claims_from_token {
         assert_eq!(claims.sub, id);
         assert_eq!(claims.exp, now_plus_hour);
     }
+
+    #[tokio::test]
+    async fn fail_on_revoked_token() {
+        let id = Uuid::new_v4();
+        let now_plus_hour = Time::hour_hence().unwrap().as_secs();
+        let token = create_test_jwt(&id, &Role::Admin, now_plus_hour);
+        let claims = get_claims_from_token(&token).unwrap();
+
+        let connection = create_mock_conn(vec![vec![token_revocation::Model {
+            id: Uuid::new_v4(),
+            user_id: id,
+            jti: Some(claims.jti),
+            session_id: None,
+            revoked_at: 0,
+            expires_at: claims.exp as i64,
+        }]]);
+        revoke_token(&claims, &connection).await.unwrap();
+
+        let res = get_claims_from_token(&token);
+        assert!(res.is_err());
+        assert_eq!(res.err().unwrap().to_string(), "Token has been revoked");
+    }
+
+    #[test]
+    fn mfa_pending_token_is_only_accepted_for_mfa() {
+        let user = user::Model {
+            id: Uuid::new_v4(),
+            name: "test user".to_string(),
+            email: "test@test.com".to_string(),
+            password: "passwordhash".to_string(),
+            status: Status::Offline,
+            role: Role::Teacher,
+            email_verified_at: None,
+            suspended_at: None,
+        };
+
+        let pending = create_mfa_pending_jwt(&user).unwrap();
+        let res = get_claims_from_token(&pending);
+        assert_eq!(
+            res.err().unwrap().to_string(),
+            "Two-factor authentication must be completed"
+        );
+        let claims = get_mfa_pending_claims(&pending).unwrap();
+        assert_eq!(claims.sub, user.id);
+
+        let full = create_jwt(&user, &Uuid::new_v4(), true).unwrap();
+        let res = get_mfa_pending_claims(&full);
+        assert_eq!(
+            res.err().unwrap().to_string(),
+            "Token is not awaiting two-factor authentication"
+        );
+        assert!(get_claims_from_token(&full).unwrap().mfa_authenticated);
+    }
 }
 
 #[cfg(test)]
@@ -251,6 +523,7 @@ mod test_claims {
         let got = Claims::new(
             &id,
             &Role::Teacher,
+            false,
             Duration::from_secs(HOUR_IN_SECONDS.into()),
         )
         .unwrap();
@@ -265,5 +538,10 @@ mod test_claims {
         );
         assert_eq!(got.role, "Teacher");
         assert_eq!(got.sub, id);
+        assert_eq!(got.iat + 3600, got.exp);
+        assert!(!got.jti.is_nil());
+        assert!(!got.email_verified);
+        assert!(!got.mfa_pending);
+        assert!(!got.mfa_authenticated);
     }
 }
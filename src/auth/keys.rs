@@ -0,0 +1,464 @@
+use std::{fs, str::FromStr};
+
+use base64::URL_SAFE_NO_PAD;
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use once_cell::sync::OnceCell;
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair};
+use serde::{Deserialize, Serialize};
+use simple_asn1::{from_der, ASN1Block};
+
+use crate::{
+    auth::jwt::Claims,
+    config::{config, JwtConfig},
+    errors::{AuthorizationError, KeyError},
+};
+
+static KEYS: OnceCell<KeyManager> = OnceCell::new();
+
+/// Loads the keys from the configuration on first use so that
+/// configuration errors can be surfaced on startup
+pub fn load_keys() -> Result<&'static KeyManager, KeyError> {
+    KEYS.get_or_try_init(|| KeyManager::from_config(&config().jwt))
+}
+
+pub fn keys() -> &'static KeyManager {
+    load_keys().expect("Unable to load JWT signing keys")
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    #[serde(rename = "use")]
+    pub key_use: String,
+    pub alg: String,
+    pub kid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+pub struct JwtKey {
+    kid: Option<String>,
+    algorithm: Algorithm,
+    encoding: Option<EncodingKey>,
+    decoding: DecodingKey,
+    jwk: Option<Jwk>,
+}
+
+impl JwtKey {
+    /// A symmetric key without a `kid`. It is never published in the JWKS.
+    pub fn from_secret(secret: &[u8]) -> Self {
+        JwtKey {
+            kid: None,
+            algorithm: Algorithm::HS512,
+            encoding: Some(EncodingKey::from_secret(secret)),
+            decoding: DecodingKey::from_secret(secret),
+            jwk: None,
+        }
+    }
+
+    /// Accepts either a private key, which can sign and verify tokens,
+    /// or a public key, which can only verify tokens
+    pub fn from_pem(kid: &str, algorithm: Algorithm, pem: &[u8]) -> Result<Self, KeyError> {
+        let unreadable = |reason: String| KeyError::UnreadableKey {
+            kid: kid.to_string(),
+            reason,
+        };
+        let parsed = pem::parse(pem).map_err(|e| unreadable(e.to_string()))?;
+        let private = parsed.tag.ends_with("PRIVATE KEY");
+
+        let (jwk, decoding) = match algorithm {
+            Algorithm::RS256 => {
+                let (n, e) = match private {
+                    true => rsa_private_components(&parsed.tag, &parsed.contents),
+                    false => rsa_public_components(&parsed.tag, &parsed.contents),
+                }
+                .ok_or_else(|| unreadable("Not an RSA key".to_string()))?;
+                let n = base64::encode_config(n, URL_SAFE_NO_PAD);
+                let e = base64::encode_config(e, URL_SAFE_NO_PAD);
+                let decoding = DecodingKey::from_rsa_components(&n, &e)
+                    .map_err(|e| unreadable(e.to_string()))?;
+                let jwk = Jwk {
+                    kty: "RSA".to_string(),
+                    key_use: "sig".to_string(),
+                    alg: "RS256".to_string(),
+                    kid: kid.to_string(),
+                    n: Some(n),
+                    e: Some(e),
+                    crv: None,
+                    x: None,
+                };
+                (jwk, decoding)
+            }
+            Algorithm::EdDSA => {
+                let x = match private {
+                    true => ed25519_private_component(&parsed.contents),
+                    false => ed25519_public_component(&parsed.contents),
+                }
+                .ok_or_else(|| unreadable("Not an Ed25519 key".to_string()))?;
+                let x = base64::encode_config(x, URL_SAFE_NO_PAD);
+                let decoding =
+                    DecodingKey::from_ed_components(&x).map_err(|e| unreadable(e.to_string()))?;
+                let jwk = Jwk {
+                    kty: "OKP".to_string(),
+                    key_use: "sig".to_string(),
+                    alg: "EdDSA".to_string(),
+                    kid: kid.to_string(),
+                    n: None,
+                    e: None,
+                    crv: Some("Ed25519".to_string()),
+                    x: Some(x),
+                };
+                (jwk, decoding)
+            }
+            other => return Err(KeyError::UnsupportedAlgorithm(format!("{:?}", other))),
+        };
+
+        let encoding = match (private, algorithm) {
+            (false, _) => None,
+            (true, Algorithm::RS256) => Some(EncodingKey::from_rsa_pem(pem)),
+            (true, _) => Some(EncodingKey::from_ed_pem(pem)),
+        }
+        .transpose()
+        .map_err(|e| unreadable(e.to_string()))?;
+
+        Ok(JwtKey {
+            kid: Some(kid.to_string()),
+            algorithm,
+            encoding,
+            decoding,
+            jwk: Some(jwk),
+        })
+    }
+}
+
+/// Signs tokens with the active key and verifies them against any loaded key
+pub struct KeyManager {
+    keys: Vec<JwtKey>,
+    active: usize,
+}
+
+impl KeyManager {
+    pub fn new(keys: Vec<JwtKey>, active_kid: &str) -> Result<Self, KeyError> {
+        let active = keys
+            .iter()
+            .position(|key| key.kid.as_deref() == Some(active_kid))
+            .ok_or_else(|| KeyError::UnknownActiveKey(active_kid.to_string()))?;
+        if keys[active].encoding.is_none() {
+            return Err(KeyError::NoSigningKey(active_kid.to_string()));
+        }
+        Ok(KeyManager { keys, active })
+    }
+
+    pub fn from_secret(secret: &[u8]) -> Self {
+        KeyManager {
+            keys: vec![JwtKey::from_secret(secret)],
+            active: 0,
+        }
+    }
+
+    /// Loads the `kid:algorithm:path` entries in `keys`. `active_kid` selects the
+    /// signing key and defaults to the first entry.
+    /// Keys listed in `retired_kids` are neither loaded nor published.
+    /// Without `keys`, tokens are signed with `secret` using HS512.
+    pub fn from_config(jwt: &JwtConfig) -> Result<Self, KeyError> {
+        if jwt.keys.is_empty() {
+            return match &jwt.secret {
+                Some(secret) => Ok(KeyManager::from_secret(secret.as_bytes())),
+                None => Err(KeyError::NoKeys),
+            };
+        }
+
+        let mut keys = vec![];
+        for spec in &jwt.keys {
+            let mut parts = spec.splitn(3, ':');
+            let (kid, algorithm, path) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(kid), Some(algorithm), Some(path)) => (kid, algorithm, path),
+                _ => return Err(KeyError::InvalidSpecification(spec.to_string())),
+            };
+            if jwt.retired_kids.iter().any(|retired| retired == kid) {
+                continue;
+            }
+
+            let algorithm = Algorithm::from_str(algorithm)
+                .map_err(|_| KeyError::UnsupportedAlgorithm(algorithm.to_string()))?;
+            let pem = fs::read(path).map_err(|e| KeyError::UnreadableKey {
+                kid: kid.to_string(),
+                reason: e.to_string(),
+            })?;
+            keys.push(JwtKey::from_pem(kid, algorithm, &pem)?);
+        }
+
+        let active_kid = match &jwt.active_kid {
+            Some(kid) => kid.to_string(),
+            None => keys
+                .first()
+                .and_then(|key| key.kid.clone())
+                .unwrap_or_default(),
+        };
+        KeyManager::new(keys, &active_kid)
+    }
+
+    pub fn encode(&self, claims: &Claims) -> Result<String, AuthorizationError> {
+        let key = &self.keys[self.active];
+        let mut header = Header::new(key.algorithm);
+        header.kid = key.kid.clone();
+
+        let encoding = key
+            .encoding
+            .as_ref()
+            .ok_or_else(|| AuthorizationError::EncodingError("No signing key".to_string()))?;
+        encode(&header, claims, encoding)
+            .map_err(|e| AuthorizationError::EncodingError(e.to_string()))
+    }
+
+    // The key is matched on both the kid and the algorithm so a token
+    // cannot ask for a public key to be used as an HMAC secret
+    pub fn decode(&self, token: &str) -> Result<Claims, AuthorizationError> {
+        let header =
+            decode_header(token).map_err(|e| AuthorizationError::DecodingError(e.to_string()))?;
+        let key = self
+            .keys
+            .iter()
+            .find(|key| key.kid == header.kid && key.algorithm == header.alg)
+            .ok_or_else(|| AuthorizationError::DecodingError("Unknown signing key".to_string()))?;
+
+        decode::<Claims>(token, &key.decoding, &Validation::new(key.algorithm))
+            .map(|decoded| decoded.claims)
+            .map_err(|e| AuthorizationError::DecodingError(e.to_string()))
+    }
+
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().filter_map(|key| key.jwk.clone()).collect(),
+        }
+    }
+}
+
+fn rsa_private_components(tag: &str, der: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    let pair = match tag {
+        "RSA PRIVATE KEY" => RsaKeyPair::from_der(der),
+        _ => RsaKeyPair::from_pkcs8(der),
+    }
+    .ok()?;
+    let public = pair.public_key();
+    Some((
+        public.modulus().big_endian_without_leading_zero().to_vec(),
+        public.exponent().big_endian_without_leading_zero().to_vec(),
+    ))
+}
+
+fn rsa_public_components(tag: &str, der: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    let der = match tag {
+        "RSA PUBLIC KEY" => der.to_vec(),
+        _ => subject_public_key(der)?,
+    };
+    match from_der(&der).ok()?.first()? {
+        ASN1Block::Sequence(_, blocks) => match blocks.as_slice() {
+            [ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)] => {
+                Some((n.to_bytes_be().1, e.to_bytes_be().1))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn ed25519_private_component(der: &[u8]) -> Option<Vec<u8>> {
+    let pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der).ok()?;
+    Some(pair.public_key().as_ref().to_vec())
+}
+
+fn ed25519_public_component(der: &[u8]) -> Option<Vec<u8>> {
+    subject_public_key(der).filter(|key| key.len() == 32)
+}
+
+// The key bytes of a SubjectPublicKeyInfo structure
+fn subject_public_key(der: &[u8]) -> Option<Vec<u8>> {
+    match from_der(der).ok()?.first()? {
+        ASN1Block::Sequence(_, blocks) => match blocks.as_slice() {
+            [ASN1Block::Sequence(_, _), ASN1Block::BitString(_, _, key)] => Some(key.to_owned()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test_key_manager {
+    use std::time::Duration;
+
+    use jsonwebtoken::{decode_header, Algorithm};
+    use sea_orm::prelude::Uuid;
+
+    use super::{JwtKey, KeyManager};
+    use crate::auth::jwt::Claims;
+    use entity::sea_orm_active_enums::Role;
+
+    const RSA_PRIVATE: &[u8] = include_bytes!("testdata/rsa_private.pem");
+    const RSA_PUBLIC: &[u8] = include_bytes!("testdata/rsa_public.pem");
+    const ED25519_PRIVATE: &[u8] = include_bytes!("testdata/ed25519_private.pem");
+    const ED25519_PUBLIC: &[u8] = include_bytes!("testdata/ed25519_public.pem");
+
+    fn claims() -> Claims {
+        Claims::new(
+            &Uuid::new_v4(),
+            &Role::Student,
+            true,
+            Duration::from_secs(60),
+        )
+        .unwrap()
+    }
+
+    fn rsa_key() -> JwtKey {
+        JwtKey::from_pem("rsa", Algorithm::RS256, RSA_PRIVATE).unwrap()
+    }
+
+    fn ed25519_key() -> JwtKey {
+        JwtKey::from_pem("ed", Algorithm::EdDSA, ED25519_PRIVATE).unwrap()
+    }
+
+    #[test]
+    fn sign_and_verify_with_rsa() {
+        let manager = KeyManager::new(vec![rsa_key()], "rsa").unwrap();
+        let claims = claims();
+
+        let token = manager.encode(&claims).unwrap();
+        let header = decode_header(&token).unwrap();
+        assert_eq!(header.alg, Algorithm::RS256);
+        assert_eq!(header.kid, Some("rsa".to_string()));
+
+        let got = manager.decode(&token).unwrap();
+        assert_eq!(got.sub, claims.sub);
+        assert_eq!(got.jti, claims.jti);
+    }
+
+    #[test]
+    fn sign_and_verify_with_ed25519() {
+        let manager = KeyManager::new(vec![ed25519_key()], "ed").unwrap();
+        let claims = claims();
+
+        let token = manager.encode(&claims).unwrap();
+        let header = decode_header(&token).unwrap();
+        assert_eq!(header.alg, Algorithm::EdDSA);
+        assert_eq!(header.kid, Some("ed".to_string()));
+
+        let got = manager.decode(&token).unwrap();
+        assert_eq!(got.sub, claims.sub);
+    }
+
+    #[test]
+    fn verify_with_public_keys() {
+        let signer = KeyManager::new(vec![rsa_key(), ed25519_key()], "rsa").unwrap();
+        let rsa_token = signer.encode(&claims()).unwrap();
+        let signer = KeyManager::new(vec![rsa_key(), ed25519_key()], "ed").unwrap();
+        let ed_token = signer.encode(&claims()).unwrap();
+
+        let verifier = KeyManager {
+            keys: vec![
+                JwtKey::from_pem("rsa", Algorithm::RS256, RSA_PUBLIC).unwrap(),
+                JwtKey::from_pem("ed", Algorithm::EdDSA, ED25519_PUBLIC).unwrap(),
+            ],
+            active: 0,
+        };
+        assert!(verifier.decode(&rsa_token).is_ok());
+        assert!(verifier.decode(&ed_token).is_ok());
+    }
+
+    #[test]
+    fn verify_tokens_signed_by_previous_key() {
+        let previous = KeyManager::new(vec![rsa_key()], "rsa").unwrap();
+        let token = previous.encode(&claims()).unwrap();
+
+        let rotated = KeyManager::new(vec![ed25519_key(), rsa_key()], "ed").unwrap();
+        assert!(rotated.decode(&token).is_ok());
+
+        let retired = KeyManager::new(vec![ed25519_key()], "ed").unwrap();
+        let err = retired.decode(&token).unwrap_err();
+        assert_eq!(err.to_string(), "Unable to decode JWT: Unknown signing key");
+    }
+
+    #[test]
+    fn reject_token_without_kid() {
+        let token = KeyManager::from_secret(b"secret")
+            .encode(&claims())
+            .unwrap();
+
+        let manager = KeyManager::new(vec![rsa_key()], "rsa").unwrap();
+        assert!(manager.decode(&token).is_err());
+    }
+
+    #[test]
+    fn reject_token_with_mismatched_algorithm() {
+        let token = KeyManager::new(vec![rsa_key()], "rsa")
+            .unwrap()
+            .encode(&claims())
+            .unwrap();
+
+        let manager = KeyManager::new(
+            vec![JwtKey::from_pem("rsa", Algorithm::EdDSA, ED25519_PRIVATE).unwrap()],
+            "rsa",
+        )
+        .unwrap();
+        assert!(manager.decode(&token).is_err());
+    }
+
+    #[test]
+    fn active_key_must_be_able_to_sign() {
+        let public = JwtKey::from_pem("rsa", Algorithm::RS256, RSA_PUBLIC).unwrap();
+        let err = KeyManager::new(vec![public], "rsa").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Active key `rsa` has no private key to sign with"
+        );
+
+        let err = KeyManager::new(vec![rsa_key()], "other").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Active key `other` is not configured or has been retired"
+        );
+    }
+
+    #[test]
+    fn reject_unsupported_algorithm() {
+        let got = JwtKey::from_pem("rsa", Algorithm::ES256, RSA_PRIVATE);
+        assert!(got.is_err());
+    }
+
+    #[test]
+    fn jwks_publishes_only_asymmetric_keys() {
+        let manager = KeyManager::new(
+            vec![rsa_key(), ed25519_key(), JwtKey::from_secret(b"secret")],
+            "rsa",
+        )
+        .unwrap();
+        let jwks = manager.jwks();
+        assert_eq!(jwks.keys.len(), 2);
+
+        let rsa = &jwks.keys[0];
+        assert_eq!(rsa.kty, "RSA");
+        assert_eq!(rsa.kid, "rsa");
+        assert_eq!(rsa.e, Some("AQAB".to_string()));
+        assert!(rsa.n.is_some());
+
+        let ed = &jwks.keys[1];
+        assert_eq!(ed.kty, "OKP");
+        assert_eq!(ed.crv, Some("Ed25519".to_string()));
+        assert_eq!(ed.x.as_ref().unwrap().len(), 43);
+
+        let public = JwtKey::from_pem("ed", Algorithm::EdDSA, ED25519_PUBLIC).unwrap();
+        assert_eq!(public.jwk.unwrap().x, ed.x);
+    }
+}
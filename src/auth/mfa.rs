@@ -0,0 +1,63 @@
+use sea_orm::{prelude::Uuid, DatabaseConnection};
+
+use crate::{
+    auth::{
+        token::hash_token,
+        totp::{generate_recovery_codes, verify_code},
+    },
+    errors::ApiResult,
+    time::Time,
+};
+use entity::prelude::{RecoveryCode, TotpSecret};
+
+pub async fn has_confirmed_mfa(user_id: &Uuid, conn: &DatabaseConnection) -> ApiResult<bool> {
+    let secret = TotpSecret::find_one_by_user(user_id, conn).await?;
+    Ok(secret.map(|secret| secret.is_confirmed()).unwrap_or(false))
+}
+
+/// Replaces any previous recovery codes, returning the new codes in plain text.
+/// Only their hashes are persisted.
+pub async fn issue_recovery_codes(
+    user_id: &Uuid,
+    conn: &DatabaseConnection,
+) -> ApiResult<Vec<String>> {
+    let now = Time::now()?.as_secs() as i64;
+    RecoveryCode::delete_for_user(user_id, conn).await?;
+
+    let codes = generate_recovery_codes();
+    let models = codes
+        .iter()
+        .map(|code| RecoveryCode::create_active_model(user_id, &hash_token(code), now))
+        .collect();
+    RecoveryCode::insert_all(models, conn).await?;
+
+    Ok(codes)
+}
+
+/// Accepts either a current TOTP code or an unused recovery code.
+/// Each code can only be used once.
+pub async fn verify_mfa_code(
+    user_id: &Uuid,
+    code: &str,
+    conn: &DatabaseConnection,
+) -> ApiResult<bool> {
+    let secret = match TotpSecret::find_one_by_user(user_id, conn).await? {
+        Some(secret) if secret.is_confirmed() => secret,
+        _ => return Ok(false),
+    };
+
+    let now = Time::now()?;
+    if let Some(step) = verify_code(&secret.secret, code, now.as_secs()) {
+        let updated = TotpSecret::mark_step_used(&secret.id, step, conn).await?;
+        return Ok(updated.rows_affected == 1);
+    }
+
+    let code_hash = hash_token(&code.trim().to_lowercase());
+    match RecoveryCode::find_unused(user_id, &code_hash, conn).await? {
+        Some(recovery) => {
+            let updated = RecoveryCode::mark_used(&recovery.id, now.as_secs() as i64, conn).await?;
+            Ok(updated.rows_affected == 1)
+        }
+        None => Ok(false),
+    }
+}
@@ -1,2 +1,11 @@
 pub mod hash;
 pub mod jwt;
+pub mod keys;
+pub mod mfa;
+pub mod permission;
+pub mod revocation;
+pub mod session;
+pub mod throttle;
+pub mod token;
+pub mod totp;
+pub mod user_token;
@@ -0,0 +1,183 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+use sea_orm::{DatabaseConnection, DbErr};
+
+use crate::errors::{ApiResult, AuthorizationError};
+use entity::{prelude::RolePermission, role_permission, sea_orm_active_enums::Role};
+
+pub const COURSES_READ: &str = "courses:read";
+pub const COURSES_WRITE: &str = "courses:write";
+pub const GRADES_READ: &str = "grades:read";
+pub const GRADES_PUBLISH: &str = "grades:publish";
+pub const USERS_READ: &str = "users:read";
+pub const USERS_MANAGE: &str = "users:manage";
+pub const PERMISSIONS_MANAGE: &str = "permissions:manage";
+
+pub const ALL_PERMISSIONS: &[&str] = &[
+    COURSES_READ,
+    COURSES_WRITE,
+    GRADES_READ,
+    GRADES_PUBLISH,
+    USERS_READ,
+    USERS_MANAGE,
+    PERMISSIONS_MANAGE,
+];
+
+/// The mappings seeded by the `role_permission` migration, used until
+/// the table has been loaded
+pub fn default_permissions(role: &Role) -> &'static [&'static str] {
+    match role {
+        Role::Guest => &[COURSES_READ],
+        Role::Student => &[COURSES_READ, GRADES_READ],
+        Role::Teacher => &[
+            COURSES_READ,
+            COURSES_WRITE,
+            GRADES_READ,
+            GRADES_PUBLISH,
+            USERS_READ,
+        ],
+        Role::Admin => ALL_PERMISSIONS,
+    }
+}
+
+// Permissions are resolved from the role in the token claims on every request, so
+// changing a mapping applies to existing tokens. As with revocations, every instance
+// keeps the table in memory and periodically reloads it.
+static CACHE: Lazy<RwLock<PermissionCache>> = Lazy::new(Default::default);
+
+pub struct PermissionCache {
+    // role name -> granted permissions
+    roles: HashMap<String, HashSet<String>>,
+}
+
+impl Default for PermissionCache {
+    fn default() -> Self {
+        let roles = [Role::Guest, Role::Student, Role::Teacher, Role::Admin]
+            .iter()
+            .map(|role| {
+                let permissions = default_permissions(role)
+                    .iter()
+                    .map(|permission| permission.to_string())
+                    .collect();
+                (role.to_str(), permissions)
+            })
+            .collect();
+        PermissionCache { roles }
+    }
+}
+
+impl PermissionCache {
+    pub fn from_models(models: &[role_permission::Model]) -> Self {
+        let mut roles: HashMap<String, HashSet<String>> = HashMap::new();
+        for model in models.iter() {
+            roles
+                .entry(model.role.to_str())
+                .or_default()
+                .insert(model.permission.to_owned());
+        }
+        PermissionCache { roles }
+    }
+
+    pub fn grants(&self, role: &str, permission: &str) -> bool {
+        match self.roles.get(role) {
+            Some(permissions) => permissions.contains(permission),
+            None => false,
+        }
+    }
+}
+
+pub fn role_has_permission(role: &str, permission: &str) -> bool {
+    let cache = CACHE.read().unwrap_or_else(|e| e.into_inner());
+    cache.grants(role, permission)
+}
+
+/// Replaces the cached mappings with the contents of the `role_permission` table
+pub async fn load_permissions(conn: &DatabaseConnection) -> Result<(), DbErr> {
+    let models = RolePermission::find_all(conn).await?;
+    let mut cache = CACHE.write().unwrap_or_else(|e| e.into_inner());
+    *cache = PermissionCache::from_models(&models);
+    Ok(())
+}
+
+pub async fn sync_permissions(conn: Arc<DatabaseConnection>, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        if let Err(e) = load_permissions(conn.as_ref()).await {
+            tracing::error!(error = %e, "Unable to synchronize role permissions");
+        }
+    }
+}
+
+pub async fn grant_permission(
+    role: &Role,
+    permission: &str,
+    conn: &DatabaseConnection,
+) -> ApiResult<()> {
+    if RolePermission::find_one(role, permission, conn)
+        .await?
+        .is_none()
+    {
+        let model = RolePermission::create_active_model(role, permission);
+        RolePermission::insert_one(model, conn).await?;
+    }
+    load_permissions(conn).await?;
+    Ok(())
+}
+
+/// Permissions that cannot be revoked from Admin, so that there is always a role
+/// able to manage users and to grant back any other permission
+pub const PROTECTED_ADMIN_PERMISSIONS: &[&str] = &[USERS_MANAGE, PERMISSIONS_MANAGE];
+
+pub async fn revoke_permission(
+    role: &Role,
+    permission: &str,
+    conn: &DatabaseConnection,
+) -> ApiResult<()> {
+    if *role == Role::Admin && PROTECTED_ADMIN_PERMISSIONS.contains(&permission) {
+        return Err(AuthorizationError::ProtectedPermission(permission.to_string()).into());
+    }
+    RolePermission::delete_one(role, permission, conn).await?;
+    load_permissions(conn).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_permission_cache {
+    use super::{PermissionCache, COURSES_READ, GRADES_PUBLISH, PERMISSIONS_MANAGE, USERS_MANAGE};
+    use entity::{role_permission, sea_orm_active_enums::Role};
+
+    #[test]
+    fn defaults_do_not_follow_role_ordering() {
+        let cache = PermissionCache::default();
+
+        assert!(cache.grants("Guest", COURSES_READ));
+        assert!(cache.grants("Teacher", GRADES_PUBLISH));
+        assert!(!cache.grants("Teacher", USERS_MANAGE));
+        assert!(!cache.grants("Student", GRADES_PUBLISH));
+        assert!(cache.grants("Admin", PERMISSIONS_MANAGE));
+    }
+
+    #[test]
+    fn unknown_role_has_no_permissions() {
+        let cache = PermissionCache::default();
+        assert!(!cache.grants("Principal", COURSES_READ));
+    }
+
+    #[test]
+    fn loaded_models_replace_defaults() {
+        let cache = PermissionCache::from_models(&[role_permission::Model {
+            role: Role::Student,
+            permission: GRADES_PUBLISH.to_string(),
+        }]);
+
+        assert!(cache.grants("Student", GRADES_PUBLISH));
+        assert!(!cache.grants("Student", COURSES_READ));
+        assert!(!cache.grants("Admin", PERMISSIONS_MANAGE));
+    }
+}
@@ -0,0 +1,301 @@
+use std::{collections::HashMap, sync::Arc, sync::RwLock, time::Duration};
+
+use once_cell::sync::Lazy;
+use sea_orm::{prelude::Uuid, DatabaseConnection, DbErr};
+
+use crate::{auth::jwt::Claims, config::config, errors::ApiResult, time::Time};
+use entity::{
+    prelude::{RefreshToken, Session, TokenRevocation},
+    token_revocation,
+};
+
+// Every instance keeps the unexpired revocations in memory so that validating a token
+// never has to touch the database. The table remains the source of truth and is
+// periodically reloaded so that revocations made by other instances are picked up.
+static CACHE: Lazy<RwLock<RevocationCache>> = Lazy::new(Default::default);
+
+#[derive(Default)]
+struct RevocationCache {
+    // jti -> time after which the token would have expired anyway
+    tokens: HashMap<Uuid, i64>,
+    // session id -> time after which tokens issued within the session would have expired
+    sessions: HashMap<Uuid, i64>,
+    // user id -> tokens issued before the `revoked_at` millisecond are revoked
+    users: HashMap<Uuid, UserRevocation>,
+}
+
+struct UserRevocation {
+    revoked_at: i64,
+    expires_at: i64,
+}
+
+impl RevocationCache {
+    fn insert(&mut self, revocation: &token_revocation::Model) {
+        match (revocation.jti, revocation.session_id) {
+            (Some(jti), _) => {
+                self.tokens.insert(jti, revocation.expires_at);
+            }
+            (None, Some(session_id)) => {
+                self.sessions.insert(session_id, revocation.expires_at);
+            }
+            (None, None) => {
+                let entry = self
+                    .users
+                    .entry(revocation.user_id)
+                    .or_insert(UserRevocation {
+                        revoked_at: revocation.revoked_at,
+                        expires_at: revocation.expires_at,
+                    });
+                entry.revoked_at = entry.revoked_at.max(revocation.revoked_at);
+                entry.expires_at = entry.expires_at.max(revocation.expires_at);
+            }
+        }
+    }
+
+    fn prune(&mut self, now: i64) {
+        self.tokens.retain(|_, expires_at| *expires_at > now);
+        self.sessions.retain(|_, expires_at| *expires_at > now);
+        self.users
+            .retain(|_, revocation| revocation.expires_at > now);
+    }
+
+    fn is_revoked(&self, claims: &Claims) -> bool {
+        if self.tokens.contains_key(&claims.jti) {
+            return true;
+        }
+        if let Some(sid) = claims.sid {
+            if self.sessions.contains_key(&sid) {
+                return true;
+            }
+        }
+        match self.users.get(&claims.sub) {
+            Some(revocation) => (claims.issued_at_millis() as i64) < revocation.revoked_at,
+            None => false,
+        }
+    }
+}
+
+fn cache_revocation(revocation: &token_revocation::Model) {
+    let mut cache = CACHE.write().unwrap_or_else(|e| e.into_inner());
+    cache.insert(revocation);
+}
+
+pub fn is_revoked(claims: &Claims) -> bool {
+    let cache = CACHE.read().unwrap_or_else(|e| e.into_inner());
+    cache.is_revoked(claims)
+}
+
+/// Revokes the single token the claims were decoded from
+pub async fn revoke_token(claims: &Claims, conn: &DatabaseConnection) -> ApiResult<()> {
+    let now = Time::now()?.as_millis() as i64;
+    let model = TokenRevocation::create_token_active_model(
+        &claims.sub,
+        &claims.jti,
+        now,
+        claims.exp as i64,
+    );
+    TokenRevocation::insert_one(model.clone(), conn).await?;
+    cache_revocation(&model.try_into()?);
+    Ok(())
+}
+
+// The time by which every access token issued so far will have expired
+fn access_tokens_expired_at() -> ApiResult<i64> {
+    let expires_at = Time::now_plus_duration(config().tokens.access_token_lifetime())?;
+    Ok(expires_at.as_secs() as i64 + 1)
+}
+
+/// Revokes every access and refresh token issued to the user so far
+pub async fn revoke_user_tokens(user_id: &Uuid, conn: &DatabaseConnection) -> ApiResult<()> {
+    let now = Time::now()?;
+    // Access tokens issued before now will have expired within their lifetime,
+    // after which the revocation no longer needs to be kept
+    let expires_at = access_tokens_expired_at()?;
+    let model =
+        TokenRevocation::create_user_active_model(user_id, now.as_millis() as i64, expires_at);
+    TokenRevocation::insert_one(model.clone(), conn).await?;
+    cache_revocation(&model.try_into()?);
+
+    let now = now.as_secs() as i64;
+    RefreshToken::revoke_all_for_user(user_id, now, conn).await?;
+    Session::revoke_all_for_user(user_id, now, conn).await?;
+    Ok(())
+}
+
+/// Revokes every access and refresh token issued within the session
+pub async fn revoke_session_tokens(
+    user_id: &Uuid,
+    session_id: &Uuid,
+    conn: &DatabaseConnection,
+) -> ApiResult<()> {
+    let now = Time::now()?;
+    let expires_at = access_tokens_expired_at()?;
+    let model = TokenRevocation::create_session_active_model(
+        user_id,
+        session_id,
+        now.as_millis() as i64,
+        expires_at,
+    );
+    TokenRevocation::insert_one(model.clone(), conn).await?;
+    cache_revocation(&model.try_into()?);
+
+    RefreshToken::revoke_family(session_id, now.as_secs() as i64, conn).await?;
+    Ok(())
+}
+
+/// Purges expired revocations from the database and merges the rest into the cache
+pub async fn load_revocations(conn: &DatabaseConnection) -> Result<(), DbErr> {
+    let now = Time::now()
+        .map_err(|e| DbErr::Custom(e.to_string()))?
+        .as_secs() as i64;
+    TokenRevocation::delete_expired(now, conn).await?;
+    let revocations = TokenRevocation::find_unexpired(now, conn).await?;
+
+    let mut cache = CACHE.write().unwrap_or_else(|e| e.into_inner());
+    cache.prune(now);
+    for revocation in revocations.iter() {
+        cache.insert(revocation);
+    }
+    Ok(())
+}
+
+pub async fn sync_revocations(conn: Arc<DatabaseConnection>, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        if let Err(e) = load_revocations(conn.as_ref()).await {
+            tracing::error!(error = %e, "Unable to synchronize token revocations");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_revocation_cache {
+    use sea_orm::prelude::Uuid;
+
+    use super::RevocationCache;
+    use crate::auth::jwt::Claims;
+    use entity::token_revocation;
+
+    fn claims(sub: Uuid, jti: Uuid, iat_ms: u64) -> Claims {
+        Claims {
+            sub,
+            sid: None,
+            role: "Guest".to_string(),
+            exp: iat_ms / 1000 + 3600,
+            iat: iat_ms / 1000,
+            iat_ms,
+            jti,
+            email_verified: false,
+            mfa_pending: false,
+            mfa_authenticated: false,
+        }
+    }
+
+    #[test]
+    fn revoked_token_is_detected() {
+        let mut cache = RevocationCache::default();
+        let sub = Uuid::new_v4();
+        let jti = Uuid::new_v4();
+        cache.insert(&token_revocation::Model {
+            id: Uuid::new_v4(),
+            user_id: sub,
+            jti: Some(jti),
+            session_id: None,
+            revoked_at: 100,
+            expires_at: 3700,
+        });
+
+        assert!(cache.is_revoked(&claims(sub, jti, 100)));
+        assert!(!cache.is_revoked(&claims(sub, Uuid::new_v4(), 100)));
+    }
+
+    #[test]
+    fn user_revocation_only_applies_to_earlier_tokens() {
+        let mut cache = RevocationCache::default();
+        let sub = Uuid::new_v4();
+        cache.insert(&token_revocation::Model {
+            id: Uuid::new_v4(),
+            user_id: sub,
+            jti: None,
+            session_id: None,
+            revoked_at: 200_500,
+            expires_at: 3800,
+        });
+
+        assert!(cache.is_revoked(&claims(sub, Uuid::new_v4(), 100_000)));
+        assert!(cache.is_revoked(&claims(sub, Uuid::new_v4(), 200_499)));
+        assert!(!cache.is_revoked(&claims(sub, Uuid::new_v4(), 200_500)));
+        assert!(!cache.is_revoked(&claims(Uuid::new_v4(), Uuid::new_v4(), 100_000)));
+    }
+
+    #[test]
+    fn tokens_without_milliseconds_are_revoked_within_the_same_second() {
+        let mut cache = RevocationCache::default();
+        let sub = Uuid::new_v4();
+        cache.insert(&token_revocation::Model {
+            id: Uuid::new_v4(),
+            user_id: sub,
+            jti: None,
+            session_id: None,
+            revoked_at: 200_500,
+            expires_at: 3800,
+        });
+
+        let mut legacy = claims(sub, Uuid::new_v4(), 0);
+        legacy.iat = 200;
+        assert!(cache.is_revoked(&legacy));
+        legacy.iat = 201;
+        assert!(!cache.is_revoked(&legacy));
+    }
+
+    #[test]
+    fn session_revocation_only_applies_to_its_tokens() {
+        let mut cache = RevocationCache::default();
+        let sub = Uuid::new_v4();
+        let sid = Uuid::new_v4();
+        cache.insert(&token_revocation::Model {
+            id: Uuid::new_v4(),
+            user_id: sub,
+            jti: None,
+            session_id: Some(sid),
+            revoked_at: 200,
+            expires_at: 3800,
+        });
+
+        let mut in_session = claims(sub, Uuid::new_v4(), 300);
+        in_session.sid = Some(sid);
+        let mut other_session = claims(sub, Uuid::new_v4(), 100);
+        other_session.sid = Some(Uuid::new_v4());
+
+        assert!(cache.is_revoked(&in_session));
+        assert!(!cache.is_revoked(&other_session));
+        assert!(!cache.is_revoked(&claims(sub, Uuid::new_v4(), 100)));
+    }
+
+    #[test]
+    fn prune_removes_expired_revocations() {
+        let mut cache = RevocationCache::default();
+        let sub = Uuid::new_v4();
+        let jti = Uuid::new_v4();
+        cache.insert(&token_revocation::Model {
+            id: Uuid::new_v4(),
+            user_id: sub,
+            jti: Some(jti),
+            session_id: None,
+            revoked_at: 100,
+            expires_at: 200,
+        });
+        cache.insert(&token_revocation::Model {
+            id: Uuid::new_v4(),
+            user_id: sub,
+            jti: None,
+            session_id: None,
+            revoked_at: 100,
+            expires_at: 200,
+        });
+
+        cache.prune(200);
+        assert!(!cache.is_revoked(&claims(sub, jti, 50)));
+    }
+}
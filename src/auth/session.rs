@@ -0,0 +1,176 @@
+use std::{net::IpAddr, sync::Arc, time::Duration};
+
+use sea_orm::{prelude::Uuid, DatabaseConnection, Set};
+
+use crate::{
+    auth::{
+        jwt::create_jwt,
+        revocation::revoke_session_tokens,
+        token::{generate_token, hash_token},
+    },
+    config::config,
+    errors::ApiResult,
+    events::publish_status_change,
+    graphql::schema::Context,
+    repository::{database::DatabaseUserRepository, UserRepository},
+    time::Time,
+};
+use entity::{
+    prelude::{RefreshToken, Session, User},
+    sea_orm_active_enums::Status,
+    session, user,
+};
+
+/// Describes the device a session was started from
+#[derive(Debug, Default, Clone)]
+pub struct DeviceInfo {
+    pub label: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<IpAddr>,
+}
+
+impl DeviceInfo {
+    pub fn from_context(ctx: &Context, label: Option<String>) -> Self {
+        DeviceInfo {
+            label,
+            user_agent: ctx.user_agent.to_owned(),
+            ip_address: ctx.client_ip,
+        }
+    }
+}
+
+pub struct StartedSession {
+    pub user: user::Model,
+    pub token: String,
+    pub refresh_token: String,
+}
+
+// A session stays active until it is revoked or its refresh token could no longer
+// be used, which happens once it has gone unused for the refresh token lifetime
+pub fn active_since(now: i64) -> i64 {
+    now - config().tokens.refresh_token_lifetime_secs as i64
+}
+
+// Clients refresh their access token before it expires, so a session that has not
+// been refreshed within an access token lifetime, plus a minute's grace, has gone idle
+pub fn online_since(now: i64) -> i64 {
+    now - config().tokens.access_token_lifetime_secs as i64 - PRESENCE_GRACE_IN_SECONDS
+}
+
+const PRESENCE_GRACE_IN_SECONDS: i64 = 60;
+
+/// Records a new session for the device and issues its tokens.
+/// The refresh token family is the session, so rotating refresh tokens keeps it alive,
+/// and tokens issued by refreshing are only MFA-authenticated when the signin was.
+pub async fn start_session(
+    user: user::Model,
+    mfa_authenticated: bool,
+    device: DeviceInfo,
+    users: &dyn UserRepository,
+    conn: &DatabaseConnection,
+) -> ApiResult<StartedSession> {
+    let now = Time::now()?.as_secs() as i64;
+    let mut model = Session::create_active_model(
+        &user.id,
+        device.label,
+        device.user_agent,
+        device.ip_address.map(|ip| ip.to_string()),
+        now,
+    );
+    model.mfa_authenticated = Set(mfa_authenticated);
+    Session::insert_one(model.clone(), conn).await?;
+    let created: session::Model = model.try_into()?;
+
+    let token = create_jwt(&user, &created.id, mfa_authenticated)?;
+    let refresh_token = issue_refresh_token(&user.id, &created.id, conn).await?;
+    let user = refresh_presence(user, users, conn).await?;
+
+    Ok(StartedSession {
+        user,
+        token,
+        refresh_token,
+    })
+}
+
+/// Signs out a single device, leaving the user's other sessions untouched
+pub async fn end_session(
+    user: user::Model,
+    session_id: &Uuid,
+    users: &dyn UserRepository,
+    conn: &DatabaseConnection,
+) -> ApiResult<user::Model> {
+    let now = Time::now()?.as_secs() as i64;
+    Session::revoke_one(session_id, now, conn).await?;
+    revoke_session_tokens(&user.id, session_id, conn).await?;
+    refresh_presence(user, users, conn).await
+}
+
+// Users are online while at least one of their sessions has recently been used,
+// rather than for as long as a session could still be refreshed.
+// Hidden users chose not to show their presence, so they are left as they are.
+// Changes are published so subscribers see users signing in and out.
+pub async fn refresh_presence(
+    user: user::Model,
+    users: &dyn UserRepository,
+    conn: &DatabaseConnection,
+) -> ApiResult<user::Model> {
+    if user.status == Status::Hidden {
+        return Ok(user);
+    }
+
+    let now = Time::now()?.as_secs() as i64;
+    let status = match Session::count_active_for_user(&user.id, online_since(now), conn).await? {
+        0 => Status::Offline,
+        _ => Status::Online,
+    };
+    if user.status == status {
+        return Ok(user);
+    }
+
+    let mut user: user::ActiveModel = user.into();
+    user.status = Set(status);
+    let updated = users.update_one(user).await?;
+    publish_status_change(&updated);
+    Ok(updated)
+}
+
+/// Marks users offline once none of their sessions has been used recently,
+/// since a device that was closed without signing out never ends its session
+pub async fn expire_idle_presence(
+    users: &dyn UserRepository,
+    conn: &DatabaseConnection,
+) -> ApiResult<()> {
+    let now = Time::now()?.as_secs() as i64;
+    for user in User::find_idle_online(online_since(now), conn).await? {
+        refresh_presence(user, users, conn).await?;
+    }
+    Ok(())
+}
+
+pub async fn sync_presence(conn: Arc<DatabaseConnection>, period: Duration) {
+    let users = DatabaseUserRepository::new(conn.clone());
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        if let Err(e) = expire_idle_presence(&users, conn.as_ref()).await {
+            tracing::error!(error = %e, "Unable to expire idle presence");
+        }
+    }
+}
+
+pub async fn issue_refresh_token(
+    user_id: &Uuid,
+    family_id: &Uuid,
+    conn: &DatabaseConnection,
+) -> ApiResult<String> {
+    let token = generate_token();
+    let now = Time::now()?.as_secs() as i64;
+    let expires_at = Time::now_plus_duration(config().tokens.refresh_token_lifetime())?;
+    let expires_at = expires_at.as_secs() as i64;
+
+    let model =
+        RefreshToken::create_active_model(user_id, family_id, &hash_token(&token), now, expires_at);
+    RefreshToken::insert_one(model, conn).await?;
+
+    Ok(token)
+}
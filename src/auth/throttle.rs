@@ -0,0 +1,186 @@
+use std::net::IpAddr;
+
+use sea_orm::DatabaseConnection;
+
+use crate::{
+    errors::{ApiResult, UserError},
+    time::{Time, DAY_IN_SECONDS, HOUR_IN_SECONDS},
+};
+use entity::{prelude::LoginAttempt, sea_orm_active_enums::AttemptScope};
+
+/// Failed attempts allowed against a single account before it is locked
+pub const ACCOUNT_MAX_ATTEMPTS: i32 = 5;
+/// Failed attempts allowed from a single IP address, which may be shared
+/// by many legitimate users, before it is locked
+pub const IP_MAX_ATTEMPTS: i32 = 20;
+pub const BASE_LOCKOUT_SECONDS: i64 = 30;
+
+// Each subsequent failure while locked doubles the lockout up to an hour.
+// Failures older than a day no longer count towards a lockout.
+fn lockout_duration(failures: i32, max_attempts: i32) -> Option<i64> {
+    if failures < max_attempts {
+        return None;
+    }
+    let doublings = (failures - max_attempts).min(16) as u32;
+    let duration = BASE_LOCKOUT_SECONDS.saturating_mul(2_i64.pow(doublings));
+    Some(duration.min(HOUR_IN_SECONDS.into()))
+}
+
+fn subjects(email: &str, ip: Option<IpAddr>) -> Vec<(AttemptScope, String, i32)> {
+    let mut subjects = vec![(
+        AttemptScope::Account,
+        email.to_lowercase(),
+        ACCOUNT_MAX_ATTEMPTS,
+    )];
+    if let Some(ip) = ip {
+        subjects.push((AttemptScope::Ip, ip.to_string(), IP_MAX_ATTEMPTS));
+    }
+    subjects
+}
+
+/// Errors with `UserError::AccountLocked` if either the account or the IP address
+/// the request came from is currently locked
+pub async fn check_signin_allowed(
+    email: &str,
+    ip: Option<IpAddr>,
+    conn: &DatabaseConnection,
+) -> ApiResult<()> {
+    let now = Time::now()?.as_secs() as i64;
+    for (scope, subject, _) in subjects(email, ip) {
+        let attempt = LoginAttempt::find_one(scope, &subject, conn).await?;
+        if let Some(retry_after) = attempt.and_then(|attempt| attempt.retry_after(now)) {
+            return Err(UserError::AccountLocked { retry_after }.into());
+        }
+    }
+    Ok(())
+}
+
+pub async fn record_failed_signin(
+    email: &str,
+    ip: Option<IpAddr>,
+    conn: &DatabaseConnection,
+) -> ApiResult<()> {
+    let now = Time::now()?.as_secs() as i64;
+    for (scope, subject, max_attempts) in subjects(email, ip) {
+        let attempt =
+            LoginAttempt::record_failure(scope, &subject, now, DAY_IN_SECONDS.into(), conn).await?;
+        if let Some(duration) = lockout_duration(attempt.failures, max_attempts) {
+            LoginAttempt::lock_until(&attempt.id, now + duration, conn).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Forgets failed attempts against the account. Attempts from the IP address
+/// are kept so one valid account cannot be used to reset an IP lockout.
+pub async fn clear_failed_signins(email: &str, conn: &DatabaseConnection) -> ApiResult<()> {
+    LoginAttempt::delete_one(AttemptScope::Account, &email.to_lowercase(), conn).await?;
+    Ok(())
+}
+
+/// Forgets failed attempts from the IP address, for when the legitimate users
+/// sharing it have been locked out
+pub async fn clear_failed_ip_signins(ip: IpAddr, conn: &DatabaseConnection) -> ApiResult<()> {
+    LoginAttempt::delete_one(AttemptScope::Ip, &ip.to_string(), conn).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_lockout_duration {
+    use super::{lockout_duration, ACCOUNT_MAX_ATTEMPTS, BASE_LOCKOUT_SECONDS};
+
+    #[test]
+    fn no_lockout_below_max_attempts() {
+        for failures in 0..ACCOUNT_MAX_ATTEMPTS {
+            assert_eq!(lockout_duration(failures, ACCOUNT_MAX_ATTEMPTS), None);
+        }
+    }
+
+    #[test]
+    fn lockout_doubles_with_each_failure() {
+        let got = lockout_duration(ACCOUNT_MAX_ATTEMPTS, ACCOUNT_MAX_ATTEMPTS);
+        assert_eq!(got, Some(BASE_LOCKOUT_SECONDS));
+
+        let got = lockout_duration(ACCOUNT_MAX_ATTEMPTS + 1, ACCOUNT_MAX_ATTEMPTS);
+        assert_eq!(got, Some(BASE_LOCKOUT_SECONDS * 2));
+
+        let got = lockout_duration(ACCOUNT_MAX_ATTEMPTS + 3, ACCOUNT_MAX_ATTEMPTS);
+        assert_eq!(got, Some(BASE_LOCKOUT_SECONDS * 8));
+    }
+
+    #[test]
+    fn lockout_is_capped_at_an_hour() {
+        let got = lockout_duration(ACCOUNT_MAX_ATTEMPTS + 10, ACCOUNT_MAX_ATTEMPTS);
+        assert_eq!(got, Some(3600));
+
+        let got = lockout_duration(i32::MAX, ACCOUNT_MAX_ATTEMPTS);
+        assert_eq!(got, Some(3600));
+    }
+}
+
+#[cfg(test)]
+mod test_check_signin_allowed {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use sea_orm::prelude::Uuid;
+
+    use super::check_signin_allowed;
+    use crate::{testutils::create_mock_conn, time::Time};
+    use entity::{login_attempt, sea_orm_active_enums::AttemptScope};
+
+    fn attempt(scope: AttemptScope, locked_until: Option<i64>) -> login_attempt::Model {
+        login_attempt::Model {
+            id: Uuid::new_v4(),
+            scope,
+            subject: "subject".to_string(),
+            failures: 5,
+            last_failed_at: 0,
+            locked_until,
+        }
+    }
+
+    #[tokio::test]
+    async fn allowed_without_lockouts() {
+        let conn = create_mock_conn::<login_attempt::Model>(vec![vec![], vec![]]);
+        let ip = Some(IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        let got = check_signin_allowed("test@test.com", ip, &conn).await;
+        assert!(got.is_ok());
+    }
+
+    #[tokio::test]
+    async fn allowed_after_lockout_expires() {
+        let conn = create_mock_conn(vec![vec![attempt(AttemptScope::Account, Some(100))]]);
+
+        let got = check_signin_allowed("test@test.com", None, &conn).await;
+        assert!(got.is_ok());
+    }
+
+    #[tokio::test]
+    async fn locked_account_is_rejected() {
+        let locked_until = Time::now().unwrap().as_secs() as i64 + 60;
+        let conn = create_mock_conn(vec![vec![attempt(
+            AttemptScope::Account,
+            Some(locked_until),
+        )]]);
+
+        let got = check_signin_allowed("test@test.com", None, &conn).await;
+        let err = got.unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Too many failed signin attempts"));
+    }
+
+    #[tokio::test]
+    async fn locked_ip_is_rejected() {
+        let locked_until = Time::now().unwrap().as_secs() as i64 + 60;
+        let conn = create_mock_conn(vec![
+            vec![],
+            vec![attempt(AttemptScope::Ip, Some(locked_until))],
+        ]);
+        let ip = Some(IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        let got = check_signin_allowed("test@test.com", ip, &conn).await;
+        assert!(got.is_err());
+    }
+}
@@ -0,0 +1,43 @@
+use pbkdf2::password_hash::rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+const TOKEN_BYTES: usize = 32;
+
+/// Creates an opaque, url-safe random token to be handed to the client.
+/// Only the hash of the token, produced by `hash_token`, should be persisted.
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+pub fn hash_token(token: &str) -> String {
+    let digest = Sha256::digest(token.as_bytes());
+    base64::encode_config(digest, base64::URL_SAFE_NO_PAD)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{generate_token, hash_token};
+
+    #[test]
+    fn generated_tokens_are_unique() {
+        let first = generate_token();
+        let second = generate_token();
+
+        assert_ne!(first, second);
+        assert_eq!(first.len(), 43);
+    }
+
+    #[test]
+    fn hash_is_deterministic() {
+        let token = generate_token();
+        assert_eq!(hash_token(&token), hash_token(&token));
+        assert_ne!(hash_token(&token), token);
+    }
+
+    #[test]
+    fn hash_differs_between_tokens() {
+        assert_ne!(hash_token("first"), hash_token("second"));
+    }
+}
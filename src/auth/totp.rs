@@ -0,0 +1,218 @@
+use hmac::{Hmac, Mac};
+use pbkdf2::password_hash::rand_core::{OsRng, RngCore};
+use sha1::Sha1;
+
+const SECRET_BYTES: usize = 20;
+const STEP_SECONDS: u64 = 30;
+const DIGITS: u32 = 6;
+// Codes from the previous and next time step are accepted to allow for clock drift
+const ALLOWED_DRIFT_STEPS: i64 = 1;
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+// Ambiguous characters such as 0/O and 1/I are left out of recovery codes
+const RECOVERY_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Creates a random base32 encoded secret to be shared with an authenticator app
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; SECRET_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    base32_encode(&bytes)
+}
+
+/// Creates single-use codes in the form `xxxxx-xxxxx`. Only their hashes should be persisted.
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 10];
+            OsRng.fill_bytes(&mut bytes);
+            let chars: String = bytes
+                .iter()
+                .map(|byte| RECOVERY_ALPHABET[*byte as usize % RECOVERY_ALPHABET.len()] as char)
+                .collect();
+            format!("{}-{}", &chars[..5], &chars[5..])
+        })
+        .collect()
+}
+
+/// The URI authenticator apps read from a QR code, see
+/// https://github.com/google/google-authenticator/wiki/Key-Uri-Format
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        percent_encode(issuer),
+        percent_encode(account),
+        secret,
+        percent_encode(issuer),
+        DIGITS,
+        STEP_SECONDS
+    )
+}
+
+pub fn time_step(now: u64) -> i64 {
+    (now / STEP_SECONDS) as i64
+}
+
+/// Returns the time step the code is valid for, if any
+pub fn verify_code(secret: &str, code: &str, now: u64) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let key = base32_decode(secret)?;
+    let current = time_step(now);
+    (current - ALLOWED_DRIFT_STEPS..=current + ALLOWED_DRIFT_STEPS)
+        .find(|step| format_code(generate_code(&key, *step)) == code)
+}
+
+/// The code an authenticator app would show at the given time
+pub fn code_at(secret: &str, now: u64) -> Option<String> {
+    let key = base32_decode(secret)?;
+    Some(format_code(generate_code(&key, time_step(now))))
+}
+
+fn generate_code(key: &[u8], step: i64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation as described in RFC 4226
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    binary % 10_u32.pow(DIGITS)
+}
+
+fn format_code(code: u32) -> String {
+    format!("{:0width$}", code, width = DIGITS as usize)
+}
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = vec![];
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in encoded.trim_end_matches('=').chars() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a as char == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'@' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        base32_decode, base32_encode, code_at, generate_code, generate_recovery_codes,
+        generate_secret, otpauth_uri, verify_code, RECOVERY_CODE_COUNT,
+    };
+
+    // The SHA1 secret from the RFC 6238 test vectors
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn generate_rfc_6238_codes() {
+        assert_eq!(generate_code(RFC_SECRET, 59 / 30), 287082);
+        assert_eq!(generate_code(RFC_SECRET, 1111111109 / 30), 81804);
+        assert_eq!(generate_code(RFC_SECRET, 1234567890 / 30), 5924);
+        assert_eq!(generate_code(RFC_SECRET, 2000000000 / 30), 279037);
+    }
+
+    #[test]
+    fn verify_codes_within_drift() {
+        let secret = base32_encode(RFC_SECRET);
+
+        assert_eq!(verify_code(&secret, "081804", 1111111109), Some(37037036));
+        assert_eq!(
+            verify_code(&secret, "081804", 1111111109 + 30),
+            Some(37037036)
+        );
+        assert_eq!(
+            verify_code(&secret, "081804", 1111111109 - 30),
+            Some(37037036)
+        );
+        assert_eq!(verify_code(&secret, "081804", 1111111109 + 90), None);
+
+        let code = code_at(&secret, 1111111109).unwrap();
+        assert_eq!(code, "081804");
+    }
+
+    #[test]
+    fn reject_malformed_codes() {
+        let secret = base32_encode(RFC_SECRET);
+
+        assert_eq!(verify_code(&secret, "81804", 1111111109), None);
+        assert_eq!(verify_code(&secret, "08180a", 1111111109), None);
+        assert_eq!(verify_code("not base32!", "081804", 1111111109), None);
+    }
+
+    #[test]
+    fn base32_round_trip() {
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32_decode("MZXW6YTBOI").unwrap(), b"foobar");
+        assert_eq!(base32_decode("mzxw6ytboi======").unwrap(), b"foobar");
+
+        let secret = generate_secret();
+        assert_eq!(secret.len(), 32);
+        assert_eq!(base32_decode(&secret).unwrap().len(), 20);
+    }
+
+    #[test]
+    fn recovery_codes_are_unique() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert_eq!(codes[0].len(), 11);
+
+        let mut deduped = codes.clone();
+        deduped.sort();
+        deduped.dedup();
+        assert_eq!(deduped.len(), RECOVERY_CODE_COUNT);
+    }
+
+    #[test]
+    fn build_otpauth_uri() {
+        let got = otpauth_uri("Gilded University", "test@test.com", "SECRET");
+        assert_eq!(
+            got,
+            "otpauth://totp/Gilded%20University:test@test.com?secret=SECRET&issuer=Gilded%20University&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use sea_orm::{prelude::Uuid, DatabaseConnection};
+
+use crate::{
+    auth::token::{generate_token, hash_token},
+    errors::ApiResult,
+    time::Time,
+};
+use entity::{prelude::UserToken, sea_orm_active_enums::TokenPurpose, user_token};
+
+/// Creates a single-use token for the purpose, invalidating any the user
+/// was previously sent for the same purpose. Only the hash is persisted.
+pub async fn issue_user_token(
+    user_id: &Uuid,
+    purpose: TokenPurpose,
+    expires_at: i64,
+    payload: Option<String>,
+    conn: &DatabaseConnection,
+) -> ApiResult<String> {
+    let now = Time::now()?.as_secs() as i64;
+    UserToken::invalidate_for_user(user_id, purpose.clone(), now, conn).await?;
+
+    let token = generate_token();
+    let model = UserToken::create_active_model(
+        user_id,
+        purpose,
+        &hash_token(&token),
+        payload,
+        now,
+        expires_at,
+    );
+    UserToken::insert_one(model, conn).await?;
+
+    Ok(token)
+}
+
+/// Marks the token as used, returning `None` if it does not exist, has expired
+/// or was already redeemed
+pub async fn redeem_user_token(
+    token: &str,
+    purpose: TokenPurpose,
+    conn: &DatabaseConnection,
+) -> ApiResult<Option<user_token::Model>> {
+    let found = UserToken::find_one_by_hash(&hash_token(token), purpose, conn).await?;
+    let found = match found {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+
+    let now = Time::now()?.as_secs() as i64;
+    if !found.is_redeemable(now) {
+        return Ok(None);
+    }
+
+    let redeemed = UserToken::mark_used(&found.id, now, conn).await?;
+    if redeemed.rows_affected == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(found))
+}
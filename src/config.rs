@@ -0,0 +1,1203 @@
+use std::{
+    env, fs,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::Path,
+    str::FromStr,
+    time::Duration,
+};
+
+use argon2::Params as Argon2Params;
+use lettre::message::Mailbox;
+use once_cell::sync::OnceCell;
+use pbkdf2::Params as Pbkdf2Params;
+use serde::Deserialize;
+use url::Url;
+use warp::http::{header::HeaderName, Method};
+
+use crate::{
+    auth::hash::HashAlgorithm,
+    errors::ConfigError,
+    time::{
+        DAY_IN_SECONDS, HOUR_IN_SECONDS, MFA_PENDING_LIFETIME_IN_SECONDS,
+        REFRESH_TOKEN_LIFETIME_IN_SECONDS,
+    },
+};
+use entity::sea_orm_active_enums::Role;
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+static CONFIG: OnceCell<Config> = OnceCell::new();
+
+/// Loads the configuration on first use so that errors can be surfaced on startup
+#[cfg(not(test))]
+pub fn load_config() -> Result<&'static Config, ConfigError> {
+    CONFIG.get_or_try_init(Config::load)
+}
+
+// Unit tests share fixed settings, so they pass regardless of the environment
+// they run in or the order they run in
+#[cfg(test)]
+pub fn load_config() -> Result<&'static Config, ConfigError> {
+    Ok(CONFIG.get_or_init(|| {
+        let mut config = Config::default();
+        config.jwt.secret = Some("jwtsecret".to_string());
+        config
+    }))
+}
+
+pub fn config() -> &'static Config {
+    load_config().expect("Unable to load configuration")
+}
+
+/// Settings read once at startup. Values in the TOML file named by `CONFIG_FILE`
+/// (`config.toml` when it exists) are overridden by environment variables.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub jwt: JwtConfig,
+    pub tokens: TokenConfig,
+    pub password: PasswordConfig,
+    pub mfa: MfaConfig,
+    /// OpenID Connect login is only enabled when a provider is configured
+    pub oidc: Option<OidcConfig>,
+    pub mail: MailConfig,
+    pub cors: CorsConfig,
+    pub log: LogConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: IpAddr,
+    pub port: u16,
+    /// Read the client IP from `X-Forwarded-For`. Only enable behind a proxy that sets it.
+    pub trust_forwarded_for: bool,
+    /// How many proxies in front of the server append to `X-Forwarded-For`
+    pub trusted_proxy_hops: usize,
+    /// How long in-flight requests and websocket connections are given to finish on shutdown
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            host: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 8080,
+            trust_forwarded_for: false,
+            trusted_proxy_hops: 1,
+            shutdown_timeout_secs: 30,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn address(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout_secs)
+    }
+
+    /// The number of `X-Forwarded-For` entries appended by trusted proxies,
+    /// none unless `trust_forwarded_for` is set
+    pub fn forwarded_hops(&self) -> usize {
+        match self.trust_forwarded_for {
+            true => self.trusted_proxy_hops,
+            false => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: Option<String>,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub connect_timeout_secs: u64,
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: None,
+            max_connections: 10,
+            min_connections: 1,
+            connect_timeout_secs: 8,
+            idle_timeout_secs: 600,
+        }
+    }
+}
+
+impl DatabaseConfig {
+    pub fn url(&self) -> Result<&str, ConfigError> {
+        self.url
+            .as_deref()
+            .ok_or_else(|| ConfigError::Missing("DATABASE_URL".to_string()))
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout_secs)
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_timeout_secs)
+    }
+}
+
+/// Tokens are signed with `secret` using HS512 unless `keys` are given
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JwtConfig {
+    pub secret: Option<String>,
+    /// `kid:algorithm:path` entries pointing to PEM keys
+    pub keys: Vec<String>,
+    /// Defaults to the first of `keys`
+    pub active_kid: Option<String>,
+    pub retired_kids: Vec<String>,
+    /// Accounts must verify their email before using anything above Guest
+    pub require_email_verification: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TokenConfig {
+    pub access_token_lifetime_secs: u64,
+    pub refresh_token_lifetime_secs: u64,
+    pub mfa_pending_lifetime_secs: u64,
+    pub password_reset_lifetime_secs: u64,
+    pub email_verification_lifetime_secs: u64,
+}
+
+impl Default for TokenConfig {
+    fn default() -> Self {
+        TokenConfig {
+            access_token_lifetime_secs: HOUR_IN_SECONDS.into(),
+            refresh_token_lifetime_secs: REFRESH_TOKEN_LIFETIME_IN_SECONDS.into(),
+            mfa_pending_lifetime_secs: MFA_PENDING_LIFETIME_IN_SECONDS.into(),
+            password_reset_lifetime_secs: HOUR_IN_SECONDS.into(),
+            email_verification_lifetime_secs: DAY_IN_SECONDS.into(),
+        }
+    }
+}
+
+impl TokenConfig {
+    pub fn access_token_lifetime(&self) -> Duration {
+        Duration::from_secs(self.access_token_lifetime_secs)
+    }
+
+    pub fn refresh_token_lifetime(&self) -> Duration {
+        Duration::from_secs(self.refresh_token_lifetime_secs)
+    }
+
+    pub fn mfa_pending_lifetime(&self) -> Duration {
+        Duration::from_secs(self.mfa_pending_lifetime_secs)
+    }
+
+    pub fn password_reset_lifetime(&self) -> Duration {
+        Duration::from_secs(self.password_reset_lifetime_secs)
+    }
+
+    pub fn email_verification_lifetime(&self) -> Duration {
+        Duration::from_secs(self.email_verification_lifetime_secs)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PasswordHashAlgorithm {
+    Argon2id,
+    Pbkdf2,
+}
+
+impl FromStr for PasswordHashAlgorithm {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "argon2id" => Ok(PasswordHashAlgorithm::Argon2id),
+            "pbkdf2" => Ok(PasswordHashAlgorithm::Pbkdf2),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How new password hashes are created. Existing hashes are upgraded on signin.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PasswordConfig {
+    pub algorithm: PasswordHashAlgorithm,
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+    pub pbkdf2_rounds: u32,
+}
+
+impl Default for PasswordConfig {
+    fn default() -> Self {
+        PasswordConfig {
+            algorithm: PasswordHashAlgorithm::Argon2id,
+            argon2_memory_kib: Argon2Params::DEFAULT_M_COST,
+            argon2_iterations: Argon2Params::DEFAULT_T_COST,
+            argon2_parallelism: Argon2Params::DEFAULT_P_COST,
+            pbkdf2_rounds: Pbkdf2Params::default().rounds,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MfaConfig {
+    /// Roles that must complete two-factor authentication, e.g. `["Teacher", "Admin"]`
+    pub required_roles: Vec<String>,
+    /// The name authenticator apps show for codes
+    pub issuer: String,
+}
+
+impl Default for MfaConfig {
+    fn default() -> Self {
+        MfaConfig {
+            required_roles: vec![],
+            issuer: "Gilded University".to_string(),
+        }
+    }
+}
+
+impl MfaConfig {
+    pub fn required_roles(&self) -> Vec<Role> {
+        self.required_roles
+            .iter()
+            .filter_map(|role| Role::from_str(role).ok())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    /// Not needed for public clients
+    pub client_secret: Option<String>,
+    /// The URL of `/auth/oidc/callback` as registered with the provider
+    pub redirect_uri: String,
+    pub scopes: String,
+    /// A frontend URL the tokens are passed to, otherwise the callback responds with JSON
+    pub post_login_redirect: Option<String>,
+}
+
+impl Default for OidcConfig {
+    fn default() -> Self {
+        OidcConfig {
+            issuer: "".to_string(),
+            client_id: "".to_string(),
+            client_secret: None,
+            redirect_uri: "".to_string(),
+            scopes: "openid email profile".to_string(),
+            post_login_redirect: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransport {
+    /// Sends real emails through an SMTP relay
+    Smtp,
+    /// Writes emails to `dir`
+    File,
+    /// Keeps emails in memory
+    Memory,
+}
+
+impl FromStr for MailTransport {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "smtp" => Ok(MailTransport::Smtp),
+            "file" => Ok(MailTransport::File),
+            "memory" => Ok(MailTransport::Memory),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The `smtp_` settings and `from` are required with the `smtp` transport
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MailConfig {
+    pub transport: MailTransport,
+    pub dir: String,
+    pub from: Option<String>,
+    pub smtp_host: Option<String>,
+    pub smtp_port: Option<u16>,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        MailConfig {
+            transport: MailTransport::File,
+            dir: "mail".to_string(),
+            from: None,
+            smtp_host: None,
+            smtp_port: None,
+            smtp_username: None,
+            smtp_password: None,
+        }
+    }
+}
+
+/// Any origin is allowed when no origins are listed
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    /// Request headers a browser may send, besides those it always allows
+    pub allowed_headers: Vec<String>,
+    /// Response headers scripts may read
+    pub exposed_headers: Vec<String>,
+    /// Lets browsers send cookies, only allowed with a list of origins
+    pub allow_credentials: bool,
+    /// How long browsers may cache a preflight response
+    pub max_age_secs: Option<u64>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            allowed_origins: vec![],
+            allowed_methods: vec!["GET".to_string(), "POST".to_string(), "DELETE".to_string()],
+            allowed_headers: vec![
+                "Accept".to_string(),
+                "Authorization".to_string(),
+                "Content-Type".to_string(),
+                "X-Request-Id".to_string(),
+            ],
+            exposed_headers: vec!["X-Request-Id".to_string()],
+            allow_credentials: false,
+            max_age_secs: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    /// Which events are logged, in the `RUST_LOG` directive syntax, e.g. `info,sea_orm=debug`
+    pub filter: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            format: LogFormat::Text,
+            filter: "info".to_string(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut config = match env::var("CONFIG_FILE") {
+            Ok(path) => Config::from_file(&path)?,
+            Err(_) if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Config::from_file(DEFAULT_CONFIG_FILE)?
+            }
+            Err(_) => Config::default(),
+        };
+        config.apply_env(|key| env::var(key).ok())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path).map_err(|e| ConfigError::UnreadableFile {
+            path: path.to_string(),
+            reason: e.to_string(),
+        })?;
+        Config::from_toml(&contents).map_err(|e| match e {
+            ConfigError::InvalidFile { reason, .. } => ConfigError::InvalidFile {
+                path: path.to_string(),
+                reason,
+            },
+            e => e,
+        })
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, ConfigError> {
+        toml::from_str(contents).map_err(|e| ConfigError::InvalidFile {
+            path: "".to_string(),
+            reason: e.to_string(),
+        })
+    }
+
+    /// Overrides file values with `HOST`, `PORT`, `TRUST_FORWARDED_FOR`, `TRUSTED_PROXY_HOPS`,
+    /// `SHUTDOWN_TIMEOUT_SECS`,
+    /// `DATABASE_URL`, `DATABASE_MAX_CONNECTIONS`, `DATABASE_MIN_CONNECTIONS`,
+    /// `DATABASE_CONNECT_TIMEOUT_SECS`, `DATABASE_IDLE_TIMEOUT_SECS`, `JWT_SECRET`, `JWT_KEYS`, `JWT_ACTIVE_KID`, `JWT_RETIRED_KIDS`,
+    /// `REQUIRE_EMAIL_VERIFICATION`, `ACCESS_TOKEN_LIFETIME_SECS`, `REFRESH_TOKEN_LIFETIME_SECS`,
+    /// `MFA_PENDING_LIFETIME_SECS`, `PASSWORD_RESET_LIFETIME_SECS`,
+    /// `EMAIL_VERIFICATION_LIFETIME_SECS`, `PASSWORD_HASH_ALGORITHM`, `ARGON2_MEMORY_KIB`,
+    /// `ARGON2_ITERATIONS`, `ARGON2_PARALLELISM`, `PBKDF2_ROUNDS`, `MFA_REQUIRED_ROLES`,
+    /// `MFA_ISSUER`, `OIDC_ISSUER`, `OIDC_CLIENT_ID`, `OIDC_CLIENT_SECRET`, `OIDC_REDIRECT_URI`,
+    /// `OIDC_SCOPES`, `OIDC_POST_LOGIN_REDIRECT`, `MAIL_TRANSPORT`, `MAIL_DIR`, `MAIL_FROM`,
+    /// `SMTP_HOST`, `SMTP_PORT`, `SMTP_USERNAME`, `SMTP_PASSWORD`, `CORS_ALLOWED_ORIGINS`, `CORS_ALLOWED_METHODS`,
+    /// `CORS_ALLOWED_HEADERS`, `CORS_EXPOSED_HEADERS`, `CORS_ALLOW_CREDENTIALS`,
+    /// `CORS_MAX_AGE_SECS`, `LOG_FORMAT` and `RUST_LOG`
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        let var = |key: &str| var(key).filter(|val| !val.trim().is_empty());
+
+        set_parsed(&mut self.server.host, "HOST", &var)?;
+        set_parsed(&mut self.server.port, "PORT", &var)?;
+        set_flag(
+            &mut self.server.trust_forwarded_for,
+            "TRUST_FORWARDED_FOR",
+            &var,
+        )?;
+        set_parsed(
+            &mut self.server.trusted_proxy_hops,
+            "TRUSTED_PROXY_HOPS",
+            &var,
+        )?;
+        set_parsed(
+            &mut self.server.shutdown_timeout_secs,
+            "SHUTDOWN_TIMEOUT_SECS",
+            &var,
+        )?;
+
+        if let Some(url) = var("DATABASE_URL") {
+            self.database.url = Some(url);
+        }
+        let database = &mut self.database;
+        set_parsed(
+            &mut database.max_connections,
+            "DATABASE_MAX_CONNECTIONS",
+            &var,
+        )?;
+        set_parsed(
+            &mut database.min_connections,
+            "DATABASE_MIN_CONNECTIONS",
+            &var,
+        )?;
+        set_parsed(
+            &mut database.connect_timeout_secs,
+            "DATABASE_CONNECT_TIMEOUT_SECS",
+            &var,
+        )?;
+        set_parsed(
+            &mut database.idle_timeout_secs,
+            "DATABASE_IDLE_TIMEOUT_SECS",
+            &var,
+        )?;
+
+        if let Some(secret) = var("JWT_SECRET") {
+            self.jwt.secret = Some(secret);
+        }
+        if let Some(keys) = var("JWT_KEYS") {
+            self.jwt.keys = split_list(&keys);
+        }
+        if let Some(kid) = var("JWT_ACTIVE_KID") {
+            self.jwt.active_kid = Some(kid);
+        }
+        if let Some(kids) = var("JWT_RETIRED_KIDS") {
+            self.jwt.retired_kids = split_list(&kids);
+        }
+        set_flag(
+            &mut self.jwt.require_email_verification,
+            "REQUIRE_EMAIL_VERIFICATION",
+            &var,
+        )?;
+
+        let tokens = &mut self.tokens;
+        set_parsed(
+            &mut tokens.access_token_lifetime_secs,
+            "ACCESS_TOKEN_LIFETIME_SECS",
+            &var,
+        )?;
+        set_parsed(
+            &mut tokens.refresh_token_lifetime_secs,
+            "REFRESH_TOKEN_LIFETIME_SECS",
+            &var,
+        )?;
+        set_parsed(
+            &mut tokens.mfa_pending_lifetime_secs,
+            "MFA_PENDING_LIFETIME_SECS",
+            &var,
+        )?;
+        set_parsed(
+            &mut tokens.password_reset_lifetime_secs,
+            "PASSWORD_RESET_LIFETIME_SECS",
+            &var,
+        )?;
+        set_parsed(
+            &mut tokens.email_verification_lifetime_secs,
+            "EMAIL_VERIFICATION_LIFETIME_SECS",
+            &var,
+        )?;
+
+        let password = &mut self.password;
+        set_parsed(&mut password.algorithm, "PASSWORD_HASH_ALGORITHM", &var)?;
+        set_parsed(&mut password.argon2_memory_kib, "ARGON2_MEMORY_KIB", &var)?;
+        set_parsed(&mut password.argon2_iterations, "ARGON2_ITERATIONS", &var)?;
+        set_parsed(&mut password.argon2_parallelism, "ARGON2_PARALLELISM", &var)?;
+        set_parsed(&mut password.pbkdf2_rounds, "PBKDF2_ROUNDS", &var)?;
+
+        if let Some(roles) = var("MFA_REQUIRED_ROLES") {
+            self.mfa.required_roles = split_list(&roles);
+        }
+        if let Some(issuer) = var("MFA_ISSUER") {
+            self.mfa.issuer = issuer;
+        }
+
+        // Setting any of these enables OpenID Connect, the rest are then checked when validating
+        let oidc_keys = [
+            "OIDC_ISSUER",
+            "OIDC_CLIENT_ID",
+            "OIDC_CLIENT_SECRET",
+            "OIDC_REDIRECT_URI",
+            "OIDC_SCOPES",
+            "OIDC_POST_LOGIN_REDIRECT",
+        ];
+        if oidc_keys.iter().any(|key| var(key).is_some()) {
+            let oidc = self.oidc.get_or_insert_with(OidcConfig::default);
+            if let Some(issuer) = var("OIDC_ISSUER") {
+                oidc.issuer = issuer;
+            }
+            if let Some(client_id) = var("OIDC_CLIENT_ID") {
+                oidc.client_id = client_id;
+            }
+            if let Some(secret) = var("OIDC_CLIENT_SECRET") {
+                oidc.client_secret = Some(secret);
+            }
+            if let Some(uri) = var("OIDC_REDIRECT_URI") {
+                oidc.redirect_uri = uri;
+            }
+            if let Some(scopes) = var("OIDC_SCOPES") {
+                oidc.scopes = scopes;
+            }
+            if let Some(redirect) = var("OIDC_POST_LOGIN_REDIRECT") {
+                oidc.post_login_redirect = Some(redirect);
+            }
+        }
+
+        let mail = &mut self.mail;
+        set_parsed(&mut mail.transport, "MAIL_TRANSPORT", &var)?;
+        if let Some(dir) = var("MAIL_DIR") {
+            mail.dir = dir;
+        }
+        if let Some(from) = var("MAIL_FROM") {
+            mail.from = Some(from);
+        }
+        if let Some(host) = var("SMTP_HOST") {
+            mail.smtp_host = Some(host);
+        }
+        if let Some(port) = var("SMTP_PORT") {
+            let port = port.trim().parse().map_err(|_| ConfigError::InvalidValue {
+                key: "SMTP_PORT".to_string(),
+                value: port,
+            })?;
+            mail.smtp_port = Some(port);
+        }
+        if let Some(username) = var("SMTP_USERNAME") {
+            mail.smtp_username = Some(username);
+        }
+        if let Some(password) = var("SMTP_PASSWORD") {
+            mail.smtp_password = Some(password);
+        }
+
+        if let Some(origins) = var("CORS_ALLOWED_ORIGINS") {
+            self.cors.allowed_origins = split_list(&origins);
+        }
+        if let Some(methods) = var("CORS_ALLOWED_METHODS") {
+            self.cors.allowed_methods = split_list(&methods);
+        }
+        if let Some(headers) = var("CORS_ALLOWED_HEADERS") {
+            self.cors.allowed_headers = split_list(&headers);
+        }
+        if let Some(headers) = var("CORS_EXPOSED_HEADERS") {
+            self.cors.exposed_headers = split_list(&headers);
+        }
+        set_flag(
+            &mut self.cors.allow_credentials,
+            "CORS_ALLOW_CREDENTIALS",
+            &var,
+        )?;
+        if let Some(max_age) = var("CORS_MAX_AGE_SECS") {
+            let max_age = max_age
+                .trim()
+                .parse()
+                .map_err(|_| ConfigError::InvalidValue {
+                    key: "CORS_MAX_AGE_SECS".to_string(),
+                    value: max_age,
+                })?;
+            self.cors.max_age_secs = Some(max_age);
+        }
+
+        set_parsed(&mut self.log.format, "LOG_FORMAT", &var)?;
+        if let Some(filter) = var("RUST_LOG") {
+            self.log.filter = filter;
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.server.trusted_proxy_hops == 0 {
+            return Err(invalid("server.trusted_proxy_hops must be at least 1"));
+        }
+
+        let database = &self.database;
+        if database.max_connections == 0 {
+            return Err(invalid("database.max_connections must be at least 1"));
+        }
+        if database.min_connections > database.max_connections {
+            return Err(invalid(
+                "database.min_connections cannot exceed database.max_connections",
+            ));
+        }
+        if database.connect_timeout_secs == 0 {
+            return Err(invalid("database.connect_timeout_secs must be at least 1"));
+        }
+
+        if self.jwt.keys.is_empty() && self.jwt.secret.is_none() {
+            return Err(ConfigError::Missing("JWT_SECRET or JWT_KEYS".to_string()));
+        }
+        if let Some(kid) = &self.jwt.active_kid {
+            if self.jwt.retired_kids.contains(kid) {
+                return Err(invalid(&format!(
+                    "jwt.active_kid `{}` cannot also be retired",
+                    kid
+                )));
+            }
+        }
+
+        let tokens = &self.tokens;
+        let lifetimes = [
+            ("access_token", tokens.access_token_lifetime_secs),
+            ("refresh_token", tokens.refresh_token_lifetime_secs),
+            ("mfa_pending", tokens.mfa_pending_lifetime_secs),
+            ("password_reset", tokens.password_reset_lifetime_secs),
+            (
+                "email_verification",
+                tokens.email_verification_lifetime_secs,
+            ),
+        ];
+        if let Some((name, _)) = lifetimes.iter().find(|(_, secs)| *secs == 0) {
+            return Err(invalid(&format!(
+                "tokens.{}_lifetime_secs must be at least 1",
+                name
+            )));
+        }
+        // A session would be considered inactive while its access token is still valid
+        if tokens.access_token_lifetime_secs > tokens.refresh_token_lifetime_secs {
+            return Err(invalid(
+                "tokens.access_token_lifetime_secs cannot exceed tokens.refresh_token_lifetime_secs",
+            ));
+        }
+
+        if let Err(e) = HashAlgorithm::from_config(&self.password).check() {
+            return Err(invalid(&format!(
+                "password hashing parameters are not usable: {}",
+                e
+            )));
+        }
+
+        check_each("mfa.required_roles", &self.mfa.required_roles, |role| {
+            Role::from_str(role).is_ok()
+        })?;
+        if self.mfa.issuer.trim().is_empty() {
+            return Err(invalid("mfa.issuer cannot be empty"));
+        }
+
+        if let Some(oidc) = &self.oidc {
+            if oidc.issuer.is_empty() {
+                return Err(ConfigError::Missing("OIDC_ISSUER".to_string()));
+            }
+            if oidc.client_id.is_empty() {
+                return Err(ConfigError::Missing("OIDC_CLIENT_ID".to_string()));
+            }
+            if oidc.redirect_uri.is_empty() {
+                return Err(ConfigError::Missing("OIDC_REDIRECT_URI".to_string()));
+            }
+            check_each("oidc.issuer", &[oidc.issuer.to_owned()], is_http_url)?;
+            check_each(
+                "oidc.redirect_uri",
+                &[oidc.redirect_uri.to_owned()],
+                is_http_url,
+            )?;
+            if let Some(redirect) = &oidc.post_login_redirect {
+                check_each(
+                    "oidc.post_login_redirect",
+                    &[redirect.to_owned()],
+                    is_http_url,
+                )?;
+            }
+            if !oidc
+                .scopes
+                .split_whitespace()
+                .any(|scope| scope == "openid")
+            {
+                return Err(invalid("oidc.scopes must include `openid`"));
+            }
+        }
+
+        let mail = &self.mail;
+        if mail.transport == MailTransport::Smtp {
+            let required = [
+                ("SMTP_HOST", &mail.smtp_host),
+                ("SMTP_USERNAME", &mail.smtp_username),
+                ("SMTP_PASSWORD", &mail.smtp_password),
+                ("MAIL_FROM", &mail.from),
+            ];
+            if let Some((key, _)) = required.iter().find(|(_, value)| value.is_none()) {
+                return Err(ConfigError::Missing(key.to_string()));
+            }
+        }
+        if let Some(from) = &mail.from {
+            check_each("mail.from", &[from.to_owned()], |from| {
+                from.parse::<Mailbox>().is_ok()
+            })?;
+        }
+
+        let cors = &self.cors;
+        check_each("cors.allowed_origins", &cors.allowed_origins, is_origin)?;
+        check_each("cors.allowed_methods", &cors.allowed_methods, |method| {
+            Method::from_bytes(method.as_bytes()).is_ok()
+        })?;
+        check_each("cors.allowed_headers", &cors.allowed_headers, is_header)?;
+        check_each("cors.exposed_headers", &cors.exposed_headers, is_header)?;
+        // Browsers refuse credentialed responses that allow any origin
+        if cors.allow_credentials && cors.allowed_origins.is_empty() {
+            return Err(invalid(
+                "cors.allow_credentials requires cors.allowed_origins to be listed",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn invalid(reason: &str) -> ConfigError {
+    ConfigError::Invalid(reason.to_string())
+}
+
+fn set_parsed<T: FromStr>(
+    field: &mut T,
+    key: &str,
+    var: &impl Fn(&str) -> Option<String>,
+) -> Result<(), ConfigError> {
+    if let Some(value) = var(key) {
+        *field = value
+            .trim()
+            .parse()
+            .map_err(|_| ConfigError::InvalidValue {
+                key: key.to_string(),
+                value,
+            })?;
+    }
+    Ok(())
+}
+
+fn set_flag(
+    field: &mut bool,
+    key: &str,
+    var: &impl Fn(&str) -> Option<String>,
+) -> Result<(), ConfigError> {
+    if let Some(value) = var(key) {
+        *field = match value.trim().to_lowercase().as_str() {
+            "true" | "1" => true,
+            "false" | "0" => false,
+            _ => {
+                return Err(ConfigError::InvalidValue {
+                    key: key.to_string(),
+                    value,
+                })
+            }
+        };
+    }
+    Ok(())
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn check_each(
+    key: &str,
+    values: &[String],
+    is_valid: impl Fn(&str) -> bool,
+) -> Result<(), ConfigError> {
+    match values.iter().find(|value| !is_valid(value)) {
+        Some(value) => Err(ConfigError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+fn is_http_url(url: &str) -> bool {
+    match Url::parse(url) {
+        Ok(url) => matches!(url.scheme(), "http" | "https") && url.host().is_some(),
+        Err(_) => false,
+    }
+}
+
+fn is_header(header: &str) -> bool {
+    HeaderName::from_bytes(header.as_bytes()).is_ok()
+}
+
+// Browsers send origins as a scheme, host and optional port without a path
+fn is_origin(origin: &str) -> bool {
+    match Url::parse(origin) {
+        Ok(url) => {
+            matches!(url.scheme(), "http" | "https")
+                && url.host().is_some()
+                && url.path() == "/"
+                && !origin.ends_with('/')
+                && url.query().is_none()
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod test_config {
+    use std::{collections::HashMap, net::Ipv4Addr, time::Duration};
+
+    use super::{Config, LogFormat, MailTransport, PasswordHashAlgorithm};
+    use crate::{auth::hash::HashAlgorithm, errors::ConfigError};
+    use entity::sea_orm_active_enums::Role;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn defaults_bind_localhost() {
+        let config = Config::default();
+        assert_eq!(config.server.address().to_string(), "127.0.0.1:8080");
+        assert_eq!(
+            config.tokens.access_token_lifetime(),
+            Duration::from_secs(3600)
+        );
+        assert!(config.cors.allowed_origins.is_empty());
+    }
+
+    #[test]
+    fn reads_toml_sections() {
+        let config = Config::from_toml(
+            r#"
+            [server]
+            host = "0.0.0.0"
+            port = 9000
+
+            [database]
+            url = "sqlite::memory:"
+            max_connections = 4
+
+            [jwt]
+            secret = "secret"
+            require_email_verification = true
+
+            [cors]
+            allowed_origins = ["https://gilded.university"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.server.host, Ipv4Addr::UNSPECIFIED);
+        assert_eq!(config.server.port, 9000);
+        assert_eq!(config.database.url().unwrap(), "sqlite::memory:");
+        assert_eq!(config.database.max_connections, 4);
+        assert_eq!(config.database.min_connections, 1);
+        assert!(config.jwt.require_email_verification);
+        assert_eq!(
+            config.cors.allowed_origins,
+            vec!["https://gilded.university"]
+        );
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn example_file_is_valid() {
+        let config = Config::from_file("config.example.toml").unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let got = Config::from_toml("[server]\nhots = \"0.0.0.0\"");
+        assert!(matches!(got, Err(ConfigError::InvalidFile { .. })));
+    }
+
+    #[test]
+    fn env_overrides_file() {
+        let mut config = Config::from_toml("[server]\nport = 9000").unwrap();
+        config
+            .apply_env(env(&[
+                ("PORT", "9100"),
+                ("SHUTDOWN_TIMEOUT_SECS", "5"),
+                ("JWT_SECRET", "secret"),
+                ("JWT_KEYS", "a:RS256:a.pem, b:EdDSA:b.pem"),
+                ("TRUST_FORWARDED_FOR", "TRUE"),
+                ("TRUSTED_PROXY_HOPS", "2"),
+                (
+                    "CORS_ALLOWED_ORIGINS",
+                    "http://localhost:3000,https://gilded.university",
+                ),
+                ("CORS_ALLOWED_HEADERS", "Content-Type, Authorization"),
+                ("CORS_ALLOW_CREDENTIALS", "true"),
+                ("CORS_MAX_AGE_SECS", "600"),
+                ("LOG_FORMAT", "JSON"),
+            ]))
+            .unwrap();
+        assert_eq!(config.server.port, 9100);
+        assert_eq!(config.server.shutdown_timeout(), Duration::from_secs(5));
+        assert!(config.server.trust_forwarded_for);
+        assert_eq!(config.server.forwarded_hops(), 2);
+        assert_eq!(config.jwt.keys, vec!["a:RS256:a.pem", "b:EdDSA:b.pem"]);
+        assert_eq!(config.cors.allowed_origins.len(), 2);
+        assert_eq!(
+            config.cors.allowed_headers,
+            vec!["Content-Type", "Authorization"]
+        );
+        assert_eq!(config.cors.allowed_methods, vec!["GET", "POST", "DELETE"]);
+        assert!(config.cors.allow_credentials);
+        assert_eq!(config.cors.max_age_secs, Some(600));
+        assert_eq!(config.log.format, LogFormat::Json);
+        assert_eq!(config.log.filter, "info");
+    }
+
+    #[test]
+    fn rejects_unparseable_env_values() {
+        let mut config = Config::default();
+        let got = config.apply_env(env(&[("PORT", "eighty")]));
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "PORT has an invalid value `eighty`"
+        );
+
+        let got = config.apply_env(env(&[("REQUIRE_EMAIL_VERIFICATION", "yes please")]));
+        assert!(got.is_err());
+    }
+
+    #[test]
+    fn validates_settings() {
+        let valid = || {
+            let mut config = Config::default();
+            config.jwt.secret = Some("secret".to_string());
+            config
+        };
+        assert!(valid().validate().is_ok());
+
+        let got = Config::default().validate();
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "JWT_SECRET or JWT_KEYS must be set"
+        );
+
+        let mut config = valid();
+        config.database.min_connections = 20;
+        assert!(config.validate().is_err());
+
+        let mut config = valid();
+        config.tokens.access_token_lifetime_secs = 0;
+        assert_eq!(
+            config.validate().err().unwrap().to_string(),
+            "Invalid configuration: tokens.access_token_lifetime_secs must be at least 1"
+        );
+
+        let mut config = valid();
+        config.tokens.refresh_token_lifetime_secs = 60;
+        assert!(config.validate().is_err());
+
+        for origin in [
+            "gilded.university",
+            "https://gilded.university/",
+            "ftp://files",
+        ] {
+            let mut config = valid();
+            config.cors.allowed_origins = vec![origin.to_string()];
+            assert!(config.validate().is_err(), "{} should be rejected", origin);
+        }
+        let mut config = valid();
+        config.cors.allowed_origins = vec!["http://localhost:3000".to_string()];
+        assert!(config.validate().is_ok());
+
+        let mut config = valid();
+        config.cors.allowed_methods = vec!["GET POST".to_string()];
+        assert_eq!(
+            config.validate().err().unwrap().to_string(),
+            "cors.allowed_methods has an invalid value `GET POST`"
+        );
+
+        let mut config = valid();
+        config.cors.allowed_headers = vec!["X Custom".to_string()];
+        assert!(config.validate().is_err());
+
+        let mut config = valid();
+        config.cors.allow_credentials = true;
+        assert!(config.validate().is_err());
+        config.cors.allowed_origins = vec!["https://gilded.university".to_string()];
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn reads_password_mfa_oidc_and_mail_settings() {
+        let mut config = Config::default();
+        config
+            .apply_env(env(&[
+                ("PASSWORD_HASH_ALGORITHM", "PBKDF2"),
+                ("PBKDF2_ROUNDS", "20000"),
+                ("MFA_REQUIRED_ROLES", "Teacher, Admin"),
+                ("OIDC_ISSUER", "https://idp.example.com"),
+                ("OIDC_CLIENT_ID", "client"),
+                (
+                    "OIDC_REDIRECT_URI",
+                    "http://localhost:8080/auth/oidc/callback",
+                ),
+                ("MAIL_TRANSPORT", "smtp"),
+                ("SMTP_HOST", "smtp.example.com"),
+                ("SMTP_PORT", "2525"),
+            ]))
+            .unwrap();
+        assert_eq!(
+            HashAlgorithm::from_config(&config.password),
+            HashAlgorithm::Pbkdf2 { rounds: 20000 }
+        );
+        assert_eq!(
+            config.mfa.required_roles(),
+            vec![Role::Teacher, Role::Admin]
+        );
+        assert_eq!(config.mfa.issuer, "Gilded University");
+        let oidc = config.oidc.as_ref().unwrap();
+        assert_eq!(oidc.issuer, "https://idp.example.com");
+        assert_eq!(oidc.scopes, "openid email profile");
+        assert!(oidc.client_secret.is_none());
+        assert_eq!(config.mail.transport, MailTransport::Smtp);
+        assert_eq!(config.mail.smtp_port, Some(2525));
+
+        assert!(Config::default().oidc.is_none());
+        assert_eq!(Config::default().mail.transport, MailTransport::File);
+    }
+
+    // Mistyped values are reported rather than replaced by the defaults
+    #[test]
+    fn rejects_invalid_password_mfa_oidc_and_mail_settings() {
+        for (key, value) in [
+            ("PASSWORD_HASH_ALGORITHM", "bcrypt"),
+            ("ARGON2_MEMORY_KIB", "lots"),
+            ("PBKDF2_ROUNDS", "-1"),
+            ("MAIL_TRANSPORT", "carrier-pigeon"),
+            ("SMTP_PORT", "smtp"),
+        ] {
+            let got = Config::default().apply_env(env(&[(key, value)]));
+            assert_eq!(
+                got.err().unwrap().to_string(),
+                format!("{} has an invalid value `{}`", key, value)
+            );
+        }
+
+        let valid = || {
+            let mut config = Config::default();
+            config.jwt.secret = Some("secret".to_string());
+            config
+        };
+
+        let mut config = valid();
+        config.password.argon2_parallelism = 0;
+        assert!(config.validate().is_err());
+
+        let mut config = valid();
+        config.password.algorithm = PasswordHashAlgorithm::Pbkdf2;
+        config.password.pbkdf2_rounds = 0;
+        assert!(config.validate().is_err());
+
+        let mut config = valid();
+        config.mfa.required_roles = vec!["Teacher".to_string(), "admin".to_string()];
+        assert_eq!(
+            config.validate().err().unwrap().to_string(),
+            "mfa.required_roles has an invalid value `admin`"
+        );
+
+        let mut config = valid();
+        config
+            .apply_env(env(&[("OIDC_CLIENT_ID", "client")]))
+            .unwrap();
+        assert_eq!(
+            config.validate().err().unwrap().to_string(),
+            "OIDC_ISSUER must be set"
+        );
+
+        let mut config = valid();
+        config
+            .apply_env(env(&[
+                ("OIDC_ISSUER", "idp.example.com"),
+                ("OIDC_CLIENT_ID", "client"),
+                (
+                    "OIDC_REDIRECT_URI",
+                    "http://localhost:8080/auth/oidc/callback",
+                ),
+            ]))
+            .unwrap();
+        assert_eq!(
+            config.validate().err().unwrap().to_string(),
+            "oidc.issuer has an invalid value `idp.example.com`"
+        );
+
+        let mut config = valid();
+        config.mail.transport = MailTransport::Smtp;
+        config.mail.smtp_host = Some("smtp.example.com".to_string());
+        assert_eq!(
+            config.validate().err().unwrap().to_string(),
+            "SMTP_USERNAME must be set"
+        );
+        config.mail.smtp_username = Some("user".to_string());
+        config.mail.smtp_password = Some("password".to_string());
+        config.mail.from = Some("not an address".to_string());
+        assert!(config.validate().is_err());
+        config.mail.from = Some("Gilded University <noreply@gilded.university>".to_string());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn database_url_is_required_to_connect() {
+        let got = Config::default().database.url().err().unwrap();
+        assert_eq!(got.to_string(), "DATABASE_URL must be set");
+    }
+}
@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use warp::cors::Builder;
+
+use crate::config::CorsConfig;
+
+/// Builds the CORS policy applied to every route. The configuration is validated
+/// on startup, as warp panics on origins, methods or headers it cannot parse.
+pub fn create_cors(config: &CorsConfig) -> Builder {
+    let cors = match config.allowed_origins.is_empty() {
+        true => warp::cors().allow_any_origin(),
+        false => warp::cors().allow_origins(config.allowed_origins.iter().map(String::as_str)),
+    };
+    let cors = cors
+        .allow_methods(config.allowed_methods.iter().map(String::as_str))
+        .allow_headers(config.allowed_headers.iter().map(String::as_str))
+        .expose_headers(config.exposed_headers.iter().map(String::as_str))
+        .allow_credentials(config.allow_credentials);
+    match config.max_age_secs {
+        Some(max_age) => cors.max_age(Duration::from_secs(max_age)),
+        None => cors,
+    }
+}
+
+#[cfg(test)]
+mod test_cors {
+    use warp::{http::StatusCode, test::request, Filter};
+
+    use super::create_cors;
+    use crate::config::CorsConfig;
+
+    fn preflight(origin: &str, headers: &str) -> warp::test::RequestBuilder {
+        request()
+            .method("OPTIONS")
+            .path("/graphql")
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", "POST")
+            .header("Access-Control-Request-Headers", headers)
+    }
+
+    #[tokio::test]
+    async fn any_origin_is_allowed_by_default() {
+        let filter = warp::any()
+            .map(warp::reply)
+            .with(create_cors(&CorsConfig::default()));
+
+        let res = preflight("https://example.com", "authorization, content-type")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()["access-control-allow-origin"],
+            "https://example.com"
+        );
+        assert!(res
+            .headers()
+            .get("access-control-allow-credentials")
+            .is_none());
+
+        let res = preflight("https://example.com", "x-custom")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn credentials_are_allowed_for_listed_origins() {
+        let config = CorsConfig {
+            allowed_origins: vec!["https://gilded.university".to_string()],
+            allow_credentials: true,
+            max_age_secs: Some(600),
+            ..CorsConfig::default()
+        };
+        let filter = warp::any().map(warp::reply).with(create_cors(&config));
+
+        let res = preflight("https://gilded.university", "content-type")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["access-control-allow-credentials"], "true");
+        assert_eq!(res.headers()["access-control-max-age"], "600");
+
+        let res = request()
+            .path("/graphql")
+            .header("Origin", "https://gilded.university")
+            .reply(&filter)
+            .await;
+        assert_eq!(
+            res.headers()["access-control-expose-headers"],
+            "x-request-id"
+        );
+
+        let res = preflight("https://example.com", "content-type")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+}
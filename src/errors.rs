@@ -1,5 +1,119 @@
+use std::fmt::Display;
+
+use juniper::{FieldError, IntoFieldError, Object, ScalarValue, Value};
+use sea_orm::DbErr;
 use thiserror::Error;
 
+pub const UNAUTHENTICATED: &str = "UNAUTHENTICATED";
+pub const FORBIDDEN: &str = "FORBIDDEN";
+pub const CONFLICT: &str = "CONFLICT";
+pub const NOT_FOUND: &str = "NOT_FOUND";
+pub const BAD_USER_INPUT: &str = "BAD_USER_INPUT";
+pub const TOO_MANY_REQUESTS: &str = "TOO_MANY_REQUESTS";
+pub const INTERNAL_SERVER_ERROR: &str = "INTERNAL_SERVER_ERROR";
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+/// Every error a resolver can return. Each reaches clients with an
+/// `extensions.code`, and a `reason` telling apart errors that share a code.
+#[derive(Error, Debug)]
+pub enum ApiError {
+    #[error(transparent)]
+    User(#[from] UserError),
+    #[error(transparent)]
+    Authorization(#[from] AuthorizationError),
+    #[error(transparent)]
+    Time(#[from] TimeError),
+    #[error(transparent)]
+    Query(#[from] QueryError),
+    #[error(transparent)]
+    Mail(#[from] MailError),
+    #[error(transparent)]
+    Oidc(#[from] OidcError),
+    #[error(transparent)]
+    Database(#[from] DbErr),
+    #[error("Unable to hash password: {0}")]
+    Hash(argon2::password_hash::Error),
+}
+
+impl ApiError {
+    /// The `reason` clients receive, database and hashing errors only have a code
+    pub fn reason(&self) -> String {
+        match self {
+            ApiError::User(e) => reason(e),
+            ApiError::Authorization(e) => reason(e),
+            ApiError::Time(e) => reason(e),
+            ApiError::Query(e) => reason(e),
+            ApiError::Mail(e) => reason(e),
+            ApiError::Oidc(e) => reason(e),
+            ApiError::Database(_) | ApiError::Hash(_) => INTERNAL_SERVER_ERROR.to_string(),
+        }
+    }
+}
+
+// Password hash errors do not implement `std::error::Error`, so they cannot be a source
+impl From<argon2::password_hash::Error> for ApiError {
+    fn from(e: argon2::password_hash::Error) -> Self {
+        ApiError::Hash(e)
+    }
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for ApiError {
+    fn into_field_error(self) -> FieldError<S> {
+        match self {
+            ApiError::User(e) => e.into_field_error(),
+            ApiError::Authorization(e) => e.into_field_error(),
+            ApiError::Time(e) => e.into_field_error(),
+            ApiError::Query(e) => e.into_field_error(),
+            ApiError::Mail(e) => field_error(&e, INTERNAL_SERVER_ERROR, reason(&e), vec![]),
+            ApiError::Oidc(e) => field_error(&e, BAD_USER_INPUT, reason(&e), vec![]),
+            // The underlying errors can include SQL and table contents, so they are only logged
+            ApiError::Database(_) | ApiError::Hash(_) => {
+                tracing::error!(error = %self, "Unable to complete request");
+                let mut extensions = Object::with_capacity(1);
+                extensions.add_field("code", Value::scalar(INTERNAL_SERVER_ERROR.to_string()));
+                FieldError::new("Unable to complete request", Value::Object(extensions))
+            }
+        }
+    }
+}
+
+fn field_error<S: ScalarValue>(
+    message: impl Display,
+    code: &str,
+    reason: String,
+    details: Vec<(&str, Value<S>)>,
+) -> FieldError<S> {
+    let mut extensions = Object::with_capacity(details.len() + 2);
+    extensions.add_field("code", Value::scalar(code.to_string()));
+    extensions.add_field("reason", Value::scalar(reason));
+    for (key, value) in details {
+        extensions.add_field(key, value);
+    }
+    FieldError::new(message, Value::Object(extensions))
+}
+
+// The variant name in upper snake case, e.g. `TOKEN_EXPIRED` for `TokenExpired`
+fn reason(error: &impl std::fmt::Debug) -> String {
+    let debug = format!("{:?}", error);
+    let variant = debug
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default();
+    let mut reason = String::with_capacity(variant.len() + 4);
+    for (i, c) in variant.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            reason.push('_');
+        }
+        reason.push(c.to_ascii_uppercase());
+    }
+    reason
+}
+
+fn int<S: ScalarValue>(value: u64) -> Value<S> {
+    Value::scalar(i32::try_from(value).unwrap_or(i32::MAX))
+}
+
 #[derive(Error, Debug)]
 pub enum UserError {
     #[error("Incorrect email or password")]
@@ -8,19 +122,79 @@ pub enum UserError {
     UnableToComplete,
     #[error("User with email `{0}` already exists")]
     UserWithEmailAlreadyExists(String),
+    #[error("Password reset token is invalid or has expired")]
+    InvalidResetToken,
+    #[error("Verification token is invalid or has expired")]
+    InvalidVerificationToken,
+    #[error("Email address has already been verified")]
+    EmailAlreadyVerified,
+    #[error("Too many failed signin attempts, try again in {retry_after} seconds")]
+    AccountLocked { retry_after: u64 },
+    #[error("Two-factor authentication is already enabled")]
+    MfaAlreadyEnabled,
+    #[error("Two-factor authentication has not been set up")]
+    MfaNotEnrolled,
+    #[error("Invalid two-factor authentication code")]
+    InvalidMfaCode,
+    #[error("Account has been suspended")]
+    AccountSuspended,
+    #[error("Account is already suspended")]
+    AlreadySuspended,
+    #[error("Account is not suspended")]
+    NotSuspended,
+    #[error("The last active Admin cannot be demoted, suspended or deleted")]
+    LastAdmin,
+    #[error("Current password is incorrect")]
+    IncorrectPassword,
+    #[error("Name cannot be empty")]
+    EmptyName,
+    #[error("Email change token is invalid or has expired")]
+    InvalidEmailChangeToken,
+    #[error("Session not found")]
+    SessionNotFound,
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for UserError {
+    fn into_field_error(self) -> FieldError<S> {
+        let code = match self {
+            UserError::IncorrectEmailOrPassword => UNAUTHENTICATED,
+            UserError::AccountSuspended => FORBIDDEN,
+            UserError::UserWithEmailAlreadyExists(_)
+            | UserError::EmailAlreadyVerified
+            | UserError::MfaAlreadyEnabled
+            | UserError::AlreadySuspended
+            | UserError::NotSuspended
+            | UserError::LastAdmin => CONFLICT,
+            UserError::SessionNotFound => NOT_FOUND,
+            UserError::AccountLocked { .. } => TOO_MANY_REQUESTS,
+            UserError::UnableToComplete => INTERNAL_SERVER_ERROR,
+            UserError::InvalidResetToken
+            | UserError::InvalidVerificationToken
+            | UserError::InvalidEmailChangeToken
+            | UserError::MfaNotEnrolled
+            | UserError::InvalidMfaCode
+            | UserError::IncorrectPassword
+            | UserError::EmptyName => BAD_USER_INPUT,
+        };
+        let details = match &self {
+            UserError::UserWithEmailAlreadyExists(email) => {
+                vec![("email", Value::scalar(email.to_owned()))]
+            }
+            UserError::AccountLocked { retry_after } => vec![("retryAfter", int(*retry_after))],
+            _ => vec![],
+        };
+        field_error(&self, code, reason(&self), details)
+    }
 }
 
 #[derive(Error, Debug)]
 pub enum AuthorizationError {
-    #[error(
-        "Required permission is {} but user has permission {}",
-        required,
-        permission
-    )]
-    InsufficientPermission {
-        required: String,
-        permission: String,
-    },
+    #[error("Permission `{required}` is not granted to role {role}")]
+    MissingPermission { required: String, role: String },
+    #[error("Unknown permission `{0}`")]
+    UnknownPermission(String),
+    #[error("Permission `{0}` cannot be revoked from Admin")]
+    ProtectedPermission(String),
     #[error("Unable to decode JWT: {0}")]
     DecodingError(String),
     #[error("Unable to encode JWT: {0}")]
@@ -29,6 +203,57 @@ pub enum AuthorizationError {
     TokenExpired,
     #[error("Token missing")]
     TokenMissing,
+    #[error("Token has been revoked")]
+    TokenRevoked,
+    #[error("Email address must be verified")]
+    EmailNotVerified,
+    #[error("Refresh token is invalid")]
+    RefreshTokenInvalid,
+    #[error("Refresh token has expired")]
+    RefreshTokenExpired,
+    #[error("Refresh token has already been used")]
+    RefreshTokenReused,
+    #[error("Two-factor authentication must be completed")]
+    MfaPending,
+    #[error("Token is not awaiting two-factor authentication")]
+    MfaNotPending,
+    #[error("Two-factor authentication is required for this account")]
+    MfaRequired,
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for AuthorizationError {
+    fn into_field_error(self) -> FieldError<S> {
+        let code = match self {
+            AuthorizationError::MissingPermission { .. }
+            | AuthorizationError::EmailNotVerified
+            | AuthorizationError::MfaRequired => FORBIDDEN,
+            AuthorizationError::UnknownPermission(_) | AuthorizationError::MfaNotPending => {
+                BAD_USER_INPUT
+            }
+            AuthorizationError::ProtectedPermission(_) => CONFLICT,
+            AuthorizationError::EncodingError(_) => INTERNAL_SERVER_ERROR,
+            AuthorizationError::DecodingError(_)
+            | AuthorizationError::TokenExpired
+            | AuthorizationError::TokenMissing
+            | AuthorizationError::TokenRevoked
+            | AuthorizationError::RefreshTokenInvalid
+            | AuthorizationError::RefreshTokenExpired
+            | AuthorizationError::RefreshTokenReused
+            | AuthorizationError::MfaPending => UNAUTHENTICATED,
+        };
+        let details = match &self {
+            AuthorizationError::MissingPermission { required, role } => vec![
+                ("requiredPermission", Value::scalar(required.to_owned())),
+                ("role", Value::scalar(role.to_owned())),
+            ],
+            AuthorizationError::UnknownPermission(permission)
+            | AuthorizationError::ProtectedPermission(permission) => {
+                vec![("permission", Value::scalar(permission.to_owned()))]
+            }
+            _ => vec![],
+        };
+        field_error(&self, code, reason(&self), details)
+    }
 }
 
 #[derive(Error, Debug)]
@@ -38,3 +263,175 @@ pub enum TimeError {
     #[error("Unable to determine {0} seconds from now")]
     CalculationError(u64),
 }
+
+impl<S: ScalarValue> IntoFieldError<S> for TimeError {
+    fn into_field_error(self) -> FieldError<S> {
+        field_error(&self, INTERNAL_SERVER_ERROR, reason(&self), vec![])
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum MailError {
+    #[error("Invalid email address `{0}`")]
+    InvalidAddress(String),
+    #[error("Unable to configure mail transport: {0}")]
+    TransportError(String),
+    #[error("Unable to send email: {0}")]
+    SendError(String),
+}
+
+#[derive(Error, Debug)]
+pub enum KeyError {
+    #[error("Invalid key specification `{0}`, expected `kid:algorithm:path`")]
+    InvalidSpecification(String),
+    #[error("Unsupported signing algorithm `{0}`")]
+    UnsupportedAlgorithm(String),
+    #[error("Unable to read key `{kid}`: {reason}")]
+    UnreadableKey { kid: String, reason: String },
+    #[error("Active key `{0}` is not configured or has been retired")]
+    UnknownActiveKey(String),
+    #[error("Active key `{0}` has no private key to sign with")]
+    NoSigningKey(String),
+    #[error("Neither a JWT secret nor signing keys are configured")]
+    NoKeys,
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Unable to read config file {path}: {reason}")]
+    UnreadableFile { path: String, reason: String },
+    #[error("Invalid config file {path}: {reason}")]
+    InvalidFile { path: String, reason: String },
+    #[error("{key} has an invalid value `{value}`")]
+    InvalidValue { key: String, value: String },
+    #[error("{0} must be set")]
+    Missing(String),
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
+}
+
+#[derive(Error, Debug)]
+pub enum OidcError {
+    #[error("Unable to reach identity provider: {0}")]
+    Http(String),
+    #[error("Unable to read identity provider configuration: {0}")]
+    Discovery(String),
+    #[error("Login request is invalid or has expired")]
+    InvalidState,
+    #[error("Identity provider returned an error: {0}")]
+    ProviderError(String),
+    #[error("Unable to exchange authorization code: {0}")]
+    TokenExchange(String),
+    #[error("Invalid ID token: {0}")]
+    InvalidIdToken(String),
+    #[error("Identity provider did not supply an email address")]
+    MissingEmail,
+}
+
+#[derive(Error, Debug)]
+pub enum QueryError {
+    #[error("Cursor is invalid or was created for a different ordering")]
+    InvalidCursor,
+    #[error("`first` must be between 0 and {max}")]
+    PageSizeOutOfRange { max: i32 },
+    #[error("`{0}` is not a valid ID")]
+    InvalidId(String),
+    #[error("`{0}` is not a valid IP address")]
+    InvalidIpAddress(String),
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for QueryError {
+    fn into_field_error(self) -> FieldError<S> {
+        let details = match &self {
+            QueryError::PageSizeOutOfRange { max } => vec![("max", Value::scalar(*max))],
+            QueryError::InvalidId(id) => vec![("id", Value::scalar(id.to_owned()))],
+            QueryError::InvalidIpAddress(ip) => {
+                vec![("ipAddress", Value::scalar(ip.to_owned()))]
+            }
+            QueryError::InvalidCursor => vec![],
+        };
+        field_error(&self, BAD_USER_INPUT, reason(&self), details)
+    }
+}
+
+#[cfg(test)]
+mod test_errors {
+    use juniper::{graphql_value, DefaultScalarValue, FieldError, IntoFieldError};
+    use sea_orm::DbErr;
+
+    use super::{ApiError, AuthorizationError, QueryError, UserError};
+
+    fn into_field_error(error: impl Into<ApiError>) -> FieldError<DefaultScalarValue> {
+        error.into().into_field_error()
+    }
+
+    #[test]
+    fn errors_carry_code_and_reason() {
+        let got = into_field_error(UserError::IncorrectEmailOrPassword);
+        assert_eq!(got.message(), "Incorrect email or password");
+        assert_eq!(
+            got.extensions(),
+            &graphql_value!({
+                "code": "UNAUTHENTICATED",
+                "reason": "INCORRECT_EMAIL_OR_PASSWORD",
+            })
+        );
+
+        let got = into_field_error(AuthorizationError::TokenMissing);
+        assert_eq!(
+            got.extensions(),
+            &graphql_value!({"code": "UNAUTHENTICATED", "reason": "TOKEN_MISSING"})
+        );
+    }
+
+    #[test]
+    fn errors_carry_details() {
+        let got = into_field_error(AuthorizationError::MissingPermission {
+            required: "users:manage".to_string(),
+            role: "Student".to_string(),
+        });
+        assert_eq!(
+            got.extensions(),
+            &graphql_value!({
+                "code": "FORBIDDEN",
+                "reason": "MISSING_PERMISSION",
+                "requiredPermission": "users:manage",
+                "role": "Student",
+            })
+        );
+
+        let got = into_field_error(UserError::UserWithEmailAlreadyExists(
+            "test@test.com".to_string(),
+        ));
+        assert_eq!(
+            got.extensions(),
+            &graphql_value!({
+                "code": "CONFLICT",
+                "reason": "USER_WITH_EMAIL_ALREADY_EXISTS",
+                "email": "test@test.com",
+            })
+        );
+
+        let got = into_field_error(QueryError::PageSizeOutOfRange { max: 100 });
+        assert_eq!(
+            got.extensions(),
+            &graphql_value!({
+                "code": "BAD_USER_INPUT",
+                "reason": "PAGE_SIZE_OUT_OF_RANGE",
+                "max": 100,
+            })
+        );
+    }
+
+    #[test]
+    fn database_errors_are_not_exposed() {
+        let got = into_field_error(DbErr::Custom(
+            "SELECT password FROM user WHERE id = 1".to_string(),
+        ));
+        assert_eq!(got.message(), "Unable to complete request");
+        assert_eq!(
+            got.extensions(),
+            &graphql_value!({"code": "INTERNAL_SERVER_ERROR"})
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use once_cell::sync::Lazy;
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+use entity::user;
+
+// Subscribers that fall this far behind skip ahead to the most recent changes
+const CAPACITY: usize = 256;
+
+// Changes are only broadcast to subscribers connected to this instance,
+// subscribers on other instances hear about the changes made there
+static BUS: Lazy<Sender<StatusChange>> = Lazy::new(|| broadcast::channel(CAPACITY).0);
+
+/// A user's status as it was after the change
+#[derive(Debug, Clone)]
+pub struct StatusChange {
+    pub user: user::Model,
+}
+
+pub fn publish_status_change(user: &user::Model) {
+    // Sending only fails when nobody is subscribed
+    let _ = BUS.send(StatusChange { user: user.clone() });
+}
+
+pub fn subscribe_status_changes() -> Receiver<StatusChange> {
+    BUS.subscribe()
+}
+
+#[cfg(test)]
+mod test_events {
+    use entity::{
+        sea_orm_active_enums::{Role, Status},
+        user,
+    };
+    use sea_orm::prelude::Uuid;
+
+    use super::{publish_status_change, subscribe_status_changes};
+
+    #[tokio::test]
+    async fn subscribers_receive_published_changes() {
+        let user = user::Model {
+            id: Uuid::new_v4(),
+            email: "test@test.com".to_string(),
+            name: "test".to_string(),
+            password: "".to_string(),
+            status: Status::Online,
+            role: Role::Guest,
+            email_verified_at: None,
+            suspended_at: None,
+        };
+        let mut receiver = subscribe_status_changes();
+
+        publish_status_change(&user);
+
+        // Other tests publish to the same bus
+        loop {
+            let change = receiver.recv().await.unwrap();
+            if change.user.id == user.id {
+                assert_eq!(change.user.status, Status::Online);
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::{collections::HashMap, str, sync::Arc, time::Instant};
+
+use tracing::{field, info_span, Instrument};
+
+use juniper::{
+    futures::future::join_all,
+    http::{GraphQLBatchRequest, GraphQLBatchResponse, GraphQLRequest, GraphQLResponse},
+    InputValue,
+};
+use warp::{
+    body,
+    filters::BoxedFilter,
+    http::{Response, StatusCode},
+    hyper::body::Bytes,
+    query, Filter,
+};
+
+use super::schema::{Context, Schema};
+use crate::{auth::jwt::get_claims_from_token, metrics::metrics, telemetry::REQUEST_ID_HEADER};
+
+/// Serves queries and mutations as `juniper_warp::make_graphql_filter` does, sent as GET
+/// parameters, POSTed JSON or batches, or a POSTed `application/graphql` document.
+/// Each operation's duration and error codes are recorded in the metrics, and it runs
+/// in a span carrying the request ID, which is returned in the `X-Request-Id` header.
+pub fn create_graphql_handler(
+    schema: Schema,
+    context: BoxedFilter<(Context,)>,
+) -> BoxedFilter<(Response<Vec<u8>>,)> {
+    let schema = Arc::new(schema);
+    let json_schema = schema.clone();
+    let graphql_schema = schema.clone();
+
+    let get = warp::get().and(context.clone()).and(query::query()).then(
+        move |ctx: Context, mut params: HashMap<String, String>| {
+            let schema = schema.clone();
+            async move {
+                let variables = params
+                    .remove("variables")
+                    .map(|variables| serde_json::from_str::<InputValue>(&variables))
+                    .transpose();
+                match (params.remove("query"), variables) {
+                    (Some(query), Ok(variables)) => {
+                        let operation_name = params.remove("operation_name");
+                        let request = GraphQLRequest::new(query, operation_name, variables);
+                        execute(&schema, &ctx, GraphQLBatchRequest::Single(request)).await
+                    }
+                    _ => build_response(&ctx, None),
+                }
+            }
+        },
+    );
+
+    let post_json = warp::post().and(context.clone()).and(body::json()).then(
+        move |ctx: Context, request: GraphQLBatchRequest| {
+            let schema = json_schema.clone();
+            async move { execute(&schema, &ctx, request).await }
+        },
+    );
+
+    let post_graphql =
+        warp::post()
+            .and(context)
+            .and(body::bytes())
+            .then(move |ctx: Context, body: Bytes| {
+                let schema = graphql_schema.clone();
+                async move {
+                    match str::from_utf8(body.as_ref()) {
+                        Ok(query) => {
+                            let request = GraphQLRequest::new(query.to_string(), None, None);
+                            execute(&schema, &ctx, GraphQLBatchRequest::Single(request)).await
+                        }
+                        Err(_) => build_response(&ctx, None),
+                    }
+                }
+            });
+
+    get.or(post_json).unify().or(post_graphql).unify().boxed()
+}
+
+async fn execute(
+    schema: &Schema,
+    ctx: &Context,
+    request: GraphQLBatchRequest,
+) -> Response<Vec<u8>> {
+    let span = info_span!("request", request_id = %ctx.request_id, user_id = field::Empty);
+    if let Ok(claims) = get_claims_from_token(&ctx.token) {
+        span.record("user_id", field::display(claims.sub));
+    }
+    execute_batch(schema, ctx, request).instrument(span).await
+}
+
+async fn execute_batch(
+    schema: &Schema,
+    ctx: &Context,
+    request: GraphQLBatchRequest,
+) -> Response<Vec<u8>> {
+    let response = match &request {
+        GraphQLBatchRequest::Single(request) => {
+            GraphQLBatchResponse::Single(execute_one(schema, ctx, request).await)
+        }
+        GraphQLBatchRequest::Batch(requests) => {
+            let responses = requests
+                .iter()
+                .map(|request| execute_one(schema, ctx, request));
+            GraphQLBatchResponse::Batch(join_all(responses).await)
+        }
+    };
+    let body = serde_json::to_vec(&response).ok();
+    build_response(ctx, body.map(|body| (body, response.is_ok())))
+}
+
+async fn execute_one<'a>(
+    schema: &'a Schema,
+    ctx: &'a Context,
+    request: &'a GraphQLRequest,
+) -> GraphQLResponse<'a> {
+    let operation = request.operation_name().unwrap_or("anonymous");
+    let span = info_span!("graphql", operation);
+    let start = Instant::now();
+    let response = request.execute(schema, ctx).instrument(span.clone()).await;
+    let elapsed = start.elapsed();
+
+    let errors = error_codes(&response);
+    let codes: Vec<&str> = errors.iter().map(String::as_str).collect();
+    metrics().observe_graphql(request.operation_name(), elapsed, &codes);
+    span.in_scope(|| match codes.is_empty() {
+        true => tracing::debug!(
+            elapsed_ms = elapsed.as_millis() as u64,
+            "operation completed"
+        ),
+        false => tracing::warn!(
+            elapsed_ms = elapsed.as_millis() as u64,
+            errors = ?codes,
+            "operation failed"
+        ),
+    });
+    response
+}
+
+// Errors raised before execution, such as validation errors, have no code
+fn error_codes(response: &GraphQLResponse) -> Vec<String> {
+    let response = match serde_json::to_value(response) {
+        Ok(response) => response,
+        Err(_) => return vec![],
+    };
+    response["errors"]
+        .as_array()
+        .map(|errors| {
+            errors
+                .iter()
+                .map(|error| {
+                    error["extensions"]["code"]
+                        .as_str()
+                        .unwrap_or("UNKNOWN")
+                        .to_string()
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Mirrors juniper_warp: 400 when the request could not be executed, 500 when it could not be read
+fn build_response(ctx: &Context, response: Option<(Vec<u8>, bool)>) -> Response<Vec<u8>> {
+    let builder = Response::builder().header(REQUEST_ID_HEADER, &ctx.request_id);
+    match response {
+        Some((body, is_ok)) => builder
+            .status(match is_ok {
+                true => StatusCode::OK,
+                false => StatusCode::BAD_REQUEST,
+            })
+            .header("content-type", "application/json")
+            .body(body)
+            .expect("response is valid"),
+        None => builder
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(vec![])
+            .expect("status code is valid"),
+    }
+}
+
+#[cfg(test)]
+mod test_handler {
+    use juniper::http::GraphQLResponse;
+    use warp::{http::StatusCode, test::request, Filter};
+
+    use super::{create_graphql_handler, error_codes};
+    use crate::{
+        errors::{ApiError, UserError},
+        graphql::schema::create_schema,
+        testutils::create_mock_context,
+    };
+    use entity::user;
+
+    #[test]
+    fn error_codes_are_read_from_extensions() {
+        let error = ApiError::from(UserError::IncorrectEmailOrPassword);
+        let response = GraphQLResponse::error(juniper::IntoFieldError::into_field_error(error));
+        assert_eq!(error_codes(&response), vec!["UNAUTHENTICATED"]);
+    }
+
+    #[tokio::test]
+    async fn serves_get_and_post_requests() {
+        let context = warp::any()
+            .map(|| create_mock_context::<user::Model>(vec![], None))
+            .boxed();
+        let filter = create_graphql_handler(create_schema(), context);
+
+        let res = request()
+            .path("/?query=%7B__typename%7D")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["x-request-id"], "test");
+        assert_eq!(&res.body()[..], br#"{"data":{"__typename":"QueryRoot"}}"#);
+
+        let res = request()
+            .method("POST")
+            .json(&serde_json::json!([{"query": "{__typename}"}, {"query": "{"}]))
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(body[0]["data"]["__typename"], "QueryRoot");
+        assert!(body[1]["errors"].is_array());
+
+        let res = request()
+            .method("POST")
+            .header("content-type", "application/graphql")
+            .body("{__typename}")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = request().path("/").reply(&filter).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
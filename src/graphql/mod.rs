@@ -1,5 +1,9 @@
+pub mod handler;
 pub mod mutation;
+pub mod page;
 pub mod query;
 pub mod schema;
+pub mod session;
 pub mod subscription;
 pub mod user;
+pub mod ws;
@@ -0,0 +1,169 @@
+use sea_orm::{prelude::Uuid, Set};
+
+use super::SuccessResponse;
+use crate::{
+    auth::{
+        jwt::authorize_permission,
+        permission::{
+            grant_permission as grant, revoke_permission as revoke, ALL_PERMISSIONS,
+            PERMISSIONS_MANAGE, USERS_MANAGE,
+        },
+        revocation::revoke_user_tokens,
+        session::refresh_presence,
+        throttle::{clear_failed_ip_signins, clear_failed_signins},
+    },
+    errors::{ApiResult, AuthorizationError, QueryError, UserError},
+    graphql::{schema::Context, user::GQLUser},
+    time::Time,
+};
+use entity::{sea_orm_active_enums::Role, user};
+
+/// Lifts the lockout on the account and, when given, on the IP address the failed
+/// signins came from. Otherwise an IP lockout is left to expire, as clearing the
+/// account's does not lift it.
+pub async fn unlock_account(
+    ctx: &Context,
+    email: String,
+    ip_address: Option<String>,
+) -> ApiResult<SuccessResponse> {
+    if ctx.token.is_empty() {
+        return Err(AuthorizationError::TokenMissing.into());
+    }
+    authorize_permission(USERS_MANAGE, &ctx.token)?;
+    let ip = ip_address
+        .map(|ip| ip.parse().map_err(|_| QueryError::InvalidIpAddress(ip)))
+        .transpose()?;
+
+    let conn = ctx.connection.as_ref();
+    clear_failed_signins(&email, conn).await?;
+    if let Some(ip) = ip {
+        clear_failed_ip_signins(ip, conn).await?;
+    }
+    Ok(SuccessResponse::complete())
+}
+
+/// Signs the user out everywhere so that their next token carries the new role
+pub async fn set_user_role(ctx: &Context, id: String, role: Role) -> ApiResult<GQLUser> {
+    let conn = ctx.connection.as_ref();
+    let found = find_managed_user(ctx, &id).await?;
+    if found.role == role {
+        return Ok(GQLUser::single(&found));
+    }
+    if role != Role::Guest
+        && ctx.config.jwt.require_email_verification
+        && !found.is_email_verified()
+    {
+        return Err(AuthorizationError::EmailNotVerified.into());
+    }
+
+    let mut found: user::ActiveModel = found.into();
+    found.role = Set(role);
+    let updated = ctx
+        .users
+        .update_unless_last_admin(found)
+        .await?
+        .ok_or(UserError::LastAdmin)?;
+    revoke_user_tokens(&updated.id, conn).await?;
+    let updated = refresh_presence(updated, ctx.users.as_ref(), conn).await?;
+
+    Ok(GQLUser::single(&updated))
+}
+
+/// Suspended users are signed out and cannot sign in or refresh tokens until reinstated
+pub async fn suspend_user(ctx: &Context, id: String) -> ApiResult<GQLUser> {
+    let conn = ctx.connection.as_ref();
+    let found = find_managed_user(ctx, &id).await?;
+    if found.is_suspended() {
+        return Err(UserError::AlreadySuspended.into());
+    }
+
+    let now = Time::now()?.as_secs() as i64;
+    let mut found: user::ActiveModel = found.into();
+    found.suspended_at = Set(Some(now));
+    let updated = ctx
+        .users
+        .update_unless_last_admin(found)
+        .await?
+        .ok_or(UserError::LastAdmin)?;
+    revoke_user_tokens(&updated.id, conn).await?;
+    let updated = refresh_presence(updated, ctx.users.as_ref(), conn).await?;
+
+    Ok(GQLUser::single(&updated))
+}
+
+pub async fn reinstate_user(ctx: &Context, id: String) -> ApiResult<GQLUser> {
+    let found = find_managed_user(ctx, &id).await?;
+    if !found.is_suspended() {
+        return Err(UserError::NotSuspended.into());
+    }
+
+    let mut found: user::ActiveModel = found.into();
+    found.suspended_at = Set(None);
+    let updated = ctx.users.update_one(found).await?;
+
+    Ok(GQLUser::single(&updated))
+}
+
+pub async fn delete_user(ctx: &Context, id: String) -> ApiResult<SuccessResponse> {
+    let conn = ctx.connection.as_ref();
+    let found = find_managed_user(ctx, &id).await?;
+    ctx.users
+        .delete_unless_last_admin(&found.id)
+        .await?
+        .ok_or(UserError::LastAdmin)?;
+
+    // The revocation is not tied to the user row, so it outlives the delete and
+    // other instances pick it up on their next sync.
+    revoke_user_tokens(&found.id, conn).await?;
+
+    Ok(SuccessResponse::complete())
+}
+
+async fn find_managed_user(ctx: &Context, id: &str) -> ApiResult<user::Model> {
+    if ctx.token.is_empty() {
+        return Err(AuthorizationError::TokenMissing.into());
+    }
+    authorize_permission(USERS_MANAGE, &ctx.token)?;
+
+    let id = Uuid::parse_str(id).map_err(|_| QueryError::InvalidId(id.to_owned()))?;
+    let found = ctx
+        .users
+        .find_one_by_id(&id)
+        .await?
+        .ok_or(UserError::UnableToComplete)?;
+    Ok(found)
+}
+
+pub async fn grant_permission(
+    ctx: &Context,
+    role: Role,
+    permission: String,
+) -> ApiResult<SuccessResponse> {
+    authorize_permission_change(ctx, &permission)?;
+
+    grant(&role, &permission, ctx.connection.as_ref()).await?;
+    Ok(SuccessResponse::complete())
+}
+
+pub async fn revoke_permission(
+    ctx: &Context,
+    role: Role,
+    permission: String,
+) -> ApiResult<SuccessResponse> {
+    authorize_permission_change(ctx, &permission)?;
+
+    revoke(&role, &permission, ctx.connection.as_ref()).await?;
+    Ok(SuccessResponse::complete())
+}
+
+fn authorize_permission_change(ctx: &Context, permission: &str) -> ApiResult<()> {
+    if ctx.token.is_empty() {
+        return Err(AuthorizationError::TokenMissing.into());
+    }
+    authorize_permission(PERMISSIONS_MANAGE, &ctx.token)?;
+
+    if !ALL_PERMISSIONS.contains(&permission) {
+        return Err(AuthorizationError::UnknownPermission(permission.to_string()).into());
+    }
+    Ok(())
+}
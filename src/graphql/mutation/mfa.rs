@@ -0,0 +1,88 @@
+use juniper::GraphQLObject;
+
+use crate::{
+    auth::{
+        jwt::get_claims_from_token,
+        mfa::issue_recovery_codes,
+        totp::{generate_secret, otpauth_uri, verify_code},
+    },
+    config::config,
+    errors::{ApiResult, AuthorizationError, UserError},
+    graphql::schema::Context,
+    time::Time,
+};
+use entity::prelude::TotpSecret;
+
+#[derive(GraphQLObject)]
+pub struct MfaEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(GraphQLObject)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+/// Starts enrollment with a new secret, replacing any that was never confirmed
+pub async fn enroll_mfa(ctx: &Context) -> ApiResult<MfaEnrollment> {
+    if ctx.token.is_empty() {
+        return Err(AuthorizationError::TokenMissing.into());
+    }
+    let claims = get_claims_from_token(&ctx.token)?;
+
+    let conn = ctx.connection.as_ref();
+    let found = ctx
+        .users
+        .find_one_by_id(&claims.sub)
+        .await?
+        .ok_or(UserError::UnableToComplete)?;
+    if let Some(existing) = TotpSecret::find_one_by_user(&found.id, conn).await? {
+        if existing.is_confirmed() {
+            return Err(UserError::MfaAlreadyEnabled.into());
+        }
+        TotpSecret::delete_for_user(&found.id, conn).await?;
+    }
+
+    let secret = generate_secret();
+    let now = Time::now()?.as_secs() as i64;
+    TotpSecret::insert_one(
+        TotpSecret::create_active_model(&found.id, &secret, now),
+        conn,
+    )
+    .await?;
+
+    Ok(MfaEnrollment {
+        otpauth_uri: otpauth_uri(&config().mfa.issuer, &found.email, &secret),
+        secret,
+    })
+}
+
+/// Enables two-factor authentication once the user proves their authenticator
+/// app is set up, returning recovery codes that are only shown once
+pub async fn confirm_mfa(ctx: &Context, code: String) -> ApiResult<RecoveryCodesResponse> {
+    if ctx.token.is_empty() {
+        return Err(AuthorizationError::TokenMissing.into());
+    }
+    let claims = get_claims_from_token(&ctx.token)?;
+
+    let conn = ctx.connection.as_ref();
+    let secret = TotpSecret::find_one_by_user(&claims.sub, conn)
+        .await?
+        .ok_or(UserError::MfaNotEnrolled)?;
+    if secret.is_confirmed() {
+        return Err(UserError::MfaAlreadyEnabled.into());
+    }
+
+    let now = Time::now()?;
+    let step =
+        verify_code(&secret.secret, &code, now.as_secs()).ok_or(UserError::InvalidMfaCode)?;
+    TotpSecret::mark_step_used(&secret.id, step, conn).await?;
+    let confirmed = TotpSecret::confirm(&secret.id, now.as_secs() as i64, conn).await?;
+    if confirmed.rows_affected == 0 {
+        return Err(UserError::MfaAlreadyEnabled.into());
+    }
+
+    let recovery_codes = issue_recovery_codes(&claims.sub, conn).await?;
+    Ok(RecoveryCodesResponse { recovery_codes })
+}
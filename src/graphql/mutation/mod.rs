@@ -1,4 +1,183 @@
+use juniper::{graphql_object, GraphQLObject};
+
+use super::{schema::Context, user::GQLUser};
+use crate::{
+    errors::ApiResult,
+    metrics::{metrics, SigninOutcome},
+};
+use admin::{
+    delete_user, grant_permission, reinstate_user, revoke_permission, set_user_role, suspend_user,
+    unlock_account,
+};
+use entity::sea_orm_active_enums::Role;
+use mfa::{confirm_mfa, enroll_mfa, MfaEnrollment, RecoveryCodesResponse};
+use password::{request_password_reset, reset_password};
+use profile::{change_email, change_password, confirm_email_change, update_profile};
+use session::revoke_session;
+use user::{
+    refresh_token, signin, signout, signout_everywhere, signup, verify_mfa, AuthResponse,
+    SignoutResponse,
+};
+use verification::{send_verification_email, verify_email};
+
+pub mod admin;
+pub mod mfa;
+pub mod password;
+pub mod profile;
+pub mod session;
 pub mod tests;
 pub mod user;
+pub mod verification;
 
 pub struct MutationRoot;
+
+#[derive(GraphQLObject)]
+pub struct SuccessResponse {
+    pub success: bool,
+}
+
+impl SuccessResponse {
+    pub fn complete() -> Self {
+        SuccessResponse { success: true }
+    }
+}
+
+#[graphql_object(Context = Context)]
+impl MutationRoot {
+    pub async fn signup(
+        ctx: &Context,
+        email: String,
+        name: String,
+        password: String,
+    ) -> ApiResult<AuthResponse> {
+        signup(ctx, email, name, password).await
+    }
+
+    pub async fn signin(
+        ctx: &Context,
+        email: String,
+        password: String,
+        device_label: Option<String>,
+    ) -> ApiResult<AuthResponse> {
+        let res = signin(ctx, email, password, device_label).await;
+        metrics().record_signin("password", SigninOutcome::of(&res));
+        res
+    }
+
+    pub async fn verify_mfa(
+        ctx: &Context,
+        code: String,
+        device_label: Option<String>,
+    ) -> ApiResult<AuthResponse> {
+        let res = verify_mfa(ctx, code, device_label).await;
+        metrics().record_signin("mfa", SigninOutcome::of(&res));
+        res
+    }
+
+    pub async fn signout(ctx: &Context, email: String) -> ApiResult<SignoutResponse> {
+        signout(ctx, email).await
+    }
+
+    pub async fn signout_everywhere(ctx: &Context) -> ApiResult<SignoutResponse> {
+        signout_everywhere(ctx).await
+    }
+
+    pub async fn revoke_session(ctx: &Context, id: String) -> ApiResult<SuccessResponse> {
+        revoke_session(ctx, id).await
+    }
+
+    pub async fn refresh_token(ctx: &Context, token: String) -> ApiResult<AuthResponse> {
+        refresh_token(ctx, token).await
+    }
+
+    pub async fn request_password_reset(
+        ctx: &Context,
+        email: String,
+    ) -> ApiResult<SuccessResponse> {
+        request_password_reset(ctx, email).await
+    }
+
+    pub async fn reset_password(
+        ctx: &Context,
+        token: String,
+        new_password: String,
+    ) -> ApiResult<SuccessResponse> {
+        reset_password(ctx, token, new_password).await
+    }
+
+    pub async fn send_verification_email(ctx: &Context) -> ApiResult<SuccessResponse> {
+        send_verification_email(ctx).await
+    }
+
+    pub async fn verify_email(ctx: &Context, token: String) -> ApiResult<SuccessResponse> {
+        verify_email(ctx, token).await
+    }
+
+    pub async fn update_profile(ctx: &Context, name: String) -> ApiResult<GQLUser> {
+        update_profile(ctx, name).await
+    }
+
+    pub async fn change_password(
+        ctx: &Context,
+        current_password: String,
+        new_password: String,
+    ) -> ApiResult<AuthResponse> {
+        change_password(ctx, current_password, new_password).await
+    }
+
+    pub async fn change_email(ctx: &Context, new_email: String) -> ApiResult<SuccessResponse> {
+        change_email(ctx, new_email).await
+    }
+
+    pub async fn confirm_email_change(ctx: &Context, token: String) -> ApiResult<GQLUser> {
+        confirm_email_change(ctx, token).await
+    }
+
+    pub async fn enroll_mfa(ctx: &Context) -> ApiResult<MfaEnrollment> {
+        enroll_mfa(ctx).await
+    }
+
+    pub async fn confirm_mfa(ctx: &Context, code: String) -> ApiResult<RecoveryCodesResponse> {
+        confirm_mfa(ctx, code).await
+    }
+
+    pub async fn unlock_account(
+        ctx: &Context,
+        email: String,
+        ip_address: Option<String>,
+    ) -> ApiResult<SuccessResponse> {
+        unlock_account(ctx, email, ip_address).await
+    }
+
+    pub async fn set_user_role(ctx: &Context, id: String, role: Role) -> ApiResult<GQLUser> {
+        set_user_role(ctx, id, role).await
+    }
+
+    pub async fn suspend_user(ctx: &Context, id: String) -> ApiResult<GQLUser> {
+        suspend_user(ctx, id).await
+    }
+
+    pub async fn reinstate_user(ctx: &Context, id: String) -> ApiResult<GQLUser> {
+        reinstate_user(ctx, id).await
+    }
+
+    pub async fn delete_user(ctx: &Context, id: String) -> ApiResult<SuccessResponse> {
+        delete_user(ctx, id).await
+    }
+
+    pub async fn grant_permission(
+        ctx: &Context,
+        role: Role,
+        permission: String,
+    ) -> ApiResult<SuccessResponse> {
+        grant_permission(ctx, role, permission).await
+    }
+
+    pub async fn revoke_permission(
+        ctx: &Context,
+        role: Role,
+        permission: String,
+    ) -> ApiResult<SuccessResponse> {
+        revoke_permission(ctx, role, permission).await
+    }
+}
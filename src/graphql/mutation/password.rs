@@ -0,0 +1,74 @@
+use sea_orm::Set;
+
+use super::SuccessResponse;
+use crate::{
+    auth::{
+        hash::hash,
+        revocation::revoke_user_tokens,
+        session::refresh_presence,
+        user_token::{issue_user_token, redeem_user_token},
+    },
+    errors::{ApiResult, UserError},
+    graphql::schema::Context,
+    mail::templates,
+    time::Time,
+};
+use entity::{sea_orm_active_enums::TokenPurpose, user};
+
+// The response is the same whether or not an account exists for the email,
+// and whether or not the email could be sent, so that the mutation cannot be
+// used to discover registered addresses
+pub async fn request_password_reset(ctx: &Context, email: String) -> ApiResult<SuccessResponse> {
+    if let Some(found) = ctx.users.find_one_by_email(&email).await? {
+        if let Err(e) = send_password_reset(ctx, &found).await {
+            tracing::warn!(error = %e, "Unable to send password reset email");
+        }
+    }
+    Ok(SuccessResponse::complete())
+}
+
+async fn send_password_reset(ctx: &Context, found: &user::Model) -> ApiResult<()> {
+    let conn = ctx.connection.as_ref();
+    let lifetime = ctx.config.tokens.password_reset_lifetime();
+    let expires_at = Time::now_plus_duration(lifetime)?.as_secs() as i64;
+    let token = issue_user_token(
+        &found.id,
+        TokenPurpose::PasswordReset,
+        expires_at,
+        None,
+        conn,
+    )
+    .await?;
+
+    ctx.mailer
+        .send(templates::password_reset(&found.email, &token, lifetime))
+        .await?;
+    Ok(())
+}
+
+pub async fn reset_password(
+    ctx: &Context,
+    token: String,
+    new_password: String,
+) -> ApiResult<SuccessResponse> {
+    let conn = ctx.connection.as_ref();
+    let redeemed = redeem_user_token(&token, TokenPurpose::PasswordReset, conn)
+        .await?
+        .ok_or(UserError::InvalidResetToken)?;
+
+    let user = ctx
+        .users
+        .find_one_by_id(&redeemed.user_id)
+        .await?
+        .ok_or(UserError::InvalidResetToken)?;
+
+    let mut user: user::ActiveModel = user.into();
+    user.password = Set(hash(&new_password)?);
+    let user = ctx.users.update_one(user).await?;
+
+    // Anyone holding a session from before the reset is signed out
+    revoke_user_tokens(&user.id, conn).await?;
+    refresh_presence(user, ctx.users.as_ref(), conn).await?;
+
+    Ok(SuccessResponse::complete())
+}
@@ -0,0 +1,144 @@
+use sea_orm::Set;
+
+use super::{user::AuthResponse, SuccessResponse};
+use crate::{
+    auth::{
+        hash::{hash, verify},
+        jwt::{get_claims_from_token, Claims},
+        revocation::revoke_user_tokens,
+        session::{start_session, DeviceInfo},
+        throttle::{check_signin_allowed, clear_failed_signins, record_failed_signin},
+        user_token::{issue_user_token, redeem_user_token},
+    },
+    errors::{ApiResult, AuthorizationError, UserError},
+    graphql::{schema::Context, user::GQLUser},
+    mail::templates,
+    time::Time,
+};
+use entity::{prelude::Session, sea_orm_active_enums::TokenPurpose, user};
+
+pub async fn update_profile(ctx: &Context, name: String) -> ApiResult<GQLUser> {
+    let (_, found) = find_caller(ctx).await?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(UserError::EmptyName.into());
+    }
+
+    let mut found: user::ActiveModel = found.into();
+    found.name = Set(name.to_string());
+    let updated = ctx.users.update_one(found).await?;
+
+    Ok(GQLUser::single(&updated))
+}
+
+// Every session is signed out, and the caller's device is given a new session
+// so that they stay signed in
+pub async fn change_password(
+    ctx: &Context,
+    current_password: String,
+    new_password: String,
+) -> ApiResult<AuthResponse> {
+    let (claims, found) = find_caller(ctx).await?;
+    let conn = ctx.connection.as_ref();
+
+    // Guessing the current password is throttled like signin
+    check_signin_allowed(&found.email, ctx.client_ip, conn).await?;
+    if verify(&current_password, &found.password).is_err() {
+        record_failed_signin(&found.email, ctx.client_ip, conn).await?;
+        return Err(UserError::IncorrectPassword.into());
+    }
+    clear_failed_signins(&found.email, conn).await?;
+
+    let label = match claims.sid {
+        Some(session_id) => Session::find_one_by_id(&session_id, conn)
+            .await?
+            .and_then(|session| session.device_label),
+        None => None,
+    };
+
+    let mut found: user::ActiveModel = found.into();
+    found.password = Set(hash(&new_password)?);
+    let updated = ctx.users.update_one(found).await?;
+    revoke_user_tokens(&updated.id, conn).await?;
+
+    let device = DeviceInfo::from_context(ctx, label);
+    let session = start_session(
+        updated,
+        claims.mfa_authenticated,
+        device,
+        ctx.users.as_ref(),
+        conn,
+    )
+    .await?;
+    Ok(AuthResponse::new(
+        &session.token,
+        &session.refresh_token,
+        GQLUser::single(&session.user),
+    ))
+}
+
+/// The address is only changed once the token sent to it is confirmed
+pub async fn change_email(ctx: &Context, new_email: String) -> ApiResult<SuccessResponse> {
+    let (_, found) = find_caller(ctx).await?;
+    let conn = ctx.connection.as_ref();
+    if ctx.users.find_one_by_email(&new_email).await?.is_some() {
+        return Err(UserError::UserWithEmailAlreadyExists(new_email).into());
+    }
+
+    let lifetime = ctx.config.tokens.email_verification_lifetime();
+    let expires_at = Time::now_plus_duration(lifetime)?.as_secs() as i64;
+    let token = issue_user_token(
+        &found.id,
+        TokenPurpose::EmailChange,
+        expires_at,
+        Some(new_email.to_owned()),
+        conn,
+    )
+    .await?;
+
+    ctx.mailer
+        .send(templates::email_change(&new_email, &token, lifetime))
+        .await?;
+    Ok(SuccessResponse::complete())
+}
+
+// Receiving the token proves ownership of the new address, so it is also verified
+pub async fn confirm_email_change(ctx: &Context, token: String) -> ApiResult<GQLUser> {
+    let conn = ctx.connection.as_ref();
+    let redeemed = redeem_user_token(&token, TokenPurpose::EmailChange, conn)
+        .await?
+        .ok_or(UserError::InvalidEmailChangeToken)?;
+    let new_email = redeemed.payload.ok_or(UserError::InvalidEmailChangeToken)?;
+
+    let found = ctx
+        .users
+        .find_one_by_id(&redeemed.user_id)
+        .await?
+        .ok_or(UserError::InvalidEmailChangeToken)?;
+    // The address may have been taken since the change was requested
+    if ctx.users.find_one_by_email(&new_email).await?.is_some() {
+        return Err(UserError::UserWithEmailAlreadyExists(new_email).into());
+    }
+
+    let now = Time::now()?.as_secs() as i64;
+    let mut found: user::ActiveModel = found.into();
+    found.email = Set(new_email);
+    found.email_verified_at = Set(Some(now));
+    let updated = ctx.users.update_one(found).await?;
+
+    Ok(GQLUser::single(&updated))
+}
+
+async fn find_caller(ctx: &Context) -> ApiResult<(Claims, user::Model)> {
+    if ctx.token.is_empty() {
+        return Err(AuthorizationError::TokenMissing.into());
+    }
+    let claims = get_claims_from_token(&ctx.token)?;
+
+    let found = ctx
+        .users
+        .find_one_by_id(&claims.sub)
+        .await?
+        .ok_or(UserError::UnableToComplete)?;
+    Ok((claims, found))
+}
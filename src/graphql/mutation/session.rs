@@ -0,0 +1,33 @@
+use sea_orm::prelude::Uuid;
+
+use super::SuccessResponse;
+use crate::{
+    auth::{jwt::get_claims_from_token, session::end_session},
+    errors::{ApiResult, AuthorizationError, QueryError, UserError},
+    graphql::schema::Context,
+};
+use entity::prelude::Session;
+
+/// Signs out one of the caller's devices, which may be the current one
+pub async fn revoke_session(ctx: &Context, id: String) -> ApiResult<SuccessResponse> {
+    if ctx.token.is_empty() {
+        return Err(AuthorizationError::TokenMissing.into());
+    }
+    let claims = get_claims_from_token(&ctx.token)?;
+    let conn = ctx.connection.as_ref();
+
+    // Sessions belonging to other users are reported as missing
+    let id = Uuid::parse_str(&id).map_err(|_| QueryError::InvalidId(id.to_owned()))?;
+    let session = Session::find_one_by_id(&id, conn)
+        .await?
+        .filter(|session| session.user_id == claims.sub && session.revoked_at.is_none())
+        .ok_or(UserError::SessionNotFound)?;
+    let found = ctx
+        .users
+        .find_one_by_id(&claims.sub)
+        .await?
+        .ok_or(UserError::UnableToComplete)?;
+
+    end_session(found, &session.id, ctx.users.as_ref(), conn).await?;
+    Ok(SuccessResponse::complete())
+}
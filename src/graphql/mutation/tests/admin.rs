@@ -0,0 +1,280 @@
+#[cfg(test)]
+mod test_unlock_account {
+    use migration::DbErr;
+    use sea_orm::prelude::Uuid;
+
+    use crate::{
+        graphql::mutation::admin::unlock_account,
+        testutils::{create_errored_context, create_mock_context, create_test_jwt},
+        time::Time,
+    };
+    use entity::{sea_orm_active_enums::Role, user as user_entity};
+
+    #[tokio::test]
+    async fn fail_without_token() {
+        let results: Vec<Vec<user_entity::Model>> = vec![];
+        let context = create_mock_context(results, None);
+
+        let got = unlock_account(&context, "test@test.com".to_string(), None).await;
+        assert!(got.is_err());
+        assert_eq!(got.err().unwrap().to_string(), "Token missing");
+    }
+
+    #[tokio::test]
+    async fn fail_without_admin_role() {
+        let exp = Time::hour_hence().unwrap().as_secs();
+        let token = create_test_jwt(&Uuid::new_v4(), &Role::Teacher, exp);
+        let results: Vec<Vec<user_entity::Model>> = vec![];
+        let context = create_mock_context(results, Some(token));
+
+        let got = unlock_account(&context, "test@test.com".to_string(), None).await;
+        assert!(got.is_err());
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Permission `users:manage` is not granted to role Teacher"
+        );
+    }
+
+    #[tokio::test]
+    async fn fail_with_invalid_ip_address() {
+        let exp = Time::hour_hence().unwrap().as_secs();
+        let token = create_test_jwt(&Uuid::new_v4(), &Role::Admin, exp);
+        let results: Vec<Vec<user_entity::Model>> = vec![];
+        let context = create_mock_context(results, Some(token));
+
+        let got = unlock_account(
+            &context,
+            "test@test.com".to_string(),
+            Some("10.0.0".to_string()),
+        )
+        .await;
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "`10.0.0` is not a valid IP address"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_error_for_unlock_account() {
+        let exp = Time::hour_hence().unwrap().as_secs();
+        let token = create_test_jwt(&Uuid::new_v4(), &Role::Admin, exp);
+        let context = create_errored_context(vec![DbErr::ConnectionAcquire], Some(token));
+
+        let got = unlock_account(&context, "test@test.com".to_string(), None).await;
+        assert!(got.is_err());
+    }
+}
+
+#[cfg(test)]
+mod test_manage_permissions {
+    use migration::DbErr;
+    use sea_orm::prelude::Uuid;
+
+    use crate::{
+        graphql::mutation::admin::{grant_permission, revoke_permission},
+        testutils::{create_errored_context, create_mock_context, create_test_jwt},
+        time::Time,
+    };
+    use entity::{sea_orm_active_enums::Role, user as user_entity};
+
+    #[tokio::test]
+    async fn fail_without_token() {
+        let results: Vec<Vec<user_entity::Model>> = vec![];
+        let context = create_mock_context(results, None);
+
+        let got = grant_permission(&context, Role::Student, "grades:publish".to_string()).await;
+        assert_eq!(got.err().unwrap().to_string(), "Token missing");
+    }
+
+    #[tokio::test]
+    async fn fail_without_permissions_manage() {
+        let exp = Time::hour_hence().unwrap().as_secs();
+        let token = create_test_jwt(&Uuid::new_v4(), &Role::Teacher, exp);
+        let results: Vec<Vec<user_entity::Model>> = vec![];
+        let context = create_mock_context(results, Some(token));
+
+        let got = revoke_permission(&context, Role::Student, "grades:read".to_string()).await;
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Permission `permissions:manage` is not granted to role Teacher"
+        );
+    }
+
+    #[tokio::test]
+    async fn fail_for_unknown_permission() {
+        let exp = Time::hour_hence().unwrap().as_secs();
+        let token = create_test_jwt(&Uuid::new_v4(), &Role::Admin, exp);
+        let results: Vec<Vec<user_entity::Model>> = vec![];
+        let context = create_mock_context(results, Some(token));
+
+        let got = grant_permission(&context, Role::Student, "library:burn".to_string()).await;
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Unknown permission `library:burn`"
+        );
+    }
+
+    #[tokio::test]
+    async fn fail_to_revoke_management_from_admin() {
+        let exp = Time::hour_hence().unwrap().as_secs();
+        let token = create_test_jwt(&Uuid::new_v4(), &Role::Admin, exp);
+        for permission in ["users:manage", "permissions:manage"] {
+            let results: Vec<Vec<user_entity::Model>> = vec![];
+            let context = create_mock_context(results, Some(token.clone()));
+
+            let got = revoke_permission(&context, Role::Admin, permission.to_string()).await;
+            assert_eq!(
+                got.err().unwrap().to_string(),
+                format!("Permission `{}` cannot be revoked from Admin", permission)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn get_error_for_grant_permission() {
+        let exp = Time::hour_hence().unwrap().as_secs();
+        let token = create_test_jwt(&Uuid::new_v4(), &Role::Admin, exp);
+        let context = create_errored_context(vec![DbErr::ConnectionAcquire], Some(token));
+
+        let got = grant_permission(&context, Role::Student, "grades:publish".to_string()).await;
+        assert!(got.is_err());
+    }
+}
+
+#[cfg(test)]
+mod test_manage_users {
+    use std::sync::Arc;
+
+    use sea_orm::prelude::Uuid;
+
+    use crate::{
+        graphql::mutation::admin::{delete_user, reinstate_user, set_user_role, suspend_user},
+        repository::memory::MemoryUserRepository,
+        testutils::{
+            create_mock_context, create_mock_database, create_repository_context, create_test_jwt,
+        },
+        time::Time,
+    };
+    use entity::{
+        sea_orm_active_enums::{Role, Status},
+        user as user_entity,
+    };
+
+    fn admin_token() -> Option<String> {
+        let exp = Time::hour_hence().unwrap().as_secs();
+        Some(create_test_jwt(&Uuid::new_v4(), &Role::Admin, exp))
+    }
+
+    fn user(suspended_at: Option<i64>) -> user_entity::Model {
+        user_entity::Model {
+            id: Uuid::new_v4(),
+            name: "test user".to_string(),
+            email: "test@test.com".to_string(),
+            password: "testpassword".to_string(),
+            status: Status::Offline,
+            role: Role::Student,
+            email_verified_at: None,
+            suspended_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn fail_without_token() {
+        let results: Vec<Vec<user_entity::Model>> = vec![];
+        let context = create_mock_context(results, None);
+
+        let got = suspend_user(&context, Uuid::new_v4().to_string()).await;
+        assert_eq!(got.err().unwrap().to_string(), "Token missing");
+    }
+
+    #[tokio::test]
+    async fn fail_without_users_manage() {
+        let exp = Time::hour_hence().unwrap().as_secs();
+        let token = create_test_jwt(&Uuid::new_v4(), &Role::Teacher, exp);
+        let results: Vec<Vec<user_entity::Model>> = vec![];
+        let context = create_mock_context(results, Some(token));
+
+        let got = set_user_role(&context, Uuid::new_v4().to_string(), Role::Admin).await;
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Permission `users:manage` is not granted to role Teacher"
+        );
+    }
+
+    #[tokio::test]
+    async fn fail_for_unknown_user() {
+        let results: Vec<Vec<user_entity::Model>> = vec![vec![]];
+        let context = create_mock_context(results, admin_token());
+
+        let got = delete_user(&context, Uuid::new_v4().to_string()).await;
+        assert_eq!(got.err().unwrap().to_string(), "Unable to complete request");
+    }
+
+    #[tokio::test]
+    async fn role_unchanged_is_noop() {
+        let found = user(None);
+        let results = vec![vec![found.clone()]];
+        let context = create_mock_context(results, admin_token());
+
+        let got = set_user_role(&context, found.id.to_string(), Role::Student)
+            .await
+            .unwrap();
+        assert_eq!(got.role, Role::Student);
+    }
+
+    #[tokio::test]
+    async fn fail_to_suspend_suspended_user() {
+        let found = user(Some(100));
+        let results = vec![vec![found.clone()]];
+        let context = create_mock_context(results, admin_token());
+
+        let got = suspend_user(&context, found.id.to_string()).await;
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Account is already suspended"
+        );
+    }
+
+    #[tokio::test]
+    async fn fail_to_reinstate_active_user() {
+        let found = user(None);
+        let results = vec![vec![found.clone()]];
+        let context = create_mock_context(results, admin_token());
+
+        let got = reinstate_user(&context, found.id.to_string()).await;
+        assert_eq!(got.err().unwrap().to_string(), "Account is not suspended");
+    }
+
+    #[tokio::test]
+    async fn reinstate_suspended_user() {
+        let found = user(Some(100));
+        let users = Arc::new(MemoryUserRepository::with_users(vec![found.clone()]));
+        let context =
+            create_repository_context(users.clone(), create_mock_database(), admin_token());
+
+        let got = reinstate_user(&context, found.id.to_string())
+            .await
+            .unwrap();
+        assert_eq!(got.suspended, Some(false));
+        assert_eq!(users.users()[0].suspended_at, None);
+    }
+
+    #[tokio::test]
+    async fn fail_to_suspend_last_admin() {
+        let mut admin = user(None);
+        admin.role = Role::Admin;
+        let mut suspended = user(Some(100));
+        suspended.role = Role::Admin;
+        let users = Arc::new(MemoryUserRepository::with_users(vec![
+            admin.clone(),
+            suspended,
+        ]));
+        let context = create_repository_context(users, create_mock_database(), admin_token());
+
+        let got = suspend_user(&context, admin.id.to_string()).await;
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "The last active Admin cannot be demoted, suspended or deleted"
+        );
+    }
+}
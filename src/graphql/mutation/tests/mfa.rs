@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod test_enroll_mfa {
+    use crate::{graphql::mutation::mfa::enroll_mfa, testutils::create_mock_context};
+    use entity::user as user_entity;
+
+    #[tokio::test]
+    async fn fail_without_token() {
+        let results: Vec<Vec<user_entity::Model>> = vec![];
+        let context = create_mock_context(results, None);
+
+        let got = enroll_mfa(&context).await;
+        assert!(got.is_err());
+        assert_eq!(got.err().unwrap().to_string(), "Token missing");
+    }
+}
+
+#[cfg(test)]
+mod test_confirm_mfa {
+    use sea_orm::prelude::Uuid;
+
+    use crate::{
+        auth::totp::generate_secret,
+        graphql::mutation::mfa::confirm_mfa,
+        testutils::{create_mock_context, create_test_jwt},
+        time::Time,
+    };
+    use entity::{sea_orm_active_enums::Role, totp_secret};
+
+    fn secret(user_id: &Uuid, confirmed_at: Option<i64>) -> totp_secret::Model {
+        totp_secret::Model {
+            id: Uuid::new_v4(),
+            user_id: user_id.to_owned(),
+            secret: generate_secret(),
+            created_at: 0,
+            confirmed_at,
+            last_used_step: None,
+        }
+    }
+
+    fn token(user_id: &Uuid) -> String {
+        let exp = Time::hour_hence().unwrap().as_secs();
+        create_test_jwt(user_id, &Role::Teacher, exp)
+    }
+
+    #[tokio::test]
+    async fn fail_without_enrollment() {
+        let id = Uuid::new_v4();
+        let results: Vec<Vec<totp_secret::Model>> = vec![vec![]];
+        let context = create_mock_context(results, Some(token(&id)));
+
+        let got = confirm_mfa(&context, "123456".to_string()).await;
+        assert!(got.is_err());
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Two-factor authentication has not been set up"
+        );
+    }
+
+    #[tokio::test]
+    async fn fail_when_already_confirmed() {
+        let id = Uuid::new_v4();
+        let context = create_mock_context(vec![vec![secret(&id, Some(100))]], Some(token(&id)));
+
+        let got = confirm_mfa(&context, "123456".to_string()).await;
+        assert!(got.is_err());
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Two-factor authentication is already enabled"
+        );
+    }
+
+    #[tokio::test]
+    async fn fail_on_invalid_code() {
+        let id = Uuid::new_v4();
+        let context = create_mock_context(vec![vec![secret(&id, None)]], Some(token(&id)));
+
+        let got = confirm_mfa(&context, "not a code".to_string()).await;
+        assert!(got.is_err());
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Invalid two-factor authentication code"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_verify_mfa {
+    use sea_orm::prelude::Uuid;
+
+    use crate::{
+        graphql::mutation::user::verify_mfa,
+        testutils::{create_mock_context, create_test_jwt},
+        time::Time,
+    };
+    use entity::{sea_orm_active_enums::Role, user as user_entity};
+
+    #[tokio::test]
+    async fn fail_without_token() {
+        let results: Vec<Vec<user_entity::Model>> = vec![];
+        let context = create_mock_context(results, None);
+
+        let got = verify_mfa(&context, "123456".to_string(), None).await;
+        assert!(got.is_err());
+        assert_eq!(got.err().unwrap().to_string(), "Token missing");
+    }
+
+    #[tokio::test]
+    async fn fail_with_full_token() {
+        let exp = Time::hour_hence().unwrap().as_secs();
+        let token = create_test_jwt(&Uuid::new_v4(), &Role::Teacher, exp);
+        let results: Vec<Vec<user_entity::Model>> = vec![];
+        let context = create_mock_context(results, Some(token));
+
+        let got = verify_mfa(&context, "123456".to_string(), None).await;
+        assert!(got.is_err());
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Token is not awaiting two-factor authentication"
+        );
+    }
+}
@@ -1 +1,6 @@
+pub mod admin;
+pub mod mfa;
+pub mod password;
+pub mod profile;
+pub mod session;
 pub mod user;
@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod test_success_response {
+    use crate::graphql::mutation::SuccessResponse;
+
+    #[test]
+    fn complete_success_response() {
+        let response = SuccessResponse::complete();
+        assert!(response.success);
+    }
+}
+
+#[cfg(test)]
+mod test_request_password_reset {
+    use migration::DbErr;
+    use sea_orm::prelude::Uuid;
+
+    use crate::{
+        graphql::mutation::password::request_password_reset,
+        testutils::{
+            create_database_context, create_errored_context, create_mock_context,
+            create_mock_database,
+        },
+    };
+    use entity::{
+        sea_orm_active_enums::{Role, Status},
+        user as user_entity,
+    };
+
+    #[tokio::test]
+    async fn succeed_without_email_for_unknown_user() {
+        let results: Vec<Vec<user_entity::Model>> = vec![vec![]];
+        let context = create_mock_context(results, None);
+
+        let got = request_password_reset(&context, "test@test.com".to_string())
+            .await
+            .unwrap();
+        assert!(got.success);
+    }
+
+    #[tokio::test]
+    async fn succeed_when_email_cannot_be_sent() {
+        let user = user_entity::Model {
+            id: Uuid::new_v4(),
+            name: "test user".to_string(),
+            email: "test@test.com".to_string(),
+            password: "passwordhash".to_string(),
+            status: Status::Offline,
+            role: Role::Student,
+            email_verified_at: None,
+            suspended_at: None,
+        };
+        let db = create_mock_database()
+            .append_query_results(vec![vec![user]])
+            .append_query_errors(vec![DbErr::ConnectionAcquire]);
+        let context = create_database_context(db, None);
+
+        let got = request_password_reset(&context, "test@test.com".to_string())
+            .await
+            .unwrap();
+        assert!(got.success);
+    }
+
+    #[tokio::test]
+    async fn get_error_for_request_password_reset() {
+        let context = create_errored_context(vec![DbErr::ConnectionAcquire], None);
+        let got = request_password_reset(&context, "test@test.com".to_string()).await;
+
+        assert!(got.is_err());
+    }
+}
+
+#[cfg(test)]
+mod test_reset_password {
+    use migration::DbErr;
+
+    use crate::{
+        graphql::mutation::password::reset_password,
+        testutils::{create_errored_context, create_mock_context},
+    };
+    use entity::user_token;
+
+    #[tokio::test]
+    async fn fail_on_unknown_token() {
+        let results: Vec<Vec<user_token::Model>> = vec![vec![]];
+        let context = create_mock_context(results, None);
+
+        let got = reset_password(&context, "token".to_string(), "password".to_string()).await;
+        assert!(got.is_err());
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Password reset token is invalid or has expired"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_error_for_reset_password() {
+        let context = create_errored_context(vec![DbErr::ConnectionAcquire], None);
+        let got = reset_password(&context, "token".to_string(), "password".to_string()).await;
+
+        assert!(got.is_err());
+    }
+}
@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod test_update_profile {
+    use std::sync::Arc;
+
+    use sea_orm::prelude::Uuid;
+
+    use crate::{
+        graphql::mutation::profile::update_profile,
+        repository::memory::MemoryUserRepository,
+        testutils::{
+            create_mock_context, create_mock_database, create_repository_context, create_test_jwt,
+        },
+        time::Time,
+    };
+    use entity::{
+        sea_orm_active_enums::{Role, Status},
+        user as user_entity,
+    };
+
+    #[tokio::test]
+    async fn fail_without_token() {
+        let results: Vec<Vec<user_entity::Model>> = vec![];
+        let context = create_mock_context(results, None);
+
+        let got = update_profile(&context, "new name".to_string()).await;
+        assert_eq!(got.err().unwrap().to_string(), "Token missing");
+    }
+
+    #[tokio::test]
+    async fn fail_with_empty_name() {
+        let id = Uuid::new_v4();
+        let exp = Time::hour_hence().unwrap().as_secs();
+        let token = create_test_jwt(&id, &Role::Student, exp);
+        let results = vec![vec![user_entity::Model {
+            id,
+            name: "test user".to_string(),
+            email: "test@test.com".to_string(),
+            password: "testpassword".to_string(),
+            status: Status::Online,
+            role: Role::Student,
+            email_verified_at: None,
+            suspended_at: None,
+        }]];
+        let context = create_mock_context(results, Some(token));
+
+        let got = update_profile(&context, "   ".to_string()).await;
+        assert_eq!(got.err().unwrap().to_string(), "Name cannot be empty");
+    }
+
+    #[tokio::test]
+    async fn update_name() {
+        let id = Uuid::new_v4();
+        let exp = Time::hour_hence().unwrap().as_secs();
+        let token = create_test_jwt(&id, &Role::Student, exp);
+        let users = Arc::new(MemoryUserRepository::with_users(vec![user_entity::Model {
+            id,
+            name: "test user".to_string(),
+            email: "test@test.com".to_string(),
+            password: "testpassword".to_string(),
+            status: Status::Online,
+            role: Role::Student,
+            email_verified_at: None,
+            suspended_at: None,
+        }]));
+        let context = create_repository_context(users.clone(), create_mock_database(), Some(token));
+
+        let got = update_profile(&context, "  new name ".to_string())
+            .await
+            .unwrap();
+        assert_eq!(got.name, "new name");
+        assert_eq!(users.users()[0].name, "new name");
+    }
+}
+
+#[cfg(test)]
+mod test_change_email {
+    use crate::{graphql::mutation::profile::change_email, testutils::create_mock_context};
+    use entity::user as user_entity;
+
+    #[tokio::test]
+    async fn fail_without_token() {
+        let results: Vec<Vec<user_entity::Model>> = vec![];
+        let context = create_mock_context(results, None);
+
+        let got = change_email(&context, "new@test.com".to_string()).await;
+        assert_eq!(got.err().unwrap().to_string(), "Token missing");
+    }
+}
+
+#[cfg(test)]
+mod test_confirm_email_change {
+    use crate::{graphql::mutation::profile::confirm_email_change, testutils::create_mock_context};
+    use entity::user_token;
+
+    #[tokio::test]
+    async fn fail_with_unknown_token() {
+        let results: Vec<Vec<user_token::Model>> = vec![vec![]];
+        let context = create_mock_context(results, None);
+
+        let got = confirm_email_change(&context, "token".to_string()).await;
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Email change token is invalid or has expired"
+        );
+    }
+}
@@ -0,0 +1,42 @@
+#[cfg(test)]
+mod test_revoke_session {
+    use sea_orm::prelude::Uuid;
+
+    use crate::{
+        graphql::mutation::session::revoke_session,
+        testutils::{create_mock_context, create_test_jwt},
+        time::Time,
+    };
+    use entity::{sea_orm_active_enums::Role, session};
+
+    #[tokio::test]
+    async fn fail_without_token() {
+        let results: Vec<Vec<session::Model>> = vec![];
+        let context = create_mock_context(results, None);
+
+        let got = revoke_session(&context, Uuid::new_v4().to_string()).await;
+        assert_eq!(got.err().unwrap().to_string(), "Token missing");
+    }
+
+    #[tokio::test]
+    async fn fail_for_session_of_another_user() {
+        let exp = Time::hour_hence().unwrap().as_secs();
+        let token = create_test_jwt(&Uuid::new_v4(), &Role::Student, exp);
+        let id = Uuid::new_v4();
+        let results = vec![vec![session::Model {
+            id,
+            user_id: Uuid::new_v4(),
+            device_label: None,
+            user_agent: None,
+            ip_address: None,
+            created_at: 100,
+            last_seen_at: 100,
+            revoked_at: None,
+            mfa_authenticated: false,
+        }]];
+        let context = create_mock_context(results, Some(token));
+
+        let got = revoke_session(&context, id.to_string()).await;
+        assert_eq!(got.err().unwrap().to_string(), "Session not found");
+    }
+}
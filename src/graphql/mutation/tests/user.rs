@@ -8,18 +8,23 @@ mod test_auth_response {
     fn create_new_auth_response() {
         let user = GQLUser {
             id: "id".to_string(),
-            email: "test@test.com".to_string(),
+            email: Some("test@test.com".to_string()),
             name: "test user".to_string(),
             role: Role::Teacher,
             status: Status::Offline,
+            email_verified: Some(true),
+            suspended: Some(false),
         };
-        let new = AuthResponse::new("token", user);
+        let new = AuthResponse::new("token", "refresh", user);
         assert_eq!(new.token, "token");
+        assert_eq!(new.refresh_token, Some("refresh".to_string()));
         assert_eq!(new.user.id, "id");
-        assert_eq!(new.user.email, "test@test.com");
+        assert_eq!(new.user.email, Some("test@test.com".to_string()));
         assert_eq!(new.user.name, "test user");
         assert_eq!(new.user.role, Role::Teacher);
         assert_eq!(new.user.status, Status::Offline);
+        assert_eq!(new.user.email_verified, Some(true));
+        assert_eq!(new.user.suspended, Some(false));
     }
 }
 
@@ -33,3 +38,536 @@ mod test_signout_response {
         assert!(signout.success);
     }
 }
+
+#[cfg(test)]
+mod test_signup {
+    use std::{collections::BTreeMap, sync::Arc};
+
+    use sea_orm::{prelude::Uuid, MockExecResult, Value};
+
+    use crate::{
+        auth::jwt::get_claims_from_token,
+        graphql::mutation::user::signup,
+        repository::memory::MemoryUserRepository,
+        testutils::{create_mock_database, create_repository_context},
+    };
+    use entity::{
+        refresh_token,
+        sea_orm_active_enums::{Role, Status, TokenPurpose},
+        session, user as user_entity, user_token,
+    };
+
+    fn existing() -> user_entity::Model {
+        user_entity::Model {
+            id: Uuid::new_v4(),
+            name: "test user".to_string(),
+            email: "test@test.com".to_string(),
+            password: "passwordhash".to_string(),
+            status: Status::Offline,
+            role: Role::Student,
+            email_verified_at: None,
+            suspended_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn fail_with_existing_email() {
+        let users = Arc::new(MemoryUserRepository::with_users(vec![existing()]));
+        let context = create_repository_context(users.clone(), create_mock_database(), None);
+
+        let got = signup(
+            &context,
+            "test@test.com".to_string(),
+            "other user".to_string(),
+            "testpassword".to_string(),
+        )
+        .await;
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "User with email `test@test.com` already exists"
+        );
+        assert_eq!(users.users().len(), 1);
+    }
+
+    // Inserts read back the primary key, and the count decides the user's presence
+    #[tokio::test]
+    async fn create_user_with_session() {
+        let users = Arc::new(MemoryUserRepository::default());
+        let id = Uuid::new_v4();
+        let db = create_mock_database()
+            .append_query_results(vec![vec![session::Model {
+                id,
+                user_id: id,
+                device_label: None,
+                user_agent: None,
+                ip_address: None,
+                created_at: 0,
+                last_seen_at: 0,
+                revoked_at: None,
+                mfa_authenticated: false,
+            }]])
+            .append_query_results(vec![vec![refresh_token::Model {
+                id,
+                user_id: id,
+                family_id: id,
+                token_hash: "hash".to_string(),
+                created_at: 0,
+                expires_at: 0,
+                rotated_at: None,
+                revoked_at: None,
+            }]])
+            .append_query_results(vec![vec![BTreeMap::from([(
+                "num_items",
+                Value::from(1_i64),
+            )])]])
+            .append_exec_results(vec![MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 0,
+            }])
+            .append_query_results(vec![vec![user_token::Model {
+                id,
+                user_id: id,
+                purpose: TokenPurpose::EmailVerification,
+                token_hash: "hash".to_string(),
+                payload: None,
+                created_at: 0,
+                expires_at: 0,
+                used_at: None,
+            }]]);
+        let context = create_repository_context(users.clone(), db, None);
+
+        let got = signup(
+            &context,
+            "test@test.com".to_string(),
+            "test user".to_string(),
+            "testpassword".to_string(),
+        )
+        .await
+        .unwrap();
+
+        let created = users.users();
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].email, "test@test.com");
+        assert_ne!(created[0].password, "testpassword");
+        assert_eq!(created[0].status, Status::Online);
+        assert_eq!(got.user.id, created[0].id.to_string());
+        assert_eq!(got.user.status, Status::Online);
+        assert!(got.refresh_token.is_some());
+        assert!(!got.mfa_pending);
+
+        let claims = get_claims_from_token(&got.token).unwrap();
+        assert_eq!(claims.sub, created[0].id);
+        assert!(claims.sid.is_some());
+        assert!(!claims.email_verified);
+    }
+}
+
+#[cfg(test)]
+mod test_signin {
+    use std::{collections::BTreeMap, sync::Arc};
+
+    use sea_orm::{prelude::Uuid, MockDatabase, MockExecResult, Value};
+
+    use crate::{
+        auth::{hash::hash, jwt::get_claims_from_token},
+        graphql::mutation::user::signin,
+        repository::memory::MemoryUserRepository,
+        testutils::{create_mock_database, create_repository_context},
+    };
+    use entity::{
+        login_attempt, refresh_token,
+        sea_orm_active_enums::{AttemptScope, Role, Status},
+        session, totp_secret, user as user_entity,
+    };
+
+    fn user(suspended_at: Option<i64>) -> user_entity::Model {
+        user_entity::Model {
+            id: Uuid::new_v4(),
+            name: "test user".to_string(),
+            email: "test@test.com".to_string(),
+            password: hash("testpassword").unwrap(),
+            status: Status::Offline,
+            role: Role::Student,
+            email_verified_at: Some(100),
+            suspended_at,
+        }
+    }
+
+    fn attempt(failures: i32, locked_until: Option<i64>) -> login_attempt::Model {
+        login_attempt::Model {
+            id: Uuid::new_v4(),
+            scope: AttemptScope::Account,
+            subject: "test@test.com".to_string(),
+            failures,
+            last_failed_at: 0,
+            locked_until,
+        }
+    }
+
+    fn exec() -> Vec<MockExecResult> {
+        vec![MockExecResult {
+            last_insert_id: 0,
+            rows_affected: 1,
+        }]
+    }
+
+    // The account has no lockout and the password is correct
+    fn allowed() -> MockDatabase {
+        create_mock_database()
+            .append_query_results::<login_attempt::Model>(vec![vec![]])
+            .append_exec_results(exec())
+    }
+
+    #[tokio::test]
+    async fn fail_with_unknown_email() {
+        let users = Arc::new(MemoryUserRepository::default());
+        let db = create_mock_database().append_query_results(vec![vec![], vec![attempt(1, None)]]);
+        let context = create_repository_context(users, db, None);
+
+        let got = signin(
+            &context,
+            "test@test.com".to_string(),
+            "testpassword".to_string(),
+            None,
+        )
+        .await;
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Incorrect email or password"
+        );
+    }
+
+    #[tokio::test]
+    async fn fail_with_incorrect_password() {
+        let users = Arc::new(MemoryUserRepository::with_users(vec![user(None)]));
+        let db = create_mock_database().append_query_results(vec![vec![], vec![attempt(1, None)]]);
+        let context = create_repository_context(users, db, None);
+
+        let got = signin(
+            &context,
+            "test@test.com".to_string(),
+            "wrongpassword".to_string(),
+            None,
+        )
+        .await;
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Incorrect email or password"
+        );
+    }
+
+    #[tokio::test]
+    async fn fail_when_locked() {
+        let users = Arc::new(MemoryUserRepository::with_users(vec![user(None)]));
+        let db =
+            create_mock_database().append_query_results(vec![vec![attempt(5, Some(i64::MAX))]]);
+        let context = create_repository_context(users, db, None);
+
+        let got = signin(
+            &context,
+            "test@test.com".to_string(),
+            "testpassword".to_string(),
+            None,
+        )
+        .await;
+        assert!(got
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("Too many failed signin attempts"));
+    }
+
+    #[tokio::test]
+    async fn fail_when_suspended() {
+        let users = Arc::new(MemoryUserRepository::with_users(vec![user(Some(100))]));
+        let context = create_repository_context(users, allowed(), None);
+
+        let got = signin(
+            &context,
+            "test@test.com".to_string(),
+            "testpassword".to_string(),
+            None,
+        )
+        .await;
+        assert_eq!(got.err().unwrap().to_string(), "Account has been suspended");
+    }
+
+    #[tokio::test]
+    async fn mfa_pending_without_session() {
+        let found = user(None);
+        let users = Arc::new(MemoryUserRepository::with_users(vec![found.clone()]));
+        let db = allowed().append_query_results(vec![vec![totp_secret::Model {
+            id: Uuid::new_v4(),
+            user_id: found.id,
+            secret: "secret".to_string(),
+            created_at: 0,
+            confirmed_at: Some(100),
+            last_used_step: None,
+        }]]);
+        let context = create_repository_context(users.clone(), db, None);
+
+        let got = signin(
+            &context,
+            "test@test.com".to_string(),
+            "testpassword".to_string(),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(got.mfa_pending);
+        assert!(got.refresh_token.is_none());
+        assert!(get_claims_from_token(&got.token).is_err());
+        assert_eq!(users.users()[0].status, Status::Offline);
+    }
+
+    #[tokio::test]
+    async fn start_session() {
+        let found = user(None);
+        let users = Arc::new(MemoryUserRepository::with_users(vec![found.clone()]));
+        let session_id = Uuid::new_v4();
+        let db = allowed()
+            .append_query_results::<totp_secret::Model>(vec![vec![]])
+            .append_query_results(vec![vec![session::Model {
+                id: session_id,
+                user_id: found.id,
+                device_label: Some("laptop".to_string()),
+                user_agent: None,
+                ip_address: None,
+                created_at: 0,
+                last_seen_at: 0,
+                revoked_at: None,
+                mfa_authenticated: false,
+            }]])
+            .append_query_results(vec![vec![refresh_token::Model {
+                id: Uuid::new_v4(),
+                user_id: found.id,
+                family_id: session_id,
+                token_hash: "hash".to_string(),
+                created_at: 0,
+                expires_at: 0,
+                rotated_at: None,
+                revoked_at: None,
+            }]])
+            .append_query_results(vec![vec![BTreeMap::from([(
+                "num_items",
+                Value::from(1_i64),
+            )])]]);
+        let context = create_repository_context(users.clone(), db, None);
+
+        let got = signin(
+            &context,
+            "test@test.com".to_string(),
+            "testpassword".to_string(),
+            Some("laptop".to_string()),
+        )
+        .await
+        .unwrap();
+        assert!(!got.mfa_pending);
+        assert!(got.refresh_token.is_some());
+        assert_eq!(got.user.status, Status::Online);
+        assert_eq!(users.users()[0].status, Status::Online);
+
+        let claims = get_claims_from_token(&got.token).unwrap();
+        assert_eq!(claims.sub, found.id);
+        assert!(claims.sid.is_some());
+        assert!(claims.email_verified);
+        assert!(!claims.mfa_authenticated);
+    }
+}
+
+#[cfg(test)]
+mod test_refresh_token {
+    use std::{collections::BTreeMap, sync::Arc};
+
+    use sea_orm::{prelude::Uuid, MockExecResult, Value};
+
+    use crate::{
+        auth::jwt::{create_jwt, get_claims_from_token},
+        graphql::mutation::user::refresh_token,
+        repository::memory::MemoryUserRepository,
+        testutils::{create_mock_database, create_repository_context},
+        time::Time,
+    };
+    use entity::{
+        refresh_token,
+        sea_orm_active_enums::{Role, Status},
+        session, token_revocation, user as user_entity,
+    };
+
+    fn user() -> user_entity::Model {
+        user_entity::Model {
+            id: Uuid::new_v4(),
+            name: "test user".to_string(),
+            email: "test@test.com".to_string(),
+            password: "passwordhash".to_string(),
+            status: Status::Offline,
+            role: Role::Student,
+            email_verified_at: None,
+            suspended_at: None,
+        }
+    }
+
+    fn token(user_id: &Uuid, rotated_at: Option<i64>, expires_at: i64) -> refresh_token::Model {
+        refresh_token::Model {
+            id: Uuid::new_v4(),
+            user_id: user_id.to_owned(),
+            family_id: Uuid::new_v4(),
+            token_hash: "hash".to_string(),
+            created_at: 0,
+            expires_at,
+            rotated_at,
+            revoked_at: None,
+        }
+    }
+
+    fn exec(rows_affected: u64) -> MockExecResult {
+        MockExecResult {
+            last_insert_id: 0,
+            rows_affected,
+        }
+    }
+
+    fn revocation(current: &refresh_token::Model) -> token_revocation::Model {
+        token_revocation::Model {
+            id: Uuid::new_v4(),
+            user_id: current.user_id,
+            jti: None,
+            session_id: Some(current.family_id),
+            revoked_at: 0,
+            expires_at: 0,
+        }
+    }
+
+    // Access tokens issued within the session are no longer accepted
+    fn assert_session_revoked(found: &user_entity::Model, current: &refresh_token::Model) {
+        let token = create_jwt(found, &current.family_id, false).unwrap();
+        let got = get_claims_from_token(&token);
+        assert_eq!(got.err().unwrap().to_string(), "Token has been revoked");
+    }
+
+    #[tokio::test]
+    async fn fail_with_unknown_token() {
+        let users = Arc::new(MemoryUserRepository::default());
+        let db = create_mock_database().append_query_results::<refresh_token::Model>(vec![vec![]]);
+        let context = create_repository_context(users, db, None);
+
+        let got = refresh_token(&context, "token".to_string()).await;
+        assert_eq!(got.err().unwrap().to_string(), "Refresh token is invalid");
+    }
+
+    #[tokio::test]
+    async fn fail_when_expired() {
+        let found = user();
+        let users = Arc::new(MemoryUserRepository::with_users(vec![found.clone()]));
+        let db =
+            create_mock_database().append_query_results(vec![vec![token(&found.id, None, 100)]]);
+        let context = create_repository_context(users, db, None);
+
+        let got = refresh_token(&context, "token".to_string()).await;
+        assert_eq!(got.err().unwrap().to_string(), "Refresh token has expired");
+    }
+
+    #[tokio::test]
+    async fn fail_when_reused() {
+        let found = user();
+        let users = Arc::new(MemoryUserRepository::with_users(vec![found.clone()]));
+        let expires_at = Time::hour_hence().unwrap().as_secs() as i64;
+        let current = token(&found.id, Some(100), expires_at);
+        let db = create_mock_database()
+            .append_query_results(vec![vec![current.clone()]])
+            .append_query_results(vec![vec![revocation(&current)]])
+            .append_exec_results(vec![exec(1), exec(1)]);
+        let context = create_repository_context(users, db, None);
+
+        let got = refresh_token(&context, "token".to_string()).await;
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Refresh token has already been used"
+        );
+        assert_session_revoked(&found, &current);
+    }
+
+    #[tokio::test]
+    async fn fail_when_rotated_concurrently() {
+        let found = user();
+        let users = Arc::new(MemoryUserRepository::with_users(vec![found.clone()]));
+        let expires_at = Time::hour_hence().unwrap().as_secs() as i64;
+        let current = token(&found.id, None, expires_at);
+        let db = create_mock_database()
+            .append_query_results(vec![vec![current.clone()]])
+            .append_query_results(vec![vec![revocation(&current)]])
+            .append_exec_results(vec![exec(0), exec(1), exec(1)]);
+        let context = create_repository_context(users, db, None);
+
+        let got = refresh_token(&context, "token".to_string()).await;
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Refresh token has already been used"
+        );
+        assert_session_revoked(&found, &current);
+    }
+
+    #[tokio::test]
+    async fn rotate_token() {
+        let found = user();
+        let users = Arc::new(MemoryUserRepository::with_users(vec![found.clone()]));
+        let expires_at = Time::hour_hence().unwrap().as_secs() as i64;
+        let current = token(&found.id, None, expires_at);
+        let db = create_mock_database()
+            .append_query_results(vec![vec![current.clone()]])
+            .append_exec_results(vec![exec(1), exec(1)])
+            .append_query_results(vec![vec![session::Model {
+                id: current.family_id,
+                user_id: found.id,
+                device_label: None,
+                user_agent: None,
+                ip_address: None,
+                created_at: 0,
+                last_seen_at: 0,
+                revoked_at: None,
+                mfa_authenticated: true,
+            }]])
+            .append_query_results(vec![vec![BTreeMap::from([(
+                "num_items",
+                Value::from(1_i64),
+            )])]])
+            .append_query_results(vec![vec![token(&found.id, None, expires_at)]]);
+        let context = create_repository_context(users.clone(), db, None);
+
+        let got = refresh_token(&context, "token".to_string()).await.unwrap();
+        assert!(got.refresh_token.is_some());
+        assert_ne!(got.refresh_token, Some("token".to_string()));
+        assert_eq!(users.users()[0].status, Status::Online);
+
+        let claims = get_claims_from_token(&got.token).unwrap();
+        assert_eq!(claims.sub, found.id);
+        assert_eq!(claims.sid, Some(current.family_id));
+        assert!(claims.mfa_authenticated);
+    }
+
+    #[tokio::test]
+    async fn fail_when_session_revoked() {
+        let found = user();
+        let users = Arc::new(MemoryUserRepository::with_users(vec![found.clone()]));
+        let expires_at = Time::hour_hence().unwrap().as_secs() as i64;
+        let current = token(&found.id, None, expires_at);
+        let db = create_mock_database()
+            .append_query_results(vec![vec![current.clone()]])
+            .append_exec_results(vec![exec(1)])
+            .append_query_results(vec![vec![session::Model {
+                id: current.family_id,
+                user_id: found.id,
+                device_label: None,
+                user_agent: None,
+                ip_address: None,
+                created_at: 0,
+                last_seen_at: 0,
+                revoked_at: Some(100),
+                mfa_authenticated: false,
+            }]]);
+        let context = create_repository_context(users, db, None);
+
+        let got = refresh_token(&context, "token".to_string()).await;
+        assert_eq!(got.err().unwrap().to_string(), "Refresh token is invalid");
+    }
+}
@@ -1,33 +1,52 @@
-use juniper::{graphql_object, FieldResult, GraphQLObject};
-use sea_orm::Set;
+use juniper::GraphQLObject;
+use sea_orm::{DatabaseConnection, Set};
 
-use super::MutationRoot;
 use crate::{
     auth::{
-        hash::{hash, verify},
-        jwt::{create_jwt, get_claims_from_token},
+        hash::{hash, hash_with, needs_rehash, verify, HashAlgorithm},
+        jwt::{create_jwt, create_mfa_pending_jwt, get_claims_from_token, get_mfa_pending_claims},
+        mfa::{has_confirmed_mfa, verify_mfa_code},
+        revocation::{revoke_session_tokens, revoke_token, revoke_user_tokens},
+        session::{end_session, issue_refresh_token, refresh_presence, start_session, DeviceInfo},
+        throttle::{check_signin_allowed, clear_failed_signins, record_failed_signin},
+        token::hash_token,
     },
-    errors::{AuthorizationError, UserError},
-    graphql::{schema::Context, user::GQLUser},
+    config::config,
+    errors::{ApiResult, AuthorizationError, UserError},
+    graphql::{mutation::verification::send_verification, schema::Context, user::GQLUser},
+    time::Time,
 };
 use entity::{
-    prelude::User,
-    sea_orm_active_enums::{Role, Status},
-    user,
+    prelude::{RefreshToken, Session, User},
+    refresh_token, user,
 };
 
 #[derive(GraphQLObject)]
 pub struct AuthResponse {
-    // TODO: Add refresh token
     pub token: String,
+    /// Not issued until any second factor has been verified
+    pub refresh_token: Option<String>,
     pub user: GQLUser,
+    pub mfa_pending: bool,
 }
 
 impl AuthResponse {
-    pub fn new(token: &str, user: GQLUser) -> Self {
+    pub fn new(token: &str, refresh_token: &str, user: GQLUser) -> Self {
         AuthResponse {
             token: token.to_string(),
+            refresh_token: Some(refresh_token.to_string()),
             user,
+            mfa_pending: false,
+        }
+    }
+
+    // The token can only be used to complete signin with `verifyMfa`
+    pub fn mfa_pending(token: &str, user: GQLUser) -> Self {
+        AuthResponse {
+            token: token.to_string(),
+            refresh_token: None,
+            user,
+            mfa_pending: true,
         }
     }
 }
@@ -43,83 +62,148 @@ impl SignoutResponse {
     }
 }
 
-#[graphql_object(Context = Context)]
-impl MutationRoot {
-    pub async fn signup(
-        ctx: &Context,
-        email: String,
-        name: String,
-        password: String,
-    ) -> FieldResult<AuthResponse> {
-        signup(ctx, email, name, password).await
-    }
-
-    pub async fn signin(
-        ctx: &Context,
-        email: String,
-        password: String,
-    ) -> FieldResult<AuthResponse> {
-        signin(ctx, email, password).await
-    }
-
-    pub async fn signout(ctx: &Context, email: String) -> FieldResult<SignoutResponse> {
-        signout(ctx, email).await
-    }
-}
-
-// Any function that inserts/updates the database won't work with the mock database
 pub async fn signup(
     ctx: &Context,
     email: String,
     name: String,
     password: String,
-) -> FieldResult<AuthResponse> {
+) -> ApiResult<AuthResponse> {
     let conn = ctx.connection.as_ref();
-    let existing = User::find_one_by_email(&email, conn).await?;
+    let existing = ctx.users.find_one_by_email(&email).await?;
     if existing.is_some() {
-        return Err(UserError::UnableToComplete.into());
+        return Err(UserError::UserWithEmailAlreadyExists(email).into());
     }
 
     let pass = hash(&password)?;
     let new_user = User::create_active_model(&email, &name, &pass);
-    let res = User::insert_one(new_user.clone(), conn).await?;
+    let created = ctx.users.insert_one(new_user).await?;
+
+    let session = start_session(
+        created,
+        false,
+        DeviceInfo::from_context(ctx, None),
+        ctx.users.as_ref(),
+        conn,
+    )
+    .await?;
+    let created = session.user;
 
-    let id = res.last_insert_id;
-    let token = create_jwt(&id, &Role::Guest)?;
+    // The user can request another verification email if this one can't be sent
+    if let Err(e) = send_verification(&created, ctx.mailer.as_ref(), conn).await {
+        tracing::warn!(error = %e, "Unable to send verification email");
+    }
 
-    let user = GQLUser::from_active_model(new_user);
+    let user = GQLUser::single(&created);
 
-    Ok(AuthResponse::new(&token, user))
+    Ok(AuthResponse::new(
+        &session.token,
+        &session.refresh_token,
+        user,
+    ))
 }
 
-pub async fn signin(ctx: &Context, email: String, password: String) -> FieldResult<AuthResponse> {
+// Each signin starts a new session, so a user can be signed in on several devices at once
+pub async fn signin(
+    ctx: &Context,
+    email: String,
+    password: String,
+    device_label: Option<String>,
+) -> ApiResult<AuthResponse> {
     let conn = ctx.connection.as_ref();
-    let found = User::find_one_by_email(&email, conn).await?;
+    check_signin_allowed(&email, ctx.client_ip, conn).await?;
+
+    let found = ctx.users.find_one_by_email(&email).await?;
     match found {
         Some(found) => {
-            if found.status != Status::Offline {
-                return Err(UserError::UnableToComplete.into());
+            if verify(&password, &found.password).is_err() {
+                record_failed_signin(&email, ctx.client_ip, conn).await?;
+                return Err(UserError::IncorrectEmailOrPassword.into());
             }
-            verify(&password, &found.password).map_err(|_| UserError::IncorrectEmailOrPassword)?;
+            clear_failed_signins(&email, conn).await?;
+            if found.is_suspended() {
+                return Err(UserError::AccountSuspended.into());
+            }
+
+            // Hashes created with an outdated algorithm or parameters are upgraded
+            // while the plaintext password is available
+            let algorithm = HashAlgorithm::from_config(&config().password);
+            let found = match needs_rehash(&found.password, &algorithm) {
+                true => {
+                    let rehashed = hash_with(&algorithm, &password)?;
+                    let mut found: user::ActiveModel = found.into();
+                    found.password = Set(rehashed);
+                    ctx.users.update_one(found).await?
+                }
+                false => found,
+            };
 
-            let mut found: user::ActiveModel = found.into();
-            found.status = Set(Status::Online.to_owned());
-            let found: user::Model = User::update_one(found, conn).await?;
+            // No session is started until the second factor is verified
+            if has_confirmed_mfa(&found.id, conn).await? {
+                let token = create_mfa_pending_jwt(&found)?;
+                return Ok(AuthResponse::mfa_pending(&token, GQLUser::single(&found)));
+            }
 
-            let token = create_jwt(&found.id, &found.role)?;
-            let user = GQLUser::single(&found);
-            Ok(AuthResponse::new(&token, user))
+            let device = DeviceInfo::from_context(ctx, device_label);
+            let session = start_session(found, false, device, ctx.users.as_ref(), conn).await?;
+            let user = GQLUser::single(&session.user);
+            Ok(AuthResponse::new(
+                &session.token,
+                &session.refresh_token,
+                user,
+            ))
+        }
+        None => {
+            record_failed_signin(&email, ctx.client_ip, conn).await?;
+            Err(UserError::IncorrectEmailOrPassword.into())
         }
-        None => Err(UserError::IncorrectEmailOrPassword.into()),
     }
 }
 
-pub async fn signout(ctx: &Context, email: String) -> FieldResult<SignoutResponse> {
+/// Completes a signin for a user with two-factor authentication
+/// using the token returned by `signin`
+pub async fn verify_mfa(
+    ctx: &Context,
+    code: String,
+    device_label: Option<String>,
+) -> ApiResult<AuthResponse> {
     if ctx.token.is_empty() {
         return Err(AuthorizationError::TokenMissing.into());
     }
+    let claims = get_mfa_pending_claims(&ctx.token)?;
+
     let conn = ctx.connection.as_ref();
-    let found = User::find_one_by_email(&email, conn).await?;
+    let found = ctx
+        .users
+        .find_one_by_id(&claims.sub)
+        .await?
+        .ok_or(UserError::UnableToComplete)?;
+    if found.is_suspended() {
+        return Err(UserError::AccountSuspended.into());
+    }
+
+    check_signin_allowed(&found.email, ctx.client_ip, conn).await?;
+    if !verify_mfa_code(&found.id, &code, conn).await? {
+        record_failed_signin(&found.email, ctx.client_ip, conn).await?;
+        return Err(UserError::InvalidMfaCode.into());
+    }
+    clear_failed_signins(&found.email, conn).await?;
+    revoke_token(&claims, conn).await?;
+
+    let device = DeviceInfo::from_context(ctx, device_label);
+    let session = start_session(found, true, device, ctx.users.as_ref(), conn).await?;
+    Ok(AuthResponse::new(
+        &session.token,
+        &session.refresh_token,
+        GQLUser::single(&session.user),
+    ))
+}
+
+pub async fn signout(ctx: &Context, email: String) -> ApiResult<SignoutResponse> {
+    if ctx.token.is_empty() {
+        return Err(AuthorizationError::TokenMissing.into());
+    }
+    let conn = ctx.connection.as_ref();
+    let found = ctx.users.find_one_by_email(&email).await?;
 
     match found {
         Some(found) => {
@@ -128,12 +212,118 @@ pub async fn signout(ctx: &Context, email: String) -> FieldResult<SignoutRespons
                 return Err(UserError::UnableToComplete.into());
             }
 
-            let mut found: user::ActiveModel = found.into();
-            found.status = Set(Status::Offline.to_owned());
-            User::update_one(found, conn).await?;
+            match claims.sid {
+                Some(session_id) => {
+                    end_session(found, &session_id, ctx.users.as_ref(), conn).await?;
+                }
+                // Tokens issued before sessions were tracked sign out every device
+                None => {
+                    let now = Time::now()?.as_secs() as i64;
+                    RefreshToken::revoke_all_for_user(&found.id, now, conn).await?;
+                    Session::revoke_all_for_user(&found.id, now, conn).await?;
+                    revoke_token(&claims, conn).await?;
+                    refresh_presence(found, ctx.users.as_ref(), conn).await?;
+                }
+            }
 
             Ok(SignoutResponse::complete())
         }
         None => Err(UserError::UnableToComplete.into()),
     }
 }
+
+/// Ends every session the user has, including the current one
+pub async fn signout_everywhere(ctx: &Context) -> ApiResult<SignoutResponse> {
+    if ctx.token.is_empty() {
+        return Err(AuthorizationError::TokenMissing.into());
+    }
+    let claims = get_claims_from_token(&ctx.token)?;
+    let conn = ctx.connection.as_ref();
+    let found = ctx
+        .users
+        .find_one_by_id(&claims.sub)
+        .await?
+        .ok_or(UserError::UnableToComplete)?;
+
+    revoke_user_tokens(&found.id, conn).await?;
+    refresh_presence(found, ctx.users.as_ref(), conn).await?;
+
+    Ok(SignoutResponse::complete())
+}
+
+// Exchanges a refresh token for a new access token and rotates the refresh token.
+// Presenting a token that has already been rotated means it was leaked or replayed,
+// so every token descended from the same signin is revoked.
+// A reused refresh token may have leaked, so the whole session is ended along
+// with any access tokens already issued within it
+async fn revoke_reused_session(
+    found: &refresh_token::Model,
+    now: i64,
+    conn: &DatabaseConnection,
+) -> ApiResult<()> {
+    Session::revoke_one(&found.family_id, now, conn).await?;
+    revoke_session_tokens(&found.user_id, &found.family_id, conn).await
+}
+
+pub async fn refresh_token(ctx: &Context, token: String) -> ApiResult<AuthResponse> {
+    let conn = ctx.connection.as_ref();
+    let found = RefreshToken::find_one_by_hash(&hash_token(&token), conn)
+        .await?
+        .ok_or(AuthorizationError::RefreshTokenInvalid)?;
+
+    let now = Time::now()?.as_secs() as i64;
+    if found.is_rotated() {
+        revoke_reused_session(&found, now, conn).await?;
+        return Err(AuthorizationError::RefreshTokenReused.into());
+    }
+    if found.is_revoked() {
+        return Err(AuthorizationError::RefreshTokenInvalid.into());
+    }
+    if found.is_expired(now) {
+        return Err(AuthorizationError::RefreshTokenExpired.into());
+    }
+
+    let rotated = RefreshToken::mark_rotated(&found.id, now, conn).await?;
+    if rotated.rows_affected == 0 {
+        revoke_reused_session(&found, now, conn).await?;
+        return Err(AuthorizationError::RefreshTokenReused.into());
+    }
+
+    let user = ctx
+        .users
+        .find_one_by_id(&found.user_id)
+        .await?
+        .ok_or(AuthorizationError::RefreshTokenInvalid)?;
+    if user.is_suspended() {
+        return Err(UserError::AccountSuspended.into());
+    }
+
+    // The refresh token family is the session. Families issued before sessions
+    // were tracked are adopted as a session of their own, which is not known
+    // to have passed a second factor.
+    let mfa_authenticated = match Session::find_one_by_id(&found.family_id, conn).await? {
+        Some(session) if session.revoked_at.is_some() => {
+            return Err(AuthorizationError::RefreshTokenInvalid.into());
+        }
+        Some(session) => {
+            Session::touch(&session.id, now, conn).await?;
+            session.mfa_authenticated
+        }
+        None => {
+            let mut session = Session::create_active_model(&user.id, None, None, None, now);
+            session.id = Set(found.family_id);
+            Session::insert_one(session, conn).await?;
+            false
+        }
+    };
+    // Refreshing is how an idle user comes back online
+    let user = refresh_presence(user, ctx.users.as_ref(), conn).await?;
+
+    let token = create_jwt(&user, &found.family_id, mfa_authenticated)?;
+    let refresh_token = issue_refresh_token(&user.id, &found.family_id, conn).await?;
+    Ok(AuthResponse::new(
+        &token,
+        &refresh_token,
+        GQLUser::single(&user),
+    ))
+}
@@ -0,0 +1,77 @@
+use sea_orm::{DatabaseConnection, Set};
+
+use super::SuccessResponse;
+use crate::{
+    auth::{
+        jwt::get_claims_from_token,
+        user_token::{issue_user_token, redeem_user_token},
+    },
+    config::config,
+    errors::{ApiResult, AuthorizationError, UserError},
+    graphql::schema::Context,
+    mail::{templates, Mailer},
+    time::Time,
+};
+use entity::{sea_orm_active_enums::TokenPurpose, user};
+
+pub async fn send_verification_email(ctx: &Context) -> ApiResult<SuccessResponse> {
+    if ctx.token.is_empty() {
+        return Err(AuthorizationError::TokenMissing.into());
+    }
+    let claims = get_claims_from_token(&ctx.token)?;
+
+    let conn = ctx.connection.as_ref();
+    let found = ctx
+        .users
+        .find_one_by_id(&claims.sub)
+        .await?
+        .ok_or(UserError::UnableToComplete)?;
+    if found.is_email_verified() {
+        return Err(UserError::EmailAlreadyVerified.into());
+    }
+
+    send_verification(&found, ctx.mailer.as_ref(), conn).await?;
+    Ok(SuccessResponse::complete())
+}
+
+pub async fn verify_email(ctx: &Context, token: String) -> ApiResult<SuccessResponse> {
+    let conn = ctx.connection.as_ref();
+    let redeemed = redeem_user_token(&token, TokenPurpose::EmailVerification, conn)
+        .await?
+        .ok_or(UserError::InvalidVerificationToken)?;
+
+    let found = ctx
+        .users
+        .find_one_by_id(&redeemed.user_id)
+        .await?
+        .ok_or(UserError::InvalidVerificationToken)?;
+
+    let now = Time::now()?.as_secs() as i64;
+    let mut found: user::ActiveModel = found.into();
+    found.email_verified_at = Set(Some(now));
+    ctx.users.update_one(found).await?;
+
+    Ok(SuccessResponse::complete())
+}
+
+pub async fn send_verification(
+    user: &user::Model,
+    mailer: &dyn Mailer,
+    conn: &DatabaseConnection,
+) -> ApiResult<()> {
+    let lifetime = config().tokens.email_verification_lifetime();
+    let expires_at = Time::now_plus_duration(lifetime)?.as_secs() as i64;
+    let token = issue_user_token(
+        &user.id,
+        TokenPurpose::EmailVerification,
+        expires_at,
+        None,
+        conn,
+    )
+    .await?;
+
+    mailer
+        .send(templates::email_verification(&user.email, &token, lifetime))
+        .await?;
+    Ok(())
+}
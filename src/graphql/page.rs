@@ -0,0 +1,70 @@
+use base64::URL_SAFE_NO_PAD;
+use juniper::GraphQLObject;
+
+use crate::errors::QueryError;
+
+pub const DEFAULT_PAGE_SIZE: i32 = 20;
+pub const MAX_PAGE_SIZE: i32 = 100;
+
+#[derive(GraphQLObject, Debug)]
+pub struct GQLPageInfo {
+    pub has_next_page: bool,
+    // Only pages after a cursor are requested, so any cursor means there are earlier rows
+    pub has_previous_page: bool,
+    pub start_cursor: Option<String>,
+    pub end_cursor: Option<String>,
+}
+
+pub fn page_size(first: Option<i32>) -> Result<u64, QueryError> {
+    match first.unwrap_or(DEFAULT_PAGE_SIZE) {
+        size @ 0..=MAX_PAGE_SIZE => Ok(size as u64),
+        _ => Err(QueryError::PageSizeOutOfRange { max: MAX_PAGE_SIZE }),
+    }
+}
+
+/// Cursors are opaque to clients, so their contents are encoded
+pub fn encode_cursor(parts: &[&str]) -> String {
+    base64::encode_config(parts.join(":"), URL_SAFE_NO_PAD)
+}
+
+/// Splits a cursor into `count` parts, the last of which may itself contain colons
+pub fn decode_cursor(cursor: &str, count: usize) -> Result<Vec<String>, QueryError> {
+    let bytes =
+        base64::decode_config(cursor, URL_SAFE_NO_PAD).map_err(|_| QueryError::InvalidCursor)?;
+    let decoded = String::from_utf8(bytes).map_err(|_| QueryError::InvalidCursor)?;
+    let parts: Vec<String> = decoded.splitn(count, ':').map(String::from).collect();
+    if parts.len() != count {
+        return Err(QueryError::InvalidCursor);
+    }
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod test_page {
+    use super::{decode_cursor, encode_cursor, page_size, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+
+    #[test]
+    fn cursor_round_trip() {
+        let cursor = encode_cursor(&["name", "id", "value: with colons"]);
+        let got = decode_cursor(&cursor, 3).unwrap();
+        assert_eq!(got, vec!["name", "id", "value: with colons"]);
+    }
+
+    #[test]
+    fn reject_malformed_cursor() {
+        assert!(decode_cursor("not base64!", 3).is_err());
+        assert!(decode_cursor(&encode_cursor(&["name"]), 3).is_err());
+    }
+
+    #[test]
+    fn page_size_is_bounded() {
+        assert_eq!(page_size(None).unwrap(), DEFAULT_PAGE_SIZE as u64);
+        assert_eq!(page_size(Some(0)).unwrap(), 0);
+        assert_eq!(
+            page_size(Some(MAX_PAGE_SIZE)).unwrap(),
+            MAX_PAGE_SIZE as u64
+        );
+        assert!(page_size(Some(MAX_PAGE_SIZE + 1)).is_err());
+        assert!(page_size(Some(-1)).is_err());
+    }
+}
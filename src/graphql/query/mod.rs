@@ -1,3 +1,4 @@
+pub mod session;
 pub mod tests;
 pub mod user;
 pub struct QueryRoot;
@@ -0,0 +1,21 @@
+use crate::{
+    auth::{jwt::get_claims_from_token, session::active_since},
+    errors::{ApiResult, AuthorizationError},
+    graphql::{schema::Context, session::GQLSession},
+    time::Time,
+};
+use entity::prelude::Session;
+
+/// The caller's active sessions, most recently seen first
+pub async fn my_sessions(ctx: &Context) -> ApiResult<Vec<GQLSession>> {
+    if ctx.token.is_empty() {
+        return Err(AuthorizationError::TokenMissing.into());
+    }
+    let claims = get_claims_from_token(&ctx.token)?;
+
+    let now = Time::now()?.as_secs() as i64;
+    let sessions =
+        Session::find_active_for_user(&claims.sub, active_since(now), ctx.connection.as_ref())
+            .await?;
+    Ok(GQLSession::multiple(sessions, claims.sid))
+}
@@ -1 +1,4 @@
+pub mod session;
+// These tests predate running clippy on test targets
+#[allow(clippy::clone_on_copy, clippy::useless_conversion, clippy::useless_vec)]
 pub mod user;
@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod test_session_response {
+    use sea_orm::prelude::Uuid;
+
+    use crate::graphql::session::GQLSession;
+    use entity::session;
+
+    fn model(label: &str) -> session::Model {
+        session::Model {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            device_label: Some(label.to_string()),
+            user_agent: Some("Mozilla/5.0".to_string()),
+            ip_address: Some("10.0.0.1".to_string()),
+            created_at: 100,
+            last_seen_at: 200,
+            revoked_at: None,
+            mfa_authenticated: false,
+        }
+    }
+
+    #[test]
+    fn mark_only_current_session() {
+        let models = vec![model("laptop"), model("phone")];
+        let current = Some(models[1].id);
+        let got = GQLSession::multiple(models, current);
+
+        assert_eq!(got.len(), 2);
+        assert!(!got[0].current);
+        assert!(got[1].current);
+        assert_eq!(got[1].device_label, Some("phone".to_string()));
+        assert_eq!(got[1].ip_address, Some("10.0.0.1".to_string()));
+        assert_eq!(got[1].last_seen_at, 200.0);
+    }
+}
+
+#[cfg(test)]
+mod test_my_sessions {
+    use sea_orm::prelude::Uuid;
+
+    use crate::{
+        graphql::query::session::my_sessions,
+        testutils::{create_mock_context, create_test_jwt},
+        time::Time,
+    };
+    use entity::{sea_orm_active_enums::Role, session};
+
+    #[tokio::test]
+    async fn fail_without_token() {
+        let results: Vec<Vec<session::Model>> = vec![];
+        let context = create_mock_context(results, None);
+
+        let got = my_sessions(&context).await;
+        assert_eq!(got.err().unwrap().to_string(), "Token missing");
+    }
+
+    #[tokio::test]
+    async fn list_sessions() {
+        let id = Uuid::new_v4();
+        let exp = Time::hour_hence().unwrap().as_secs();
+        let token = create_test_jwt(&id, &Role::Student, exp);
+        let results = vec![vec![session::Model {
+            id: Uuid::new_v4(),
+            user_id: id,
+            device_label: None,
+            user_agent: None,
+            ip_address: None,
+            created_at: 100,
+            last_seen_at: 100,
+            revoked_at: None,
+            mfa_authenticated: false,
+        }]];
+        let context = create_mock_context(results, Some(token));
+
+        let got = my_sessions(&context).await.unwrap();
+        assert_eq!(got.len(), 1);
+        assert!(!got[0].current);
+    }
+}
@@ -18,13 +18,16 @@ mod test_user_response {
             password: "passwordhash".to_string(),
             role: Role::Student,
             status: Status::Hidden,
+            email_verified_at: None,
+            suspended_at: None,
         };
         let got = GQLUser::single(&model);
-        assert_eq!(got.email, "test@test.com");
+        assert_eq!(got.email, Some("test@test.com".to_string()));
         assert_eq!(got.id, id.to_string());
         assert_eq!(got.name, "test user");
         assert_eq!(got.role, Role::Student);
         assert_eq!(got.status, Status::Hidden);
+        assert_eq!(got.email_verified, Some(false));
     }
 
     #[test]
@@ -40,6 +43,8 @@ mod test_user_response {
                 password: "passwordhash".to_string(),
                 role: roles[i % 4].clone(),
                 status: statuses[i % 3].clone(),
+                email_verified_at: None,
+                suspended_at: None,
             })
             .collect();
         let responses = GQLUser::multiple(models);
@@ -47,7 +52,7 @@ mod test_user_response {
         for i in 0..9 {
             let got = &responses[i];
             assert_eq!(got.id, ids[i].to_string());
-            assert_eq!(got.email, format!("test{}@test.com", i));
+            assert_eq!(got.email, Some(format!("test{}@test.com", i)));
             assert_eq!(got.name, format!("test user{}", i));
             assert_eq!(got.role, roles[i % 4].clone());
             assert_eq!(got.status, statuses[i % 3].clone());
@@ -64,7 +69,7 @@ mod test_find_user_by_email {
 
     use crate::{
         graphql::query::user::*,
-        testutils::{create_errored_context, create_mock_context},
+        testutils::{create_errored_context, create_mock_context, create_role_jwt},
     };
     use entity::{
         sea_orm_active_enums::{Role, Status},
@@ -81,15 +86,17 @@ mod test_find_user_by_email {
             password: "testpass".to_string(),
             status: Status::Online,
             role: Role::Teacher,
+            email_verified_at: None,
+            suspended_at: None,
         }]];
-        let context = create_mock_context(users, None);
+        let context = create_mock_context(users, Some(create_role_jwt(&Role::Teacher)));
 
         let got = find_user_by_email(&context, "test@test.com".to_string())
             .await
             .unwrap()
             .unwrap();
         assert_eq!(got.id, id.to_string());
-        assert_eq!(got.email, "test1@test.com");
+        assert_eq!(got.email, Some("test1@test.com".to_string()));
         assert_eq!(got.name, "test user1");
         assert_eq!(got.status, Status::Online);
         assert_eq!(got.role, Role::Teacher);
@@ -98,7 +105,7 @@ mod test_find_user_by_email {
     #[tokio::test]
     async fn find_no_user_for_find_user_by_email() {
         let results: Vec<Vec<user_entity::Model>> = vec![vec![]];
-        let context = create_mock_context(results, None);
+        let context = create_mock_context(results, Some(create_role_jwt(&Role::Teacher)));
 
         let got = find_user_by_email(&context, "test@test.com".to_string())
             .await
@@ -109,7 +116,10 @@ mod test_find_user_by_email {
 
     #[tokio::test]
     async fn get_error_for_find_user_by_email() {
-        let context = create_errored_context(vec![DbErr::ConnectionAcquire.into()], None);
+        let context = create_errored_context(
+            vec![DbErr::ConnectionAcquire.into()],
+            Some(create_role_jwt(&Role::Teacher)),
+        );
         let got = find_user_by_email(&context, "test@test.com".to_string()).await;
 
         assert!(got.is_err());
@@ -125,7 +135,7 @@ mod test_find_user_by_id {
 
     use crate::{
         graphql::query::user::*,
-        testutils::{create_errored_context, create_mock_context},
+        testutils::{create_errored_context, create_mock_context, create_role_jwt},
     };
     use entity::{
         sea_orm_active_enums::{Role, Status},
@@ -142,15 +152,17 @@ mod test_find_user_by_id {
             password: "testpass".to_string(),
             status: Status::Online,
             role: Role::Teacher,
+            email_verified_at: None,
+            suspended_at: None,
         }]];
-        let context = create_mock_context(users, None);
+        let context = create_mock_context(users, Some(create_role_jwt(&Role::Teacher)));
 
         let got = find_user_by_id(&context, id.to_string())
             .await
             .unwrap()
             .unwrap();
         assert_eq!(got.id, id.to_string());
-        assert_eq!(got.email, "test1@test.com");
+        assert_eq!(got.email, Some("test1@test.com".to_string()));
         assert_eq!(got.name, "test user1");
         assert_eq!(got.status, Status::Online);
         assert_eq!(got.role, Role::Teacher);
@@ -159,7 +171,7 @@ mod test_find_user_by_id {
     #[tokio::test]
     async fn find_no_user_for_find_user_by_id() {
         let results: Vec<Vec<user_entity::Model>> = vec![vec![]];
-        let context = create_mock_context(results, None);
+        let context = create_mock_context(results, Some(create_role_jwt(&Role::Teacher)));
 
         let got = find_user_by_id(&context, Uuid::new_v4().to_string())
             .await
@@ -170,7 +182,10 @@ mod test_find_user_by_id {
 
     #[tokio::test]
     async fn get_error_for_find_user_by_id() {
-        let context = create_errored_context(vec![DbErr::ConnectionAcquire.into()], None);
+        let context = create_errored_context(
+            vec![DbErr::ConnectionAcquire.into()],
+            Some(create_role_jwt(&Role::Teacher)),
+        );
         let got = find_user_by_id(&context, Uuid::new_v4().to_string()).await;
 
         assert!(got.is_err());
@@ -179,63 +194,338 @@ mod test_find_user_by_id {
 
 #[cfg(test)]
 mod test_get_users {
-    use std::vec;
+    use std::collections::BTreeMap;
 
     use migration::DbErr;
-    use sea_orm::prelude::Uuid;
+    use sea_orm::{prelude::Uuid, Value};
 
     use crate::{
-        graphql::query::user::*,
-        testutils::{create_errored_context, create_mock_context},
+        graphql::{query::user::*, user::GQLUserOrder},
+        testutils::{
+            create_database_context, create_errored_context, create_mock_database, create_role_jwt,
+        },
     };
     use entity::{
         sea_orm_active_enums::{Role, Status},
+        traits::user::{SortDirection, UserOrderField},
         user as user_entity,
     };
 
-    #[tokio::test]
-    async fn find_users_for_get_users() {
-        let ids = (0..10).map(|_| Uuid::new_v4()).collect::<Vec<Uuid>>();
+    fn count(total: i64) -> Vec<BTreeMap<&'static str, Value>> {
+        vec![BTreeMap::from([("num_items", total.into())])]
+    }
+
+    fn models(total: usize) -> Vec<user_entity::Model> {
         let roles = vec![Role::Guest, Role::Student, Role::Teacher, Role::Admin];
         let statuses = vec![Status::Online, Status::Offline, Status::Hidden];
-        let users = (0..10)
+        (0..total)
             .map(|i| user_entity::Model {
-                id: ids[i].clone(),
+                id: Uuid::new_v4(),
                 email: format!("test{}@test.com", i),
                 name: format!("test user{}", i),
                 password: "testpass".to_string(),
                 status: statuses[i % 3].clone(),
                 role: roles[i % 4].clone(),
+                email_verified_at: None,
+                suspended_at: None,
             })
-            .collect::<Vec<user_entity::Model>>();
-        let results = vec![users];
-        let context = create_mock_context(results, None);
+            .collect()
+    }
 
-        let result = get_users(&context).await.unwrap();
-        for i in 0..10 {
-            let got = &result[i];
-            assert_eq!(got.id, ids[i].to_string());
-            assert_eq!(got.email, format!("test{}@test.com", i));
-            assert_eq!(got.name, format!("test user{}", i));
-            assert_eq!(got.role, roles[i % 4].clone());
-            assert_eq!(got.status, statuses[i % 3].clone());
+    #[tokio::test]
+    async fn find_users_for_get_users() {
+        let users = models(10);
+        let db = create_mock_database()
+            .append_query_results(vec![users.clone()])
+            .append_query_results(vec![count(10)]);
+        let context = create_database_context(db, Some(create_role_jwt(&Role::Admin)));
+
+        let result = get_users(&context, None, None, None, None).await.unwrap();
+        assert_eq!(result.total_count, 10);
+        assert!(!result.page_info.has_next_page);
+        assert!(!result.page_info.has_previous_page);
+        for (i, user) in users.iter().enumerate() {
+            let got = &result.edges[i].node;
+            assert_eq!(got.id, user.id.to_string());
+            assert_eq!(got.email.as_ref(), Some(&user.email));
+            assert_eq!(got.name, user.name);
+            assert_eq!(got.role, user.role);
+            assert_eq!(got.status, user.status);
         }
+        assert_eq!(
+            result.page_info.end_cursor.as_ref(),
+            Some(&result.edges[9].cursor)
+        );
+    }
+
+    // The extra row fetched beyond the page is only used to detect a next page
+    #[tokio::test]
+    async fn detect_next_page_for_get_users() {
+        let db = create_mock_database()
+            .append_query_results(vec![models(3)])
+            .append_query_results(vec![count(10)]);
+        let context = create_database_context(db, Some(create_role_jwt(&Role::Teacher)));
+
+        let result = get_users(&context, Some(2), None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(result.edges.len(), 2);
+        assert!(result.page_info.has_next_page);
     }
 
     #[tokio::test]
     async fn find_no_users_for_get_users() {
-        let results: Vec<Vec<user_entity::Model>> = vec![vec![]];
-        let context = create_mock_context(results, None);
+        let db = create_mock_database()
+            .append_query_results(vec![Vec::<user_entity::Model>::new()])
+            .append_query_results(vec![count(0)]);
+        let context = create_database_context(db, Some(create_role_jwt(&Role::Teacher)));
+
+        let result = get_users(&context, None, None, None, None).await.unwrap();
+        assert_eq!(result.edges.len(), 0);
+        assert_eq!(result.total_count, 0);
+        assert!(result.page_info.start_cursor.is_none());
+    }
 
-        let result = get_users(&context).await.unwrap();
-        assert_eq!(result.len(), 0);
+    #[tokio::test]
+    async fn reject_cursor_for_other_ordering() {
+        let db = create_mock_database()
+            .append_query_results(vec![models(1)])
+            .append_query_results(vec![count(1)]);
+        let context = create_database_context(db, Some(create_role_jwt(&Role::Teacher)));
+        let result = get_users(&context, None, None, None, None).await.unwrap();
+        let cursor = result.edges[0].cursor.to_owned();
+
+        let context = create_database_context(
+            create_mock_database(),
+            Some(create_role_jwt(&Role::Teacher)),
+        );
+        let order = GQLUserOrder {
+            field: UserOrderField::Email,
+            direction: SortDirection::Asc,
+        };
+        let got = get_users(&context, None, Some(cursor), None, Some(order)).await;
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Cursor is invalid or was created for a different ordering"
+        );
     }
 
     #[tokio::test]
     async fn get_error_for_get_users() {
-        let context = create_errored_context(vec![DbErr::ConnectionAcquire.into()], None);
-        let got = get_users(&context).await;
+        let context = create_errored_context(
+            vec![DbErr::ConnectionAcquire.into()],
+            Some(create_role_jwt(&Role::Teacher)),
+        );
+        let got = get_users(&context, None, None, None, None).await;
 
         assert!(got.is_err());
     }
 }
+
+#[cfg(test)]
+mod test_user_privacy {
+    use sea_orm::prelude::Uuid;
+
+    use crate::{
+        auth::{
+            jwt::{check_permission, Claims},
+            permission::PermissionCache,
+        },
+        graphql::{
+            query::user::*,
+            user::{GQLUser, GQLUserOrder, Viewer},
+        },
+        testutils::{create_mock_context, create_role_jwt, create_test_jwt},
+        time::Time,
+    };
+    use entity::{
+        sea_orm_active_enums::{Role, Status},
+        traits::user::{SortDirection, UserOrderField},
+        user as user_entity,
+    };
+
+    fn model(id: Uuid, status: Status) -> user_entity::Model {
+        user_entity::Model {
+            id,
+            email: "test@test.com".to_string(),
+            name: "test user".to_string(),
+            password: "testpass".to_string(),
+            status,
+            role: Role::Student,
+            email_verified_at: None,
+            suspended_at: None,
+        }
+    }
+
+    fn viewer(read_emails: bool, see_hidden: bool) -> Viewer {
+        Viewer {
+            id: Uuid::new_v4(),
+            role: "Student".to_string(),
+            read_emails,
+            see_hidden,
+            read_account_state: read_emails || see_hidden,
+        }
+    }
+
+    #[test]
+    fn redact_other_users_for_viewer() {
+        let user = model(Uuid::new_v4(), Status::Hidden);
+
+        let got = GQLUser::visible_to(&user, &viewer(false, false));
+        assert!(got.email.is_none());
+        assert_eq!(got.status, Status::Offline);
+
+        let got = GQLUser::visible_to(&user, &viewer(true, false));
+        assert_eq!(got.email, Some("test@test.com".to_string()));
+        assert_eq!(got.status, Status::Offline);
+
+        let got = GQLUser::visible_to(&user, &viewer(true, true));
+        assert_eq!(got.status, Status::Hidden);
+    }
+
+    #[test]
+    fn redact_account_state_for_viewer() {
+        let mut user = model(Uuid::new_v4(), Status::Online);
+        user.email_verified_at = Some(100);
+        user.suspended_at = Some(200);
+
+        let got = GQLUser::visible_to(&user, &viewer(false, false));
+        assert_eq!(got.email_verified, None);
+        assert_eq!(got.suspended, None);
+
+        let got = GQLUser::visible_to(&user, &viewer(true, false));
+        assert_eq!(got.email_verified, Some(true));
+        assert_eq!(got.suspended, Some(true));
+
+        let got = GQLUser::visible_to(&user, &viewer(false, true));
+        assert_eq!(got.email_verified, Some(true));
+        assert_eq!(got.suspended, Some(true));
+    }
+
+    #[test]
+    fn show_full_record_to_user_themselves() {
+        let own = viewer(false, false);
+        let user = model(own.id, Status::Hidden);
+
+        let got = GQLUser::visible_to(&user, &own);
+        assert_eq!(got.email, Some("test@test.com".to_string()));
+        assert_eq!(got.status, Status::Hidden);
+        assert_eq!(got.email_verified, Some(false));
+        assert_eq!(got.suspended, Some(false));
+    }
+
+    #[test]
+    fn hidden_users_appear_offline() {
+        let student = viewer(false, false);
+        assert_eq!(
+            student.statuses_appearing_as(Status::Offline),
+            vec![Status::Offline, Status::Hidden]
+        );
+        assert!(student.statuses_appearing_as(Status::Hidden).is_empty());
+        assert_eq!(
+            viewer(true, true).statuses_appearing_as(Status::Hidden),
+            vec![Status::Hidden]
+        );
+    }
+
+    fn claims(role: &Role, email_verified: bool, mfa_authenticated: bool) -> Claims {
+        Claims {
+            sub: Uuid::new_v4(),
+            sid: None,
+            role: role.to_str(),
+            exp: 3700,
+            iat: 100,
+            iat_ms: 100_000,
+            jti: Uuid::new_v4(),
+            email_verified,
+            mfa_pending: false,
+            mfa_authenticated,
+        }
+    }
+
+    fn defaults(role: &str, permission: &str) -> bool {
+        PermissionCache::default().grants(role, permission)
+    }
+
+    #[test]
+    fn unverified_staff_view_as_unprivileged() {
+        let teacher = claims(&Role::Teacher, false, false);
+        let got = Viewer::new(&teacher, |permission| {
+            check_permission(permission, &teacher, false, &[], defaults).is_ok()
+        });
+        assert!(got.read_emails);
+
+        let got = Viewer::new(&teacher, |permission| {
+            check_permission(permission, &teacher, true, &[], defaults).is_ok()
+        });
+        assert!(!got.read_emails);
+        assert!(!got.see_hidden);
+    }
+
+    #[test]
+    fn admins_without_mfa_view_as_unprivileged() {
+        let admin = claims(&Role::Admin, true, false);
+        let got = Viewer::new(&admin, |permission| {
+            check_permission(permission, &admin, false, &[Role::Admin], defaults).is_ok()
+        });
+        assert!(!got.read_emails);
+        assert!(!got.see_hidden);
+
+        let admin = claims(&Role::Admin, true, true);
+        let got = Viewer::new(&admin, |permission| {
+            check_permission(permission, &admin, false, &[Role::Admin], defaults).is_ok()
+        });
+        assert!(got.read_emails);
+        assert!(got.see_hidden);
+    }
+
+    #[tokio::test]
+    async fn fail_without_token() {
+        let results = vec![vec![model(Uuid::new_v4(), Status::Online)]];
+        let context = create_mock_context(results, None);
+
+        let got = find_user_by_id(&context, Uuid::new_v4().to_string()).await;
+        assert_eq!(got.err().unwrap().to_string(), "Token missing");
+    }
+
+    // Looking up another user's email would confirm who the address belongs to
+    #[tokio::test]
+    async fn find_only_own_email_without_permission() {
+        let results = vec![vec![model(Uuid::new_v4(), Status::Online)]];
+        let context = create_mock_context(results, Some(create_role_jwt(&Role::Student)));
+
+        let got = find_user_by_email(&context, "test@test.com".to_string())
+            .await
+            .unwrap();
+        assert!(got.is_none());
+
+        let id = Uuid::new_v4();
+        let exp = Time::hour_hence().unwrap().as_secs();
+        let token = create_test_jwt(&id, &Role::Student, exp);
+        let results = vec![vec![model(id, Status::Hidden)]];
+        let context = create_mock_context(results, Some(token));
+
+        let got = find_user_by_email(&context, "test@test.com".to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(got.email, Some("test@test.com".to_string()));
+        assert_eq!(got.status, Status::Hidden);
+    }
+
+    #[tokio::test]
+    async fn reject_email_ordering_without_permission() {
+        let results: Vec<Vec<user_entity::Model>> = vec![];
+        let context = create_mock_context(results, Some(create_role_jwt(&Role::Student)));
+        let order = GQLUserOrder {
+            field: UserOrderField::Email,
+            direction: SortDirection::Asc,
+        };
+
+        let got = get_users(&context, None, None, None, Some(order)).await;
+        assert_eq!(
+            got.err().unwrap().to_string(),
+            "Permission `users:read` is not granted to role Student"
+        );
+    }
+}
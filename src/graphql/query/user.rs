@@ -1,44 +1,113 @@
-use juniper::{graphql_object, FieldResult};
+use juniper::graphql_object;
 use sea_orm::prelude::Uuid;
 
-use super::QueryRoot;
-use crate::graphql::{schema::Context, user::GQLUser};
-use entity::prelude::User;
+use super::{session::my_sessions, QueryRoot};
+use crate::{
+    auth::{jwt::get_claims_from_token, permission::USERS_READ},
+    errors::{ApiResult, AuthorizationError, QueryError},
+    graphql::{
+        page::page_size,
+        schema::Context,
+        session::GQLSession,
+        user::{
+            decode_user_cursor, GQLUser, GQLUserConnection, GQLUserFilter, GQLUserOrder, Viewer,
+        },
+    },
+};
+use entity::traits::user::{UserFilter, UserOrder, UserOrderField};
 
 #[graphql_object(Context = Context)]
 impl QueryRoot {
-    pub async fn user_by_email(ctx: &Context, email: String) -> FieldResult<Option<GQLUser>> {
+    pub async fn user_by_email(ctx: &Context, email: String) -> ApiResult<Option<GQLUser>> {
         find_user_by_email(ctx, email).await
     }
 
-    pub async fn user_by_id(ctx: &Context, id: String) -> FieldResult<Option<GQLUser>> {
+    pub async fn user_by_id(ctx: &Context, id: String) -> ApiResult<Option<GQLUser>> {
         find_user_by_id(ctx, id).await
     }
 
-    pub async fn users(ctx: &Context) -> FieldResult<Vec<GQLUser>> {
-        get_users(ctx).await
+    /// Pages through users in the requested order, by name ascending by default
+    pub async fn users(
+        ctx: &Context,
+        first: Option<i32>,
+        after: Option<String>,
+        filter: Option<GQLUserFilter>,
+        order_by: Option<GQLUserOrder>,
+    ) -> ApiResult<GQLUserConnection> {
+        get_users(ctx, first, after, filter, order_by).await
     }
+
+    pub async fn my_sessions(ctx: &Context) -> ApiResult<Vec<GQLSession>> {
+        my_sessions(ctx).await
+    }
+}
+
+// Any signed in user can look up other users, but only sees what their role allows
+pub fn viewer(ctx: &Context) -> Result<Viewer, AuthorizationError> {
+    if ctx.token.is_empty() {
+        return Err(AuthorizationError::TokenMissing);
+    }
+    let claims = get_claims_from_token(&ctx.token)?;
+    Ok(Viewer::from_claims(&claims))
 }
 
-pub async fn find_user_by_email(ctx: &Context, email: String) -> FieldResult<Option<GQLUser>> {
-    let conn = ctx.connection.as_ref();
-    let found_user = User::find_one_by_email(&email, conn).await?;
+/// Viewers that cannot read emails can only look up their own,
+/// so that this cannot be used to find out who an address belongs to
+pub async fn find_user_by_email(ctx: &Context, email: String) -> ApiResult<Option<GQLUser>> {
+    let viewer = viewer(ctx)?;
+    let found_user = ctx.users.find_one_by_email(&email).await?;
 
-    let res = found_user.map(|model| GQLUser::single(&model));
+    let res = found_user
+        .filter(|model| viewer.read_emails || model.id == viewer.id)
+        .map(|model| GQLUser::visible_to(&model, &viewer));
     Ok(res)
 }
 
-pub async fn find_user_by_id(ctx: &Context, id: String) -> FieldResult<Option<GQLUser>> {
-    let conn = ctx.connection.as_ref();
-    let id = Uuid::parse_str(&id)?;
-    let found_user = User::find_one_by_id(&id, conn).await?;
-    let res = found_user.map(|model| GQLUser::single(&model));
+pub async fn find_user_by_id(ctx: &Context, id: String) -> ApiResult<Option<GQLUser>> {
+    let viewer = viewer(ctx)?;
+    let id = Uuid::parse_str(&id).map_err(|_| QueryError::InvalidId(id.to_owned()))?;
+    let found_user = ctx.users.find_one_by_id(&id).await?;
+    let res = found_user.map(|model| GQLUser::visible_to(&model, &viewer));
     Ok(res)
 }
 
-pub async fn get_users(ctx: &Context) -> FieldResult<Vec<GQLUser>> {
-    let conn = ctx.connection.as_ref();
-    let users = User::find_all(conn).await?;
-    let res = GQLUser::multiple(users);
-    Ok(res)
+pub async fn get_users(
+    ctx: &Context,
+    first: Option<i32>,
+    after: Option<String>,
+    filter: Option<GQLUserFilter>,
+    order_by: Option<GQLUserOrder>,
+) -> ApiResult<GQLUserConnection> {
+    let viewer = viewer(ctx)?;
+    let limit = page_size(first)?;
+    let filter: UserFilter = filter.unwrap_or_default().for_viewer(&viewer);
+    let order: UserOrder = order_by.map(Into::into).unwrap_or_default();
+    // The order, and the cursors that encode it, would reveal emails
+    if order.field == UserOrderField::Email && !viewer.read_emails {
+        return Err(AuthorizationError::MissingPermission {
+            required: USERS_READ.to_string(),
+            role: viewer.role,
+        }
+        .into());
+    }
+    let after = match after {
+        Some(after) => Some(decode_user_cursor(&after, &order)?),
+        None => None,
+    };
+
+    // One more row than requested is fetched to find out whether there is a next page
+    let users = ctx
+        .users
+        .find_page(&filter, &order, after.as_ref(), limit + 1)
+        .await?;
+    let total_count = ctx.users.count_filtered(&filter).await?;
+
+    Ok(GQLUserConnection::new(
+        users,
+        limit,
+        &order,
+        &viewer,
+        after.is_some(),
+        total_count,
+    ))
 }
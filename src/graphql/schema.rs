@@ -1,19 +1,27 @@
-use std::sync::Arc;
+use std::{net::IpAddr, sync::Arc};
 
-use juniper::{EmptySubscription, RootNode};
+use juniper::RootNode;
 use sea_orm::DatabaseConnection;
 
-use super::{mutation::MutationRoot, query::QueryRoot};
+use super::{mutation::MutationRoot, query::QueryRoot, subscription::SubscriptionRoot};
+use crate::{config::Config, mail::Mailer, repository::UserRepository};
 
-pub type Schema = RootNode<'static, QueryRoot, MutationRoot, EmptySubscription<Context>>;
+pub type Schema = RootNode<'static, QueryRoot, MutationRoot, SubscriptionRoot>;
 
 pub struct Context {
+    pub config: &'static Config,
     pub connection: Arc<DatabaseConnection>,
+    pub users: Arc<dyn UserRepository>,
     pub token: String,
+    pub mailer: Arc<dyn Mailer>,
+    pub client_ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    /// From the `X-Request-Id` header, or generated when the client sent none
+    pub request_id: String,
 }
 
 pub fn create_schema() -> Schema {
-    Schema::new(QueryRoot, MutationRoot, EmptySubscription::<Context>::new())
+    Schema::new(QueryRoot, MutationRoot, SubscriptionRoot)
 }
 
 impl juniper::Context for Context {}
@@ -0,0 +1,37 @@
+use entity::session;
+use juniper::GraphQLObject;
+use sea_orm::prelude::Uuid;
+
+#[derive(GraphQLObject, Debug)]
+pub struct GQLSession {
+    pub id: String,
+    pub device_label: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    // Seconds since the epoch. GraphQL integers are 32 bit, so they are sent as floats.
+    pub created_at: f64,
+    pub last_seen_at: f64,
+    // Whether this is the session the request was made with
+    pub current: bool,
+}
+
+impl GQLSession {
+    pub fn single(model: &session::Model, current: Option<Uuid>) -> Self {
+        GQLSession {
+            id: model.id.to_string(),
+            device_label: model.device_label.to_owned(),
+            user_agent: model.user_agent.to_owned(),
+            ip_address: model.ip_address.to_owned(),
+            created_at: model.created_at as f64,
+            last_seen_at: model.last_seen_at as f64,
+            current: current == Some(model.id),
+        }
+    }
+
+    pub fn multiple(models: Vec<session::Model>, current: Option<Uuid>) -> Vec<Self> {
+        models
+            .iter()
+            .map(|model| GQLSession::single(model, current))
+            .collect()
+    }
+}
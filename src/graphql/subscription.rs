@@ -1 +1,118 @@
-// TODO
+use std::{future::ready, pin::Pin};
+
+use juniper::{
+    futures::{stream, Stream, StreamExt},
+    graphql_subscription,
+};
+use sea_orm::prelude::Uuid;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::{query::user::viewer, schema::Context, user::GQLUser};
+use crate::{
+    auth::jwt::get_claims_from_token,
+    errors::{ApiResult, QueryError},
+    events::{subscribe_status_changes, StatusChange},
+};
+
+pub struct SubscriptionRoot;
+
+type UserStream = Pin<Box<dyn Stream<Item = ApiResult<GQLUser>> + Send>>;
+
+#[graphql_subscription(context = Context)]
+impl SubscriptionRoot {
+    /// Emits users as their status changes, or only the user with `id` when given
+    async fn user_status_changed(ctx: &Context, id: Option<String>) -> ApiResult<UserStream> {
+        user_status_changed(ctx, id)
+    }
+}
+
+// The connection may outlive the token it was opened with,
+// so the stream ends once the token expires or is revoked
+pub fn user_status_changed(ctx: &Context, id: Option<String>) -> ApiResult<UserStream> {
+    let viewer = viewer(ctx)?;
+    let id = id
+        .map(|id| Uuid::parse_str(&id).map_err(|_| QueryError::InvalidId(id.to_owned())))
+        .transpose()?;
+    let token = ctx.token.to_owned();
+
+    let changes = stream::unfold(subscribe_status_changes(), |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(change) => return Some((change, receiver)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    let stream = changes
+        .take_while(move |_| ready(get_claims_from_token(&token).is_ok()))
+        .filter(move |change: &StatusChange| ready(id.is_none_or(|id| change.user.id == id)))
+        .map(move |change| Ok(GQLUser::visible_to(&change.user, &viewer)));
+
+    Ok(Box::pin(stream))
+}
+
+#[cfg(test)]
+mod test_subscription {
+    use juniper::futures::StreamExt;
+    use sea_orm::prelude::Uuid;
+
+    use super::user_status_changed;
+    use crate::{
+        events::publish_status_change,
+        testutils::{create_mock_context, create_role_jwt},
+    };
+    use entity::{
+        sea_orm_active_enums::{Role, Status},
+        user,
+    };
+
+    fn user_with_status(status: Status) -> user::Model {
+        user::Model {
+            id: Uuid::new_v4(),
+            email: "test@test.com".to_string(),
+            name: "test".to_string(),
+            password: "".to_string(),
+            status,
+            role: Role::Guest,
+            email_verified_at: None,
+            suspended_at: None,
+        }
+    }
+
+    #[test]
+    fn user_status_changed_requires_token() {
+        let ctx = create_mock_context::<user::Model>(vec![], None);
+        let res = user_status_changed(&ctx, None);
+        assert_eq!(res.err().unwrap().to_string(), "Token missing");
+    }
+
+    #[tokio::test]
+    async fn user_status_changed_emits_changes_of_the_user() {
+        let ctx = create_mock_context::<user::Model>(vec![], Some(create_role_jwt(&Role::Student)));
+        let watched = user_with_status(Status::Hidden);
+        let mut stream = user_status_changed(&ctx, Some(watched.id.to_string())).unwrap();
+
+        publish_status_change(&user_with_status(Status::Online));
+        publish_status_change(&watched);
+
+        // Students can neither read emails nor see hidden users
+        let got = stream.next().await.unwrap().unwrap();
+        assert_eq!(got.id, watched.id.to_string());
+        assert_eq!(got.email, None);
+        assert_eq!(got.status, Status::Offline);
+    }
+
+    #[tokio::test]
+    async fn user_status_changed_shows_admins_everything() {
+        let ctx = create_mock_context::<user::Model>(vec![], Some(create_role_jwt(&Role::Admin)));
+        let watched = user_with_status(Status::Hidden);
+        let mut stream = user_status_changed(&ctx, Some(watched.id.to_string())).unwrap();
+
+        publish_status_change(&watched);
+
+        let got = stream.next().await.unwrap().unwrap();
+        assert_eq!(got.email, Some("test@test.com".to_string()));
+        assert_eq!(got.status, Status::Hidden);
+    }
+}
@@ -1,26 +1,85 @@
 use entity::{
     sea_orm_active_enums::{Role, Status},
+    traits::user::{SortDirection, UserCursor, UserFilter, UserOrder, UserOrderField},
     user,
 };
-use juniper::GraphQLObject;
+use juniper::{GraphQLInputObject, GraphQLObject};
+use sea_orm::prelude::Uuid;
+
+use crate::{
+    auth::{
+        jwt::{has_permission, Claims},
+        permission::{USERS_MANAGE, USERS_READ},
+    },
+    errors::QueryError,
+    graphql::page::{decode_cursor, encode_cursor, GQLPageInfo},
+};
 
 #[derive(GraphQLObject, Debug)]
 pub struct GQLUser {
     pub id: String,
     pub name: String,
-    pub email: String,
+    /// Only visible to the user themselves and to staff
+    pub email: Option<String>,
     pub role: Role,
     pub status: Status,
+    /// Only visible to the user themselves and to staff
+    pub email_verified: Option<bool>,
+    /// Only visible to the user themselves and to staff
+    pub suspended: Option<bool>,
+}
+
+/// What the caller of a query is allowed to see of other users
+#[derive(Debug, Clone)]
+pub struct Viewer {
+    pub id: Uuid,
+    pub role: String,
+    pub read_emails: bool,
+    pub see_hidden: bool,
+    // Whether accounts are verified or suspended
+    pub read_account_state: bool,
+}
+
+impl Viewer {
+    pub fn from_claims(claims: &Claims) -> Self {
+        Viewer::new(claims, |permission| has_permission(permission, claims))
+    }
+
+    /// Privileges are only granted for permissions the account may use,
+    /// otherwise the viewer sees what any other signed in user sees
+    pub fn new(claims: &Claims, may_use: impl Fn(&str) -> bool) -> Self {
+        Viewer {
+            id: claims.sub,
+            role: claims.role.to_owned(),
+            read_emails: may_use(USERS_READ),
+            see_hidden: may_use(USERS_MANAGE),
+            read_account_state: may_use(USERS_READ) || may_use(USERS_MANAGE),
+        }
+    }
+
+    /// Users that appear with `status`, given that hidden users appear offline
+    /// to viewers that may not see them
+    pub fn statuses_appearing_as(&self, status: Status) -> Vec<Status> {
+        match (status, self.see_hidden) {
+            (status, true) => vec![status],
+            (Status::Offline, false) => vec![Status::Offline, Status::Hidden],
+            (Status::Hidden, false) => vec![],
+            (status, false) => vec![status],
+        }
+    }
 }
 
 impl GQLUser {
+    /// The full record, for the user themselves and for admins
     pub fn single(model: &user::Model) -> Self {
         GQLUser {
             id: model.id.to_string(),
             name: model.name.to_string(),
-            email: model.email.to_string(),
+            email: Some(model.email.to_string()),
             role: model.role.to_owned(),
             status: model.status.to_owned(),
+            email_verified: Some(model.is_email_verified()),
+            suspended: Some(model.is_suspended()),
         }
     }
 
@@ -30,6 +89,25 @@ impl GQLUser {
             .map(|model| GQLUser::single(&model))
             .collect()
     }
+
+    pub fn visible_to(model: &user::Model, viewer: &Viewer) -> Self {
+        let mut user = GQLUser::single(model);
+        if model.id == viewer.id {
+            return user;
+        }
+        if !viewer.read_emails {
+            user.email = None;
+        }
+        if model.status == Status::Hidden && !viewer.see_hidden {
+            user.status = Status::Offline;
+        }
+        if !viewer.read_account_state {
+            user.email_verified = None;
+            user.suspended = None;
+        }
+        user
+    }
+
     pub fn from_active_model(model: user::ActiveModel) -> Self {
         let user = user::Model {
             id: model.id.unwrap(),
@@ -38,7 +116,123 @@ impl GQLUser {
             status: model.status.unwrap(),
             role: model.role.unwrap(),
             password: model.password.unwrap(),
+            email_verified_at: model.email_verified_at.unwrap(),
+            suspended_at: model.suspended_at.unwrap(),
         };
         GQLUser::single(&user)
     }
 }
+
+#[derive(GraphQLInputObject, Debug, Default)]
+pub struct GQLUserFilter {
+    pub role: Option<Role>,
+    pub status: Option<Status>,
+    /// Matches a case-insensitive substring of the name or email
+    pub search: Option<String>,
+}
+
+impl GQLUserFilter {
+    // Viewers are only able to filter by what they can see
+    pub fn for_viewer(self, viewer: &Viewer) -> UserFilter {
+        UserFilter {
+            role: self.role,
+            statuses: self
+                .status
+                .map(|status| viewer.statuses_appearing_as(status)),
+            search: self.search.filter(|search| !search.is_empty()),
+            search_names_only: !viewer.read_emails,
+        }
+    }
+}
+
+#[derive(GraphQLInputObject, Debug)]
+pub struct GQLUserOrder {
+    pub field: UserOrderField,
+    pub direction: SortDirection,
+}
+
+impl From<GQLUserOrder> for UserOrder {
+    fn from(order: GQLUserOrder) -> Self {
+        UserOrder {
+            field: order.field,
+            direction: order.direction,
+        }
+    }
+}
+
+#[derive(GraphQLObject, Debug)]
+pub struct GQLUserEdge {
+    pub cursor: String,
+    pub node: GQLUser,
+}
+
+#[derive(GraphQLObject, Debug)]
+pub struct GQLUserConnection {
+    pub edges: Vec<GQLUserEdge>,
+    pub page_info: GQLPageInfo,
+    pub total_count: i32,
+}
+
+// The ordering is part of the cursor so that it cannot be used with another ordering
+fn order_key(order: &UserOrder) -> &'static str {
+    match (order.field, order.direction) {
+        (UserOrderField::Name, SortDirection::Asc) => "name_asc",
+        (UserOrderField::Name, SortDirection::Desc) => "name_desc",
+        (UserOrderField::Email, SortDirection::Asc) => "email_asc",
+        (UserOrderField::Email, SortDirection::Desc) => "email_desc",
+    }
+}
+
+pub fn encode_user_cursor(model: &user::Model, order: &UserOrder) -> String {
+    let cursor = UserCursor::from_model(model, order);
+    encode_cursor(&[
+        order_key(order),
+        &cursor.id.to_string(),
+        cursor.value.as_str(),
+    ])
+}
+
+pub fn decode_user_cursor(cursor: &str, order: &UserOrder) -> Result<UserCursor, QueryError> {
+    let parts = decode_cursor(cursor, 3)?;
+    if parts[0] != order_key(order) {
+        return Err(QueryError::InvalidCursor);
+    }
+    let id = Uuid::parse_str(&parts[1]).map_err(|_| QueryError::InvalidCursor)?;
+    Ok(UserCursor {
+        value: parts[2].to_owned(),
+        id,
+    })
+}
+
+impl GQLUserConnection {
+    /// `models` may hold one row more than `limit`, which shows there is a next page
+    pub fn new(
+        mut models: Vec<user::Model>,
+        limit: u64,
+        order: &UserOrder,
+        viewer: &Viewer,
+        has_previous_page: bool,
+        total_count: u64,
+    ) -> Self {
+        let has_next_page = models.len() as u64 > limit;
+        models.truncate(limit as usize);
+        let edges: Vec<GQLUserEdge> = models
+            .iter()
+            .map(|model| GQLUserEdge {
+                cursor: encode_user_cursor(model, order),
+                node: GQLUser::visible_to(model, viewer),
+            })
+            .collect();
+        let page_info = GQLPageInfo {
+            has_next_page,
+            has_previous_page,
+            start_cursor: edges.first().map(|edge| edge.cursor.to_owned()),
+            end_cursor: edges.last().map(|edge| edge.cursor.to_owned()),
+        };
+        GQLUserConnection {
+            edges,
+            page_info,
+            total_count: total_count.try_into().unwrap_or(i32::MAX),
+        }
+    }
+}
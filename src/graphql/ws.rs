@@ -0,0 +1,289 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use juniper::{
+    futures::{
+        stream::{self, BoxStream, SplitStream},
+        SinkExt, StreamExt,
+    },
+    http::{resolve_into_stream, GraphQLRequest, GraphQLResponse},
+    Object, Value, ValuesStream,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::mpsc::{self, UnboundedSender},
+    task::JoinHandle,
+    time::{interval, timeout},
+};
+use warp::ws::{Message, WebSocket};
+
+use super::schema::{Context, Schema};
+use crate::{
+    auth::jwt::get_claims_from_token,
+    errors::AuthorizationError,
+    get_token_from_header,
+    shutdown::{shutdown_started, track_session},
+};
+
+/// The subprotocol spoken by `subscriptions-transport-ws` clients, including GraphiQL
+pub const PROTOCOL: &str = "graphql-ws";
+
+const CONNECTION_INIT_TIMEOUT: Duration = Duration::from_secs(10);
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+// The close code for an endpoint that is going away, such as a server shutting down
+const GOING_AWAY: u16 = 1001;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    ConnectionInit {
+        #[serde(default)]
+        payload: InitPayload,
+    },
+    Start {
+        id: String,
+        payload: GraphQLRequest,
+    },
+    Stop {
+        id: String,
+    },
+    ConnectionTerminate,
+}
+
+/// Browsers cannot set headers on websocket requests,
+/// so the token is sent the way it would be in the `Authorization` header
+#[derive(Debug, Default, Deserialize)]
+pub struct InitPayload {
+    #[serde(rename = "Authorization", alias = "authorization")]
+    pub authorization: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage<'a> {
+    ConnectionAck,
+    ConnectionError {
+        payload: ErrorPayload,
+    },
+    #[serde(rename = "ka")]
+    KeepAlive,
+    Data {
+        id: String,
+        payload: GraphQLResponse<'a>,
+    },
+    Error {
+        id: String,
+        payload: ErrorPayload,
+    },
+    Complete {
+        id: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorPayload {
+    pub message: String,
+}
+
+impl ErrorPayload {
+    fn new(message: impl ToString) -> Self {
+        ErrorPayload {
+            message: message.to_string(),
+        }
+    }
+}
+
+fn send(outgoing: &UnboundedSender<Message>, message: ServerMessage) {
+    if let Ok(text) = serde_json::to_string(&message) {
+        let _ = outgoing.send(Message::text(text));
+    }
+}
+
+// The connection is only authenticated once, so operations are refused until the
+// client has sent a valid token. Each operation then runs in its own task until
+// its stream ends, the client stops it or the connection closes.
+pub async fn serve(socket: WebSocket, schema: Arc<Schema>, context: Context) {
+    let _session = track_session();
+    let (mut sink, mut incoming) = socket.split();
+    let (outgoing, mut queued) = mpsc::unbounded_channel::<Message>();
+    let writer = tokio::spawn(async move {
+        while let Some(message) = queued.recv().await {
+            let closing = message.is_close();
+            if sink.send(message).await.is_err() || closing {
+                break;
+            }
+        }
+    });
+
+    let initialised = timeout(
+        CONNECTION_INIT_TIMEOUT,
+        initialise(&mut incoming, &outgoing, context),
+    )
+    .await;
+    if let Ok(Some(context)) = initialised {
+        run_operations(&mut incoming, &outgoing, schema, Arc::new(context)).await;
+    }
+
+    drop(outgoing);
+    let _ = writer.await;
+}
+
+fn authenticate(token: &str) -> Result<(), AuthorizationError> {
+    if token.is_empty() {
+        return Err(AuthorizationError::TokenMissing);
+    }
+    get_claims_from_token(token)?;
+    Ok(())
+}
+
+async fn initialise(
+    incoming: &mut SplitStream<WebSocket>,
+    outgoing: &UnboundedSender<Message>,
+    mut context: Context,
+) -> Option<Context> {
+    while let Some(message) = next_message(incoming, outgoing).await {
+        match message {
+            ClientMessage::ConnectionInit { payload } => {
+                let token = get_token_from_header(payload.authorization);
+                return match authenticate(&token) {
+                    Ok(()) => {
+                        context.token = token;
+                        send(outgoing, ServerMessage::ConnectionAck);
+                        Some(context)
+                    }
+                    Err(e) => {
+                        let payload = ErrorPayload::new(e);
+                        send(outgoing, ServerMessage::ConnectionError { payload });
+                        let _ = outgoing.send(Message::close());
+                        None
+                    }
+                };
+            }
+            ClientMessage::Start { id, .. } => {
+                let payload = ErrorPayload::new("Connection has not been initialised");
+                send(outgoing, ServerMessage::Error { id, payload });
+            }
+            ClientMessage::Stop { .. } => {}
+            ClientMessage::ConnectionTerminate => return None,
+        }
+    }
+    None
+}
+
+async fn run_operations(
+    incoming: &mut SplitStream<WebSocket>,
+    outgoing: &UnboundedSender<Message>,
+    schema: Arc<Schema>,
+    context: Arc<Context>,
+) {
+    let mut operations: HashMap<String, JoinHandle<()>> = HashMap::new();
+    let mut keep_alive = interval(KEEP_ALIVE_INTERVAL);
+
+    loop {
+        let message = tokio::select! {
+            message = next_message(incoming, outgoing) => message,
+            _ = keep_alive.tick() => {
+                send(outgoing, ServerMessage::KeepAlive);
+                continue;
+            }
+            _ = shutdown_started() => {
+                let _ = outgoing.send(Message::close_with(GOING_AWAY, "Server is shutting down"));
+                break;
+            }
+        };
+        match message {
+            Some(ClientMessage::Start { id, payload }) => {
+                let operation = tokio::spawn(run_operation(
+                    id.to_owned(),
+                    payload,
+                    schema.clone(),
+                    context.clone(),
+                    outgoing.clone(),
+                ));
+                if let Some(previous) = operations.insert(id, operation) {
+                    previous.abort();
+                }
+            }
+            Some(ClientMessage::Stop { id }) => {
+                if let Some(operation) = operations.remove(&id) {
+                    operation.abort();
+                    send(outgoing, ServerMessage::Complete { id });
+                }
+            }
+            Some(ClientMessage::ConnectionInit { .. }) => {}
+            Some(ClientMessage::ConnectionTerminate) | None => break,
+        }
+    }
+
+    for (_, operation) in operations.drain() {
+        operation.abort();
+    }
+}
+
+async fn run_operation(
+    id: String,
+    request: GraphQLRequest,
+    schema: Arc<Schema>,
+    context: Arc<Context>,
+    outgoing: UnboundedSender<Message>,
+) {
+    let mut responses = match resolve_into_stream(&request, &schema, &context).await {
+        Ok((value, errors)) if errors.is_empty() => into_responses(value),
+        Ok((_, errors)) => {
+            stream::once(async { GraphQLResponse::from_result(Ok((Value::null(), errors))) })
+                .boxed()
+        }
+        Err(e) => stream::once(async { GraphQLResponse::from_result(Err(e)) }).boxed(),
+    };
+
+    while let Some(payload) = responses.next().await {
+        let id = id.to_owned();
+        send(&outgoing, ServerMessage::Data { id, payload });
+    }
+    send(&outgoing, ServerMessage::Complete { id });
+}
+
+// Every field of a subscription resolves to a stream of its values,
+// each of which is sent as its own response
+fn into_responses<'a>(value: Value<ValuesStream<'a>>) -> BoxStream<'a, GraphQLResponse<'a>> {
+    let fields = match value {
+        Value::Object(object) => object
+            .into_iter()
+            .filter_map(|(name, value)| match value {
+                Value::Scalar(values) => Some(values.map(move |item| match item {
+                    Ok(value) => {
+                        let mut data = Object::with_capacity(1);
+                        data.add_field(name.to_owned(), value);
+                        GraphQLResponse::from_result(Ok((Value::Object(data), vec![])))
+                    }
+                    Err(e) => GraphQLResponse::from_result(Ok((Value::null(), vec![e]))),
+                })),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    };
+    stream::select_all(fields).boxed()
+}
+
+async fn next_message(
+    incoming: &mut SplitStream<WebSocket>,
+    outgoing: &UnboundedSender<Message>,
+) -> Option<ClientMessage> {
+    loop {
+        let message = match incoming.next().await {
+            Some(Ok(message)) if !message.is_close() => message,
+            _ => return None,
+        };
+        let text = match message.to_str() {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        match serde_json::from_str(text) {
+            Ok(message) => return Some(message),
+            Err(e) => {
+                let payload = ErrorPayload::new(format!("Invalid message: {}", e));
+                send(outgoing, ServerMessage::ConnectionError { payload });
+            }
+        }
+    }
+}
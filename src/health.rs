@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use sea_orm::{ConnectionTrait, DatabaseConnection, Statement};
+use serde::Serialize;
+use warp::{
+    filters::BoxedFilter,
+    http::StatusCode,
+    reply::{self, Response},
+    Filter, Reply,
+};
+
+use migration::{Migrator, MigratorTrait};
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, Serialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Unavailable,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ComponentHealth {
+    pub status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(rename = "pendingMigrations", skip_serializing_if = "Option::is_none")]
+    pub pending_migrations: Option<usize>,
+}
+
+impl ComponentHealth {
+    fn ok() -> Self {
+        ComponentHealth {
+            status: Status::Ok,
+            error: None,
+            pending_migrations: None,
+        }
+    }
+
+    fn unavailable(error: impl ToString) -> Self {
+        ComponentHealth {
+            status: Status::Unavailable,
+            error: Some(error.to_string()),
+            pending_migrations: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthResponse {
+    pub status: Status,
+    pub version: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub components: Option<Components>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Components {
+    pub database: ComponentHealth,
+    pub migrations: ComponentHealth,
+}
+
+impl Components {
+    fn status(&self) -> Status {
+        match (self.database.status, self.migrations.status) {
+            (Status::Ok, Status::Ok) => Status::Ok,
+            _ => Status::Unavailable,
+        }
+    }
+}
+
+/// Reports on the database and its migrations, which are applied on startup
+pub async fn check_components(conn: &DatabaseConnection) -> Components {
+    let select_one = Statement::from_string(conn.get_database_backend(), "SELECT 1".to_string());
+    let database = match conn.execute(select_one).await {
+        Ok(_) => ComponentHealth::ok(),
+        Err(e) => ComponentHealth::unavailable(e),
+    };
+
+    let migrations = match Migrator::get_pending_migrations(conn).await {
+        Ok(pending) if pending.is_empty() => ComponentHealth::ok(),
+        Ok(pending) => ComponentHealth {
+            pending_migrations: Some(pending.len()),
+            ..ComponentHealth::unavailable("Migrations have not been applied")
+        },
+        Err(e) => ComponentHealth::unavailable(e),
+    };
+
+    Components {
+        database,
+        migrations,
+    }
+}
+
+/// Serves `/healthz`, which only reports that the process is up, and `/readyz`,
+/// which responds with 503 while the database cannot serve requests.
+/// Neither requires a token so that orchestrators can probe them.
+pub fn create_health_filter(
+    connection: impl Into<Arc<DatabaseConnection>>,
+) -> BoxedFilter<(Response,)> {
+    let connection = connection.into();
+
+    let live = warp::get().and(warp::path!("healthz")).map(|| {
+        reply::json(&HealthResponse {
+            status: Status::Ok,
+            version: VERSION,
+            components: None,
+        })
+        .into_response()
+    });
+
+    let ready = warp::get()
+        .and(warp::path!("readyz"))
+        .and(warp::any().map(move || connection.clone()))
+        .then(|connection: Arc<DatabaseConnection>| async move {
+            let components = check_components(&connection).await;
+            let status = components.status();
+            let code = match status {
+                Status::Ok => StatusCode::OK,
+                Status::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            };
+            let health = HealthResponse {
+                status,
+                version: VERSION,
+                components: Some(components),
+            };
+            reply::with_status(reply::json(&health), code).into_response()
+        });
+
+    live.or(ready).unify().boxed()
+}
+
+#[cfg(test)]
+mod test_health {
+    use migration::DbErr;
+    use warp::{http::StatusCode, test::request};
+
+    use super::{check_components, create_health_filter, Status};
+    use crate::testutils::create_mock_errored_conn;
+
+    #[tokio::test]
+    async fn liveness_ignores_the_database() {
+        let filter = create_health_filter(create_mock_errored_conn(vec![]));
+        let res = request().path("/healthz").reply(&filter).await;
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        assert!(body.get("components").is_none());
+    }
+
+    #[tokio::test]
+    async fn database_errors_are_reported() {
+        let conn = create_mock_errored_conn(vec![DbErr::ConnectionAcquire]);
+        let components = check_components(&conn).await;
+        assert_eq!(components.database.status, Status::Unavailable);
+        assert_eq!(components.status(), Status::Unavailable);
+    }
+}
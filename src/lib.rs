@@ -1,46 +1,228 @@
-use std::{env, sync::Arc};
+use std::{
+    env,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
 
 use graphql::schema::create_schema;
-use sea_orm::{ConnectOptions, Database, DatabaseConnection};
+use sea_orm::{
+    ConnectOptions, DatabaseConnection, RuntimeErr, SqlxPostgresConnector, SqlxSqliteConnector,
+};
+use sqlx::{
+    postgres::PgConnectOptions, sqlite::SqliteConnectOptions, ConnectOptions as _, PgPool,
+    SqlitePool,
+};
+use tracing::{info_span, Instrument};
 
-use crate::graphql::schema::Context;
+use crate::{
+    auth::keys::keys,
+    config::{config, DatabaseConfig},
+    graphql::{
+        handler::create_graphql_handler,
+        schema::Context,
+        ws::{serve, PROTOCOL},
+    },
+    mail::Mailer,
+    metrics::metrics,
+    repository::database::DatabaseUserRepository,
+    telemetry::request_id,
+};
 use migration::{DbErr, Migrator, MigratorTrait};
-use warp::{filters::BoxedFilter, http::Response, Filter};
+use warp::{
+    filters::BoxedFilter,
+    http::Response,
+    reply::{Json, Reply},
+    ws::Ws,
+    Filter,
+};
 
 pub mod auth;
+pub mod config;
+pub mod cors;
 pub mod errors;
+pub mod events;
 pub mod graphql;
+pub mod health;
+pub mod mail;
+pub mod metrics;
+pub mod oidc;
+pub mod repository;
+pub mod shutdown;
+pub mod telemetry;
 pub mod testutils;
 pub mod time;
 
-pub async fn connect_to_database(key: &str) -> Result<DatabaseConnection, DbErr> {
-    let url = get_env(key);
-    let opt = ConnectOptions::new(url);
-    // Connections options can be inserted
+/// The pool behind a `DatabaseConnection`, kept so that it can be closed on shutdown
+#[derive(Clone, Debug)]
+pub enum DatabasePool {
+    Postgres(PgPool),
+    Sqlite(SqlitePool),
+}
 
-    let connection = Database::connect(opt).await?;
-    Migrator::up(&connection, None).await?;
+impl DatabasePool {
+    /// Stops handing out connections, then waits for those in use to be returned and closes them all
+    pub async fn close(&self) {
+        match self {
+            DatabasePool::Postgres(pool) => pool.close().await,
+            DatabasePool::Sqlite(pool) => pool.close().await,
+        }
+    }
+}
+
+/// Connects to the Postgres or SQLite database at `url` and runs any pending migrations.
+/// SQLite accepts `sqlite://<path>`, which is created if missing, and `sqlite::memory:`.
+pub async fn connect_to_database(
+    url: &str,
+    options: &DatabaseConfig,
+) -> Result<DatabaseConnection, DbErr> {
+    let (connection, _) = open_database(url, options).await?;
     Ok(connection)
 }
 
-pub fn create_gql_filter(connection: DatabaseConnection) -> BoxedFilter<(Response<Vec<u8>>,)> {
-    let connection = Arc::new(connection);
-    let state = warp::any()
+/// Connects as `connect_to_database` does, also returning the pool so that it can be closed
+pub async fn open_database(
+    url: &str,
+    options: &DatabaseConfig,
+) -> Result<(DatabaseConnection, DatabasePool), DbErr> {
+    let url = create_missing_sqlite_file(url.to_string());
+    let mut opt = ConnectOptions::new(url.to_owned());
+    opt.max_connections(options.max_connections)
+        .min_connections(options.min_connections)
+        .connect_timeout(options.connect_timeout())
+        .idle_timeout(options.idle_timeout());
+
+    // sea-orm keeps its pool private, so the pool is created here to keep a handle to it.
+    // Statements are already traced by sea-orm, so sqlx does not log them as well.
+    let scheme = url.split_once(':').map_or("", |(scheme, _)| scheme);
+    let (mut connection, pool) = match scheme {
+        "postgres" | "postgresql" => {
+            let mut connect: PgConnectOptions = url.parse().map_err(connection_error)?;
+            connect.disable_statement_logging();
+            let pool = opt
+                .pool_options()
+                .connect_with(connect)
+                .await
+                .map_err(connection_error)?;
+            let connection = SqlxPostgresConnector::from_sqlx_postgres_pool(pool.clone());
+            (connection, DatabasePool::Postgres(pool))
+        }
+        "sqlite" => {
+            let mut connect: SqliteConnectOptions = url.parse().map_err(connection_error)?;
+            connect.disable_statement_logging();
+            let pool = opt
+                .pool_options()
+                .connect_with(connect)
+                .await
+                .map_err(connection_error)?;
+            let connection = SqlxSqliteConnector::from_sqlx_sqlite_pool(pool.clone());
+            (connection, DatabasePool::Sqlite(pool))
+        }
+        _ => {
+            return Err(DbErr::Conn(RuntimeErr::Internal(format!(
+                "`{}` is not a Postgres or SQLite URL",
+                url
+            ))))
+        }
+    };
+
+    connection.set_metric_callback(|info| metrics().observe_query(info));
+    Migrator::up(&connection, None).await?;
+    Ok((connection, pool))
+}
+
+fn connection_error(e: sqlx::Error) -> DbErr {
+    DbErr::Conn(RuntimeErr::SqlxError(e))
+}
+
+// SQLite refuses to open a database file that does not exist unless told to create it
+fn create_missing_sqlite_file(url: String) -> String {
+    if !url.starts_with("sqlite:") || url.contains(":memory:") || url.contains("mode=") {
+        return url;
+    }
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{}{}mode=rwc", url, separator)
+}
+
+fn create_context_filter(
+    connection: Arc<DatabaseConnection>,
+    mailer: Arc<dyn Mailer>,
+) -> BoxedFilter<(Context,)> {
+    let users = Arc::new(DatabaseUserRepository::new(connection.clone()));
+    let config = config();
+    warp::any()
         .and(warp::header::optional::<String>("Authorization"))
-        .map(move |auth: Option<String>| -> Context {
-            let token = get_token_from_header(auth);
-            Context {
-                connection: connection.clone(),
-                token,
-            }
-        });
-    juniper_warp::make_graphql_filter(create_schema(), state.boxed())
+        .and(warp::header::optional::<String>("X-Forwarded-For"))
+        .and(warp::header::optional::<String>("User-Agent"))
+        .and(warp::addr::remote())
+        .and(request_id())
+        .map(
+            move |auth: Option<String>,
+                  forwarded: Option<String>,
+                  user_agent: Option<String>,
+                  remote: Option<SocketAddr>,
+                  request_id: String|
+                  -> Context {
+                let token = get_token_from_header(auth);
+                let client_ip = get_client_ip(forwarded, remote, config.server.forwarded_hops());
+                Context {
+                    config,
+                    connection: connection.clone(),
+                    users: users.clone(),
+                    token,
+                    mailer: mailer.clone(),
+                    client_ip,
+                    user_agent,
+                    request_id,
+                }
+            },
+        )
+        .boxed()
+}
+
+pub fn create_gql_filter(
+    connection: impl Into<Arc<DatabaseConnection>>,
+    mailer: Arc<dyn Mailer>,
+) -> BoxedFilter<(Response<Vec<u8>>,)> {
+    let state = create_context_filter(connection.into(), mailer);
+    create_graphql_handler(create_schema(), state)
+}
+
+/// Serves subscriptions over websockets, the token is sent when the connection is initialised
+pub fn create_gql_subscription_filter(
+    connection: impl Into<Arc<DatabaseConnection>>,
+    mailer: Arc<dyn Mailer>,
+) -> BoxedFilter<(warp::reply::Response,)> {
+    let schema = Arc::new(create_schema());
+    warp::ws()
+        .and(create_context_filter(connection.into(), mailer))
+        .map(move |ws: Ws, ctx: Context| {
+            let schema = schema.clone();
+            let span = info_span!("subscription", request_id = %ctx.request_id);
+            let reply = ws.on_upgrade(move |socket| serve(socket, schema, ctx).instrument(span));
+            warp::reply::with_header(reply, "Sec-WebSocket-Protocol", PROTOCOL).into_response()
+        })
+        .boxed()
+}
+
+/// Serves the public keys tokens are signed with so other services can verify them
+pub fn create_jwks_filter() -> BoxedFilter<(Json,)> {
+    warp::get()
+        .and(warp::path!(".well-known" / "jwks.json"))
+        .map(|| warp::reply::json(&keys().jwks()))
+        .boxed()
 }
 
 pub fn get_env(key: &str) -> String {
     env::var(key).unwrap_or_else(|_| panic!("{} environment variable is not defined", key))
 }
 
+pub fn get_env_flag(key: &str) -> bool {
+    matches!(
+        env::var(key).map(|val| val.to_lowercase()).as_deref(),
+        Ok("true") | Ok("1")
+    )
+}
+
 pub fn get_token_from_header(header: Option<String>) -> String {
     match header {
         None => "".to_string(),
@@ -63,11 +245,38 @@ pub fn get_token_from_header(header: Option<String>) -> String {
     }
 }
 
+// The X-Forwarded-For header can be set by any client, so it is only used when the
+// server is known to run behind proxies. Each proxy appends the address it received
+// the request from, so only the last `trusted_hops` entries can be relied upon and
+// the earliest of those is the client.
+pub fn get_client_ip(
+    forwarded: Option<String>,
+    remote: Option<SocketAddr>,
+    trusted_hops: usize,
+) -> Option<IpAddr> {
+    if trusted_hops > 0 {
+        let forwarded = forwarded
+            .as_deref()
+            .and_then(|header| header.split(',').rev().nth(trusted_hops - 1))
+            .and_then(|ip| ip.trim().parse().ok());
+        if forwarded.is_some() {
+            return forwarded;
+        }
+    }
+    remote.map(|addr| addr.ip())
+}
+
 #[cfg(test)]
 mod test {
-    use std::env;
+    use std::{
+        env,
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+    };
 
-    use crate::{get_env, get_token_from_header};
+    use crate::{
+        auth::keys::JwkSet, config::DatabaseConfig, create_jwks_filter, create_missing_sqlite_file,
+        get_client_ip, get_env, get_env_flag, get_token_from_header, open_database,
+    };
     #[test]
     fn get_env_success() {
         env::remove_var("TEST_VALUE");
@@ -86,6 +295,23 @@ mod test {
         get_env("TEST_VALUE");
     }
 
+    #[test]
+    fn get_env_flag_values() {
+        env::remove_var("TEST_FLAG");
+        assert!(!get_env_flag("TEST_FLAG"));
+
+        env::set_var("TEST_FLAG", "true");
+        assert!(get_env_flag("TEST_FLAG"));
+
+        env::set_var("TEST_FLAG", "1");
+        assert!(get_env_flag("TEST_FLAG"));
+
+        env::set_var("TEST_FLAG", "no");
+        assert!(!get_env_flag("TEST_FLAG"));
+
+        env::remove_var("TEST_FLAG");
+    }
+
     #[test]
     fn get_token_returns_empty_if_no_header() {
         let res = get_token_from_header(None);
@@ -114,4 +340,89 @@ mod test {
         let res = get_token_from_header(Some("Bearer abcdef".to_string()));
         assert_eq!(res, "abcdef");
     }
+
+    #[test]
+    fn get_client_ip_uses_remote_address() {
+        let remote = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 8080);
+        let res = get_client_ip(Some("1.2.3.4".to_string()), Some(remote), 0);
+        assert_eq!(res, Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))));
+
+        let res = get_client_ip(None, None, 0);
+        assert_eq!(res, None);
+    }
+
+    #[test]
+    fn get_client_ip_uses_address_appended_by_trusted_proxy() {
+        let remote = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 8080);
+        // The first entry was sent by the client and cannot be trusted
+        let res = get_client_ip(Some("1.2.3.4, 5.6.7.8".to_string()), Some(remote), 1);
+        assert_eq!(res, Some(IpAddr::V4(Ipv4Addr::new(5, 6, 7, 8))));
+
+        let res = get_client_ip(
+            Some("1.2.3.4, 5.6.7.8, 10.0.0.2".to_string()),
+            Some(remote),
+            2,
+        );
+        assert_eq!(res, Some(IpAddr::V4(Ipv4Addr::new(5, 6, 7, 8))));
+
+        let res = get_client_ip(Some("5.6.7.8".to_string()), Some(remote), 2);
+        assert_eq!(res, Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))));
+
+        let res = get_client_ip(Some("unknown".to_string()), Some(remote), 1);
+        assert_eq!(res, Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))));
+    }
+
+    #[tokio::test]
+    async fn jwks_endpoint_serves_key_set() {
+        let response = warp::test::request()
+            .path("/.well-known/jwks.json")
+            .reply(&create_jwks_filter())
+            .await;
+
+        assert_eq!(response.status(), 200);
+        let got: JwkSet = serde_json::from_slice(response.body()).unwrap();
+        assert!(got.keys.is_empty());
+    }
+
+    #[test]
+    fn create_missing_sqlite_file_only_for_sqlite_files() {
+        assert_eq!(
+            create_missing_sqlite_file("sqlite://data/gu.db".to_string()),
+            "sqlite://data/gu.db?mode=rwc"
+        );
+        assert_eq!(
+            create_missing_sqlite_file("sqlite://gu.db?cache=shared".to_string()),
+            "sqlite://gu.db?cache=shared&mode=rwc"
+        );
+        assert_eq!(
+            create_missing_sqlite_file("sqlite://gu.db?mode=ro".to_string()),
+            "sqlite://gu.db?mode=ro"
+        );
+        assert_eq!(
+            create_missing_sqlite_file("sqlite::memory:".to_string()),
+            "sqlite::memory:"
+        );
+        assert_eq!(
+            create_missing_sqlite_file("postgres://localhost/gu".to_string()),
+            "postgres://localhost/gu"
+        );
+    }
+
+    #[tokio::test]
+    async fn open_database_accepts_postgres_schemes() {
+        let options = DatabaseConfig {
+            connect_timeout_secs: 1,
+            ..Default::default()
+        };
+        for url in ["postgres://localhost:1/gu", "postgresql://localhost:1/gu"] {
+            let err = open_database(url, &options).await.err().unwrap();
+            assert!(!err.to_string().contains("is not a Postgres or SQLite URL"));
+        }
+
+        let err = open_database("mysql://localhost:1/gu", &options)
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("is not a Postgres or SQLite URL"));
+    }
 }
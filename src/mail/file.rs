@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use sea_orm::prelude::Uuid;
+
+use super::{Email, Mailer};
+use crate::errors::MailError;
+
+/// Writes every email to its own file in a directory instead of delivering it,
+/// which is useful for local development
+pub struct FileMailer {
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        FileMailer { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), MailError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| MailError::SendError(e.to_string()))?;
+
+        let path = self.dir.join(format!("{}.eml", Uuid::new_v4()));
+        let contents = format!(
+            "To: {}\nSubject: {}\n\n{}\n",
+            email.to, email.subject, email.body
+        );
+        tokio::fs::write(path, contents)
+            .await
+            .map_err(|e| MailError::SendError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+
+    use sea_orm::prelude::Uuid;
+
+    use super::FileMailer;
+    use crate::mail::{Email, Mailer};
+
+    #[tokio::test]
+    async fn writes_email_to_directory() {
+        let dir = env::temp_dir().join(Uuid::new_v4().to_string());
+        let mailer = FileMailer::new(&dir);
+        mailer
+            .send(Email::new("test@test.com", "subject", "body"))
+            .await
+            .unwrap();
+
+        let mut entries = std::fs::read_dir(&dir).unwrap();
+        let path = entries.next().unwrap().unwrap().path();
+        let contents = std::fs::read_to_string(path).unwrap();
+        assert_eq!(contents, "To: test@test.com\nSubject: subject\n\nbody\n");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
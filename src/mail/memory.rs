@@ -0,0 +1,67 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use super::{Email, Mailer};
+use crate::errors::MailError;
+
+/// Keeps every sent email so tests can inspect what would have been delivered
+#[derive(Default)]
+pub struct MemoryMailer {
+    sent: Mutex<Vec<Email>>,
+}
+
+impl MemoryMailer {
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn last_sent_to(&self, to: &str) -> Option<Email> {
+        self.sent().into_iter().rev().find(|email| email.to == to)
+    }
+}
+
+#[async_trait]
+impl Mailer for MemoryMailer {
+    async fn send(&self, email: Email) -> Result<(), MailError> {
+        self.sent
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MemoryMailer;
+    use crate::mail::{Email, Mailer};
+
+    #[tokio::test]
+    async fn stores_sent_emails() {
+        let mailer = MemoryMailer::default();
+        mailer
+            .send(Email::new("test@test.com", "first", "body"))
+            .await
+            .unwrap();
+        mailer
+            .send(Email::new("test2@test.com", "second", "body"))
+            .await
+            .unwrap();
+        mailer
+            .send(Email::new("test@test.com", "third", "body"))
+            .await
+            .unwrap();
+
+        assert_eq!(mailer.sent().len(), 3);
+        assert_eq!(
+            mailer.last_sent_to("test@test.com").unwrap().subject,
+            "third"
+        );
+        assert_eq!(
+            mailer.last_sent_to("test2@test.com").unwrap().subject,
+            "second"
+        );
+        assert!(mailer.last_sent_to("test3@test.com").is_none());
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    config::{MailConfig, MailTransport},
+    errors::MailError,
+};
+
+pub mod file;
+pub mod memory;
+pub mod smtp;
+pub mod templates;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+impl Email {
+    pub fn new(to: &str, subject: &str, body: &str) -> Self {
+        Email {
+            to: to.to_string(),
+            subject: subject.to_string(),
+            body: body.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), MailError>;
+}
+
+/// `smtp` sends real emails, `file` writes them to the configured directory
+/// and `memory` keeps them in memory
+pub fn create_mailer(config: &MailConfig) -> Result<Arc<dyn Mailer>, MailError> {
+    match config.transport {
+        MailTransport::Smtp => {
+            // Loading the configuration checks these are set for the smtp transport
+            let setting = |value: &Option<String>| value.to_owned().unwrap_or_default();
+            let mailer = smtp::SmtpMailer::new(
+                &setting(&config.smtp_host),
+                config.smtp_port,
+                &setting(&config.smtp_username),
+                &setting(&config.smtp_password),
+                &setting(&config.from),
+            )?;
+            Ok(Arc::new(mailer))
+        }
+        MailTransport::File => Ok(Arc::new(file::FileMailer::new(config.dir.to_owned()))),
+        MailTransport::Memory => Ok(Arc::new(memory::MemoryMailer::default())),
+    }
+}
@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use super::Email;
+
+pub fn password_reset(to: &str, token: &str, lifetime: Duration) -> Email {
+    Email::new(
+        to,
+        "Reset your Gilded University password",
+        &format!(
+            "We received a request to reset your password.\n\n\
+            Use the following token to choose a new password. It expires in {}.\n\n\
+            {}\n\n\
+            If you did not request a password reset, you can ignore this email.",
+            describe(lifetime),
+            token
+        ),
+    )
+}
+
+pub fn email_verification(to: &str, token: &str, lifetime: Duration) -> Email {
+    Email::new(
+        to,
+        "Verify your Gilded University email address",
+        &format!(
+            "Please confirm that this is your email address.\n\n\
+            Use the following token to verify it. It expires in {}.\n\n\
+            {}\n\n\
+            If you did not create an account, you can ignore this email.",
+            describe(lifetime),
+            token
+        ),
+    )
+}
+
+pub fn email_change(to: &str, token: &str, lifetime: Duration) -> Email {
+    Email::new(
+        to,
+        "Confirm your new Gilded University email address",
+        &format!(
+            "We received a request to change your account's email address to this one.\n\n\
+            Use the following token to confirm the change. It expires in {}.\n\n\
+            {}\n\n\
+            If you did not request this change, you can ignore this email.",
+            describe(lifetime),
+            token
+        ),
+    )
+}
+
+// Lifetimes are described in the largest unit they divide evenly into
+fn describe(lifetime: Duration) -> String {
+    let secs = lifetime.as_secs();
+    let (count, unit) = [(24 * 3600, "day"), (3600, "hour"), (60, "minute")]
+        .into_iter()
+        .find(|(unit, _)| secs >= *unit && secs.is_multiple_of(*unit))
+        .map(|(unit, name)| (secs / unit, name))
+        .unwrap_or((secs, "second"));
+    match count {
+        1 => format!("one {}", unit),
+        _ => format!("{} {}s", count, unit),
+    }
+}
+
+#[cfg(test)]
+mod test_templates {
+    use std::time::Duration;
+
+    use super::{describe, password_reset};
+
+    #[test]
+    fn describe_lifetimes() {
+        assert_eq!(describe(Duration::from_secs(3600)), "one hour");
+        assert_eq!(describe(Duration::from_secs(2 * 24 * 3600)), "2 days");
+        assert_eq!(describe(Duration::from_secs(90 * 60)), "90 minutes");
+        assert_eq!(describe(Duration::from_secs(45)), "45 seconds");
+    }
+
+    #[test]
+    fn password_reset_states_lifetime() {
+        let email = password_reset("test@test.com", "token", Duration::from_secs(1800));
+        assert!(email.body.contains("It expires in 30 minutes."));
+    }
+}
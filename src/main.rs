@@ -1,66 +1,138 @@
-use std::env;
+use std::{sync::Arc, time::Duration};
 
 use dotenvy::dotenv;
-use warp::{http::Method, hyper::Uri, Filter};
+use tokio::time::timeout;
+use warp::{hyper::Uri, reply::Response, Filter};
 
-use gilded_university_server::{connect_to_database, create_gql_filter};
+use gilded_university_server::{
+    auth::{
+        keys::load_keys,
+        permission::{load_permissions, sync_permissions},
+        revocation::{load_revocations, sync_revocations},
+        session::sync_presence,
+    },
+    config::load_config,
+    cors::create_cors,
+    create_gql_filter, create_gql_subscription_filter, create_jwks_filter,
+    health::create_health_filter,
+    mail::create_mailer,
+    metrics::{create_metrics_filter, http_metrics},
+    oidc::{create_oidc_filter, http::HyperClient},
+    open_database,
+    shutdown::{begin_shutdown, sessions_closed, shutdown_signal, shutdown_started},
+    telemetry::{access_log, init_tracing},
+};
 
 #[tokio::main]
 async fn main() {
-    dotenv().expect(".env environment file not found");
-    env::set_var("RUST_LOG", "warp_server");
-    env_logger::init();
-
-    let log = warp::log("warp_server");
+    // A .env file is optional, settings can also come from the environment or a config file
+    dotenv().ok();
 
     let redirect = warp::path::end().map(|| warp::redirect(Uri::from_static("/graphiql")));
 
-    let connection = connect_to_database("DATABASE_URL")
+    let config = load_config().unwrap_or_else(|e| panic!("Unable to load configuration: {}", e));
+    init_tracing(&config.log);
+    load_keys().expect("Unable to load JWT signing keys");
+
+    let url = config
+        .database
+        .url()
+        .unwrap_or_else(|e| panic!("Unable to load configuration: {}", e));
+    let (connection, pool) = open_database(url, &config.database)
         .await
         .expect("Unable to establish connection to database");
 
-    println!("Connection established to database");
-
-    let graphql_filter = create_gql_filter(connection);
-
-    let cors = warp::cors()
-        .allow_any_origin()
-        .allow_methods(&[Method::GET, Method::POST, Method::DELETE])
-        .allow_headers(vec![
-            "Accept",
-            "Accept-Encoding",
-            "Accept-Language",
-            "Access-Control-Request-Headers",
-            "Access-Control-Request-Method",
-            "Connection",
-            "Host",
-            "Origin",
-            "Referer",
-            "Sec-Fetch-Mode",
-            "Sec-Fetch-Mode",
-            "Sec-Fetch-Site",
-            "User-Agent",
-            "accept",
-            "sec-ch-ua",
-            "sec-ch-ua-mobile",
-            "sec-ch-ua-platform",
-            "Content-Length",
-            "content-type",
-        ])
-        .allow_credentials(false);
-
-    // TODO: Set host by environment variable
-    println!("Starting host at localhost:8080");
-
-    warp::serve(
-        warp::get()
-            .and(warp::path("graphiql"))
-            .and(juniper_warp::graphiql_filter("/graphql", None))
-            .or(redirect)
-            .or(warp::path("graphql").and(graphql_filter))
-            .with(cors)
-            .with(log),
-    )
-    .run(([127, 0, 0, 1], 8080))
-    .await
+    tracing::info!("Connection established to database");
+
+    let connection = Arc::new(connection);
+    load_revocations(&connection)
+        .await
+        .expect("Unable to load token revocations");
+    tokio::spawn(sync_revocations(
+        connection.clone(),
+        Duration::from_secs(60),
+    ));
+
+    tokio::spawn(sync_presence(connection.clone(), Duration::from_secs(60)));
+
+    load_permissions(&connection)
+        .await
+        .expect("Unable to load role permissions");
+    tokio::spawn(sync_permissions(
+        connection.clone(),
+        Duration::from_secs(60),
+    ));
+
+    let mailer = create_mailer(&config.mail).expect("Unable to configure mail transport");
+
+    let oidc_filter = match &config.oidc {
+        Some(oidc) => create_oidc_filter(
+            connection.clone(),
+            oidc.clone(),
+            Arc::new(HyperClient::default()),
+        ),
+        None => warp::any()
+            .and_then(|| async { Err::<Response, _>(warp::reject::not_found()) })
+            .boxed(),
+    };
+
+    let health_filter = create_health_filter(connection.clone());
+    let subscription_filter = create_gql_subscription_filter(connection.clone(), mailer.clone());
+    let graphql_filter = create_gql_filter(connection, mailer);
+
+    let routes = warp::get()
+        .and(warp::path("graphiql"))
+        .and(juniper_warp::graphiql_filter(
+            "/graphql",
+            Some("/subscriptions"),
+        ))
+        .or(redirect)
+        .or(health_filter)
+        .or(create_metrics_filter())
+        .or(create_jwks_filter())
+        .or(oidc_filter)
+        .or(warp::path("subscriptions").and(subscription_filter))
+        .or(warp::path("graphql").and(graphql_filter))
+        .with(create_cors(&config.cors))
+        .with(access_log())
+        .with(http_metrics());
+
+    // Once signalled the server stops accepting connections and finishes those it has
+    let (address, server) =
+        warp::serve(routes).bind_with_graceful_shutdown(config.server.address(), async {
+            shutdown_signal().await;
+            tracing::info!("Shutting down");
+            begin_shutdown();
+        });
+    tracing::info!(%address, "Starting host");
+
+    let drained = async {
+        server.await;
+        sessions_closed().await;
+    };
+    tokio::pin!(drained);
+    // Both can be ready at once, and the drain cannot be awaited again once it has finished
+    let stopped = tokio::select! {
+        _ = &mut drained => true,
+        _ = shutdown_started() => false,
+    };
+
+    let drain_timeout = config.server.shutdown_timeout();
+    let drain = async {
+        if !stopped {
+            drained.await;
+        }
+    };
+    if timeout(drain_timeout, drain).await.is_err() {
+        tracing::warn!(
+            timeout_secs = drain_timeout.as_secs(),
+            "Requests were still in flight when the shutdown timed out"
+        );
+    }
+    // Closed whether or not the drain finished. Idle connections are closed at once,
+    // while those still held by unfinished requests are not waited on indefinitely.
+    match timeout(drain_timeout, pool.close()).await {
+        Ok(()) => tracing::info!("Shutdown complete"),
+        Err(_) => tracing::warn!("Database connections were still in use when the pool closed"),
+    }
 }
@@ -0,0 +1,358 @@
+use std::{collections::HashSet, sync::Mutex, time::Duration};
+
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use warp::{
+    filters::{
+        log::{Info, Log},
+        BoxedFilter,
+    },
+    http::{header::CONTENT_TYPE, Method, StatusCode},
+    reply::{self, Response},
+    Filter, Reply,
+};
+
+use crate::{errors::ApiResult, graphql::mutation::user::AuthResponse};
+
+// Operation names are chosen by clients, so only this many are kept as
+// labels and any others are counted as `other`
+const MAX_OPERATION_NAMES: usize = 100;
+
+const ROUTES: [&str; 10] = [
+    "/",
+    "/graphql",
+    "/graphiql",
+    "/subscriptions",
+    "/healthz",
+    "/readyz",
+    "/metrics",
+    "/.well-known/jwks.json",
+    "/auth/oidc/start",
+    "/auth/oidc/callback",
+];
+
+const DATABASE_BUCKETS: [f64; 12] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+];
+
+static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+/// How a sign in attempt ended, failures are labelled with the error's `reason`
+#[derive(Debug, PartialEq, Eq)]
+pub enum SigninOutcome {
+    Success,
+    MfaPending,
+    Failure(String),
+}
+
+impl SigninOutcome {
+    pub fn of(result: &ApiResult<AuthResponse>) -> Self {
+        match result {
+            Ok(response) if response.mfa_pending => SigninOutcome::MfaPending,
+            Ok(_) => SigninOutcome::Success,
+            Err(e) => SigninOutcome::Failure(e.reason()),
+        }
+    }
+}
+
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    graphql_duration: HistogramVec,
+    graphql_errors: IntCounterVec,
+    signin_attempts: IntCounterVec,
+    signin_failures: IntCounterVec,
+    db_query_duration: HistogramVec,
+    db_query_errors: IntCounterVec,
+    operation_names: Mutex<HashSet<String>>,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let counter = |name: &str, help: &str, labels: &[&str]| {
+            let counter = IntCounterVec::new(Opts::new(name, help), labels).expect("valid metric");
+            registry
+                .register(Box::new(counter.clone()))
+                .expect("metric registered once");
+            counter
+        };
+        let histogram = |opts: HistogramOpts, labels: &[&str]| {
+            let histogram = HistogramVec::new(opts, labels).expect("valid metric");
+            registry
+                .register(Box::new(histogram.clone()))
+                .expect("metric registered once");
+            histogram
+        };
+
+        Metrics {
+            http_requests: counter(
+                "http_requests_total",
+                "HTTP requests by route, method and status",
+                &["route", "method", "status"],
+            ),
+            http_duration: histogram(
+                HistogramOpts::new(
+                    "http_request_duration_seconds",
+                    "HTTP request latency by route and method",
+                ),
+                &["route", "method"],
+            ),
+            graphql_duration: histogram(
+                HistogramOpts::new(
+                    "graphql_operation_duration_seconds",
+                    "Time taken to resolve each GraphQL operation",
+                ),
+                &["operation"],
+            ),
+            graphql_errors: counter(
+                "graphql_errors_total",
+                "GraphQL errors by operation and error code",
+                &["operation", "code"],
+            ),
+            signin_attempts: counter(
+                "signin_attempts_total",
+                "Sign in attempts by method and result",
+                &["method", "result"],
+            ),
+            signin_failures: counter(
+                "signin_failures_total",
+                "Failed sign in attempts by method and reason",
+                &["method", "reason"],
+            ),
+            db_query_duration: histogram(
+                HistogramOpts::new(
+                    "db_query_duration_seconds",
+                    "Database query latency by statement type",
+                )
+                .buckets(DATABASE_BUCKETS.to_vec()),
+                &["statement"],
+            ),
+            db_query_errors: counter(
+                "db_query_errors_total",
+                "Failed database queries by statement type",
+                &["statement"],
+            ),
+            registry,
+            operation_names: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn observe_http(&self, path: &str, method: &Method, status: StatusCode, elapsed: Duration) {
+        let route = route_label(path);
+        self.http_requests
+            .with_label_values(&[route, method.as_str(), status.as_str()])
+            .inc();
+        self.http_duration
+            .with_label_values(&[route, method.as_str()])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_graphql(
+        &self,
+        operation: Option<&str>,
+        elapsed: Duration,
+        error_codes: &[&str],
+    ) {
+        let operation = self.operation_label(operation);
+        self.graphql_duration
+            .with_label_values(&[&operation])
+            .observe(elapsed.as_secs_f64());
+        for code in error_codes {
+            self.graphql_errors
+                .with_label_values(&[&operation, code])
+                .inc();
+        }
+    }
+
+    pub fn record_signin(&self, method: &str, outcome: SigninOutcome) {
+        let result = match outcome {
+            SigninOutcome::Success => "success",
+            SigninOutcome::MfaPending => "mfa_pending",
+            SigninOutcome::Failure(reason) => {
+                self.signin_failures
+                    .with_label_values(&[method, &reason])
+                    .inc();
+                "failure"
+            }
+        };
+        self.signin_attempts
+            .with_label_values(&[method, result])
+            .inc();
+    }
+
+    /// Passed to `DatabaseConnection::set_metric_callback`. sea-orm times the query
+    /// once a pooled connection has been acquired, so waiting for the pool is not included.
+    pub fn observe_query(&self, info: &sea_orm::metric::Info) {
+        let statement = statement_label(&info.statement.sql);
+        self.db_query_duration
+            .with_label_values(&[statement])
+            .observe(info.elapsed.as_secs_f64());
+        if info.failed {
+            self.db_query_errors.with_label_values(&[statement]).inc();
+        }
+    }
+
+    /// All metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut buffer = vec![];
+        let encoder = TextEncoder::new();
+        if let Err(e) = encoder.encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!(error = %e, "Unable to encode metrics");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+
+    fn operation_label(&self, operation: Option<&str>) -> String {
+        let operation = match operation {
+            Some(operation) if !operation.is_empty() => operation,
+            _ => return "anonymous".to_string(),
+        };
+        let mut names = self
+            .operation_names
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if names.contains(operation) {
+            return operation.to_string();
+        }
+        if names.len() >= MAX_OPERATION_NAMES {
+            return "other".to_string();
+        }
+        names.insert(operation.to_string());
+        operation.to_string()
+    }
+}
+
+// Unknown paths are grouped so that scanners cannot create a series per path
+fn route_label(path: &str) -> &'static str {
+    let path = match path.len() > 1 {
+        true => path.trim_end_matches('/'),
+        false => path,
+    };
+    ROUTES
+        .iter()
+        .find(|route| **route == path)
+        .copied()
+        .unwrap_or("other")
+}
+
+fn statement_label(sql: &str) -> &'static str {
+    let keyword = sql.split_whitespace().next().unwrap_or_default();
+    ["SELECT", "INSERT", "UPDATE", "DELETE"]
+        .into_iter()
+        .find(|statement| statement.eq_ignore_ascii_case(keyword))
+        .unwrap_or("OTHER")
+}
+
+/// Records the count and latency of every request, to be applied with `Filter::with`
+pub fn http_metrics() -> Log<impl Fn(Info<'_>) + Copy + Send> {
+    warp::log::custom(|info: Info<'_>| {
+        metrics().observe_http(info.path(), info.method(), info.status(), info.elapsed())
+    })
+}
+
+/// Serves `/metrics` in the Prometheus text format for scraping
+pub fn create_metrics_filter() -> BoxedFilter<(Response,)> {
+    warp::get()
+        .and(warp::path!("metrics"))
+        .map(|| {
+            let body = metrics().render();
+            reply::with_header(body, CONTENT_TYPE, TextEncoder::new().format_type()).into_response()
+        })
+        .boxed()
+}
+
+#[cfg(test)]
+mod test_metrics {
+    use std::time::Duration;
+
+    use sea_orm::{metric::Info, DbBackend, Statement};
+    use warp::{
+        http::{Method, StatusCode},
+        test::request,
+    };
+
+    use super::{
+        create_metrics_filter, metrics, route_label, statement_label, Metrics, SigninOutcome,
+        MAX_OPERATION_NAMES,
+    };
+    use crate::errors::{ApiError, UserError};
+
+    #[test]
+    fn routes_are_grouped() {
+        assert_eq!(route_label("/graphql"), "/graphql");
+        assert_eq!(route_label("/graphql/"), "/graphql");
+        assert_eq!(route_label("/"), "/");
+        assert_eq!(route_label("/wp-admin.php"), "other");
+    }
+
+    #[test]
+    fn statements_are_labelled_by_keyword() {
+        assert_eq!(
+            statement_label("SELECT \"user\".\"id\" FROM \"user\""),
+            "SELECT"
+        );
+        assert_eq!(statement_label("  delete from user"), "DELETE");
+        assert_eq!(statement_label("CREATE TABLE x"), "OTHER");
+    }
+
+    #[test]
+    fn signin_failures_are_labelled_by_reason() {
+        let result = Err(ApiError::from(UserError::IncorrectEmailOrPassword));
+        assert_eq!(
+            SigninOutcome::of(&result),
+            SigninOutcome::Failure("INCORRECT_EMAIL_OR_PASSWORD".to_string())
+        );
+    }
+
+    #[test]
+    fn operation_names_are_capped() {
+        let metrics = Metrics::new();
+        for i in 0..MAX_OPERATION_NAMES {
+            metrics.operation_label(Some(&format!("Operation{}", i)));
+        }
+        assert_eq!(metrics.operation_label(Some("Operation0")), "Operation0");
+        assert_eq!(metrics.operation_label(Some("OneTooMany")), "other");
+        assert_eq!(metrics.operation_label(None), "anonymous");
+    }
+
+    #[tokio::test]
+    async fn metrics_are_served_as_text() {
+        let metrics = metrics();
+        metrics.observe_http(
+            "/healthz",
+            &Method::GET,
+            StatusCode::OK,
+            Duration::from_millis(3),
+        );
+        metrics.record_signin(
+            "password",
+            SigninOutcome::Failure("ACCOUNT_LOCKED".to_string()),
+        );
+        let statement = Statement::from_string(DbBackend::Postgres, "SELECT 1".to_string());
+        metrics.observe_query(&Info {
+            elapsed: Duration::from_millis(1),
+            statement: &statement,
+            failed: true,
+        });
+
+        let res = request()
+            .path("/metrics")
+            .reply(&create_metrics_filter())
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = String::from_utf8(res.body().to_vec()).unwrap();
+        assert!(body.contains(r#"http_requests_total{method="GET",route="/healthz",status="200"}"#));
+        assert!(
+            body.contains(r#"signin_failures_total{method="password",reason="ACCOUNT_LOCKED"}"#)
+        );
+        assert!(body.contains(r#"db_query_errors_total{statement="SELECT"} "#));
+    }
+}
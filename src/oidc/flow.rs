@@ -0,0 +1,259 @@
+use std::time::Duration;
+
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use sea_orm::{DatabaseConnection, Set};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    auth::{
+        hash::hash,
+        jwt::create_mfa_pending_jwt,
+        mfa::has_confirmed_mfa,
+        session::{start_session, DeviceInfo},
+        token::{generate_token, hash_token},
+    },
+    config::OidcConfig,
+    errors::{ApiResult, OidcError, UserError},
+    oidc::{http::HttpClient, ProviderMetadata},
+    repository::UserRepository,
+    time::{Time, OIDC_LOGIN_LIFETIME_IN_SECONDS},
+};
+use entity::{
+    prelude::{LinkedIdentity, OidcLogin, User},
+    sea_orm_active_enums::Status,
+    user,
+};
+
+#[derive(Debug, Serialize)]
+pub struct OidcLoginResponse {
+    pub token: String,
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: Option<String>,
+    #[serde(rename = "mfaPending")]
+    pub mfa_pending: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProviderJwk {
+    kid: Option<String>,
+    kty: String,
+    n: Option<String>,
+    e: Option<String>,
+    x: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProviderJwkSet {
+    keys: Vec<ProviderJwk>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    pub name: Option<String>,
+}
+
+/// Returns the authorization endpoint URL the user should be redirected to
+pub async fn start_login(
+    config: &OidcConfig,
+    client: &dyn HttpClient,
+    conn: &DatabaseConnection,
+) -> ApiResult<String> {
+    let metadata = ProviderMetadata::discover(config, client).await?;
+
+    let now = Time::now()?.as_secs() as i64;
+    OidcLogin::delete_expired(now, conn).await?;
+
+    let state = generate_token();
+    let nonce = generate_token();
+    let code_verifier = generate_token();
+    let expires_at =
+        Time::now_plus_duration(Duration::from_secs(OIDC_LOGIN_LIFETIME_IN_SECONDS.into()))?;
+    let login = OidcLogin::create_active_model(
+        &hash_token(&state),
+        &nonce,
+        &code_verifier,
+        now,
+        expires_at.as_secs() as i64,
+    );
+    OidcLogin::insert_one(login, conn).await?;
+
+    let mut url = Url::parse(&metadata.authorization_endpoint)
+        .map_err(|e| OidcError::Discovery(e.to_string()))?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &config.client_id)
+        .append_pair("redirect_uri", &config.redirect_uri)
+        .append_pair("scope", &config.scopes)
+        .append_pair("state", &state)
+        .append_pair("nonce", &nonce)
+        .append_pair("code_challenge", &hash_token(&code_verifier))
+        .append_pair("code_challenge_method", "S256");
+
+    Ok(url.to_string())
+}
+
+pub async fn complete_login(
+    config: &OidcConfig,
+    client: &dyn HttpClient,
+    users: &dyn UserRepository,
+    conn: &DatabaseConnection,
+    code: &str,
+    state: &str,
+    device: DeviceInfo,
+) -> ApiResult<OidcLoginResponse> {
+    // The state is single use, so it is removed before anything else can fail
+    let login = OidcLogin::find_one_by_state(&hash_token(state), conn)
+        .await?
+        .ok_or(OidcError::InvalidState)?;
+    OidcLogin::delete_one(&login.id, conn).await?;
+    let now = Time::now()?.as_secs() as i64;
+    if login.is_expired(now) {
+        return Err(OidcError::InvalidState.into());
+    }
+
+    let metadata = ProviderMetadata::discover(config, client).await?;
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", config.redirect_uri.as_str()),
+        ("client_id", config.client_id.as_str()),
+        ("code_verifier", login.code_verifier.as_str()),
+    ];
+    if let Some(secret) = &config.client_secret {
+        form.push(("client_secret", secret.as_str()));
+    }
+    let body = client.post_form(&metadata.token_endpoint, &form).await?;
+    let tokens: TokenResponse =
+        serde_json::from_slice(&body).map_err(|e| OidcError::TokenExchange(e.to_string()))?;
+
+    let claims = verify_id_token(config, &metadata, client, &tokens.id_token).await?;
+    if claims.nonce.as_deref() != Some(login.nonce.as_str()) {
+        return Err(OidcError::InvalidIdToken("Nonce does not match".to_string()).into());
+    }
+
+    let found = find_or_provision_user(config, &claims, now, users, conn).await?;
+    if found.is_suspended() {
+        return Err(UserError::AccountSuspended.into());
+    }
+
+    if has_confirmed_mfa(&found.id, conn).await? {
+        let token = create_mfa_pending_jwt(&found)?;
+        return Ok(OidcLoginResponse {
+            token,
+            refresh_token: None,
+            mfa_pending: true,
+        });
+    }
+
+    let session = start_session(found, false, device, users, conn).await?;
+
+    Ok(OidcLoginResponse {
+        token: session.token,
+        refresh_token: Some(session.refresh_token),
+        mfa_pending: false,
+    })
+}
+
+async fn verify_id_token(
+    config: &OidcConfig,
+    metadata: &ProviderMetadata,
+    client: &dyn HttpClient,
+    id_token: &str,
+) -> Result<IdTokenClaims, OidcError> {
+    let header = decode_header(id_token).map_err(|e| OidcError::InvalidIdToken(e.to_string()))?;
+    let body = client.get(&metadata.jwks_uri).await?;
+    let jwks: ProviderJwkSet =
+        serde_json::from_slice(&body).map_err(|e| OidcError::InvalidIdToken(e.to_string()))?;
+
+    let jwk = jwks
+        .keys
+        .iter()
+        .find(|jwk| header.kid.is_none() || jwk.kid == header.kid)
+        .ok_or_else(|| OidcError::InvalidIdToken("No matching signing key".to_string()))?;
+    let key = match (header.alg, jwk.kty.as_str()) {
+        (Algorithm::RS256, "RSA") => match (&jwk.n, &jwk.e) {
+            (Some(n), Some(e)) => DecodingKey::from_rsa_components(n, e),
+            _ => return Err(OidcError::InvalidIdToken("Incomplete RSA key".to_string())),
+        },
+        (Algorithm::EdDSA, "OKP") => match &jwk.x {
+            Some(x) => DecodingKey::from_ed_components(x),
+            None => {
+                return Err(OidcError::InvalidIdToken(
+                    "Incomplete EdDSA key".to_string(),
+                ))
+            }
+        },
+        (alg, _) => {
+            return Err(OidcError::InvalidIdToken(format!(
+                "Unsupported algorithm {:?}",
+                alg
+            )))
+        }
+    }
+    .map_err(|e| OidcError::InvalidIdToken(e.to_string()))?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[&metadata.issuer]);
+    validation.set_audience(&[&config.client_id]);
+    let data = decode::<IdTokenClaims>(id_token, &key, &validation)
+        .map_err(|e| OidcError::InvalidIdToken(e.to_string()))?;
+
+    Ok(data.claims)
+}
+
+// Existing accounts are only linked when the identity provider has verified
+// the email address, otherwise anyone could claim an account by its email
+async fn find_or_provision_user(
+    config: &OidcConfig,
+    claims: &IdTokenClaims,
+    now: i64,
+    users: &dyn UserRepository,
+    conn: &DatabaseConnection,
+) -> ApiResult<user::Model> {
+    if let Some(linked) = LinkedIdentity::find_one(&config.issuer, &claims.sub, conn).await? {
+        return users
+            .find_one_by_id(&linked.user_id)
+            .await?
+            .ok_or_else(|| UserError::UnableToComplete.into());
+    }
+
+    let email = claims.email.as_deref().ok_or(OidcError::MissingEmail)?;
+    let found = match users.find_one_by_email(email).await? {
+        Some(existing) => {
+            if !claims.email_verified {
+                return Err(UserError::UserWithEmailAlreadyExists(email.to_string()).into());
+            }
+            existing
+        }
+        None => {
+            let name = match &claims.name {
+                Some(name) => name.to_string(),
+                None => email.split('@').next().unwrap_or(email).to_string(),
+            };
+            // The account has no usable password until the user resets it
+            let pass = hash(&generate_token())?;
+            let mut new_user = User::create_active_model(email, &name, &pass);
+            new_user.status = Set(Status::Offline);
+            if claims.email_verified {
+                new_user.email_verified_at = Set(Some(now));
+            }
+            users.insert_one(new_user).await?
+        }
+    };
+
+    let identity = LinkedIdentity::create_active_model(&found.id, &config.issuer, &claims.sub, now);
+    LinkedIdentity::insert_one(identity, conn).await?;
+
+    Ok(found)
+}
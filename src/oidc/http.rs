@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use hyper::{
+    client::conn::handshake,
+    header::{CONTENT_TYPE, HOST},
+    Body, Method, Request,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    rustls::{ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName},
+    TlsConnector,
+};
+use url::Url;
+
+use crate::errors::OidcError;
+
+#[async_trait]
+pub trait HttpClient: Send + Sync {
+    async fn get(&self, url: &str) -> Result<Vec<u8>, OidcError>;
+    async fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<Vec<u8>, OidcError>;
+}
+
+/// Makes a single HTTP/1 request per connection, over TLS for `https` URLs
+pub struct HyperClient {
+    tls: TlsConnector,
+}
+
+impl Default for HyperClient {
+    fn default() -> Self {
+        let mut roots = RootCertStore::empty();
+        roots.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|anchor| {
+            OwnedTrustAnchor::from_subject_spki_name_constraints(
+                anchor.subject,
+                anchor.spki,
+                anchor.name_constraints,
+            )
+        }));
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        HyperClient {
+            tls: TlsConnector::from(Arc::new(config)),
+        }
+    }
+}
+
+impl HyperClient {
+    async fn send(
+        &self,
+        method: Method,
+        url: &str,
+        content_type: Option<&str>,
+        body: Body,
+    ) -> Result<Vec<u8>, OidcError> {
+        let url = Url::parse(url).map_err(|e| OidcError::Http(e.to_string()))?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| OidcError::Http(format!("No host in `{}`", url)))?;
+        let port = url.port_or_known_default().unwrap_or(80);
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        let host_header = match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+
+        let mut request = Request::builder()
+            .method(method)
+            .uri(path)
+            .header(HOST, host_header);
+        if let Some(content_type) = content_type {
+            request = request.header(CONTENT_TYPE, content_type);
+        }
+        let request = request
+            .body(body)
+            .map_err(|e| OidcError::Http(e.to_string()))?;
+
+        let stream = TcpStream::connect((host, port))
+            .await
+            .map_err(|e| OidcError::Http(e.to_string()))?;
+        match url.scheme() {
+            "https" => {
+                let name =
+                    ServerName::try_from(host).map_err(|e| OidcError::Http(e.to_string()))?;
+                let stream = self
+                    .tls
+                    .connect(name, stream)
+                    .await
+                    .map_err(|e| OidcError::Http(e.to_string()))?;
+                exchange(stream, request).await
+            }
+            "http" => exchange(stream, request).await,
+            other => Err(OidcError::Http(format!("Unsupported scheme `{}`", other))),
+        }
+    }
+}
+
+async fn exchange<S>(stream: S, request: Request<Body>) -> Result<Vec<u8>, OidcError>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (mut sender, connection) = handshake(stream)
+        .await
+        .map_err(|e| OidcError::Http(e.to_string()))?;
+    tokio::spawn(connection);
+
+    let response = sender
+        .send_request(request)
+        .await
+        .map_err(|e| OidcError::Http(e.to_string()))?;
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body())
+        .await
+        .map_err(|e| OidcError::Http(e.to_string()))?;
+    if !status.is_success() {
+        return Err(OidcError::Http(format!(
+            "Received status {}: {}",
+            status,
+            String::from_utf8_lossy(&body)
+        )));
+    }
+    Ok(body.to_vec())
+}
+
+#[async_trait]
+impl HttpClient for HyperClient {
+    async fn get(&self, url: &str) -> Result<Vec<u8>, OidcError> {
+        self.send(Method::GET, url, None, Body::empty()).await
+    }
+
+    async fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<Vec<u8>, OidcError> {
+        let body = serde_urlencoded::to_string(form).map_err(|e| OidcError::Http(e.to_string()))?;
+        self.send(
+            Method::POST,
+            url,
+            Some("application/x-www-form-urlencoded"),
+            Body::from(body),
+        )
+        .await
+    }
+}
@@ -0,0 +1,240 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use warp::{
+    filters::BoxedFilter,
+    http::{header::LOCATION, StatusCode},
+    reply::{self, Response},
+    Filter, Reply,
+};
+
+use crate::{
+    auth::session::DeviceInfo,
+    config::OidcConfig,
+    errors::{ApiError, OidcError, UserError},
+    get_client_ip,
+    metrics::{metrics, SigninOutcome},
+    repository::{database::DatabaseUserRepository, UserRepository},
+};
+use flow::{complete_login, start_login, OidcLoginResponse};
+use http::HttpClient;
+
+pub mod flow;
+pub mod http;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+impl ProviderMetadata {
+    pub async fn discover(
+        config: &OidcConfig,
+        client: &dyn HttpClient,
+    ) -> Result<ProviderMetadata, OidcError> {
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            config.issuer.trim_end_matches('/')
+        );
+        let body = client.get(&url).await?;
+        let metadata: ProviderMetadata =
+            serde_json::from_slice(&body).map_err(|e| OidcError::Discovery(e.to_string()))?;
+        if metadata.issuer != config.issuer {
+            return Err(OidcError::Discovery(format!(
+                "Expected issuer `{}` but found `{}`",
+                config.issuer, metadata.issuer
+            )));
+        }
+        Ok(metadata)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    let body = ErrorBody {
+        error: message.to_string(),
+    };
+    reply::with_status(reply::json(&body), status).into_response()
+}
+
+// As with GraphQL errors, database and hashing errors can include SQL and table contents,
+// so only problems with the login itself or the account are described to the client
+fn api_error_response(error: &ApiError) -> Response {
+    match error {
+        ApiError::Oidc(_)
+        | ApiError::User(UserError::AccountSuspended)
+        | ApiError::User(UserError::UserWithEmailAlreadyExists(_)) => {
+            error_response(StatusCode::BAD_REQUEST, &error.to_string())
+        }
+        _ => {
+            tracing::error!(error = %error, "Unable to complete OIDC login");
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &UserError::UnableToComplete.to_string(),
+            )
+        }
+    }
+}
+
+// `Uri` drops fragments, so the header is set directly rather than with `warp::redirect`
+fn redirect_response(location: &str) -> Response {
+    reply::with_header(StatusCode::FOUND, LOCATION, location).into_response()
+}
+
+// Tokens are put in the fragment so they are never sent back to a server
+fn login_response(config: &OidcConfig, login: OidcLoginResponse) -> Response {
+    match &config.post_login_redirect {
+        Some(location) => {
+            let fragment = serde_urlencoded::to_string(&login).unwrap_or_default();
+            redirect_response(&format!("{}#{}", location, fragment))
+        }
+        None => reply::json(&login).into_response(),
+    }
+}
+
+type OidcDeps = (
+    Arc<DatabaseConnection>,
+    Arc<dyn UserRepository>,
+    Arc<OidcConfig>,
+    Arc<dyn HttpClient>,
+);
+
+/// Serves `/auth/oidc/start`, which redirects to the identity provider,
+/// and `/auth/oidc/callback`, which the identity provider redirects back to
+pub fn create_oidc_filter(
+    connection: impl Into<Arc<DatabaseConnection>>,
+    config: OidcConfig,
+    client: Arc<dyn HttpClient>,
+) -> BoxedFilter<(Response,)> {
+    let connection = connection.into();
+    let users: Arc<dyn UserRepository> = Arc::new(DatabaseUserRepository::new(connection.clone()));
+    let config = Arc::new(config);
+    let deps = warp::any().map(move || {
+        (
+            connection.clone(),
+            users.clone(),
+            config.clone(),
+            client.clone(),
+        )
+    });
+
+    let start = warp::get()
+        .and(warp::path!("auth" / "oidc" / "start"))
+        .and(deps.clone())
+        .then(|(connection, _, config, client): OidcDeps| async move {
+            match start_login(&config, client.as_ref(), connection.as_ref()).await {
+                Ok(location) => redirect_response(&location),
+                Err(e) => api_error_response(&e),
+            }
+        });
+
+    let callback = warp::get()
+        .and(warp::path!("auth" / "oidc" / "callback"))
+        .and(warp::query::<CallbackQuery>())
+        .and(warp::header::optional::<String>("X-Forwarded-For"))
+        .and(warp::header::optional::<String>("User-Agent"))
+        .and(warp::addr::remote())
+        .and(deps)
+        .then(
+            |query: CallbackQuery,
+             forwarded: Option<String>,
+             user_agent: Option<String>,
+             remote: Option<SocketAddr>,
+             (connection, users, config, client): OidcDeps| async move {
+                if let Some(error) = query.error {
+                    return api_error_response(&OidcError::ProviderError(error).into());
+                }
+                let (code, state) = match (query.code, query.state) {
+                    (Some(code), Some(state)) => (code, state),
+                    _ => return api_error_response(&OidcError::InvalidState.into()),
+                };
+                let device = DeviceInfo {
+                    label: None,
+                    user_agent,
+                    ip_address: get_client_ip(
+                        forwarded,
+                        remote,
+                        crate::config::config().server.forwarded_hops(),
+                    ),
+                };
+                let login = complete_login(
+                    &config,
+                    client.as_ref(),
+                    users.as_ref(),
+                    connection.as_ref(),
+                    &code,
+                    &state,
+                    device,
+                );
+                match login.await {
+                    Ok(login) => {
+                        let outcome = match login.mfa_pending {
+                            true => SigninOutcome::MfaPending,
+                            false => SigninOutcome::Success,
+                        };
+                        metrics().record_signin("oidc", outcome);
+                        login_response(&config, login)
+                    }
+                    Err(e) => {
+                        metrics().record_signin("oidc", SigninOutcome::Failure(e.reason()));
+                        api_error_response(&e)
+                    }
+                }
+            },
+        );
+
+    start.or(callback).unify().boxed()
+}
+
+#[cfg(test)]
+mod test_api_error_response {
+    use sea_orm::DbErr;
+    use warp::hyper::body::to_bytes;
+
+    use super::api_error_response;
+    use crate::errors::{ApiError, OidcError, UserError};
+
+    async fn respond(error: ApiError) -> (u16, String) {
+        let res = api_error_response(&error);
+        let status = res.status().as_u16();
+        let body = to_bytes(res.into_body()).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn login_and_account_errors_are_described() {
+        let (status, body) = respond(OidcError::InvalidState.into()).await;
+        assert_eq!(status, 400);
+        assert_eq!(
+            body,
+            r#"{"error":"Login request is invalid or has expired"}"#
+        );
+
+        let (status, body) = respond(UserError::AccountSuspended.into()).await;
+        assert_eq!(status, 400);
+        assert_eq!(body, r#"{"error":"Account has been suspended"}"#);
+    }
+
+    #[tokio::test]
+    async fn database_errors_are_not_described() {
+        let error = DbErr::Custom("relation \"user\" does not exist".to_string());
+        let (status, body) = respond(error.into()).await;
+        assert_eq!(status, 500);
+        assert_eq!(body, r#"{"error":"Unable to complete request"}"#);
+    }
+}
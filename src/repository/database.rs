@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sea_orm::{prelude::Uuid, DatabaseConnection, DbErr, TransactionTrait};
+
+use super::UserRepository;
+use entity::{
+    prelude::User,
+    traits::user::{UserCursor, UserFilter, UserOrder},
+    user,
+};
+
+pub struct DatabaseUserRepository {
+    connection: Arc<DatabaseConnection>,
+}
+
+impl DatabaseUserRepository {
+    pub fn new(connection: Arc<DatabaseConnection>) -> Self {
+        DatabaseUserRepository { connection }
+    }
+}
+
+#[async_trait]
+impl UserRepository for DatabaseUserRepository {
+    async fn find_one_by_email(&self, email: &str) -> Result<Option<user::Model>, DbErr> {
+        User::find_one_by_email(email, &self.connection).await
+    }
+
+    async fn find_one_by_id(&self, id: &Uuid) -> Result<Option<user::Model>, DbErr> {
+        User::find_one_by_id(id, &self.connection).await
+    }
+
+    async fn find_all(&self) -> Result<Vec<user::Model>, DbErr> {
+        User::find_all(&self.connection).await
+    }
+
+    async fn find_page(
+        &self,
+        filter: &UserFilter,
+        order: &UserOrder,
+        after: Option<&UserCursor>,
+        limit: u64,
+    ) -> Result<Vec<user::Model>, DbErr> {
+        User::find_page(filter, order, after, limit)
+            .all(self.connection.as_ref())
+            .await
+    }
+
+    async fn count_filtered(&self, filter: &UserFilter) -> Result<u64, DbErr> {
+        User::count_filtered(filter, &self.connection).await
+    }
+
+    async fn insert_one(&self, model: user::ActiveModel) -> Result<user::Model, DbErr> {
+        User::insert_one(model.clone(), &self.connection).await?;
+        model.try_into()
+    }
+
+    async fn update_one(&self, model: user::ActiveModel) -> Result<user::Model, DbErr> {
+        User::update_one(model, self.connection.as_ref()).await
+    }
+
+    async fn delete_one(&self, id: &Uuid) -> Result<u64, DbErr> {
+        let res = User::delete_one(id, self.connection.as_ref()).await?;
+        Ok(res.rows_affected)
+    }
+
+    async fn update_unless_last_admin(
+        &self,
+        model: user::ActiveModel,
+    ) -> Result<Option<user::Model>, DbErr> {
+        let id =
+            model.id.clone().take().ok_or_else(|| {
+                DbErr::Custom("The id of the user to update is not set".to_string())
+            })?;
+        let txn = self.connection.begin().await?;
+        if User::is_last_active_admin(&id, &txn).await? {
+            txn.rollback().await?;
+            return Ok(None);
+        }
+        let updated = User::update_one(model, &txn).await?;
+        txn.commit().await?;
+        Ok(Some(updated))
+    }
+
+    async fn delete_unless_last_admin(&self, id: &Uuid) -> Result<Option<u64>, DbErr> {
+        let txn = self.connection.begin().await?;
+        if User::is_last_active_admin(id, &txn).await? {
+            txn.rollback().await?;
+            return Ok(None);
+        }
+        let res = User::delete_one(id, &txn).await?;
+        txn.commit().await?;
+        Ok(Some(res.rows_affected))
+    }
+}
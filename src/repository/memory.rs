@@ -0,0 +1,345 @@
+use std::{cmp::Ordering, sync::Mutex};
+
+use async_trait::async_trait;
+use sea_orm::{prelude::Uuid, ActiveValue, DbErr};
+
+use super::UserRepository;
+use entity::{
+    sea_orm_active_enums::Role,
+    traits::user::{SortDirection, UserCursor, UserFilter, UserOrder},
+    user,
+};
+
+/// Keeps users in memory so resolvers can be tested without a database.
+/// Emails are unique, as they are in the `user` table.
+#[derive(Default)]
+pub struct MemoryUserRepository {
+    users: Mutex<Vec<user::Model>>,
+}
+
+impl MemoryUserRepository {
+    pub fn with_users(users: Vec<user::Model>) -> Self {
+        MemoryUserRepository {
+            users: Mutex::new(users),
+        }
+    }
+
+    pub fn users(&self) -> Vec<user::Model> {
+        self.users.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn filtered(&self, filter: &UserFilter) -> Vec<user::Model> {
+        self.users()
+            .into_iter()
+            .filter(|model| matches(filter, model))
+            .collect()
+    }
+}
+
+fn matches(filter: &UserFilter, model: &user::Model) -> bool {
+    if let Some(role) = &filter.role {
+        if &model.role != role {
+            return false;
+        }
+    }
+    if let Some(statuses) = &filter.statuses {
+        if !statuses.contains(&model.status) {
+            return false;
+        }
+    }
+    if let Some(search) = &filter.search {
+        let search = search.to_lowercase();
+        let in_name = model.name.to_lowercase().contains(&search);
+        let in_email = !filter.search_names_only && model.email.to_lowercase().contains(&search);
+        if !in_name && !in_email {
+            return false;
+        }
+    }
+    true
+}
+
+// Orders by the cursor's value and then by id, as the database does
+fn compare(a: &UserCursor, b: &UserCursor, order: &UserOrder) -> Ordering {
+    let ordering = a.value.cmp(&b.value).then(a.id.cmp(&b.id));
+    match order.direction {
+        SortDirection::Asc => ordering,
+        SortDirection::Desc => ordering.reverse(),
+    }
+}
+
+#[async_trait]
+impl UserRepository for MemoryUserRepository {
+    async fn find_one_by_email(&self, email: &str) -> Result<Option<user::Model>, DbErr> {
+        Ok(self.users().into_iter().find(|model| model.email == email))
+    }
+
+    async fn find_one_by_id(&self, id: &Uuid) -> Result<Option<user::Model>, DbErr> {
+        Ok(self.users().into_iter().find(|model| &model.id == id))
+    }
+
+    async fn find_all(&self) -> Result<Vec<user::Model>, DbErr> {
+        Ok(self.users())
+    }
+
+    async fn find_page(
+        &self,
+        filter: &UserFilter,
+        order: &UserOrder,
+        after: Option<&UserCursor>,
+        limit: u64,
+    ) -> Result<Vec<user::Model>, DbErr> {
+        let mut page: Vec<(UserCursor, user::Model)> = self
+            .filtered(filter)
+            .into_iter()
+            .map(|model| (UserCursor::from_model(&model, order), model))
+            .filter(|(cursor, _)| match after {
+                Some(after) => compare(cursor, after, order) == Ordering::Greater,
+                None => true,
+            })
+            .collect();
+        page.sort_by(|(a, _), (b, _)| compare(a, b, order));
+
+        Ok(page
+            .into_iter()
+            .take(limit as usize)
+            .map(|(_, model)| model)
+            .collect())
+    }
+
+    async fn count_filtered(&self, filter: &UserFilter) -> Result<u64, DbErr> {
+        Ok(self.filtered(filter).len() as u64)
+    }
+
+    async fn insert_one(&self, model: user::ActiveModel) -> Result<user::Model, DbErr> {
+        let model: user::Model = model.try_into()?;
+        let mut users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        if users
+            .iter()
+            .any(|user| user.id == model.id || user.email == model.email)
+        {
+            return Err(DbErr::Custom(
+                "Duplicate key violates a unique constraint".to_string(),
+            ));
+        }
+        users.push(model.clone());
+        Ok(model)
+    }
+
+    async fn update_one(&self, model: user::ActiveModel) -> Result<user::Model, DbErr> {
+        let mut users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        update(&mut users, model)
+    }
+
+    async fn delete_one(&self, id: &Uuid) -> Result<u64, DbErr> {
+        let mut users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(delete(&mut users, id))
+    }
+
+    async fn update_unless_last_admin(
+        &self,
+        model: user::ActiveModel,
+    ) -> Result<Option<user::Model>, DbErr> {
+        let mut users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        match &model.id {
+            ActiveValue::Set(id) | ActiveValue::Unchanged(id)
+                if is_last_active_admin(&users, id) =>
+            {
+                Ok(None)
+            }
+            _ => update(&mut users, model).map(Some),
+        }
+    }
+
+    async fn delete_unless_last_admin(&self, id: &Uuid) -> Result<Option<u64>, DbErr> {
+        let mut users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        if is_last_active_admin(&users, id) {
+            return Ok(None);
+        }
+        Ok(Some(delete(&mut users, id)))
+    }
+}
+
+fn update(users: &mut [user::Model], mut model: user::ActiveModel) -> Result<user::Model, DbErr> {
+    let id = model
+        .id
+        .take()
+        .ok_or_else(|| DbErr::Custom("The id of the user to update is not set".to_string()))?;
+    let existing = users
+        .iter_mut()
+        .find(|user| user.id == id)
+        .ok_or_else(|| DbErr::RecordNotFound("None of the users were updated".to_string()))?;
+
+    let updated = user::Model {
+        id,
+        email: model.email.take().unwrap_or(existing.email.to_owned()),
+        name: model.name.take().unwrap_or(existing.name.to_owned()),
+        password: model
+            .password
+            .take()
+            .unwrap_or(existing.password.to_owned()),
+        status: model.status.take().unwrap_or(existing.status.to_owned()),
+        role: model.role.take().unwrap_or(existing.role.to_owned()),
+        email_verified_at: model
+            .email_verified_at
+            .take()
+            .unwrap_or(existing.email_verified_at),
+        suspended_at: model.suspended_at.take().unwrap_or(existing.suspended_at),
+    };
+    *existing = updated.clone();
+    Ok(updated)
+}
+
+fn delete(users: &mut Vec<user::Model>, id: &Uuid) -> u64 {
+    let before = users.len();
+    users.retain(|user| &user.id != id);
+    (before - users.len()) as u64
+}
+
+fn is_last_active_admin(users: &[user::Model], id: &Uuid) -> bool {
+    let admins: Vec<&user::Model> = users
+        .iter()
+        .filter(|model| model.role == Role::Admin && !model.is_suspended())
+        .collect();
+    admins.len() <= 1 && admins.iter().any(|admin| &admin.id == id)
+}
+
+#[cfg(test)]
+mod test {
+    use sea_orm::{prelude::Uuid, Set};
+
+    use super::MemoryUserRepository;
+    use crate::repository::UserRepository;
+    use entity::{
+        prelude::User,
+        sea_orm_active_enums::{Role, Status},
+        traits::user::{SortDirection, UserCursor, UserFilter, UserOrder, UserOrderField},
+        user,
+    };
+
+    fn user(name: &str, role: Role, status: Status) -> user::Model {
+        user::Model {
+            id: Uuid::new_v4(),
+            email: format!("{}@test.com", name.to_lowercase()),
+            name: name.to_string(),
+            password: "".to_string(),
+            status,
+            role,
+            email_verified_at: None,
+            suspended_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn inserts_and_finds_users() {
+        let repository = MemoryUserRepository::default();
+        let model = User::create_active_model("test@test.com", "test", "password");
+        let inserted = repository.insert_one(model.clone()).await.unwrap();
+
+        let got = repository.find_one_by_email("test@test.com").await.unwrap();
+        assert_eq!(got, Some(inserted.clone()));
+        let got = repository.find_one_by_id(&inserted.id).await.unwrap();
+        assert_eq!(got, Some(inserted));
+
+        // Emails are unique
+        let duplicate = User::create_active_model("test@test.com", "other", "password");
+        assert!(repository.insert_one(duplicate).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn updates_only_set_fields() {
+        let existing = user("Test", Role::Guest, Status::Offline);
+        let repository = MemoryUserRepository::with_users(vec![existing.clone()]);
+
+        let mut model: user::ActiveModel = existing.clone().into();
+        model.status = Set(Status::Online);
+        let updated = repository.update_one(model).await.unwrap();
+
+        assert_eq!(updated.status, Status::Online);
+        assert_eq!(updated.name, existing.name);
+        assert_eq!(repository.users(), vec![updated]);
+
+        let missing: user::ActiveModel = user("Missing", Role::Guest, Status::Offline).into();
+        assert!(repository.update_one(missing).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn pages_through_filtered_users() {
+        let repository = MemoryUserRepository::with_users(vec![
+            user("Carol", Role::Student, Status::Online),
+            user("Alice", Role::Student, Status::Offline),
+            user("Bob", Role::Teacher, Status::Online),
+            user("Dave", Role::Student, Status::Hidden),
+        ]);
+        let filter = UserFilter {
+            role: Some(Role::Student),
+            statuses: Some(vec![Status::Online, Status::Offline]),
+            ..Default::default()
+        };
+        let order = UserOrder {
+            field: UserOrderField::Name,
+            direction: SortDirection::Desc,
+        };
+
+        let page = repository
+            .find_page(&filter, &order, None, 1)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].name, "Carol");
+
+        let after = UserCursor::from_model(&page[0], &order);
+        let page = repository
+            .find_page(&filter, &order, Some(&after), 10)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].name, "Alice");
+
+        assert_eq!(repository.count_filtered(&filter).await.unwrap(), 2);
+
+        let search = UserFilter {
+            search: Some("BOB@".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(repository.count_filtered(&search).await.unwrap(), 1);
+        let search = UserFilter {
+            search_names_only: true,
+            ..search
+        };
+        assert_eq!(repository.count_filtered(&search).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn keeps_last_active_admin() {
+        let admin = user("Admin", Role::Admin, Status::Online);
+        let mut suspended = user("Suspended", Role::Admin, Status::Offline);
+        suspended.suspended_at = Some(0);
+        let other = user("Other", Role::Admin, Status::Online);
+        let repository =
+            MemoryUserRepository::with_users(vec![admin.clone(), suspended.clone(), other.clone()]);
+
+        let mut model: user::ActiveModel = other.into();
+        model.role = Set(Role::Teacher);
+        let updated = repository.update_unless_last_admin(model).await.unwrap();
+        assert_eq!(updated.unwrap().role, Role::Teacher);
+
+        let mut model: user::ActiveModel = admin.clone().into();
+        model.role = Set(Role::Teacher);
+        assert_eq!(
+            repository.update_unless_last_admin(model).await.unwrap(),
+            None
+        );
+        assert_eq!(
+            repository
+                .delete_unless_last_admin(&admin.id)
+                .await
+                .unwrap(),
+            None
+        );
+
+        let got = repository.delete_unless_last_admin(&suspended.id).await;
+        assert_eq!(got.unwrap(), Some(1));
+        assert_eq!(repository.delete_one(&admin.id).await.unwrap(), 1);
+        assert_eq!(repository.delete_one(&admin.id).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,52 @@
+use async_trait::async_trait;
+use sea_orm::{prelude::Uuid, DbErr};
+
+use entity::{
+    traits::user::{UserCursor, UserFilter, UserOrder},
+    user,
+};
+
+pub mod database;
+pub mod memory;
+
+/// Reads and writes users, so that resolvers do not depend on how they are stored.
+/// `database` is used when serving, `memory` lets tests run without a database.
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn find_one_by_email(&self, email: &str) -> Result<Option<user::Model>, DbErr>;
+
+    async fn find_one_by_id(&self, id: &Uuid) -> Result<Option<user::Model>, DbErr>;
+
+    async fn find_all(&self) -> Result<Vec<user::Model>, DbErr>;
+
+    /// Up to `limit` users matching the filter that come after the cursor in the order
+    async fn find_page(
+        &self,
+        filter: &UserFilter,
+        order: &UserOrder,
+        after: Option<&UserCursor>,
+        limit: u64,
+    ) -> Result<Vec<user::Model>, DbErr>;
+
+    async fn count_filtered(&self, filter: &UserFilter) -> Result<u64, DbErr>;
+
+    /// Every field of the model must be set
+    async fn insert_one(&self, model: user::ActiveModel) -> Result<user::Model, DbErr>;
+
+    /// Only the fields that are set are changed
+    async fn update_one(&self, model: user::ActiveModel) -> Result<user::Model, DbErr>;
+
+    /// Returns the number of users deleted
+    async fn delete_one(&self, id: &Uuid) -> Result<u64, DbErr>;
+
+    /// As `update_one`, unless the user is the only Admin that has not been suspended,
+    /// in which case nothing is changed and `None` is returned. The check and the update
+    /// are made together, so concurrent changes cannot remove every Admin between them.
+    async fn update_unless_last_admin(
+        &self,
+        model: user::ActiveModel,
+    ) -> Result<Option<user::Model>, DbErr>;
+
+    /// As `delete_one`, with the same check as `update_unless_last_admin`
+    async fn delete_unless_last_admin(&self, id: &Uuid) -> Result<Option<u64>, DbErr>;
+}
@@ -0,0 +1,96 @@
+use once_cell::sync::Lazy;
+use tokio::{signal, sync::watch};
+
+// Whether the server is shutting down, watched by long lived connections
+static SHUTTING_DOWN: Lazy<watch::Sender<bool>> = Lazy::new(|| watch::channel(false).0);
+
+// Websocket connections are upgraded out of the server, so they are counted
+// here for the shutdown to wait until they have been closed
+static OPEN_SESSIONS: Lazy<watch::Sender<usize>> = Lazy::new(|| watch::channel(0).0);
+
+/// Resolves once the process is asked to stop with SIGTERM or Ctrl+C
+pub async fn shutdown_signal() {
+    let interrupt = async {
+        signal::ctrl_c().await.expect("Unable to listen for Ctrl+C");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Unable to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {},
+        _ = terminate => {},
+    }
+}
+
+/// Tells open websocket connections to close
+pub fn begin_shutdown() {
+    SHUTTING_DOWN.send_replace(true);
+}
+
+/// Resolves once `begin_shutdown` has been called
+pub async fn shutdown_started() {
+    let mut shutting_down = SHUTTING_DOWN.subscribe();
+    while !*shutting_down.borrow_and_update() {
+        if shutting_down.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Counts a websocket connection as open until the guard is dropped
+pub fn track_session() -> SessionGuard {
+    OPEN_SESSIONS.send_modify(|open| *open += 1);
+    SessionGuard
+}
+
+pub struct SessionGuard;
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        OPEN_SESSIONS.send_modify(|open| *open -= 1);
+    }
+}
+
+/// Resolves once every tracked websocket connection has been closed
+pub async fn sessions_closed() {
+    let mut open = OPEN_SESSIONS.subscribe();
+    while *open.borrow_and_update() > 0 {
+        if open.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_shutdown {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use super::{begin_shutdown, sessions_closed, shutdown_started, track_session};
+
+    #[tokio::test]
+    async fn waits_for_sessions_to_close() {
+        let session = track_session();
+        let waiting = tokio::spawn(async {
+            shutdown_started().await;
+            sessions_closed().await;
+        });
+
+        begin_shutdown();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        drop(session);
+        let got = timeout(Duration::from_secs(1), waiting).await;
+        assert!(got.is_ok());
+    }
+}
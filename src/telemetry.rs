@@ -0,0 +1,94 @@
+use sea_orm::prelude::Uuid;
+use tracing_subscriber::{fmt, EnvFilter};
+use warp::{
+    filters::log::{Info, Log},
+    Filter, Rejection,
+};
+
+use crate::config::{LogConfig, LogFormat};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// Client IDs are written to every log line, so anything unusual is replaced
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// Installs the global subscriber that writes events to stdout, as text or one JSON object per line
+pub fn init_tracing(config: &LogConfig) {
+    let filter = EnvFilter::try_new(&config.filter).unwrap_or_else(|e| {
+        eprintln!(
+            "Invalid log filter `{}`, using `info`: {}",
+            config.filter, e
+        );
+        EnvFilter::new("info")
+    });
+    let builder = fmt().with_env_filter(filter);
+    match config.format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().flatten_event(true).init(),
+    }
+}
+
+/// Extracts the request ID sent by the client, or generates one
+pub fn request_id() -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::header::optional::<String>(REQUEST_ID_HEADER).map(|id: Option<String>| match id {
+        Some(id) if is_valid_request_id(&id) => id,
+        _ => Uuid::new_v4().to_string(),
+    })
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Logs every response, to be applied with `Filter::with`. The request ID is only
+/// known here when the client or a proxy sent one.
+pub fn access_log() -> Log<impl Fn(Info<'_>) + Copy + Send> {
+    warp::log::custom(|info: Info<'_>| {
+        tracing::info!(
+            target: "access",
+            method = %info.method(),
+            path = info.path(),
+            status = info.status().as_u16(),
+            elapsed_ms = info.elapsed().as_millis() as u64,
+            request_id = info
+                .request_headers()
+                .get(REQUEST_ID_HEADER)
+                .and_then(|id| id.to_str().ok()),
+            "request completed"
+        )
+    })
+}
+
+#[cfg(test)]
+mod test_telemetry {
+    use warp::test::request;
+
+    use super::{request_id, REQUEST_ID_HEADER};
+
+    #[tokio::test]
+    async fn request_ids_are_accepted_or_generated() {
+        let filter = request_id();
+
+        let got = request()
+            .header(REQUEST_ID_HEADER, "abc-123")
+            .filter(&filter)
+            .await
+            .unwrap();
+        assert_eq!(got, "abc-123");
+
+        let got = request()
+            .header(REQUEST_ID_HEADER, "not an id")
+            .filter(&filter)
+            .await
+            .unwrap();
+        assert_ne!(got, "not an id");
+        assert_eq!(got.len(), 36);
+
+        let got = request().filter(&filter).await.unwrap();
+        assert_eq!(got.len(), 36);
+    }
+}
@@ -1,9 +1,15 @@
 use std::sync::Arc;
 
-use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
 use sea_orm::{prelude::Uuid, DatabaseBackend, DatabaseConnection, MockDatabase, ModelTrait};
 
-use crate::{auth::jwt::Claims, get_env, graphql::schema::Context};
+use crate::{
+    auth::{jwt::Claims, keys::keys},
+    config::config,
+    graphql::schema::Context,
+    mail::memory::MemoryMailer,
+    repository::{database::DatabaseUserRepository, memory::MemoryUserRepository},
+    time::Time,
+};
 use entity::sea_orm_active_enums::Role;
 use migration::DbErr;
 
@@ -19,9 +25,48 @@ pub fn create_mock_conn<T: ModelTrait>(results: Vec<Vec<T>>) -> DatabaseConnecti
 
 #[allow(dead_code)]
 pub fn create_mock_context<T: ModelTrait>(results: Vec<Vec<T>>, token: Option<String>) -> Context {
+    create_database_context(create_mock_database().append_query_results(results), token)
+}
+
+/// For queries whose results are not all of the same model, e.g. a page and its count
+#[allow(dead_code)]
+pub fn create_database_context(db: MockDatabase, token: Option<String>) -> Context {
+    let token = token.unwrap_or_default();
+    let connection = Arc::new(db.into_connection());
+    let users = Arc::new(DatabaseUserRepository::new(connection.clone()));
+    let mailer = Arc::new(MemoryMailer::default());
+    Context {
+        config: config(),
+        token,
+        connection,
+        users,
+        mailer,
+        client_ip: None,
+        user_agent: None,
+        request_id: "test".to_string(),
+    }
+}
+
+/// Users are kept in the repository, while other tables are read from the mock database
+#[allow(dead_code)]
+pub fn create_repository_context(
+    users: Arc<MemoryUserRepository>,
+    db: MockDatabase,
+    token: Option<String>,
+) -> Context {
     let token = token.unwrap_or_default();
-    let connection = Arc::new(create_mock_conn(results));
-    Context { token, connection }
+    let connection = Arc::new(db.into_connection());
+    let mailer = Arc::new(MemoryMailer::default());
+    Context {
+        config: config(),
+        token,
+        connection,
+        users,
+        mailer,
+        client_ip: None,
+        user_agent: None,
+        request_id: "test".to_string(),
+    }
 }
 
 #[allow(dead_code)]
@@ -34,20 +79,41 @@ pub fn create_mock_errored_conn(results: Vec<DbErr>) -> DatabaseConnection {
 pub fn create_errored_context(results: Vec<DbErr>, token: Option<String>) -> Context {
     let token = token.unwrap_or_default();
     let connection = Arc::new(create_mock_errored_conn(results));
-    Context { connection, token }
+    let users = Arc::new(DatabaseUserRepository::new(connection.clone()));
+    let mailer = Arc::new(MemoryMailer::default());
+    Context {
+        config: config(),
+        connection,
+        users,
+        token,
+        mailer,
+        client_ip: None,
+        user_agent: None,
+        request_id: "test".to_string(),
+    }
 }
 
 #[allow(dead_code)]
 pub fn create_test_jwt(id: &Uuid, role: &Role, time: u64) -> String {
-    let val = get_env("JWT_SECRET");
-    let secret = val.as_bytes();
     let claims = Claims {
         sub: id.to_owned(),
+        sid: None,
         role: role.to_str(),
         exp: time,
+        iat: Time::now().unwrap().as_secs(),
+        iat_ms: Time::now().unwrap().as_millis() as u64,
+        jti: Uuid::new_v4(),
+        email_verified: false,
+        mfa_pending: false,
+        mfa_authenticated: false,
     };
-    let header = Header::new(Algorithm::HS512);
-    encode(&header, &claims, &EncodingKey::from_secret(secret)).unwrap()
+    keys().encode(&claims).unwrap()
+}
+
+/// A token for a random user with the role, valid for the next hour
+#[allow(dead_code)]
+pub fn create_role_jwt(role: &Role) -> String {
+    create_test_jwt(&Uuid::new_v4(), role, Time::hour_hence().unwrap().as_secs())
 }
 
 #[allow(dead_code)]
@@ -3,6 +3,10 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use crate::errors::TimeError;
 
 pub const HOUR_IN_SECONDS: u16 = 3600;
+pub const DAY_IN_SECONDS: u32 = 24 * 3600;
+pub const REFRESH_TOKEN_LIFETIME_IN_SECONDS: u32 = 30 * 24 * 3600;
+pub const MFA_PENDING_LIFETIME_IN_SECONDS: u16 = 5 * 60;
+pub const OIDC_LOGIN_LIFETIME_IN_SECONDS: u16 = 10 * 60;
 
 pub struct Time {}
 
@@ -25,13 +29,23 @@ impl Time {
     pub fn hour_hence() -> Result<Duration, TimeError> {
         Self::now_plus_duration(Duration::from_secs(HOUR_IN_SECONDS.into()))
     }
+
+    pub fn day_hence() -> Result<Duration, TimeError> {
+        Self::now_plus_duration(Duration::from_secs(DAY_IN_SECONDS.into()))
+    }
+
+    pub fn refresh_token_hence() -> Result<Duration, TimeError> {
+        Self::now_plus_duration(Duration::from_secs(
+            REFRESH_TOKEN_LIFETIME_IN_SECONDS.into(),
+        ))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-    use crate::time::HOUR_IN_SECONDS;
+    use crate::time::{DAY_IN_SECONDS, HOUR_IN_SECONDS, REFRESH_TOKEN_LIFETIME_IN_SECONDS};
 
     use super::Time;
     #[test]
@@ -73,4 +87,32 @@ mod tests {
         let difference = (got as i64) - (want as i64).abs();
         assert!(difference < 5);
     }
+
+    #[test]
+    fn now_plus_day_correct() {
+        let got = Time::day_hence().unwrap().as_secs();
+        let want = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .checked_add(Duration::from_secs(DAY_IN_SECONDS.into()))
+            .unwrap()
+            .as_secs();
+
+        assert!(got.abs_diff(want) < 5);
+    }
+
+    #[test]
+    fn refresh_token_hence_correct() {
+        let got = Time::refresh_token_hence().unwrap().as_secs();
+        let want = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .checked_add(Duration::from_secs(
+                REFRESH_TOKEN_LIFETIME_IN_SECONDS.into(),
+            ))
+            .unwrap()
+            .as_secs();
+
+        assert!(got.abs_diff(want) < 5);
+    }
 }
@@ -1,33 +1,90 @@
+use std::{cell::RefCell, sync::Arc};
+
 use migration::DbErr;
-use sea_orm::{DatabaseConnection, DeleteResult, EntityTrait};
-use warp::{filters::BoxedFilter, http::Response};
+use sea_orm::{
+    DatabaseConnection, DeleteResult, EntityTrait, QueryOrder, SqlxSqlitePoolConnection,
+};
+use warp::{filters::BoxedFilter, http::Response, reply};
 
-use entity::{prelude::User, user};
-use gilded_university_server::{connect_to_database, create_gql_filter};
+use entity::{
+    prelude::{LoginAttempt, OidcLogin, User},
+    user,
+};
+use gilded_university_server::{
+    config::DatabaseConfig, connect_to_database, create_gql_filter, create_gql_subscription_filter,
+    get_env, mail::memory::MemoryMailer,
+};
 
 pub async fn make_graphql_filter() -> BoxedFilter<(Response<Vec<u8>>,)> {
+    let (filter, _) = make_graphql_filter_with_mailer().await;
+    filter
+}
+
+pub async fn make_graphql_filter_with_mailer(
+) -> (BoxedFilter<(Response<Vec<u8>>,)>, Arc<MemoryMailer>) {
     let connection = connect_to_test_database().await;
-    user::Entity::delete_many().exec(&connection).await.unwrap();
-    create_gql_filter(connection)
+    let mailer = Arc::new(MemoryMailer::default());
+    (create_gql_filter(connection, mailer.clone()), mailer)
+}
+
+/// The query and subscription filters, sharing a connection as they do when served
+pub async fn make_graphql_and_subscription_filters() -> (
+    BoxedFilter<(Response<Vec<u8>>,)>,
+    BoxedFilter<(reply::Response,)>,
+) {
+    let connection = Arc::new(connect_to_test_database().await);
+    let mailer = Arc::new(MemoryMailer::default());
+    (
+        create_gql_filter(connection.clone(), mailer.clone()),
+        create_gql_subscription_filter(connection, mailer),
+    )
 }
 
 pub async fn delete_records(conn: &DatabaseConnection) -> Result<(), DbErr> {
     user::Entity::delete_many().exec(conn).await?;
+    LoginAttempt::delete_many().exec(conn).await?;
+    OidcLogin::delete_many().exec(conn).await?;
     Ok(())
 }
 
 pub async fn connect_to_test_database() -> DatabaseConnection {
-    let conn = connect_to_database("TEST_DATABASE_URL").await.unwrap();
+    let conn = open_test_database().await;
     delete_records(&conn).await.unwrap();
     conn
 }
 
+// An in-memory SQLite database only lives as long as its connection, so the helpers
+// used within a test share a pool rather than each opening a new, empty database.
+// Every test runs on its own thread with its own runtime, which the pool is tied to.
+pub async fn open_test_database() -> DatabaseConnection {
+    thread_local! {
+        static IN_MEMORY: RefCell<Option<SqlxSqlitePoolConnection>> = const { RefCell::new(None) };
+    }
+
+    let url = get_env("TEST_DATABASE_URL");
+    let options = DatabaseConfig::default();
+    if !url.contains(":memory:") {
+        return connect_to_database(&url, &options).await.unwrap();
+    }
+    if let Some(pool) = IN_MEMORY.with(|shared| shared.borrow().clone()) {
+        return DatabaseConnection::SqlxSqlitePoolConnection(pool);
+    }
+    let conn = connect_to_database(&url, &options).await.unwrap();
+    if let DatabaseConnection::SqlxSqlitePoolConnection(pool) = &conn {
+        IN_MEMORY.with(|shared| *shared.borrow_mut() = Some(pool.clone()));
+    }
+    conn
+}
+
 pub async fn delete_all_users() -> Result<DeleteResult, DbErr> {
-    let conn = connect_to_database("TEST_DATABASE_URL").await.unwrap();
+    let conn = open_test_database().await;
     user::Entity::delete_many().exec(&conn).await
 }
 
 pub async fn get_all_users() -> Result<Vec<user::Model>, DbErr> {
-    let conn = connect_to_database("TEST_DATABASE_URL").await.unwrap();
-    User::find().all(&conn).await
+    let conn = open_test_database().await;
+    User::find()
+        .order_by_asc(user::Column::Name)
+        .all(&conn)
+        .await
 }
@@ -22,6 +22,8 @@ mod integration_database_user {
             password: sea_orm::ActiveValue::Set("testpassword".to_string()),
             status: sea_orm::ActiveValue::Set(Status::Online),
             role: sea_orm::ActiveValue::Set(Role::Guest),
+            email_verified_at: sea_orm::ActiveValue::Set(None),
+            suspended_at: sea_orm::ActiveValue::Set(None),
         };
         let model_two = user::ActiveModel {
             id: sea_orm::ActiveValue::Set(Uuid::new_v4()),
@@ -30,6 +32,8 @@ mod integration_database_user {
             password: sea_orm::ActiveValue::Set("testpassword".to_string()),
             status: sea_orm::ActiveValue::Set(Status::Offline),
             role: sea_orm::ActiveValue::Set(Role::Teacher),
+            email_verified_at: sea_orm::ActiveValue::Set(None),
+            suspended_at: sea_orm::ActiveValue::Set(None),
         };
 
         User::insert_one(model_one, &conn).await.unwrap();
@@ -0,0 +1,39 @@
+#[cfg(test)]
+mod integration_warp_health_check {
+    use dotenvy::dotenv;
+    use serde_json::Value;
+    use warp::test::request;
+
+    use crate::common::connect_to_test_database;
+    use gilded_university_server::health::create_health_filter;
+
+    // To make sure the test steps perform exactly as needed, they are all run in a single test
+    #[tokio::test]
+    async fn health_check() {
+        dotenv().ok();
+        let filter = create_health_filter(connect_to_test_database().await);
+
+        // The process is live
+        let res = request().path("/healthz").reply(&filter).await;
+        assert_eq!(res.status(), 200);
+        let body: Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+
+        // The database is reachable and migrated
+        let res = request().path("/readyz").reply(&filter).await;
+        assert_eq!(res.status(), 200);
+        let body: Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["components"]["database"]["status"], "ok");
+        assert_eq!(body["components"]["migrations"]["status"], "ok");
+
+        // Only GET is served
+        let res = request()
+            .method("POST")
+            .path("/readyz")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), 405);
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+pub mod health;
+pub mod oidc;
 pub mod user;
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -19,6 +21,7 @@ pub struct GQLError {
     pub message: String,
     pub locations: Vec<GQLErrorLocation>,
     pub path: Vec<String>,
+    pub extensions: Option<serde_json::Value>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
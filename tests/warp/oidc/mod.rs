@@ -0,0 +1,123 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use warp::{http::StatusCode, Filter};
+
+use gilded_university_server::{
+    auth::{
+        keys::{JwtKey, KeyManager},
+        token::hash_token,
+    },
+    time::Time,
+};
+
+pub mod oidc_login;
+
+const STUB_PRIVATE_KEY: &[u8] = include_bytes!("../../../src/auth/testdata/rsa_private.pem");
+pub const STUB_CLIENT_ID: &str = "gilded-university";
+pub const STUB_CODE: &str = "authcode";
+
+/// What the stub identity provider asserts about the user, and the values
+/// it learned from the authorization request
+#[derive(Clone, Debug, Default)]
+pub struct StubState {
+    pub nonce: String,
+    pub code_challenge: String,
+    pub sub: String,
+    pub email: String,
+    pub email_verified: bool,
+}
+
+#[derive(Serialize)]
+struct StubIdTokenClaims {
+    iss: String,
+    aud: String,
+    sub: String,
+    exp: u64,
+    iat: u64,
+    nonce: String,
+    email: String,
+    email_verified: bool,
+}
+
+#[derive(Deserialize)]
+struct StubTokenRequest {
+    grant_type: String,
+    code: String,
+    client_id: String,
+    code_verifier: String,
+}
+
+/// Serves discovery, JWKS and token endpoints on an ephemeral local port and
+/// returns the issuer URL
+pub fn start_stub_provider(state: Arc<Mutex<StubState>>) -> String {
+    let issuer = Arc::new(Mutex::new(String::new()));
+    let key = JwtKey::from_pem("stub", Algorithm::RS256, STUB_PRIVATE_KEY).unwrap();
+    let jwks = KeyManager::new(vec![key], "stub").unwrap().jwks();
+
+    let discovery_issuer = issuer.clone();
+    let discovery = warp::path!(".well-known" / "openid-configuration").map(move || {
+        let issuer = discovery_issuer.lock().unwrap().clone();
+        warp::reply::json(&serde_json::json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{}/authorize", issuer),
+            "token_endpoint": format!("{}/token", issuer),
+            "jwks_uri": format!("{}/jwks", issuer),
+        }))
+    });
+    let jwks = warp::path!("jwks").map(move || warp::reply::json(&jwks));
+
+    let token_issuer = issuer.clone();
+    let token = warp::post()
+        .and(warp::path!("token"))
+        .and(warp::body::form())
+        .map(move |form: StubTokenRequest| {
+            let state = state.lock().unwrap().clone();
+            if form.grant_type != "authorization_code"
+                || form.code != STUB_CODE
+                || form.client_id != STUB_CLIENT_ID
+                || hash_token(&form.code_verifier) != state.code_challenge
+            {
+                return warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({ "error": "invalid_grant" })),
+                    StatusCode::BAD_REQUEST,
+                );
+            }
+
+            let now = Time::now().unwrap().as_secs();
+            let claims = StubIdTokenClaims {
+                iss: token_issuer.lock().unwrap().clone(),
+                aud: STUB_CLIENT_ID.to_string(),
+                sub: state.sub,
+                exp: now + 300,
+                iat: now,
+                nonce: state.nonce,
+                email: state.email,
+                email_verified: state.email_verified,
+            };
+            let mut header = Header::new(Algorithm::RS256);
+            header.kid = Some("stub".to_string());
+            let key = EncodingKey::from_rsa_pem(STUB_PRIVATE_KEY).unwrap();
+            let id_token = encode(&header, &claims, &key).unwrap();
+            warp::reply::with_status(
+                warp::reply::json(&serde_json::json!({
+                    "access_token": "access",
+                    "token_type": "Bearer",
+                    "id_token": id_token,
+                })),
+                StatusCode::OK,
+            )
+        });
+
+    let (addr, server): (SocketAddr, _) =
+        warp::serve(discovery.or(jwks).or(token)).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+
+    let url = format!("http://{}", addr);
+    *issuer.lock().unwrap() = url.clone();
+    url
+}
@@ -0,0 +1,206 @@
+#[cfg(test)]
+mod integration_warp_oidc_login {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use dotenvy::dotenv;
+    use sea_orm::{DatabaseConnection, EntityTrait, Set};
+    use serde::Deserialize;
+    use url::Url;
+    use warp::{filters::BoxedFilter, http::Response, reply};
+
+    use crate::{
+        common::{connect_to_test_database, get_all_users},
+        warp::oidc::{start_stub_provider, StubState, STUB_CLIENT_ID, STUB_CODE},
+    };
+    use entity::{
+        prelude::{LinkedIdentity, User},
+        sea_orm_active_enums::Status,
+        user,
+    };
+    use gilded_university_server::{
+        config::OidcConfig,
+        oidc::{create_oidc_filter, http::HyperClient},
+    };
+
+    #[derive(Deserialize, Debug)]
+    struct LoginRes {
+        token: String,
+        #[serde(rename = "refreshToken")]
+        refresh_token: String,
+        #[serde(rename = "mfaPending")]
+        mfa_pending: bool,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct ErrorRes {
+        error: String,
+    }
+
+    fn make_filter(
+        conn: DatabaseConnection,
+        issuer: &str,
+        post_login_redirect: Option<&str>,
+    ) -> BoxedFilter<(reply::Response,)> {
+        let config = OidcConfig {
+            issuer: issuer.to_string(),
+            client_id: STUB_CLIENT_ID.to_string(),
+            client_secret: None,
+            redirect_uri: "http://localhost:8080/auth/oidc/callback".to_string(),
+            scopes: "openid email profile".to_string(),
+            post_login_redirect: post_login_redirect.map(|s| s.to_string()),
+        };
+        create_oidc_filter(conn, config, Arc::new(HyperClient::default()))
+    }
+
+    async fn get(filter: &BoxedFilter<(reply::Response,)>, path: &str) -> Response<Vec<u8>> {
+        let res = warp::test::request().path(path).reply(filter).await;
+        let (parts, body) = res.into_parts();
+        Response::from_parts(parts, body.to_vec())
+    }
+
+    // Plays the part of the browser at the identity provider, returning the state
+    async fn start(
+        filter: &BoxedFilter<(reply::Response,)>,
+        stub: &Arc<Mutex<StubState>>,
+    ) -> String {
+        let res = get(filter, "/auth/oidc/start").await;
+        assert_eq!(res.status(), 302);
+
+        let location = res.headers()["location"].to_str().unwrap();
+        let location = Url::parse(location).unwrap();
+        let params: HashMap<String, String> = location.query_pairs().into_owned().collect();
+        assert!(location.path().ends_with("/authorize"));
+        assert_eq!(params["response_type"], "code");
+        assert_eq!(params["client_id"], STUB_CLIENT_ID);
+        assert_eq!(params["code_challenge_method"], "S256");
+
+        let mut stub = stub.lock().unwrap();
+        stub.nonce = params["nonce"].clone();
+        stub.code_challenge = params["code_challenge"].clone();
+        params["state"].clone()
+    }
+
+    async fn callback(filter: &BoxedFilter<(reply::Response,)>, state: &str) -> Response<Vec<u8>> {
+        let path = format!("/auth/oidc/callback?code={}&state={}", STUB_CODE, state);
+        get(filter, &path).await
+    }
+
+    fn error_message(res: &Response<Vec<u8>>) -> String {
+        let body: ErrorRes = serde_json::from_slice(res.body()).unwrap();
+        body.error
+    }
+
+    async fn sign_out_all(conn: &DatabaseConnection) {
+        for found in User::find().all(conn).await.unwrap() {
+            let mut found: user::ActiveModel = found.into();
+            found.status = Set(Status::Offline);
+            User::update_one(found, conn).await.unwrap();
+        }
+    }
+
+    // To make sure the test steps perform exactly as needed, they are all run in a single test
+    #[tokio::test]
+    async fn test_oidc_login() {
+        dotenv().ok();
+        let conn = connect_to_test_database().await;
+        let stub = Arc::new(Mutex::new(StubState {
+            sub: "subject-one".to_string(),
+            email: "oidc@test.com".to_string(),
+            email_verified: true,
+            ..Default::default()
+        }));
+        let issuer = start_stub_provider(stub.clone());
+        let filter = make_filter(connect_to_test_database().await, &issuer, None);
+
+        // A new user is provisioned on first login
+        let state = start(&filter, &stub).await;
+        let res = callback(&filter, &state).await;
+        assert_eq!(res.status(), 200);
+        let login: LoginRes = serde_json::from_slice(res.body()).unwrap();
+        assert!(!login.token.is_empty());
+        assert!(!login.refresh_token.is_empty());
+        assert!(!login.mfa_pending);
+
+        let users = get_all_users().await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].email, "oidc@test.com");
+        assert_eq!(users[0].name, "oidc");
+        assert_eq!(users[0].status, Status::Online);
+        assert!(users[0].is_email_verified());
+        let linked = LinkedIdentity::find_one(&issuer, "subject-one", &conn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(linked.user_id, users[0].id);
+
+        // The state can only be used once
+        let res = callback(&filter, &state).await;
+        assert_eq!(res.status(), 400);
+        assert_eq!(
+            error_message(&res),
+            "Login request is invalid or has expired"
+        );
+
+        // An unknown state is rejected
+        let res = callback(&filter, "unknownstate").await;
+        assert_eq!(res.status(), 400);
+
+        // A returning user signs in to the linked account
+        sign_out_all(&conn).await;
+        let state = start(&filter, &stub).await;
+        let res = callback(&filter, &state).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(get_all_users().await.unwrap().len(), 1);
+
+        // A PKCE verifier that doesn't match the challenge fails the exchange
+        sign_out_all(&conn).await;
+        let state = start(&filter, &stub).await;
+        stub.lock().unwrap().code_challenge = "wrong".to_string();
+        let res = callback(&filter, &state).await;
+        assert_eq!(res.status(), 400);
+        assert!(error_message(&res).starts_with("Unable to reach identity provider"));
+
+        // An unverified email cannot claim an existing account
+        {
+            let mut stub = stub.lock().unwrap();
+            stub.sub = "subject-two".to_string();
+            stub.email_verified = false;
+        }
+        let state = start(&filter, &stub).await;
+        let res = callback(&filter, &state).await;
+        assert_eq!(res.status(), 400);
+        assert_eq!(
+            error_message(&res),
+            "User with email `oidc@test.com` already exists"
+        );
+
+        // A verified email is linked to the existing account
+        stub.lock().unwrap().email_verified = true;
+        let state = start(&filter, &stub).await;
+        let res = callback(&filter, &state).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(get_all_users().await.unwrap().len(), 1);
+        assert!(LinkedIdentity::find_one(&issuer, "subject-two", &conn)
+            .await
+            .unwrap()
+            .is_some());
+
+        // Tokens are handed to the frontend in the URL fragment when configured
+        sign_out_all(&conn).await;
+        let filter = make_filter(
+            connect_to_test_database().await,
+            &issuer,
+            Some("http://localhost:3000/login"),
+        );
+        let state = start(&filter, &stub).await;
+        let res = callback(&filter, &state).await;
+        assert_eq!(res.status(), 302);
+        let location = res.headers()["location"].to_str().unwrap();
+        assert!(location.starts_with("http://localhost:3000/login#token="));
+        assert!(location.contains("&refreshToken="));
+        assert!(location.ends_with("&mfaPending=false"));
+    }
+}
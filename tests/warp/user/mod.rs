@@ -3,19 +3,30 @@ use sea_orm::{prelude::Uuid, EntityTrait, InsertResult, Set};
 use serde::{Deserialize, Serialize};
 
 use super::GQLResponse;
+use crate::common::open_test_database;
 use entity::{
     prelude::User,
     sea_orm_active_enums::{Role, Status},
     user,
 };
-use gilded_university_server::connect_to_database;
 
+pub mod user_admin;
 pub mod user_integration;
+pub mod user_lockout;
+pub mod user_mfa;
 pub mod user_mutation;
+pub mod user_pagination;
+pub mod user_password_reset;
+pub mod user_permissions;
+pub mod user_profile;
 pub mod user_query;
+pub mod user_refresh_token;
+pub mod user_sessions;
+pub mod user_subscriptions;
+pub mod user_verification;
 
 pub async fn seed_users() -> Result<InsertResult<user::ActiveModel>, DbErr> {
-    let conn = connect_to_database("TEST_DATABASE_URL").await.unwrap();
+    let conn = open_test_database().await;
     let model_one = user::ActiveModel {
         id: Set(Uuid::new_v4()),
         email: Set("test@test.com".to_string()),
@@ -23,6 +34,8 @@ pub async fn seed_users() -> Result<InsertResult<user::ActiveModel>, DbErr> {
         password: Set("testpassword".to_string()),
         status: Set(Status::Online),
         role: Set(Role::Guest),
+        email_verified_at: Set(None),
+        suspended_at: Set(None),
     };
     let model_two = user::ActiveModel {
         id: Set(Uuid::new_v4()),
@@ -31,6 +44,8 @@ pub async fn seed_users() -> Result<InsertResult<user::ActiveModel>, DbErr> {
         password: Set("testpassword".to_string()),
         status: Set(Status::Offline),
         role: Set(Role::Teacher),
+        email_verified_at: Set(None),
+        suspended_at: Set(None),
     };
     User::insert_many(vec![model_one, model_two])
         .exec(&conn)
@@ -46,7 +61,34 @@ pub type GQLUserByIdRes = GQLResponse<GQLUserByIdResponse>;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GQLUsers {
-    pub users: Vec<GQLUserModel>,
+    pub users: GQLUserConnection,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLUserConnection {
+    pub edges: Vec<GQLUserEdge>,
+    #[serde(rename = "pageInfo")]
+    pub page_info: GQLPageInfo,
+    #[serde(rename = "totalCount")]
+    pub total_count: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLUserEdge {
+    pub cursor: String,
+    pub node: GQLUserModel,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLPageInfo {
+    #[serde(rename = "hasNextPage")]
+    pub has_next_page: bool,
+    #[serde(rename = "hasPreviousPage")]
+    pub has_previous_page: bool,
+    #[serde(rename = "startCursor")]
+    pub start_cursor: Option<String>,
+    #[serde(rename = "endCursor")]
+    pub end_cursor: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -76,6 +118,12 @@ type GQLSignupRes = GQLResponse<GQLSignupResponse>;
 type GQLSigninRes = GQLResponse<GQLSigninResponse>;
 #[allow(dead_code)]
 type GQLSignoutRes = GQLResponse<GQLSignoutResponse>;
+#[allow(dead_code)]
+type GQLRefreshSignupRes = GQLResponse<GQLRefreshSignupResponse>;
+#[allow(dead_code)]
+type GQLRefreshSigninRes = GQLResponse<GQLRefreshSigninResponse>;
+#[allow(dead_code)]
+type GQLRefreshTokenRes = GQLResponse<GQLRefreshTokenResponse>;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GQLSigninResponse {
@@ -93,11 +141,258 @@ pub struct GQLAuthResponse {
     pub user: GQLUserModel,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLRefreshSignupResponse {
+    pub signup: GQLRefreshAuthResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLRefreshSigninResponse {
+    pub signin: GQLRefreshAuthResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLRefreshTokenResponse {
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: GQLRefreshAuthResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLRefreshAuthResponse {
+    pub token: String,
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+    pub user: GQLUserModel,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GQLSignoutResponse {
     pub signout: GQLSuccessResponse,
 }
 
+#[allow(dead_code)]
+type GQLRequestPasswordResetRes = GQLResponse<GQLRequestPasswordResetResponse>;
+#[allow(dead_code)]
+type GQLResetPasswordRes = GQLResponse<GQLResetPasswordResponse>;
+
+#[allow(dead_code)]
+type GQLSendVerificationEmailRes = GQLResponse<GQLSendVerificationEmailResponse>;
+#[allow(dead_code)]
+type GQLVerifyEmailRes = GQLResponse<GQLVerifyEmailResponse>;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLSendVerificationEmailResponse {
+    #[serde(rename = "sendVerificationEmail")]
+    pub send_verification_email: GQLSuccessResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLVerifyEmailResponse {
+    #[serde(rename = "verifyEmail")]
+    pub verify_email: GQLSuccessResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLRequestPasswordResetResponse {
+    #[serde(rename = "requestPasswordReset")]
+    pub request_password_reset: GQLSuccessResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLResetPasswordResponse {
+    #[serde(rename = "resetPassword")]
+    pub reset_password: GQLSuccessResponse,
+}
+
+#[allow(dead_code)]
+type GQLEnrollMfaRes = GQLResponse<GQLEnrollMfaResponse>;
+#[allow(dead_code)]
+type GQLConfirmMfaRes = GQLResponse<GQLConfirmMfaResponse>;
+#[allow(dead_code)]
+type GQLMfaSigninRes = GQLResponse<GQLMfaSigninResponse>;
+#[allow(dead_code)]
+type GQLVerifyMfaRes = GQLResponse<GQLVerifyMfaResponse>;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLEnrollMfaResponse {
+    #[serde(rename = "enrollMfa")]
+    pub enroll_mfa: GQLMfaEnrollment,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLMfaEnrollment {
+    pub secret: String,
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLConfirmMfaResponse {
+    #[serde(rename = "confirmMfa")]
+    pub confirm_mfa: GQLRecoveryCodes,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLRecoveryCodes {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLMfaSigninResponse {
+    pub signin: GQLMfaAuthResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLVerifyMfaResponse {
+    #[serde(rename = "verifyMfa")]
+    pub verify_mfa: GQLMfaAuthResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLMfaAuthResponse {
+    pub token: String,
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: Option<String>,
+    #[serde(rename = "mfaPending")]
+    pub mfa_pending: bool,
+}
+
+#[allow(dead_code)]
+type GQLUnlockAccountRes = GQLResponse<GQLUnlockAccountResponse>;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLUnlockAccountResponse {
+    #[serde(rename = "unlockAccount")]
+    pub unlock_account: GQLSuccessResponse,
+}
+
+#[allow(dead_code)]
+type GQLUpdateProfileRes = GQLResponse<GQLUpdateProfileResponse>;
+#[allow(dead_code)]
+type GQLChangePasswordRes = GQLResponse<GQLChangePasswordResponse>;
+#[allow(dead_code)]
+type GQLChangeEmailRes = GQLResponse<GQLChangeEmailResponse>;
+#[allow(dead_code)]
+type GQLConfirmEmailChangeRes = GQLResponse<GQLConfirmEmailChangeResponse>;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLUpdateProfileResponse {
+    #[serde(rename = "updateProfile")]
+    pub update_profile: GQLUserModel,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLChangePasswordResponse {
+    #[serde(rename = "changePassword")]
+    pub change_password: GQLRefreshAuthResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLChangeEmailResponse {
+    #[serde(rename = "changeEmail")]
+    pub change_email: GQLSuccessResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLConfirmEmailChangeResponse {
+    #[serde(rename = "confirmEmailChange")]
+    pub confirm_email_change: GQLUserModel,
+}
+
+#[allow(dead_code)]
+type GQLSetUserRoleRes = GQLResponse<GQLSetUserRoleResponse>;
+#[allow(dead_code)]
+type GQLSuspendUserRes = GQLResponse<GQLSuspendUserResponse>;
+#[allow(dead_code)]
+type GQLReinstateUserRes = GQLResponse<GQLReinstateUserResponse>;
+#[allow(dead_code)]
+type GQLDeleteUserRes = GQLResponse<GQLDeleteUserResponse>;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLManagedUser {
+    pub id: String,
+    pub role: String,
+    pub status: String,
+    pub suspended: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLSetUserRoleResponse {
+    #[serde(rename = "setUserRole")]
+    pub set_user_role: GQLManagedUser,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLSuspendUserResponse {
+    #[serde(rename = "suspendUser")]
+    pub suspend_user: GQLManagedUser,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLReinstateUserResponse {
+    #[serde(rename = "reinstateUser")]
+    pub reinstate_user: GQLManagedUser,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLDeleteUserResponse {
+    #[serde(rename = "deleteUser")]
+    pub delete_user: GQLSuccessResponse,
+}
+
+#[allow(dead_code)]
+type GQLGrantPermissionRes = GQLResponse<GQLGrantPermissionResponse>;
+#[allow(dead_code)]
+type GQLRevokePermissionRes = GQLResponse<GQLRevokePermissionResponse>;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLGrantPermissionResponse {
+    #[serde(rename = "grantPermission")]
+    pub grant_permission: GQLSuccessResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLRevokePermissionResponse {
+    #[serde(rename = "revokePermission")]
+    pub revoke_permission: GQLSuccessResponse,
+}
+
+#[allow(dead_code)]
+type GQLMySessionsRes = GQLResponse<GQLMySessionsResponse>;
+#[allow(dead_code)]
+type GQLRevokeSessionRes = GQLResponse<GQLRevokeSessionResponse>;
+#[allow(dead_code)]
+type GQLSignoutEverywhereRes = GQLResponse<GQLSignoutEverywhereResponse>;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLMySessionsResponse {
+    #[serde(rename = "mySessions")]
+    pub my_sessions: Vec<GQLSessionModel>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLSessionModel {
+    pub id: String,
+    #[serde(rename = "deviceLabel")]
+    pub device_label: Option<String>,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    pub current: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLRevokeSessionResponse {
+    #[serde(rename = "revokeSession")]
+    pub revoke_session: GQLSuccessResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GQLSignoutEverywhereResponse {
+    #[serde(rename = "signoutEverywhere")]
+    pub signout_everywhere: GQLSuccessResponse,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GQLSuccessResponse {
     pub success: bool,
@@ -0,0 +1,292 @@
+#[cfg(test)]
+mod integration_warp_user_admin {
+    use dotenvy::dotenv;
+    use sea_orm::{prelude::Uuid, Set};
+    use warp::{filters::BoxedFilter, http::Response};
+
+    use crate::{
+        common::{connect_to_test_database, get_all_users, make_graphql_filter},
+        warp::{
+            user::{
+                GQLDeleteUserRes, GQLRefreshSignupRes, GQLRefreshTokenRes, GQLReinstateUserRes,
+                GQLSetUserRoleRes, GQLSigninRes, GQLSuspendUserRes,
+            },
+            GQLRequest,
+        },
+    };
+    use entity::{
+        prelude::{TokenRevocation, User},
+        sea_orm_active_enums::{Role, Status},
+        user,
+    };
+    use gilded_university_server::{testutils::create_test_jwt, time::Time};
+
+    fn query(query: &str) -> GQLRequest<()> {
+        GQLRequest {
+            query: query.to_string(),
+            variables: None,
+        }
+    }
+
+    async fn send(
+        filter: &BoxedFilter<(Response<Vec<u8>>,)>,
+        token: Option<&str>,
+        body: &str,
+    ) -> Response<Vec<u8>> {
+        let mut request = warp::test::request().method("POST").json(&query(body));
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        request.filter(filter).await.unwrap()
+    }
+
+    fn signup(email: &str) -> String {
+        format!(
+            r#"
+                mutation {{
+                    signup(email: "{}", name: "test user", password: "testpassword") {{
+                        token
+                        refreshToken
+                        user {{
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }}
+                    }}
+                }}
+            "#,
+            email
+        )
+    }
+
+    fn manage(mutation: &str, id: &str, args: &str) -> String {
+        format!(
+            r#"
+                mutation {{
+                    {}(id: "{}"{}) {{
+                        id
+                        role
+                        status
+                        suspended
+                    }}
+                }}
+            "#,
+            mutation, id, args
+        )
+    }
+
+    const SIGNIN: &str = r#"
+        mutation {
+            signin(email: "test@test.com", password: "testpassword") {
+                token
+                user {
+                    id
+                    email
+                    name
+                    role
+                    status
+                }
+            }
+        }
+    "#;
+
+    // To make sure the test steps perform exactly as needed
+    // i.e. inserting/deleting records sequentially
+    // We will use one function that will perform all the test
+    #[tokio::test]
+    async fn manage_users() {
+        dotenv().ok();
+        let conn = connect_to_test_database().await;
+        let filter = make_graphql_filter().await;
+
+        let response = send(&filter, None, &signup("admin@test.com")).await;
+        let response_json: GQLRefreshSignupRes = serde_json::from_slice(response.body()).unwrap();
+        let admin_id = response_json.data.unwrap().signup.user.id;
+        let admin = User::find_one_by_email("admin@test.com", &conn)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut admin: user::ActiveModel = admin.into();
+        admin.role = Set(Role::Admin);
+        User::update_one(admin, &conn).await.unwrap();
+        // Demoting the Admin revokes their tokens, so the requests are made by another
+        let exp = Time::hour_hence().unwrap().as_secs();
+        let admin_token = create_test_jwt(&Uuid::new_v4(), &Role::Admin, exp);
+
+        let response = send(&filter, None, &signup("test@test.com")).await;
+        let response_json: GQLRefreshSignupRes = serde_json::from_slice(response.body()).unwrap();
+        let signup = response_json.data.unwrap().signup;
+        let user_id = signup.user.id;
+        // Users who hide their presence stay hidden when they are signed out
+        let hidden = User::find_one_by_email("test@test.com", &conn)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut hidden: user::ActiveModel = hidden.into();
+        hidden.status = Set(Status::Hidden);
+        User::update_one(hidden, &conn).await.unwrap();
+
+        // The only Admin cannot be demoted, suspended or deleted
+        for body in [
+            manage("setUserRole", &admin_id, ", role: GUEST"),
+            manage("suspendUser", &admin_id, ""),
+        ] {
+            let response = send(&filter, Some(&admin_token), &body).await;
+            let response_json: GQLSuspendUserRes = serde_json::from_slice(response.body()).unwrap();
+            assert_eq!(
+                response_json.errors.unwrap()[0].message,
+                "The last active Admin cannot be demoted, suspended or deleted"
+            );
+        }
+
+        let response = send(
+            &filter,
+            Some(&admin_token),
+            &manage("suspendUser", &user_id, ""),
+        )
+        .await;
+        let response_json: GQLSuspendUserRes = serde_json::from_slice(response.body()).unwrap();
+        let suspended = response_json.data.unwrap().suspend_user;
+        assert!(suspended.suspended);
+        assert_eq!(suspended.status, "HIDDEN");
+
+        // Suspension signs the user out and keeps them out
+        let response = send(&filter, None, SIGNIN).await;
+        let response_json: GQLSigninRes = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            response_json.errors.unwrap()[0].message,
+            "Account has been suspended"
+        );
+
+        let refresh = format!(
+            r#"
+                mutation {{
+                    refreshToken(token: "{}") {{
+                        token
+                        refreshToken
+                        user {{
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }}
+                    }}
+                }}
+            "#,
+            signup.refresh_token
+        );
+        let response = send(&filter, None, &refresh).await;
+        let response_json: GQLRefreshTokenRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.errors.is_some());
+
+        let response = send(
+            &filter,
+            Some(&admin_token),
+            &manage("reinstateUser", &user_id, ""),
+        )
+        .await;
+        let response_json: GQLReinstateUserRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(!response_json.data.unwrap().reinstate_user.suspended);
+
+        let response = send(&filter, None, SIGNIN).await;
+        let response_json: GQLSigninRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.errors.is_none());
+
+        // Changing the role signs the user out so the next token carries it
+        let response = send(
+            &filter,
+            Some(&admin_token),
+            &manage("setUserRole", &user_id, ", role: ADMIN"),
+        )
+        .await;
+        let response_json: GQLSetUserRoleRes = serde_json::from_slice(response.body()).unwrap();
+        let promoted = response_json.data.unwrap().set_user_role;
+        assert_eq!(promoted.role, "ADMIN");
+        assert_eq!(promoted.status, "HIDDEN");
+
+        // With a second Admin either can be demoted, but not both at once
+        let demotions: Vec<_> = [admin_id.clone(), user_id.clone()]
+            .into_iter()
+            .map(|id| {
+                let filter = filter.clone();
+                let token = admin_token.clone();
+                tokio::spawn(async move {
+                    let body = manage("setUserRole", &id, ", role: TEACHER");
+                    send(&filter, Some(&token), &body).await
+                })
+            })
+            .collect();
+        let mut demoted = vec![];
+        for demotion in demotions {
+            let response = demotion.await.unwrap();
+            let response_json: GQLSetUserRoleRes = serde_json::from_slice(response.body()).unwrap();
+            if let Some(data) = response_json.data {
+                assert_eq!(data.set_user_role.role, "TEACHER");
+                demoted.push(data.set_user_role.id);
+            }
+        }
+        assert_eq!(demoted.len(), 1);
+        let (demoted_id, remaining_id, demoted_status) = match demoted[0] == admin_id {
+            true => (admin_id, user_id, Status::Offline),
+            false => (user_id, admin_id, Status::Hidden),
+        };
+
+        let response = send(
+            &filter,
+            Some(&admin_token),
+            &format!(
+                r#"
+                    mutation {{
+                        deleteUser(id: "{}") {{
+                            success
+                        }}
+                    }}
+                "#,
+                remaining_id
+            ),
+        )
+        .await;
+        let response_json: GQLDeleteUserRes = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            response_json.errors.unwrap()[0].message,
+            "The last active Admin cannot be demoted, suspended or deleted"
+        );
+
+        let found = User::find_one_by_id(&Uuid::parse_str(&demoted_id).unwrap(), &conn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.status, demoted_status);
+        let response = send(
+            &filter,
+            Some(&admin_token),
+            &format!(
+                r#"
+                    mutation {{
+                        deleteUser(id: "{}") {{
+                            success
+                        }}
+                    }}
+                "#,
+                demoted_id
+            ),
+        )
+        .await;
+        let response_json: GQLDeleteUserRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.unwrap().delete_user.success);
+
+        let users = get_all_users().await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].id.to_string(), remaining_id);
+
+        // The revocation outlives the user so that other instances still load it
+        let now = Time::now().unwrap().as_secs() as i64;
+        let revocations = TokenRevocation::find_unexpired(now, &conn).await.unwrap();
+        assert!(revocations
+            .iter()
+            .any(|r| r.user_id.to_string() == demoted_id && r.jti.is_none()));
+    }
+}
@@ -25,6 +25,9 @@ mod integration_warp_user_integration {
     async fn user_mutation() {
         dotenv().ok();
         let filter = make_graphql_filter().await;
+        // Only admins can see every field of other users
+        let now_plus_hour = Time::hour_hence().unwrap().as_secs();
+        let admin_token = create_test_jwt(&Uuid::new_v4(), &Role::Admin, now_plus_hour);
 
         let body: GQLRequest<()> = GQLRequest {
             query: r#"
@@ -94,7 +97,10 @@ mod integration_warp_user_integration {
         assert!(response_json.errors.is_some());
 
         let errors = response_json.errors.unwrap();
-        assert_eq!(errors[0].message, "Unable to complete request");
+        assert_eq!(
+            errors[0].message,
+            "User with email `test@test.com` already exists"
+        );
         assert_eq!(errors[0].path[0], "signup");
 
         let body: GQLRequest<()> = GQLRequest {
@@ -138,11 +144,23 @@ mod integration_warp_user_integration {
             query: r#"
                 query {
                     users {
-                        id
-                        email
-                        name
-                        role
-                        status
+                        edges {
+                            cursor
+                            node {
+                                id
+                                email
+                                name
+                                role
+                                status
+                            }
+                        }
+                        pageInfo {
+                            hasNextPage
+                            hasPreviousPage
+                            startCursor
+                            endCursor
+                        }
+                        totalCount
                     }
             }"#
             .to_string(),
@@ -150,6 +168,7 @@ mod integration_warp_user_integration {
         };
         let response = warp::test::request()
             .method("POST")
+            .header("Authorization", format!("Bearer {}", admin_token))
             .json(&body)
             .filter(&filter)
             .await
@@ -160,10 +179,10 @@ mod integration_warp_user_integration {
         assert!(response_json.errors.is_none());
 
         let data = response_json.data.unwrap();
-        let user1 = &data.users[0];
-        let user2 = &data.users[1];
+        let user1 = &data.users.edges[0].node;
+        let user2 = &data.users.edges[1].node;
 
-        let id2 = data.users[1].id.clone();
+        let id2 = data.users.edges[1].node.id.clone();
 
         assert_eq!(user1.email, "test@test.com");
         assert_eq!(user1.name, "test user");
@@ -227,6 +246,7 @@ mod integration_warp_user_integration {
         };
         let response = warp::test::request()
             .method("POST")
+            .header("Authorization", format!("Bearer {}", admin_token))
             .json(&body)
             .filter(&filter)
             .await
@@ -262,6 +282,7 @@ mod integration_warp_user_integration {
         };
         let response = warp::test::request()
             .method("POST")
+            .header("Authorization", format!("Bearer {}", admin_token))
             .json(&body)
             .filter(&filter)
             .await
@@ -331,6 +352,7 @@ mod integration_warp_user_integration {
         };
         let response = warp::test::request()
             .method("POST")
+            .header("Authorization", format!("Bearer {}", admin_token))
             .json(&body)
             .filter(&filter)
             .await
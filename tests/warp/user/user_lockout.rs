@@ -0,0 +1,196 @@
+#[cfg(test)]
+mod integration_warp_user_lockout {
+    use dotenvy::dotenv;
+    use sea_orm::prelude::Uuid;
+
+    use crate::{
+        common::{delete_all_users, make_graphql_filter, open_test_database},
+        warp::{
+            user::{GQLSigninRes, GQLSignoutRes, GQLSignupRes, GQLUnlockAccountRes},
+            GQLRequest,
+        },
+    };
+    use entity::{
+        prelude::LoginAttempt,
+        sea_orm_active_enums::{AttemptScope, Role},
+    };
+    use gilded_university_server::{testutils::create_test_jwt, time::Time};
+
+    fn query(query: &str) -> GQLRequest<()> {
+        GQLRequest {
+            query: query.to_string(),
+            variables: None,
+        }
+    }
+
+    fn signin(password: &str) -> GQLRequest<()> {
+        query(&format!(
+            r#"
+                mutation {{
+                    signin(email: "test@test.com", password: "{}") {{
+                        token
+                        user {{
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }}
+                    }}
+                }}
+            "#,
+            password
+        ))
+    }
+
+    // To make sure the test steps perform exactly as needed
+    // i.e. inserting/deleting records sequentially
+    // We will use one function that will perform all the test
+    #[tokio::test]
+    async fn account_lockout() {
+        dotenv().ok();
+        let filter = make_graphql_filter().await;
+
+        let response = warp::test::request()
+            .method("POST")
+            .json(&query(
+                r#"
+                mutation {
+                    signup(email: "test@test.com", name:"test user", password:"testpassword") {
+                        token
+                        user {
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }
+                    }
+                }
+            "#,
+            ))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLSignupRes = serde_json::from_slice(response.body()).unwrap();
+        let token = response_json.data.unwrap().signup.token;
+
+        let response = warp::test::request()
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&query(
+                r#"
+                mutation {
+                    signout(email: "test@test.com") {
+                        success
+                    }
+                }
+            "#,
+            ))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLSignoutRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.unwrap().signout.success);
+
+        // Guesses made at the same time are all counted
+        let guesses: Vec<_> = (0..5)
+            .map(|_| {
+                let filter = filter.clone();
+                tokio::spawn(async move {
+                    warp::test::request()
+                        .method("POST")
+                        .json(&signin("wrongpassword"))
+                        .filter(&filter)
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+        for guess in guesses {
+            let response = guess.await.unwrap();
+            let response_json: GQLSigninRes = serde_json::from_slice(response.body()).unwrap();
+            let errors = response_json.errors.unwrap();
+            assert_eq!(errors[0].message, "Incorrect email or password");
+        }
+
+        // Even the correct password is rejected while the account is locked
+        let response = warp::test::request()
+            .method("POST")
+            .json(&signin("testpassword"))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLSigninRes = serde_json::from_slice(response.body()).unwrap();
+        let errors = response_json.errors.unwrap();
+        assert!(errors[0]
+            .message
+            .starts_with("Too many failed signin attempts, try again in"));
+        let extensions = errors[0].extensions.as_ref().unwrap();
+        assert_eq!(extensions["code"], "TOO_MANY_REQUESTS");
+        assert_eq!(extensions["reason"], "ACCOUNT_LOCKED");
+        assert!(extensions["retryAfter"].as_i64().unwrap() > 0);
+
+        let exp = Time::hour_hence().unwrap().as_secs();
+        let admin_token = create_test_jwt(&Uuid::new_v4(), &Role::Admin, exp);
+        let response = warp::test::request()
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", admin_token))
+            .json(&query(
+                r#"
+                mutation {
+                    unlockAccount(email: "test@test.com") {
+                        success
+                    }
+                }
+            "#,
+            ))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLUnlockAccountRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.unwrap().unlock_account.success);
+
+        let response = warp::test::request()
+            .method("POST")
+            .json(&signin("testpassword"))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLSigninRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.errors.is_none());
+        assert_eq!(
+            response_json.data.unwrap().signin.user.email,
+            "test@test.com"
+        );
+
+        // A lockout on the IP address is only lifted when it is given
+        let conn = open_test_database().await;
+        let now = Time::now().unwrap().as_secs() as i64;
+        let attempt = LoginAttempt::create_active_model(AttemptScope::Ip, "10.0.0.1", now, None);
+        LoginAttempt::insert_one(attempt, &conn).await.unwrap();
+        let response = warp::test::request()
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", admin_token))
+            .json(&query(
+                r#"
+                mutation {
+                    unlockAccount(email: "test@test.com", ipAddress: "10.0.0.1") {
+                        success
+                    }
+                }
+            "#,
+            ))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLUnlockAccountRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.unwrap().unlock_account.success);
+        let found = LoginAttempt::find_one(AttemptScope::Ip, "10.0.0.1", &conn)
+            .await
+            .unwrap();
+        assert!(found.is_none());
+
+        delete_all_users().await.unwrap();
+    }
+}
@@ -0,0 +1,251 @@
+#[cfg(test)]
+mod integration_warp_user_mfa {
+    use dotenvy::dotenv;
+    use warp::{filters::BoxedFilter, http::Response};
+
+    use crate::{
+        common::{delete_all_users, make_graphql_filter},
+        warp::{
+            user::{
+                GQLConfirmMfaRes, GQLEnrollMfaRes, GQLMfaSigninRes, GQLRefreshSignupRes,
+                GQLRefreshTokenRes, GQLSignoutRes, GQLVerifyMfaRes,
+            },
+            GQLRequest,
+        },
+    };
+    use gilded_university_server::{
+        auth::{jwt::get_claims_from_token, totp::code_at},
+        time::Time,
+    };
+
+    fn query(query: &str) -> GQLRequest<()> {
+        GQLRequest {
+            query: query.to_string(),
+            variables: None,
+        }
+    }
+
+    fn signin() -> GQLRequest<()> {
+        query(
+            r#"
+                mutation {
+                    signin(email: "test@test.com", password: "testpassword") {
+                        token
+                        refreshToken
+                        mfaPending
+                    }
+                }
+            "#,
+        )
+    }
+
+    fn signout() -> GQLRequest<()> {
+        query(
+            r#"
+                mutation {
+                    signout(email: "test@test.com") {
+                        success
+                    }
+                }
+            "#,
+        )
+    }
+
+    fn verify_mfa(code: &str) -> GQLRequest<()> {
+        query(&format!(
+            r#"
+                mutation {{
+                    verifyMfa(code: "{}") {{
+                        token
+                        refreshToken
+                        mfaPending
+                    }}
+                }}
+            "#,
+            code
+        ))
+    }
+
+    fn refresh(token: &str) -> GQLRequest<()> {
+        query(&format!(
+            r#"
+                mutation {{
+                    refreshToken(token: "{}") {{
+                        token
+                        refreshToken
+                        user {{
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }}
+                    }}
+                }}
+            "#,
+            token
+        ))
+    }
+
+    async fn send(
+        filter: &BoxedFilter<(Response<Vec<u8>>,)>,
+        token: Option<&str>,
+        body: &GQLRequest<()>,
+    ) -> Response<Vec<u8>> {
+        let mut request = warp::test::request().method("POST").json(body);
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        request.filter(filter).await.unwrap()
+    }
+
+    // To make sure the test steps perform exactly as needed
+    // i.e. inserting/deleting records sequentially
+    // We will use one function that will perform all the test
+    #[tokio::test]
+    async fn mfa_enrollment_and_signin() {
+        dotenv().ok();
+        let filter = make_graphql_filter().await;
+
+        let body = query(
+            r#"
+                mutation {
+                    signup(email: "test@test.com", name:"test user", password:"testpassword") {
+                        token
+                        refreshToken
+                        user {
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }
+                    }
+                }
+            "#,
+        );
+        let response = send(&filter, None, &body).await;
+        let response_json: GQLRefreshSignupRes = serde_json::from_slice(response.body()).unwrap();
+        let signup = response_json.data.unwrap().signup;
+        let token = signup.token;
+
+        let body = query(
+            r#"
+                mutation {
+                    enrollMfa {
+                        secret
+                        otpauthUri
+                    }
+                }
+            "#,
+        );
+        let response = send(&filter, Some(&token), &body).await;
+        let response_json: GQLEnrollMfaRes = serde_json::from_slice(response.body()).unwrap();
+        let enrollment = response_json.data.unwrap().enroll_mfa;
+        assert!(enrollment
+            .otpauth_uri
+            .starts_with("otpauth://totp/Gilded%20University:test@test.com?secret="));
+
+        let now = Time::now().unwrap().as_secs();
+        let code = code_at(&enrollment.secret, now).unwrap();
+        let body = query(&format!(
+            r#"
+                mutation {{
+                    confirmMfa(code: "{}") {{
+                        recoveryCodes
+                    }}
+                }}
+            "#,
+            code
+        ));
+        let response = send(&filter, Some(&token), &body).await;
+        let response_json: GQLConfirmMfaRes = serde_json::from_slice(response.body()).unwrap();
+        let recovery_codes = response_json.data.unwrap().confirm_mfa.recovery_codes;
+        assert_eq!(recovery_codes.len(), 10);
+
+        // Sessions started before enrolling never passed the second factor
+        let response = send(&filter, None, &refresh(&signup.refresh_token)).await;
+        let response_json: GQLRefreshTokenRes = serde_json::from_slice(response.body()).unwrap();
+        let refreshed = response_json.data.unwrap().refresh_token.token;
+        assert!(!get_claims_from_token(&refreshed).unwrap().mfa_authenticated);
+
+        let response = send(&filter, Some(&token), &signout()).await;
+        let response_json: GQLSignoutRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.unwrap().signout.success);
+
+        // Signin only returns a token that can be used to verify the second factor
+        let response = send(&filter, None, &signin()).await;
+        let response_json: GQLMfaSigninRes = serde_json::from_slice(response.body()).unwrap();
+        let data = response_json.data.unwrap().signin;
+        assert!(data.mfa_pending);
+        assert!(data.refresh_token.is_none());
+        let pending = data.token;
+
+        let response = send(&filter, Some(&pending), &signout()).await;
+        let response_json: GQLSignoutRes = serde_json::from_slice(response.body()).unwrap();
+        let errors = response_json.errors.unwrap();
+        assert_eq!(
+            errors[0].message,
+            "Two-factor authentication must be completed"
+        );
+
+        let response = send(&filter, Some(&pending), &verify_mfa("wrong-code")).await;
+        let response_json: GQLVerifyMfaRes = serde_json::from_slice(response.body()).unwrap();
+        let errors = response_json.errors.unwrap();
+        assert_eq!(errors[0].message, "Invalid two-factor authentication code");
+
+        // The code used to confirm enrollment cannot be used again,
+        // but the code for the next time step is accepted
+        let response = send(&filter, Some(&pending), &verify_mfa(&code)).await;
+        let response_json: GQLVerifyMfaRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.errors.is_some());
+
+        let next_code = code_at(&enrollment.secret, now + 30).unwrap();
+        let response = send(&filter, Some(&pending), &verify_mfa(&next_code)).await;
+        let response_json: GQLVerifyMfaRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.errors.is_none());
+        let data = response_json.data.unwrap().verify_mfa;
+        assert!(!data.mfa_pending);
+        let token = data.token;
+
+        let response = send(&filter, None, &refresh(&data.refresh_token.unwrap())).await;
+        let response_json: GQLRefreshTokenRes = serde_json::from_slice(response.body()).unwrap();
+        let refreshed = response_json.data.unwrap().refresh_token.token;
+        assert!(get_claims_from_token(&refreshed).unwrap().mfa_authenticated);
+
+        // The pending token cannot be reused
+        let response = send(&filter, Some(&pending), &verify_mfa(&recovery_codes[0])).await;
+        let response_json: GQLVerifyMfaRes = serde_json::from_slice(response.body()).unwrap();
+        let errors = response_json.errors.unwrap();
+        assert_eq!(errors[0].message, "Token has been revoked");
+
+        let response = send(&filter, Some(&token), &signout()).await;
+        let response_json: GQLSignoutRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.unwrap().signout.success);
+
+        // Recovery codes can be used in place of a TOTP code once
+        let response = send(&filter, None, &signin()).await;
+        let response_json: GQLMfaSigninRes = serde_json::from_slice(response.body()).unwrap();
+        let pending = response_json.data.unwrap().signin.token;
+
+        let response = send(&filter, Some(&pending), &verify_mfa(&recovery_codes[0])).await;
+        let response_json: GQLVerifyMfaRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.errors.is_none());
+        let token = response_json.data.unwrap().verify_mfa.token;
+
+        let response = send(&filter, Some(&token), &signout()).await;
+        let response_json: GQLSignoutRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.unwrap().signout.success);
+
+        let response = send(&filter, None, &signin()).await;
+        let response_json: GQLMfaSigninRes = serde_json::from_slice(response.body()).unwrap();
+        let pending = response_json.data.unwrap().signin.token;
+
+        let response = send(&filter, Some(&pending), &verify_mfa(&recovery_codes[0])).await;
+        let response_json: GQLVerifyMfaRes = serde_json::from_slice(response.body()).unwrap();
+        let errors = response_json.errors.unwrap();
+        assert_eq!(errors[0].message, "Invalid two-factor authentication code");
+
+        delete_all_users().await.unwrap();
+    }
+}
@@ -84,7 +84,10 @@ mod integration_warp_user_mutation {
         assert!(response_json.errors.is_some());
 
         let errors = response_json.errors.unwrap();
-        assert_eq!(errors[0].message, "Unable to complete request");
+        assert_eq!(
+            errors[0].message,
+            "User with email `test@test.com` already exists"
+        );
         assert_eq!(errors[0].path[0], "signup");
 
         let users = get_all_users().await.unwrap();
@@ -185,6 +188,22 @@ mod integration_warp_user_mutation {
         let data = response_json.data.unwrap();
         assert!(data.signout.success);
 
+        // The token used to sign out can no longer be used
+        let response = warp::test::request()
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&body)
+            .filter(&filter)
+            .await
+            .unwrap();
+
+        let response_json: GQLSignoutRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.is_none());
+
+        let errors = response_json.errors.unwrap();
+        assert_eq!(errors[0].message, "Token has been revoked");
+        assert_eq!(errors[0].path[0], "signout");
+
         let users = get_all_users().await.unwrap();
         let user1 = &users[0];
 
@@ -225,6 +244,9 @@ mod integration_warp_user_mutation {
         let errors = response_json.errors.unwrap();
         assert_eq!(errors[0].message, "Incorrect email or password");
         assert_eq!(errors[0].path[0], "signin");
+        let extensions = errors[0].extensions.as_ref().unwrap();
+        assert_eq!(extensions["code"], "UNAUTHENTICATED");
+        assert_eq!(extensions["reason"], "INCORRECT_EMAIL_OR_PASSWORD");
 
         let body: GQLRequest<()> = GQLRequest {
             query: r#"
@@ -329,13 +351,13 @@ mod integration_warp_user_mutation {
             .await
             .unwrap();
 
+        // Signing in again from another device starts a second session
         let response_json: GQLSigninRes = serde_json::from_slice(response.body()).unwrap();
-        assert!(response_json.data.is_none());
-        assert!(response_json.errors.is_some());
+        assert!(response_json.errors.is_none());
 
-        let errors = response_json.errors.unwrap();
-        assert_eq!(errors[0].message, "Unable to complete request");
-        assert_eq!(errors[0].path[0], "signin");
+        let data = response_json.data.unwrap();
+        assert!(!data.signin.token.is_empty());
+        assert_eq!(data.signin.user.status, "ONLINE");
 
         delete_all_users().await.unwrap();
     }
@@ -0,0 +1,248 @@
+#[cfg(test)]
+mod integration_warp_user_pagination {
+    use dotenvy::dotenv;
+    use entity::{
+        prelude::User,
+        sea_orm_active_enums::{Role, Status},
+        user,
+    };
+    use gilded_university_server::{testutils::create_test_jwt, time::Time};
+    use sea_orm::{prelude::Uuid, EntityTrait, Set};
+    use serde_json::Value;
+    use warp::{filters::BoxedFilter, http::Response};
+
+    use crate::{
+        common::{connect_to_test_database, delete_all_users, make_graphql_filter},
+        warp::{
+            user::{GQLUserConnection, GQLUsersRes},
+            GQLRequest,
+        },
+    };
+
+    async fn seed_roster() {
+        let conn = connect_to_test_database().await;
+        let roster = [
+            ("Ada", "ada@uni.edu", Role::Teacher, Status::Online),
+            ("Ben", "ben@uni.edu", Role::Student, Status::Offline),
+            ("Cleo", "cleo@uni.edu", Role::Student, Status::Online),
+            ("Dan", "dan@other.org", Role::Student, Status::Hidden),
+            ("Eve", "eve@uni.edu", Role::Admin, Status::Offline),
+        ];
+        let models = roster
+            .iter()
+            .map(|(name, email, role, status)| user::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                email: Set(email.to_string()),
+                name: Set(name.to_string()),
+                password: Set("testpassword".to_string()),
+                status: Set(status.to_owned()),
+                role: Set(role.to_owned()),
+                email_verified_at: Set(None),
+                suspended_at: Set(None),
+            });
+        User::insert_many(models).exec(&conn).await.unwrap();
+    }
+
+    fn token(role: &Role) -> String {
+        create_test_jwt(&Uuid::new_v4(), role, Time::hour_hence().unwrap().as_secs())
+    }
+
+    async fn users(
+        filter: &BoxedFilter<(Response<Vec<u8>>,)>,
+        args: &str,
+    ) -> Result<GQLUserConnection, String> {
+        let body: GQLRequest<()> = GQLRequest {
+            query: format!(
+                r#"
+                    query {{
+                        users({}) {{
+                            edges {{
+                                cursor
+                                node {{
+                                    id
+                                    email
+                                    name
+                                    role
+                                    status
+                                }}
+                            }}
+                            pageInfo {{
+                                hasNextPage
+                                hasPreviousPage
+                                startCursor
+                                endCursor
+                            }}
+                            totalCount
+                        }}
+                    }}
+                "#,
+                args
+            ),
+            variables: None,
+        };
+        let response = warp::test::request()
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", token(&Role::Admin)))
+            .json(&body)
+            .filter(filter)
+            .await
+            .unwrap();
+
+        let response_json: GQLUsersRes = serde_json::from_slice(response.body()).unwrap();
+        match response_json.errors {
+            Some(errors) => Err(errors[0].message.to_owned()),
+            None => Ok(response_json.data.unwrap().users),
+        }
+    }
+
+    fn names(connection: &GQLUserConnection) -> Vec<&str> {
+        connection
+            .edges
+            .iter()
+            .map(|edge| edge.node.name.as_str())
+            .collect()
+    }
+
+    // To make sure the test steps perform exactly as needed
+    // i.e. inserting/deleting records sequentially
+    // We will use one function that will perform all the test
+    #[tokio::test]
+    async fn user_pagination() {
+        dotenv().ok();
+        let filter = make_graphql_filter().await;
+        seed_roster().await;
+
+        let page = users(&filter, "first: 2").await.unwrap();
+        assert_eq!(names(&page), vec!["Ada", "Ben"]);
+        assert_eq!(page.total_count, 5);
+        assert!(page.page_info.has_next_page);
+        assert!(!page.page_info.has_previous_page);
+
+        let after = page.page_info.end_cursor.unwrap();
+        let page = users(&filter, &format!(r#"first: 2, after: "{}""#, after))
+            .await
+            .unwrap();
+        assert_eq!(names(&page), vec!["Cleo", "Dan"]);
+        assert!(page.page_info.has_next_page);
+        assert!(page.page_info.has_previous_page);
+
+        let after = page.page_info.end_cursor.unwrap();
+        let page = users(&filter, &format!(r#"first: 2, after: "{}""#, after))
+            .await
+            .unwrap();
+        assert_eq!(names(&page), vec!["Eve"]);
+        assert!(!page.page_info.has_next_page);
+
+        let page = users(&filter, "orderBy: { field: EMAIL, direction: DESC }")
+            .await
+            .unwrap();
+        assert_eq!(names(&page), vec!["Eve", "Dan", "Cleo", "Ben", "Ada"]);
+
+        // A cursor only continues the ordering it was created for
+        let cursor = page.edges[0].cursor.to_owned();
+        let got = users(&filter, &format!(r#"after: "{}""#, cursor)).await;
+        assert_eq!(
+            got.err().unwrap(),
+            "Cursor is invalid or was created for a different ordering"
+        );
+        let page = users(
+            &filter,
+            &format!(
+                r#"first: 2, after: "{}", orderBy: {{ field: EMAIL, direction: DESC }}"#,
+                cursor
+            ),
+        )
+        .await
+        .unwrap();
+        assert_eq!(names(&page), vec!["Dan", "Cleo"]);
+
+        let page = users(&filter, "filter: { role: STUDENT, status: ONLINE }")
+            .await
+            .unwrap();
+        assert_eq!(names(&page), vec!["Cleo"]);
+        assert_eq!(page.total_count, 1);
+
+        let page = users(&filter, r#"filter: { search: "UNI.EDU" }"#)
+            .await
+            .unwrap();
+        assert_eq!(names(&page), vec!["Ada", "Ben", "Cleo", "Eve"]);
+
+        // Wildcards are matched literally
+        let page = users(&filter, r#"filter: { search: "%" }"#).await.unwrap();
+        assert_eq!(page.total_count, 0);
+
+        let got = users(&filter, "first: 101").await;
+        assert_eq!(got.err().unwrap(), "`first` must be between 0 and 100");
+
+        // Students can neither search by email nor see emails or who is hidden
+        let body: GQLRequest<()> = GQLRequest {
+            query: r#"
+                query {
+                    users(filter: { status: OFFLINE, search: "OTHER.ORG" }) {
+                        edges {
+                            node {
+                                name
+                                email
+                                status
+                            }
+                        }
+                    }
+                    hidden: users(filter: { status: HIDDEN }) {
+                        totalCount
+                    }
+                }
+            "#
+            .to_string(),
+            variables: None,
+        };
+        let response = warp::test::request()
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", token(&Role::Student)))
+            .json(&body)
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            response_json["data"]["users"]["edges"],
+            Value::Array(vec![])
+        );
+        assert_eq!(response_json["data"]["hidden"]["totalCount"], 0);
+
+        let body: GQLRequest<()> = GQLRequest {
+            query: r#"
+                query {
+                    users(filter: { status: OFFLINE }) {
+                        edges {
+                            node {
+                                name
+                                email
+                                status
+                            }
+                        }
+                    }
+                }
+            "#
+            .to_string(),
+            variables: None,
+        };
+        let response = warp::test::request()
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", token(&Role::Student)))
+            .json(&body)
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: Value = serde_json::from_slice(response.body()).unwrap();
+        let edges = response_json["data"]["users"]["edges"].as_array().unwrap();
+        let names: Vec<&str> = edges
+            .iter()
+            .map(|edge| edge["node"]["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["Ben", "Dan", "Eve"]);
+        assert_eq!(edges[1]["node"]["status"], "OFFLINE");
+        assert!(edges[1]["node"]["email"].is_null());
+
+        delete_all_users().await.unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+#[cfg(test)]
+mod integration_warp_user_password_reset {
+    use dotenvy::dotenv;
+
+    use crate::{
+        common::{delete_all_users, make_graphql_filter_with_mailer},
+        warp::{
+            user::{
+                GQLRequestPasswordResetRes, GQLResetPasswordRes, GQLSigninRes, GQLSignoutRes,
+                GQLSignupRes,
+            },
+            GQLRequest,
+        },
+    };
+
+    fn query(query: &str) -> GQLRequest<()> {
+        GQLRequest {
+            query: query.to_string(),
+            variables: None,
+        }
+    }
+
+    fn signin(password: &str) -> GQLRequest<()> {
+        query(&format!(
+            r#"
+                mutation {{
+                    signin(email: "test@test.com", password: "{}") {{
+                        token
+                        user {{
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }}
+                    }}
+                }}
+            "#,
+            password
+        ))
+    }
+
+    fn reset_password(token: &str) -> GQLRequest<()> {
+        query(&format!(
+            r#"
+                mutation {{
+                    resetPassword(token: "{}", newPassword: "newpassword") {{
+                        success
+                    }}
+                }}
+            "#,
+            token
+        ))
+    }
+
+    // The token is on its own line in the email body
+    fn token_from_body(body: &str) -> String {
+        body.lines()
+            .find(|line| line.len() == 43 && !line.contains(' '))
+            .unwrap()
+            .to_string()
+    }
+
+    // To make sure the test steps perform exactly as needed
+    // i.e. inserting/deleting records sequentially
+    // We will use one function that will perform all the test
+    #[tokio::test]
+    async fn password_reset() {
+        dotenv().ok();
+        let (filter, mailer) = make_graphql_filter_with_mailer().await;
+
+        let response = warp::test::request()
+            .method("POST")
+            .json(&query(
+                r#"
+                mutation {
+                    signup(email: "test@test.com", name:"test user", password:"testpassword") {
+                        token
+                        user {
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }
+                    }
+                }
+            "#,
+            ))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLSignupRes = serde_json::from_slice(response.body()).unwrap();
+        let token = response_json.data.unwrap().signup.token;
+
+        let response = warp::test::request()
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&query(
+                r#"
+                mutation {
+                    signout(email: "test@test.com") {
+                        success
+                    }
+                }
+            "#,
+            ))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLSignoutRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.unwrap().signout.success);
+
+        // Unknown addresses get the same response but no email
+        let sent = mailer.sent().len();
+        let response = warp::test::request()
+            .method("POST")
+            .json(&query(
+                r#"
+                mutation {
+                    requestPasswordReset(email: "unknown@test.com") {
+                        success
+                    }
+                }
+            "#,
+            ))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLRequestPasswordResetRes =
+            serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.errors.is_none());
+        assert!(response_json.data.unwrap().request_password_reset.success);
+        assert_eq!(mailer.sent().len(), sent);
+
+        let response = warp::test::request()
+            .method("POST")
+            .json(&query(
+                r#"
+                mutation {
+                    requestPasswordReset(email: "test@test.com") {
+                        success
+                    }
+                }
+            "#,
+            ))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLRequestPasswordResetRes =
+            serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.errors.is_none());
+        assert!(response_json.data.unwrap().request_password_reset.success);
+
+        let email = mailer.last_sent_to("test@test.com").unwrap();
+        let reset_token = token_from_body(&email.body);
+
+        let response = warp::test::request()
+            .method("POST")
+            .json(&reset_password("notatoken"))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLResetPasswordRes = serde_json::from_slice(response.body()).unwrap();
+        let errors = response_json.errors.unwrap();
+        assert_eq!(
+            errors[0].message,
+            "Password reset token is invalid or has expired"
+        );
+        assert_eq!(errors[0].path[0], "resetPassword");
+
+        let response = warp::test::request()
+            .method("POST")
+            .json(&reset_password(&reset_token))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLResetPasswordRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.errors.is_none());
+        assert!(response_json.data.unwrap().reset_password.success);
+
+        // Reset tokens can only be used once
+        let response = warp::test::request()
+            .method("POST")
+            .json(&reset_password(&reset_token))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLResetPasswordRes = serde_json::from_slice(response.body()).unwrap();
+        let errors = response_json.errors.unwrap();
+        assert_eq!(
+            errors[0].message,
+            "Password reset token is invalid or has expired"
+        );
+
+        let response = warp::test::request()
+            .method("POST")
+            .json(&signin("testpassword"))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLSigninRes = serde_json::from_slice(response.body()).unwrap();
+        let errors = response_json.errors.unwrap();
+        assert_eq!(errors[0].message, "Incorrect email or password");
+
+        let response = warp::test::request()
+            .method("POST")
+            .json(&signin("newpassword"))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLSigninRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.errors.is_none());
+        assert_eq!(
+            response_json.data.unwrap().signin.user.email,
+            "test@test.com"
+        );
+
+        delete_all_users().await.unwrap();
+    }
+}
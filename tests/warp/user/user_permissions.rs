@@ -0,0 +1,131 @@
+#[cfg(test)]
+mod integration_warp_user_permissions {
+    use dotenvy::dotenv;
+    use sea_orm::prelude::Uuid;
+    use warp::{filters::BoxedFilter, http::Response};
+
+    use crate::{
+        common::make_graphql_filter,
+        warp::{
+            user::{GQLGrantPermissionRes, GQLRevokePermissionRes, GQLUnlockAccountRes},
+            GQLRequest,
+        },
+    };
+    use entity::sea_orm_active_enums::Role;
+    use gilded_university_server::{testutils::create_test_jwt, time::Time};
+
+    fn query(query: &str) -> GQLRequest<()> {
+        GQLRequest {
+            query: query.to_string(),
+            variables: None,
+        }
+    }
+
+    fn token(role: &Role) -> String {
+        let exp = Time::hour_hence().unwrap().as_secs();
+        create_test_jwt(&Uuid::new_v4(), role, exp)
+    }
+
+    async fn send(
+        filter: &BoxedFilter<(Response<Vec<u8>>,)>,
+        token: &str,
+        body: &str,
+    ) -> Response<Vec<u8>> {
+        warp::test::request()
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&query(body))
+            .filter(filter)
+            .await
+            .unwrap()
+    }
+
+    const UNLOCK: &str = r#"
+        mutation {
+            unlockAccount(email: "test@test.com") {
+                success
+            }
+        }
+    "#;
+
+    // To make sure the test steps perform exactly as needed
+    // i.e. inserting/deleting records sequentially
+    // We will use one function that will perform all the test
+    #[tokio::test]
+    async fn role_permissions() {
+        dotenv().ok();
+        let filter = make_graphql_filter().await;
+        let admin = token(&Role::Admin);
+        let teacher = token(&Role::Teacher);
+
+        // Teachers outrank students but cannot manage users by default
+        let response = send(&filter, &teacher, UNLOCK).await;
+        let response_json: GQLUnlockAccountRes = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            response_json.errors.unwrap()[0].message,
+            "Permission `users:manage` is not granted to role Teacher"
+        );
+
+        let response = send(
+            &filter,
+            &admin,
+            r#"
+                mutation {
+                    grantPermission(role: TEACHER, permission: "users:manage") {
+                        success
+                    }
+                }
+            "#,
+        )
+        .await;
+        let response_json: GQLGrantPermissionRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.unwrap().grant_permission.success);
+
+        // The mapping applies to tokens that were already issued
+        let response = send(&filter, &teacher, UNLOCK).await;
+        let response_json: GQLUnlockAccountRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.unwrap().unlock_account.success);
+
+        let response = send(
+            &filter,
+            &admin,
+            r#"
+                mutation {
+                    revokePermission(role: TEACHER, permission: "users:manage") {
+                        success
+                    }
+                }
+            "#,
+        )
+        .await;
+        let response_json: GQLRevokePermissionRes =
+            serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.unwrap().revoke_permission.success);
+
+        let response = send(&filter, &teacher, UNLOCK).await;
+        let response_json: GQLUnlockAccountRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.errors.is_some());
+
+        // Admin always keeps the permissions needed to restore the others
+        let response = send(
+            &filter,
+            &admin,
+            r#"
+                mutation {
+                    revokePermission(role: ADMIN, permission: "permissions:manage") {
+                        success
+                    }
+                }
+            "#,
+        )
+        .await;
+        let response_json: GQLRevokePermissionRes =
+            serde_json::from_slice(response.body()).unwrap();
+        let errors = response_json.errors.unwrap();
+        assert_eq!(
+            errors[0].message,
+            "Permission `permissions:manage` cannot be revoked from Admin"
+        );
+        assert_eq!(errors[0].extensions.as_ref().unwrap()["code"], "CONFLICT");
+    }
+}
@@ -0,0 +1,244 @@
+#[cfg(test)]
+mod integration_warp_user_profile {
+    use dotenvy::dotenv;
+    use warp::{filters::BoxedFilter, http::Response};
+
+    use crate::{
+        common::{delete_all_users, get_all_users, make_graphql_filter_with_mailer},
+        warp::{
+            user::{
+                GQLChangeEmailRes, GQLChangePasswordRes, GQLConfirmEmailChangeRes,
+                GQLRefreshSignupRes, GQLRefreshTokenRes, GQLUpdateProfileRes,
+            },
+            GQLRequest,
+        },
+    };
+
+    fn query(query: &str) -> GQLRequest<()> {
+        GQLRequest {
+            query: query.to_string(),
+            variables: None,
+        }
+    }
+
+    async fn send(
+        filter: &BoxedFilter<(Response<Vec<u8>>,)>,
+        token: Option<&str>,
+        body: &str,
+    ) -> Response<Vec<u8>> {
+        let mut request = warp::test::request().method("POST").json(&query(body));
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        request.filter(filter).await.unwrap()
+    }
+
+    fn change_password(current: &str) -> String {
+        format!(
+            r#"
+                mutation {{
+                    changePassword(currentPassword: "{}", newPassword: "newpassword") {{
+                        token
+                        refreshToken
+                        user {{
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }}
+                    }}
+                }}
+            "#,
+            current
+        )
+    }
+
+    fn update_profile(name: &str) -> String {
+        format!(
+            r#"
+                mutation {{
+                    updateProfile(name: "{}") {{
+                        id
+                        email
+                        name
+                        role
+                        status
+                    }}
+                }}
+            "#,
+            name
+        )
+    }
+
+    fn confirm_email_change(token: &str) -> String {
+        format!(
+            r#"
+                mutation {{
+                    confirmEmailChange(token: "{}") {{
+                        id
+                        email
+                        name
+                        role
+                        status
+                    }}
+                }}
+            "#,
+            token
+        )
+    }
+
+    // The token is on its own line in the email body
+    fn token_from_body(body: &str) -> String {
+        body.lines()
+            .find(|line| line.len() == 43 && !line.contains(' '))
+            .unwrap()
+            .to_string()
+    }
+
+    // To make sure the test steps perform exactly as needed
+    // i.e. inserting/deleting records sequentially
+    // We will use one function that will perform all the test
+    #[tokio::test]
+    async fn self_service_profile() {
+        dotenv().ok();
+        let (filter, mailer) = make_graphql_filter_with_mailer().await;
+
+        let response = send(
+            &filter,
+            None,
+            r#"
+                mutation {
+                    signup(email: "test@test.com", name: "test user", password: "testpassword") {
+                        token
+                        refreshToken
+                        user {
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }
+                    }
+                }
+            "#,
+        )
+        .await;
+        let response_json: GQLRefreshSignupRes = serde_json::from_slice(response.body()).unwrap();
+        let signup = response_json.data.unwrap().signup;
+
+        let response = send(&filter, Some(&signup.token), &update_profile("new name")).await;
+        let response_json: GQLUpdateProfileRes = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(response_json.data.unwrap().update_profile.name, "new name");
+
+        let response = send(&filter, Some(&signup.token), &change_password("wrong")).await;
+        let response_json: GQLChangePasswordRes = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            response_json.errors.unwrap()[0].message,
+            "Current password is incorrect"
+        );
+
+        let response = send(
+            &filter,
+            Some(&signup.token),
+            &change_password("testpassword"),
+        )
+        .await;
+        let response_json: GQLChangePasswordRes = serde_json::from_slice(response.body()).unwrap();
+        let changed = response_json.data.unwrap().change_password;
+        assert!(!changed.refresh_token.is_empty());
+
+        // Other sessions are signed out
+        let response = send(
+            &filter,
+            None,
+            &format!(
+                r#"
+                    mutation {{
+                        refreshToken(token: "{}") {{
+                            token
+                            refreshToken
+                            user {{
+                                id
+                                email
+                                name
+                                role
+                                status
+                            }}
+                        }}
+                    }}
+                "#,
+                signup.refresh_token
+            ),
+        )
+        .await;
+        let response_json: GQLRefreshTokenRes = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            response_json.errors.unwrap()[0].message,
+            "Refresh token is invalid"
+        );
+
+        let response = send(
+            &filter,
+            Some(&changed.token),
+            r#"
+                mutation {
+                    changeEmail(newEmail: "new@test.com") {
+                        success
+                    }
+                }
+            "#,
+        )
+        .await;
+        let response_json: GQLChangeEmailRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.unwrap().change_email.success);
+
+        // Nothing changes until the new address is confirmed
+        let users = get_all_users().await.unwrap();
+        assert_eq!(users[0].email, "test@test.com");
+
+        let email = mailer.last_sent_to("new@test.com").unwrap();
+        let token = token_from_body(&email.body);
+        let response = send(&filter, None, &confirm_email_change(&token)).await;
+        let response_json: GQLConfirmEmailChangeRes =
+            serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            response_json.data.unwrap().confirm_email_change.email,
+            "new@test.com"
+        );
+
+        let users = get_all_users().await.unwrap();
+        assert_eq!(users[0].email, "new@test.com");
+        assert!(users[0].is_email_verified());
+
+        let response = send(&filter, None, &confirm_email_change(&token)).await;
+        let response_json: GQLConfirmEmailChangeRes =
+            serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            response_json.errors.unwrap()[0].message,
+            "Email change token is invalid or has expired"
+        );
+
+        let response = send(
+            &filter,
+            Some(&changed.token),
+            r#"
+                mutation {
+                    changeEmail(newEmail: "new@test.com") {
+                        success
+                    }
+                }
+            "#,
+        )
+        .await;
+        let response_json: GQLChangeEmailRes = serde_json::from_slice(response.body()).unwrap();
+        let errors = response_json.errors.unwrap();
+        assert_eq!(
+            errors[0].message,
+            "User with email `new@test.com` already exists"
+        );
+        assert_eq!(errors[0].extensions.as_ref().unwrap()["code"], "CONFLICT");
+
+        delete_all_users().await.unwrap();
+    }
+}
@@ -1,6 +1,9 @@
 #[cfg(test)]
 mod integration_warp_user_query {
     use dotenvy::dotenv;
+    use entity::sea_orm_active_enums::Role;
+    use gilded_university_server::{testutils::create_test_jwt, time::Time};
+    use sea_orm::prelude::Uuid;
 
     use crate::{
         common::{delete_all_users, make_graphql_filter},
@@ -17,17 +20,32 @@ mod integration_warp_user_query {
     async fn user_query() {
         dotenv().ok();
         let filter = make_graphql_filter().await;
+        // Only admins can see every field of other users
+        let now_plus_hour = Time::hour_hence().unwrap().as_secs();
+        let admin_token = create_test_jwt(&Uuid::new_v4(), &Role::Admin, now_plus_hour);
         seed_users().await.unwrap();
 
         let body: GQLRequest<()> = GQLRequest {
             query: r#"
                 query {
                     users {
-                        id
-                        email
-                        name
-                        role
-                        status
+                        edges {
+                            cursor
+                            node {
+                                id
+                                email
+                                name
+                                role
+                                status
+                            }
+                        }
+                        pageInfo {
+                            hasNextPage
+                            hasPreviousPage
+                            startCursor
+                            endCursor
+                        }
+                        totalCount
                     }
             }"#
             .to_string(),
@@ -35,6 +53,7 @@ mod integration_warp_user_query {
         };
         let response = warp::test::request()
             .method("POST")
+            .header("Authorization", format!("Bearer {}", admin_token))
             .json(&body)
             .filter(&filter)
             .await
@@ -45,10 +64,10 @@ mod integration_warp_user_query {
         assert!(response_json.errors.is_none());
 
         let data = response_json.data.unwrap();
-        let user1 = &data.users[0];
-        let user2 = &data.users[1];
+        let user1 = &data.users.edges[0].node;
+        let user2 = &data.users.edges[1].node;
 
-        let id1 = data.users[0].id.clone();
+        let id1 = data.users.edges[0].node.id.clone();
 
         assert_eq!(user1.email, "test@test.com");
         assert_eq!(user1.name, "test user");
@@ -77,6 +96,7 @@ mod integration_warp_user_query {
         };
         let response = warp::test::request()
             .method("POST")
+            .header("Authorization", format!("Bearer {}", admin_token))
             .json(&body)
             .filter(&filter)
             .await
@@ -112,6 +132,7 @@ mod integration_warp_user_query {
         };
         let response = warp::test::request()
             .method("POST")
+            .header("Authorization", format!("Bearer {}", admin_token))
             .json(&body)
             .filter(&filter)
             .await
@@ -144,6 +165,7 @@ mod integration_warp_user_query {
         };
         let response = warp::test::request()
             .method("POST")
+            .header("Authorization", format!("Bearer {}", admin_token))
             .json(&body)
             .filter(&filter)
             .await
@@ -179,6 +201,7 @@ mod integration_warp_user_query {
         };
         let response = warp::test::request()
             .method("POST")
+            .header("Authorization", format!("Bearer {}", admin_token))
             .json(&body)
             .filter(&filter)
             .await
@@ -208,6 +231,7 @@ mod integration_warp_user_query {
         };
         let response = warp::test::request()
             .method("POST")
+            .header("Authorization", format!("Bearer {}", admin_token))
             .json(&body)
             .filter(&filter)
             .await
@@ -221,8 +245,12 @@ mod integration_warp_user_query {
         assert!(data.user_by_id.is_none());
 
         let errors = response_json.errors.unwrap();
-        assert_eq!(errors[0].message, "invalid character: expected an optional prefix of `urn:uuid:` followed by [0-9a-zA-Z], found `n` at 1");
+        assert_eq!(errors[0].message, "`not-a-uuid` is not a valid ID");
         assert_eq!(errors[0].path[0], "userById");
+        let extensions = errors[0].extensions.as_ref().unwrap();
+        assert_eq!(extensions["code"], "BAD_USER_INPUT");
+        assert_eq!(extensions["reason"], "INVALID_ID");
+        assert_eq!(extensions["id"], "not-a-uuid");
 
         delete_all_users().await.unwrap();
 
@@ -230,11 +258,23 @@ mod integration_warp_user_query {
             query: r#"
                 query {
                     users {
-                        id
-                        email
-                        name
-                        role
-                        status
+                        edges {
+                            cursor
+                            node {
+                                id
+                                email
+                                name
+                                role
+                                status
+                            }
+                        }
+                        pageInfo {
+                            hasNextPage
+                            hasPreviousPage
+                            startCursor
+                            endCursor
+                        }
+                        totalCount
                     }
             }"#
             .to_string(),
@@ -242,6 +282,7 @@ mod integration_warp_user_query {
         };
         let response = warp::test::request()
             .method("POST")
+            .header("Authorization", format!("Bearer {}", admin_token))
             .json(&body)
             .filter(&filter)
             .await
@@ -252,6 +293,8 @@ mod integration_warp_user_query {
         assert!(response_json.errors.is_none());
 
         let data = response_json.data.unwrap();
-        assert_eq!(data.users.len(), 0);
+        assert_eq!(data.users.edges.len(), 0);
+        assert_eq!(data.users.total_count, 0);
+        assert!(data.users.page_info.end_cursor.is_none());
     }
 }
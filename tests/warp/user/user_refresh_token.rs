@@ -0,0 +1,172 @@
+#[cfg(test)]
+mod integration_warp_user_refresh_token {
+    use dotenvy::dotenv;
+    use entity::prelude::Session;
+    use sea_orm::EntityTrait;
+
+    use crate::{
+        common::{delete_all_users, make_graphql_filter, open_test_database},
+        warp::{
+            user::{GQLMySessionsRes, GQLRefreshSignupRes, GQLRefreshTokenRes},
+            GQLRequest,
+        },
+    };
+
+    fn refresh_request(token: &str) -> GQLRequest<()> {
+        GQLRequest {
+            query: format!(
+                r#"
+                mutation {{
+                    refreshToken(token: "{}") {{
+                        token
+                        refreshToken
+                        user {{
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }}
+                    }}
+                }}
+            "#,
+                token
+            ),
+            variables: None,
+        }
+    }
+
+    // To make sure the test steps perform exactly as needed
+    // i.e. inserting/deleting records sequentially
+    // We will use one function that will perform all the test
+    #[tokio::test]
+    async fn refresh_token_rotation() {
+        dotenv().ok();
+        let filter = make_graphql_filter().await;
+
+        let body: GQLRequest<()> = GQLRequest {
+            query: r#"
+                mutation {
+                    signup(email: "test@test.com", name:"test user", password:"testpassword") {
+                        token
+                        refreshToken
+                        user {
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }
+                    }
+                }
+            "#
+            .to_string(),
+            variables: None,
+        };
+        let response = warp::test::request()
+            .method("POST")
+            .json(&body)
+            .filter(&filter)
+            .await
+            .unwrap();
+
+        let response_json: GQLRefreshSignupRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.is_some());
+        assert!(response_json.errors.is_none());
+
+        let original = response_json.data.unwrap().signup.refresh_token;
+        assert!(!original.is_empty());
+
+        let response = warp::test::request()
+            .method("POST")
+            .json(&refresh_request(&original))
+            .filter(&filter)
+            .await
+            .unwrap();
+
+        let response_json: GQLRefreshTokenRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.is_some());
+        assert!(response_json.errors.is_none());
+
+        let data = response_json.data.unwrap().refresh_token;
+        assert!(!data.token.is_empty());
+        assert_ne!(data.refresh_token, original);
+        assert_eq!(data.user.email, "test@test.com");
+        let rotated = data.refresh_token;
+        let access = data.token;
+
+        // Replaying the original token is detected as reuse
+        let response = warp::test::request()
+            .method("POST")
+            .json(&refresh_request(&original))
+            .filter(&filter)
+            .await
+            .unwrap();
+
+        let response_json: GQLRefreshTokenRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.is_none());
+
+        let errors = response_json.errors.unwrap();
+        assert_eq!(errors[0].message, "Refresh token has already been used");
+        assert_eq!(errors[0].path[0], "refreshToken");
+
+        // Which revokes the rest of the family
+        let response = warp::test::request()
+            .method("POST")
+            .json(&refresh_request(&rotated))
+            .filter(&filter)
+            .await
+            .unwrap();
+
+        let response_json: GQLRefreshTokenRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.is_none());
+
+        let errors = response_json.errors.unwrap();
+        assert_eq!(errors[0].message, "Refresh token is invalid");
+
+        // Along with the session and the access tokens issued within it
+        let conn = open_test_database().await;
+        let sessions = Session::find().all(&conn).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert!(sessions[0].revoked_at.is_some());
+
+        let body: GQLRequest<()> = GQLRequest {
+            query: r#"
+                query {
+                    mySessions {
+                        id
+                        deviceLabel
+                        userAgent
+                        current
+                    }
+                }
+            "#
+            .to_string(),
+            variables: None,
+        };
+        let response = warp::test::request()
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", access))
+            .json(&body)
+            .filter(&filter)
+            .await
+            .unwrap();
+
+        let response_json: GQLMySessionsRes = serde_json::from_slice(response.body()).unwrap();
+        let errors = response_json.errors.unwrap();
+        assert_eq!(errors[0].message, "Token has been revoked");
+
+        let response = warp::test::request()
+            .method("POST")
+            .json(&refresh_request("notatoken"))
+            .filter(&filter)
+            .await
+            .unwrap();
+
+        let response_json: GQLRefreshTokenRes = serde_json::from_slice(response.body()).unwrap();
+        let errors = response_json.errors.unwrap();
+        assert_eq!(errors[0].message, "Refresh token is invalid");
+
+        delete_all_users().await.unwrap();
+    }
+}
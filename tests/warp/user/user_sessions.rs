@@ -0,0 +1,297 @@
+#[cfg(test)]
+mod integration_warp_user_sessions {
+    use std::sync::Arc;
+
+    use dotenvy::dotenv;
+    use entity::{prelude::Session, sea_orm_active_enums::Status, session};
+    use sea_orm::{prelude::Uuid, sea_query::Expr, EntityTrait};
+    use warp::{filters::BoxedFilter, http::Response};
+
+    use crate::{
+        common::{delete_all_users, get_all_users, make_graphql_filter, open_test_database},
+        warp::{
+            user::{
+                GQLMySessionsRes, GQLRefreshAuthResponse, GQLRefreshSigninRes, GQLRefreshSignupRes,
+                GQLRefreshTokenRes, GQLRevokeSessionRes, GQLSignoutEverywhereRes, GQLSignoutRes,
+            },
+            GQLRequest,
+        },
+    };
+    use gilded_university_server::{
+        auth::session::expire_idle_presence, repository::database::DatabaseUserRepository,
+    };
+
+    fn query(query: &str) -> GQLRequest<()> {
+        GQLRequest {
+            query: query.to_string(),
+            variables: None,
+        }
+    }
+
+    async fn send(
+        filter: &BoxedFilter<(Response<Vec<u8>>,)>,
+        token: Option<&str>,
+        body: &str,
+    ) -> Response<Vec<u8>> {
+        let mut request = warp::test::request()
+            .method("POST")
+            .header("User-Agent", "integration-test/1.0")
+            .json(&query(body));
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        request.filter(filter).await.unwrap()
+    }
+
+    async fn signin(
+        filter: &BoxedFilter<(Response<Vec<u8>>,)>,
+        label: &str,
+    ) -> GQLRefreshAuthResponse {
+        let body = format!(
+            r#"
+                mutation {{
+                    signin(email: "test@test.com", password: "testpassword", deviceLabel: "{}") {{
+                        token
+                        refreshToken
+                        user {{
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }}
+                    }}
+                }}
+            "#,
+            label
+        );
+        let response = send(filter, None, &body).await;
+        let response_json: GQLRefreshSigninRes = serde_json::from_slice(response.body()).unwrap();
+        response_json.data.unwrap().signin
+    }
+
+    const MY_SESSIONS: &str = r#"
+        query {
+            mySessions {
+                id
+                deviceLabel
+                userAgent
+                current
+            }
+        }
+    "#;
+
+    fn revoke_session(id: &str) -> String {
+        format!(
+            r#"
+                mutation {{
+                    revokeSession(id: "{}") {{
+                        success
+                    }}
+                }}
+            "#,
+            id
+        )
+    }
+
+    // To make sure the test steps perform exactly as needed
+    // i.e. inserting/deleting records sequentially
+    // We will use one function that will perform all the test
+    #[tokio::test]
+    async fn user_sessions() {
+        dotenv().ok();
+        let filter = make_graphql_filter().await;
+
+        let response = send(
+            &filter,
+            None,
+            r#"
+                mutation {
+                    signup(email: "test@test.com", name: "test user", password: "testpassword") {
+                        token
+                        refreshToken
+                        user {
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }
+                    }
+                }
+            "#,
+        )
+        .await;
+        let response_json: GQLRefreshSignupRes = serde_json::from_slice(response.body()).unwrap();
+        let signup = response_json.data.unwrap().signup;
+
+        // Each device gets its own session
+        let laptop = signin(&filter, "laptop").await;
+        let phone = signin(&filter, "phone").await;
+        assert_eq!(phone.user.status, "ONLINE");
+
+        let response = send(&filter, Some(&laptop.token), MY_SESSIONS).await;
+        let response_json: GQLMySessionsRes = serde_json::from_slice(response.body()).unwrap();
+        let sessions = response_json.data.unwrap().my_sessions;
+        assert_eq!(sessions.len(), 3);
+        let current: Vec<_> = sessions.iter().filter(|s| s.current).collect();
+        assert_eq!(current.len(), 1);
+        assert_eq!(current[0].device_label.as_deref(), Some("laptop"));
+        assert_eq!(
+            current[0].user_agent.as_deref(),
+            Some("integration-test/1.0")
+        );
+        let phone_id = sessions
+            .iter()
+            .find(|s| s.device_label.as_deref() == Some("phone"))
+            .unwrap()
+            .id
+            .to_owned();
+
+        // Sessions of other users are not found
+        let response = send(
+            &filter,
+            Some(&laptop.token),
+            &revoke_session(&Uuid::new_v4().to_string()),
+        )
+        .await;
+        let response_json: GQLRevokeSessionRes = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            response_json.errors.unwrap()[0].message,
+            "Session not found"
+        );
+
+        let response = send(&filter, Some(&laptop.token), &revoke_session(&phone_id)).await;
+        let response_json: GQLRevokeSessionRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.unwrap().revoke_session.success);
+
+        // The revoked device can neither use nor refresh its tokens
+        let response = send(&filter, Some(&phone.token), MY_SESSIONS).await;
+        let response_json: GQLMySessionsRes = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            response_json.errors.unwrap()[0].message,
+            "Token has been revoked"
+        );
+
+        let refresh = format!(
+            r#"
+                mutation {{
+                    refreshToken(token: "{}") {{
+                        token
+                        refreshToken
+                        user {{
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }}
+                    }}
+                }}
+            "#,
+            phone.refresh_token
+        );
+        let response = send(&filter, None, &refresh).await;
+        let response_json: GQLRefreshTokenRes = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            response_json.errors.unwrap()[0].message,
+            "Refresh token is invalid"
+        );
+
+        // Signing out ends only the current session
+        let response = send(
+            &filter,
+            Some(&signup.token),
+            r#"
+                mutation {
+                    signout(email: "test@test.com") {
+                        success
+                    }
+                }
+            "#,
+        )
+        .await;
+        let response_json: GQLSignoutRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.unwrap().signout.success);
+
+        let users = get_all_users().await.unwrap();
+        assert_eq!(users[0].status, Status::Online);
+
+        let response = send(&filter, Some(&laptop.token), MY_SESSIONS).await;
+        let response_json: GQLMySessionsRes = serde_json::from_slice(response.body()).unwrap();
+        let sessions = response_json.data.unwrap().my_sessions;
+        assert_eq!(sessions.len(), 1);
+        assert!(sessions[0].current);
+
+        // A device that stops refreshing leaves the user offline until it returns,
+        // even though its session can still be refreshed
+        let conn = Arc::new(open_test_database().await);
+        Session::update_many()
+            .col_expr(session::Column::LastSeenAt, Expr::value(0))
+            .exec(conn.as_ref())
+            .await
+            .unwrap();
+        let users_repository = DatabaseUserRepository::new(conn.clone());
+        expire_idle_presence(&users_repository, &conn)
+            .await
+            .unwrap();
+        let users = get_all_users().await.unwrap();
+        assert_eq!(users[0].status, Status::Offline);
+
+        let refresh = format!(
+            r#"
+                mutation {{
+                    refreshToken(token: "{}") {{
+                        token
+                        refreshToken
+                        user {{
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }}
+                    }}
+                }}
+            "#,
+            laptop.refresh_token
+        );
+        let response = send(&filter, None, &refresh).await;
+        let response_json: GQLRefreshTokenRes = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            response_json.data.unwrap().refresh_token.user.status,
+            "ONLINE"
+        );
+        let users = get_all_users().await.unwrap();
+        assert_eq!(users[0].status, Status::Online);
+
+        let response = send(
+            &filter,
+            Some(&laptop.token),
+            r#"
+                mutation {
+                    signoutEverywhere {
+                        success
+                    }
+                }
+            "#,
+        )
+        .await;
+        let response_json: GQLSignoutEverywhereRes =
+            serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.unwrap().signout_everywhere.success);
+
+        // The user is offline once no sessions remain
+        let users = get_all_users().await.unwrap();
+        assert_eq!(users[0].status, Status::Offline);
+
+        let response = send(&filter, Some(&laptop.token), MY_SESSIONS).await;
+        let response_json: GQLMySessionsRes = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            response_json.errors.unwrap()[0].message,
+            "Token has been revoked"
+        );
+
+        delete_all_users().await.unwrap();
+    }
+}
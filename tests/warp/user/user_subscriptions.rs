@@ -0,0 +1,194 @@
+#[cfg(test)]
+mod integration_warp_user_subscriptions {
+    use std::time::Duration;
+
+    use dotenvy::dotenv;
+    use serde_json::{json, Value};
+    use warp::{filters::BoxedFilter, http::Response, test::WsClient};
+
+    use crate::{
+        common::{delete_all_users, make_graphql_and_subscription_filters},
+        warp::{
+            user::{GQLSigninRes, GQLSignoutRes, GQLSignupRes},
+            GQLRequest,
+        },
+    };
+
+    fn query(query: &str) -> GQLRequest<()> {
+        GQLRequest {
+            query: query.to_string(),
+            variables: None,
+        }
+    }
+
+    async fn send(
+        filter: &BoxedFilter<(Response<Vec<u8>>,)>,
+        token: Option<&str>,
+        body: &str,
+    ) -> Response<Vec<u8>> {
+        let mut request = warp::test::request().method("POST").json(&query(body));
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        request.filter(filter).await.unwrap()
+    }
+
+    // Keep alive messages can arrive at any point and are skipped
+    async fn recv(client: &mut WsClient) -> Value {
+        loop {
+            let message = client.recv().await.unwrap();
+            let value: Value = serde_json::from_str(message.to_str().unwrap()).unwrap();
+            if value["type"] != "ka" {
+                return value;
+            }
+        }
+    }
+
+    fn signup(email: &str) -> String {
+        format!(
+            r#"
+                mutation {{
+                    signup(email: "{}", name: "{}", password: "testpassword") {{
+                        token
+                        user {{
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }}
+                    }}
+                }}
+            "#,
+            email, email
+        )
+    }
+
+    // To make sure the test steps perform exactly as needed
+    // i.e. inserting/deleting records sequentially
+    // We will use one function that will perform all the test
+    #[tokio::test]
+    async fn user_subscriptions() {
+        dotenv().ok();
+        let (filter, subscriptions) = make_graphql_and_subscription_filters().await;
+
+        let response = send(&filter, None, &signup("watcher@test.com")).await;
+        let response_json: GQLSignupRes = serde_json::from_slice(response.body()).unwrap();
+        let watcher = response_json.data.unwrap().signup;
+
+        let response = send(&filter, None, &signup("test@test.com")).await;
+        let response_json: GQLSignupRes = serde_json::from_slice(response.body()).unwrap();
+        let watched = response_json.data.unwrap().signup;
+
+        // Connections without a valid token are refused
+        let mut client = warp::test::ws()
+            .path("/")
+            .handshake(subscriptions.clone())
+            .await
+            .unwrap();
+        client
+            .send_text(json!({"type": "connection_init", "payload": {}}).to_string())
+            .await;
+        let message = recv(&mut client).await;
+        assert_eq!(message["type"], "connection_error");
+        assert_eq!(message["payload"]["message"], "Token missing");
+
+        let mut client = warp::test::ws()
+            .path("/")
+            .handshake(subscriptions)
+            .await
+            .unwrap();
+        let start = json!({
+            "type": "start",
+            "id": "1",
+            "payload": {
+                "query": format!(
+                    r#"subscription {{ userStatusChanged(id: "{}") {{ id email status }} }}"#,
+                    watched.user.id
+                )
+            }
+        })
+        .to_string();
+
+        // Operations are refused until the connection is initialised
+        client.send_text(start.to_owned()).await;
+        let message = recv(&mut client).await;
+        assert_eq!(message["type"], "error");
+        assert_eq!(message["id"], "1");
+
+        client
+            .send_text(
+                json!({
+                    "type": "connection_init",
+                    "payload": {"Authorization": format!("Bearer {}", watcher.token)}
+                })
+                .to_string(),
+            )
+            .await;
+        let message = recv(&mut client).await;
+        assert_eq!(message["type"], "connection_ack");
+
+        client.send_text(start).await;
+        // Give the operation time to subscribe before anything is published
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let response = send(
+            &filter,
+            Some(&watched.token),
+            r#"
+                mutation {
+                    signout(email: "test@test.com") {
+                        success
+                    }
+                }
+            "#,
+        )
+        .await;
+        let response_json: GQLSignoutRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.data.unwrap().signout.success);
+
+        // Guests see the change without the email address
+        let message = recv(&mut client).await;
+        assert_eq!(message["type"], "data");
+        assert_eq!(message["id"], "1");
+        let changed = &message["payload"]["data"]["userStatusChanged"];
+        assert_eq!(changed["id"], watched.user.id.as_str());
+        assert_eq!(changed["status"], "OFFLINE");
+        assert!(changed["email"].is_null());
+
+        let response = send(
+            &filter,
+            None,
+            r#"
+                mutation {
+                    signin(email: "test@test.com", password: "testpassword") {
+                        token
+                        user {
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }
+                    }
+                }
+            "#,
+        )
+        .await;
+        let response_json: GQLSigninRes = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(response_json.data.unwrap().signin.user.status, "ONLINE");
+
+        let message = recv(&mut client).await;
+        let changed = &message["payload"]["data"]["userStatusChanged"];
+        assert_eq!(changed["status"], "ONLINE");
+
+        client
+            .send_text(json!({"type": "stop", "id": "1"}).to_string())
+            .await;
+        let message = recv(&mut client).await;
+        assert_eq!(message["type"], "complete");
+        assert_eq!(message["id"], "1");
+
+        delete_all_users().await.unwrap();
+    }
+}
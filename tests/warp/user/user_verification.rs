@@ -0,0 +1,162 @@
+#[cfg(test)]
+mod integration_warp_user_verification {
+    use dotenvy::dotenv;
+
+    use crate::{
+        common::{delete_all_users, get_all_users, make_graphql_filter_with_mailer},
+        warp::{
+            user::{GQLSendVerificationEmailRes, GQLSignupRes, GQLVerifyEmailRes},
+            GQLRequest,
+        },
+    };
+
+    fn query(query: &str) -> GQLRequest<()> {
+        GQLRequest {
+            query: query.to_string(),
+            variables: None,
+        }
+    }
+
+    fn verify_email(token: &str) -> GQLRequest<()> {
+        query(&format!(
+            r#"
+                mutation {{
+                    verifyEmail(token: "{}") {{
+                        success
+                    }}
+                }}
+            "#,
+            token
+        ))
+    }
+
+    fn send_verification_email() -> GQLRequest<()> {
+        query(
+            r#"
+                mutation {
+                    sendVerificationEmail {
+                        success
+                    }
+                }
+            "#,
+        )
+    }
+
+    // The token is on its own line in the email body
+    fn token_from_body(body: &str) -> String {
+        body.lines()
+            .find(|line| line.len() == 43 && !line.contains(' '))
+            .unwrap()
+            .to_string()
+    }
+
+    // To make sure the test steps perform exactly as needed
+    // i.e. inserting/deleting records sequentially
+    // We will use one function that will perform all the test
+    #[tokio::test]
+    async fn email_verification() {
+        dotenv().ok();
+        let (filter, mailer) = make_graphql_filter_with_mailer().await;
+
+        let response = warp::test::request()
+            .method("POST")
+            .json(&query(
+                r#"
+                mutation {
+                    signup(email: "test@test.com", name:"test user", password:"testpassword") {
+                        token
+                        user {
+                            id
+                            email
+                            name
+                            role
+                            status
+                        }
+                    }
+                }
+            "#,
+            ))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLSignupRes = serde_json::from_slice(response.body()).unwrap();
+        let token = response_json.data.unwrap().signup.token;
+
+        // Signing up sends the first verification email
+        let first = mailer.last_sent_to("test@test.com").unwrap();
+        let first_token = token_from_body(&first.body);
+
+        let users = get_all_users().await.unwrap();
+        assert!(users[0].email_verified_at.is_none());
+
+        let response = warp::test::request()
+            .method("POST")
+            .json(&send_verification_email())
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLSendVerificationEmailRes =
+            serde_json::from_slice(response.body()).unwrap();
+        let errors = response_json.errors.unwrap();
+        assert_eq!(errors[0].message, "Token missing");
+        assert_eq!(errors[0].path[0], "sendVerificationEmail");
+
+        let response = warp::test::request()
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&send_verification_email())
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLSendVerificationEmailRes =
+            serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.errors.is_none());
+        assert!(response_json.data.unwrap().send_verification_email.success);
+
+        let second = mailer.last_sent_to("test@test.com").unwrap();
+        let second_token = token_from_body(&second.body);
+        assert_ne!(first_token, second_token);
+
+        // Requesting a new email invalidates the previous token
+        let response = warp::test::request()
+            .method("POST")
+            .json(&verify_email(&first_token))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLVerifyEmailRes = serde_json::from_slice(response.body()).unwrap();
+        let errors = response_json.errors.unwrap();
+        assert_eq!(
+            errors[0].message,
+            "Verification token is invalid or has expired"
+        );
+        assert_eq!(errors[0].path[0], "verifyEmail");
+
+        let response = warp::test::request()
+            .method("POST")
+            .json(&verify_email(&second_token))
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLVerifyEmailRes = serde_json::from_slice(response.body()).unwrap();
+        assert!(response_json.errors.is_none());
+        assert!(response_json.data.unwrap().verify_email.success);
+
+        let users = get_all_users().await.unwrap();
+        assert!(users[0].email_verified_at.is_some());
+
+        let response = warp::test::request()
+            .method("POST")
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&send_verification_email())
+            .filter(&filter)
+            .await
+            .unwrap();
+        let response_json: GQLSendVerificationEmailRes =
+            serde_json::from_slice(response.body()).unwrap();
+        let errors = response_json.errors.unwrap();
+        assert_eq!(errors[0].message, "Email address has already been verified");
+
+        delete_all_users().await.unwrap();
+    }
+}